@@ -0,0 +1,540 @@
+//! Texts drawn on the page, formatted in runs: captions and conditions such
+//! as "ΔG‡ = 12 kcal mol⁻¹" or "CH₂Cl₂, 0 °C".
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use iced::widget::canvas::Text;
+use iced::widget::text::Alignment;
+use kurbo::Point;
+use serde::{Deserialize, Serialize};
+
+use crate::element::Element;
+use crate::font::{self, Font, FontStyle};
+use crate::render::{HorizontalAlignment, VerticalAlignment};
+use crate::shape::{TextDef, option_color};
+
+/// Size of sub- and superscripts relative to the text.
+const SCRIPT_SIZE: f64 = 0.7;
+/// How far subscripts sink below the baseline, relative to the text size.
+const SUBSCRIPT_SHIFT: f64 = 0.2;
+/// How far superscripts rise above the baseline, relative to the text size.
+const SUPERSCRIPT_SHIFT: f64 = 0.35;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Script {
+    #[default]
+    Normal,
+    Sub,
+    Super,
+}
+
+/// Formatting of a run of text.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Format {
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(default, skip_serializing_if = "is_normal")]
+    pub script: Script,
+    /// Overrides the color of the text.
+    #[serde(
+        default,
+        with = "option_color",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub color: Option<iced::Color>,
+}
+
+impl Format {
+    pub fn font_style(&self) -> FontStyle {
+        FontStyle {
+            bold: self.bold,
+            italic: self.italic,
+        }
+    }
+}
+
+/// A piece of text in one format.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    pub text: String,
+    #[serde(flatten)]
+    pub format: Format,
+}
+
+/// A text shape. `text` holds the position, size and plain content; `runs`
+/// split the same content into formatted pieces and may be left empty for
+/// plain text.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(flatten, with = "TextDef")]
+    pub text: Text,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runs: Vec<Run>,
+    /// Subscripts the digits of formulas and superscripts charges and unit
+    /// exponents while typing.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub auto_format: bool,
+}
+
+/// A run laid out on a line.
+#[derive(Clone, Debug)]
+pub struct Segment {
+    /// Byte range in the content.
+    pub range: Range<usize>,
+    pub format: Format,
+    pub font: Font,
+    pub size: f64,
+    /// Start of the segment on its (possibly shifted) baseline.
+    pub origin: Point,
+    pub width: f64,
+}
+
+/// A line of an annotation laid out for drawing and hit testing.
+#[derive(Clone, Debug)]
+pub struct Line {
+    /// Byte range in the content, without the line break.
+    pub range: Range<usize>,
+    pub baseline: f64,
+    /// Top of the line box, which is [`Annotation::line_height`] tall.
+    pub top: f64,
+    pub left: f64,
+    pub width: f64,
+    pub segments: Vec<Segment>,
+}
+
+/// Compares what is saved of the text, iced's `Text` has no equality.
+impl PartialEq for Annotation {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (&self.text, &other.text);
+        a.content == b.content
+            && a.position == b.position
+            && a.max_width == b.max_width
+            && a.color == b.color
+            && a.size == b.size
+            && a.line_height == b.line_height
+            && a.font == b.font
+            && a.align_x == b.align_x
+            && a.align_y == b.align_y
+            && a.shaping == b.shaping
+            && self.runs == other.runs
+            && self.auto_format == other.auto_format
+    }
+}
+
+impl Annotation {
+    pub fn new(text: Text) -> Self {
+        Self {
+            text,
+            runs: Vec::new(),
+            auto_format: false,
+        }
+    }
+
+    pub fn content(&self) -> &str {
+        &self.text.content
+    }
+
+    /// The runs covering the content. Plain text, or runs that no longer
+    /// match the content, are one unformatted run.
+    pub fn runs(&self) -> Cow<'_, [Run]> {
+        let matching = !self.runs.is_empty()
+            && self
+                .runs
+                .iter()
+                .map(|run| run.text.as_str())
+                .collect::<String>()
+                == self.content();
+        if matching {
+            Cow::Borrowed(&self.runs)
+        } else {
+            Cow::Owned(vec![Run {
+                text: self.content().to_string(),
+                format: Format::default(),
+            }])
+        }
+    }
+
+    /// The format of the character before `offset`, or after it at the start.
+    pub fn format_at(&self, offset: usize) -> Format {
+        let mut start = 0;
+        let runs = self.runs();
+        for run in runs.iter() {
+            let end = start + run.text.len();
+            if offset <= end && (offset > start || start == 0) {
+                return run.format;
+            }
+            start = end;
+        }
+        Format::default()
+    }
+
+    /// Replaces `range` of the content with `text` in `format`.
+    pub fn replace(&mut self, range: Range<usize>, text: &str, format: Format) {
+        let mut inserted = Some(Run {
+            text: text.to_string(),
+            format,
+        });
+        let mut runs = Vec::new();
+        let mut start = 0;
+        for run in self.split_runs(&[range.start, range.end]) {
+            let run_start = start;
+            start += run.text.len();
+            if run_start >= range.start {
+                runs.extend(inserted.take());
+            }
+            if run_start < range.start || run_start >= range.end {
+                runs.push(run);
+            }
+        }
+        runs.extend(inserted);
+        self.text.content.replace_range(range, text);
+        self.set_runs(runs);
+    }
+
+    /// Changes the format of `range`.
+    pub fn set_format(&mut self, range: Range<usize>, change: impl Fn(&mut Format)) {
+        let mut runs = self.split_runs(&[range.start, range.end]);
+        let mut start = 0;
+        for run in &mut runs {
+            if start >= range.start && start < range.end {
+                change(&mut run.format);
+            }
+            start += run.text.len();
+        }
+        self.set_runs(runs);
+    }
+
+    /// Recomputes sub- and superscripts from the content, see
+    /// [`chemistry_scripts`].
+    pub fn apply_chemistry(&mut self) {
+        self.set_format(0..self.content().len(), |format| {
+            format.script = Script::Normal
+        });
+        for (range, script) in chemistry_scripts(self.content()) {
+            self.set_format(range, |format| format.script = script);
+        }
+    }
+
+    /// Runs split so that each of `offsets` starts a run.
+    fn split_runs(&self, offsets: &[usize]) -> Vec<Run> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        for run in self.runs().iter() {
+            let end = start + run.text.len();
+            let mut cuts: Vec<usize> = offsets
+                .iter()
+                .copied()
+                .filter(|offset| *offset > start && *offset < end)
+                .collect();
+            cuts.sort_unstable();
+            cuts.dedup();
+            let mut from = start;
+            for cut in cuts.into_iter().chain([end]) {
+                runs.push(Run {
+                    text: run.text[from - start..cut - start].to_string(),
+                    format: run.format,
+                });
+                from = cut;
+            }
+            start = end;
+        }
+        runs
+    }
+
+    /// Stores `runs`, merging neighbours of the same format. Plain text
+    /// keeps no runs at all.
+    fn set_runs(&mut self, runs: Vec<Run>) {
+        let mut merged: Vec<Run> = Vec::new();
+        for run in runs.into_iter().filter(|run| !run.text.is_empty()) {
+            match merged.last_mut() {
+                Some(last) if last.format == run.format => last.text.push_str(&run.text),
+                _ => merged.push(run),
+            }
+        }
+        if merged.iter().all(|run| run.format == Format::default()) {
+            merged.clear();
+        }
+        self.runs = merged;
+    }
+
+    pub fn size(&self) -> f64 {
+        self.text.size.0 as f64
+    }
+
+    pub fn line_height(&self) -> f64 {
+        self.text.line_height.to_absolute(self.text.size).0 as f64
+    }
+
+    /// The font the text is set in, the bundled one when it is missing.
+    pub fn font(&self) -> Font {
+        font::of(self.text.font.family)
+    }
+
+    /// The lines of the text with their runs measured and placed.
+    pub fn layout(&self) -> Vec<Line> {
+        let size = self.size();
+        let line_height = self.line_height();
+        let align_x = horizontal_alignment(self.text.align_x);
+        let align_y = vertical_alignment(self.text.align_y);
+        let count = self.content().split('\n').count() as f64;
+        // The block of lines is aligned as a whole.
+        let first = self.text.position.y as f64
+            - match align_y {
+                VerticalAlignment::Top => 0.0,
+                VerticalAlignment::Center => (count - 1.0) * line_height / 2.0,
+                VerticalAlignment::Bottom => (count - 1.0) * line_height,
+            };
+        let font = self.font();
+        let typeface = font.typeface;
+        let glyph_height = typeface.ascender(size) - typeface.descender(size);
+
+        let runs = self.runs();
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for (index, text) in self.content().split('\n').enumerate() {
+            let range = start..start + text.len();
+            start = range.end + 1;
+            let anchor = Point::new(0.0, first + index as f64 * line_height);
+            let baseline = typeface
+                .origin("", anchor, size, HorizontalAlignment::Left, align_y)
+                .y;
+
+            let mut segments = Vec::new();
+            let mut run_start = 0;
+            let mut width = 0.0;
+            for run in runs.iter() {
+                let run_range = run_start..run_start + run.text.len();
+                run_start = run_range.end;
+                let from = run_range.start.max(range.start);
+                let to = run_range.end.min(range.end);
+                if from >= to {
+                    continue;
+                }
+                let (scale, shift) = match run.format.script {
+                    Script::Normal => (1.0, 0.0),
+                    Script::Sub => (SCRIPT_SIZE, SUBSCRIPT_SHIFT * size),
+                    Script::Super => (SCRIPT_SIZE, -SUPERSCRIPT_SHIFT * size),
+                };
+                let segment_size = size * scale;
+                let segment_width = typeface.width(&self.content()[from..to], segment_size);
+                segments.push(Segment {
+                    range: from..to,
+                    format: run.format,
+                    font,
+                    size: segment_size,
+                    origin: Point::new(width, baseline + shift),
+                    width: segment_width,
+                });
+                width += segment_width;
+            }
+
+            let x = self.text.position.x as f64;
+            let left = match align_x {
+                HorizontalAlignment::Left => x,
+                HorizontalAlignment::Center => x - width / 2.0,
+                HorizontalAlignment::Right => x - width,
+            };
+            for segment in &mut segments {
+                segment.origin.x += left;
+            }
+            lines.push(Line {
+                range,
+                baseline,
+                top: baseline - typeface.ascender(size) - (line_height - glyph_height) / 2.0,
+                left,
+                width,
+                segments,
+            });
+        }
+        lines
+    }
+}
+
+impl Line {
+    /// Where the caret sits on the baseline for byte `offset` of the line.
+    pub fn x_of(&self, content: &str, offset: usize) -> f64 {
+        for segment in &self.segments {
+            if offset <= segment.range.end {
+                let before = &content[segment.range.start..offset.max(segment.range.start)];
+                return segment.origin.x + segment.font.typeface.width(before, segment.size);
+            }
+        }
+        self.left + self.width
+    }
+
+    /// The byte offset on the line closest to `x`.
+    pub fn offset_at(&self, content: &str, x: f64) -> usize {
+        for segment in &self.segments {
+            let mut left = segment.origin.x;
+            for (index, c) in content[segment.range.clone()].char_indices() {
+                let advance = segment
+                    .font
+                    .typeface
+                    .width(c.encode_utf8(&mut [0; 4]), segment.size);
+                if x < left + advance / 2.0 {
+                    return segment.range.start + index;
+                }
+                left += advance;
+            }
+        }
+        self.range.end
+    }
+}
+
+pub(crate) fn horizontal_alignment(alignment: Alignment) -> HorizontalAlignment {
+    match alignment {
+        Alignment::Center => HorizontalAlignment::Center,
+        Alignment::Right => HorizontalAlignment::Right,
+        Alignment::Default | Alignment::Left | Alignment::Justified => HorizontalAlignment::Left,
+    }
+}
+
+pub(crate) fn vertical_alignment(alignment: iced::alignment::Vertical) -> VerticalAlignment {
+    match alignment {
+        iced::alignment::Vertical::Top => VerticalAlignment::Top,
+        iced::alignment::Vertical::Center => VerticalAlignment::Center,
+        iced::alignment::Vertical::Bottom => VerticalAlignment::Bottom,
+    }
+}
+
+/// Group abbreviations written like elements in formulas.
+const ABBREVIATIONS: [&str; 14] = [
+    "Me", "Et", "Pr", "Bu", "Ph", "Ac", "Bn", "Bz", "Ts", "Tf", "Ms", "Boc", "Cbz", "Ar",
+];
+/// Units whose exponents are superscripted, as in "mol-1" or "cm2".
+const UNITS: [&str; 18] = [
+    "mol", "s", "min", "h", "m", "cm", "mm", "nm", "L", "mL", "g", "mg", "kg", "K", "J", "kJ",
+    "cal", "kcal",
+];
+
+/// Sub- and superscripts a chemist would apply to `content`: digits in
+/// formulas such as "CH2Cl2" or "(CH3)2CO" are subscripted, charges such
+/// as "Fe3+" or "SO42-" and unit exponents such as "mol-1" superscripted.
+pub fn chemistry_scripts(content: &str) -> Vec<(Range<usize>, Script)> {
+    let mut scripts = Vec::new();
+    let separators = |c: char| c.is_whitespace() || matches!(c, '/' | ',' | ';' | ':');
+    let mut start = 0;
+    for token in content.split(separators) {
+        let offset = start;
+        start += token.len()
+            + content[start + token.len()..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+        let token = token.trim_end_matches('.');
+        if token.is_empty() {
+            continue;
+        }
+        if let Some(exponent) = unit_exponent(token) {
+            scripts.push((
+                offset + exponent.start..offset + exponent.end,
+                Script::Super,
+            ));
+        } else if let Some(token_scripts) = formula_scripts(token) {
+            scripts.extend(
+                token_scripts
+                    .into_iter()
+                    .map(|(range, script)| (offset + range.start..offset + range.end, script)),
+            );
+        }
+    }
+    scripts
+}
+
+/// The exponent of a unit like "mol-1", "s−1" or "cm2".
+fn unit_exponent(token: &str) -> Option<Range<usize>> {
+    let unit_end = token.find(|c: char| !c.is_ascii_alphabetic())?;
+    if !UNITS.contains(&&token[..unit_end]) {
+        return None;
+    }
+    let exponent = &token[unit_end..];
+    let digits = exponent.trim_start_matches(['-', '−']);
+    (!digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+        .then_some(unit_end..token.len())
+}
+
+/// Scripts of a formula, `None` if `token` doesn't read as one.
+fn formula_scripts(token: &str) -> Option<Vec<(Range<usize>, Script)>> {
+    let bytes = token.as_bytes();
+    if !token.is_ascii() || !bytes.iter().any(u8::is_ascii_uppercase) {
+        return None;
+    }
+
+    // A charge ends the formula: a sign with at most one digit before it,
+    // which belongs to the charge when it follows a letter ("Fe3+") or
+    // another digit ("SO42-").
+    let mut body = token.len();
+    let mut charge = None;
+    if bytes.last().is_some_and(|b| matches!(b, b'+' | b'-')) {
+        let mut charge_start = token.len() - 1;
+        if charge_start > 0 && bytes[charge_start - 1].is_ascii_digit() {
+            charge_start -= 1;
+        }
+        body = charge_start;
+        charge = Some(charge_start..token.len());
+    }
+
+    let mut scripts = Vec::new();
+    let mut index = 0;
+    // Leading digits are a coefficient, as in "2H2O".
+    while index < body && bytes[index].is_ascii_digit() {
+        index += 1;
+    }
+    let mut letters = false;
+    while index < body {
+        let rest = &token[index..body];
+        let c = bytes[index];
+        if c.is_ascii_digit() {
+            let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+            let after = bytes[index - 1];
+            if !(after.is_ascii_alphabetic() || matches!(after, b')' | b']')) {
+                return None;
+            }
+            scripts.push((index..index + digits, Script::Sub));
+            index += digits;
+        } else if matches!(c, b'(' | b')' | b'[' | b']') {
+            index += 1;
+        } else if c.is_ascii_uppercase() {
+            let symbol = [3, 2, 1].into_iter().find(|length| {
+                rest.len() >= *length
+                    && rest[1..*length].bytes().all(|b| b.is_ascii_lowercase())
+                    && (ABBREVIATIONS.contains(&&rest[..*length])
+                        || Element::from_symbol(&rest[..*length]).is_some())
+            })?;
+            index += symbol;
+            letters = true;
+        } else {
+            return None;
+        }
+    }
+    if !letters {
+        return None;
+    }
+    scripts.extend(charge.map(|charge| (charge, Script::Super)));
+    Some(scripts)
+}
+
+/// The Greek letter typed with `c` on a Symbol keyboard, or the Latin letter
+/// for a Greek one.
+pub fn greek(c: char) -> Option<char> {
+    const LATIN: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const GREEK: &str = "αβχδεφγηιϕκλμνοπθρστυϖωξψζΑΒΧΔΕΦΓΗΙϑΚΛΜΝΟΠΘΡΣΤΥςΩΞΨΖ";
+    let latin = LATIN.chars().position(|l| l == c);
+    let greek = GREEK.chars().position(|g| g == c);
+    match (latin, greek) {
+        (Some(index), _) => GREEK.chars().nth(index),
+        (_, Some(index)) => LATIN.chars().nth(index),
+        _ => None,
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_normal(script: &Script) -> bool {
+    *script == Script::Normal
+}
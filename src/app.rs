@@ -2,12 +2,23 @@ use core::f64;
 use iced::advanced::mouse::Click;
 use iced::widget::canvas;
 use iced::widget::canvas::Text;
+use iced::widget::stack;
+use std::path::PathBuf;
 
 use iced::Element;
 use iced::Point;
 use serde::{Deserialize, Serialize};
 
-use crate::{Message, canvas::Canvas, concept::Concept, shape::Shape, tool::Tool};
+use molecular::annotation::Annotation;
+use molecular::shape::Shape;
+use molecular::spatial::SpatialIndex;
+use molecular::style::Field;
+use molecular::{Document, Item};
+
+use crate::editor::Editor;
+use crate::history::History;
+use crate::ime::InputMethodArea;
+use crate::{Message, canvas::Canvas, canvas::Sheet, tool::Drag, tool::Tool};
 
 #[derive(Serialize, Deserialize)]
 #[serde(remote = "Point")]
@@ -16,66 +27,159 @@ pub struct PointDef {
     y: f32,
 }
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct App {
+    #[serde(default)]
     pub(crate) selected_tool: Tool,
-    pub(crate) shape_storage: Vec<Shape>,
-    pub(crate) concept_storage: Vec<Concept>,
-    #[serde(with = "PointDef")]
+    #[serde(flatten)]
+    pub(crate) document: Document,
+    #[serde(with = "PointDef", default)]
     pub(crate) position: iced::Point,
     #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) show_style_panel: bool,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) show_layers_panel: bool,
+    /// Text of the style setting being edited, which may not parse yet.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) style_draft: Option<(Field, String)>,
+    #[serde(skip_serializing, skip_deserializing)]
     pub(crate) panning_starting_pos: Option<iced::Point>,
     #[serde(skip_serializing, skip_deserializing)]
     pub(crate) drawing_starting_pos: Option<iced::Point>,
     #[serde(skip_serializing, skip_deserializing)]
     pub(crate) drawing_ending_pos: Option<iced::Point>,
     #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) selection: Vec<Item>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) dragging: Option<Drag>,
+    #[serde(skip_serializing, skip_deserializing)]
     pub(crate) previous_click: Option<Click>,
     #[serde(skip_serializing, skip_deserializing)]
-    pub(crate) current_text_field: Option<Text>,
+    pub(crate) current_text_field: Option<Editor>,
+    /// Where the items of the document are, updated with every change.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) index: SpatialIndex,
+    /// The document as drawn on the canvas, cleared after every change.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) cache: canvas::Cache,
+    /// Where the document was loaded from or last saved to.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) path: Option<PathBuf>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) history: History,
+    /// What went wrong or needs telling after the last action, shown under
+    /// the menu until the next one.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) notice: Option<String>,
 }
 
 impl App {
+    /// A tab with an empty document.
+    pub fn untitled() -> Self {
+        Self {
+            document: Document::default(),
+            index: SpatialIndex::default(),
+            current_text_field: None,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the document changed since it was last saved.
+    pub fn dirty(&self) -> bool {
+        self.history.changed()
+    }
+
+    /// The name of the tab, marked while there are unsaved changes.
+    pub fn title(&self) -> String {
+        let name = self
+            .path
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .map_or("untitled".into(), |name| name.to_string_lossy());
+        if self.dirty() {
+            format!("{name} •")
+        } else {
+            name.into_owned()
+        }
+    }
+
     pub fn view(&'_ self) -> Element<'_, Message> {
-        canvas(Canvas {
-            shapes: &self.shape_storage,
+        let sheet = Sheet {
+            document: &self.document,
+            index: &self.index,
+            cache: &self.cache,
+            coords: self.position,
+        };
+        let canvas = canvas(Canvas {
+            document: &self.document,
+            index: &self.index,
             coords: self.position,
             panning_starting_pos: &self.panning_starting_pos,
             drawing_starting_pos: &self.drawing_starting_pos,
             drawing_ending_pos: &self.drawing_ending_pos,
             selected_tool: &self.selected_tool,
+            selection: &self.selection,
+            dragging: self.dragging,
             previous_click: &self.previous_click,
-            current_text_field: self.current_text_field.clone(),
+            current_text_field: self.current_text_field.as_ref(),
         })
         .width(iced::Length::Fill)
-        .height(iced::Length::Fill)
-        .into()
+        .height(iced::Length::Fill);
+
+        let editor = self
+            .current_text_field
+            .as_ref()
+            .filter(|_| matches!(self.selected_tool, Tool::Typing));
+        let caret = editor.map(|editor| {
+            let caret = editor.caret_position();
+            iced::Rectangle::new(
+                Point::new(
+                    caret.x as f32 + self.position.x,
+                    caret.y as f32 + self.position.y,
+                ),
+                iced::Size::new(1.0, editor.caret_height() as f32),
+            )
+        });
+        let preedit = editor.map_or("", |editor| editor.preedit.as_str());
+        InputMethodArea::new(stack![sheet, canvas], caret, preedit).into()
     }
 }
 
 impl Default for App {
     fn default() -> Self {
-        Self {
-            selected_tool: Tool::default(),
-            shape_storage: (0..10)
+        let document = Document {
+            shapes: (0..10)
                 .map(|i| {
-                    Shape::Point(kurbo::Point {
+                    Shape::point(kurbo::Point {
                         x: i as f64 * 10.0,
                         y: i as f64 * 10.0,
                     })
                 })
                 .collect(),
-            concept_storage: Vec::new(),
+            ..Document::default()
+        };
+        Self {
+            selected_tool: Tool::default(),
+            index: SpatialIndex::new(&document),
+            cache: canvas::Cache::default(),
+            document,
+            show_style_panel: false,
+            show_layers_panel: false,
+            style_draft: None,
             position: iced::Point::new(0.0, 0.0),
             panning_starting_pos: None,
             drawing_starting_pos: None,
             drawing_ending_pos: None,
+            selection: Vec::new(),
+            dragging: None,
             previous_click: None,
-            current_text_field: Some({
-                let mut text = Text::default();
-                text.content = "penis".to_string();
-                text
-            }),
+            current_text_field: Some(Editor::new(Annotation::new(Text {
+                content: "penis".to_string(),
+                ..Default::default()
+            }))),
+            path: None,
+            history: History::default(),
+            notice: None,
         }
     }
 }
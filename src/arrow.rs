@@ -0,0 +1,491 @@
+use kurbo::{BezPath, CubicBez, ParamCurve, Point, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::concept::Concept;
+use crate::element::Element;
+use crate::molecule::{DECORATION_DISTANCE, DecorationKind, Molecule};
+use crate::shape::CubicBezDef;
+use crate::style::Appearance;
+
+const HEAD_LENGTH: f64 = 12.0;
+const HEAD_WIDTH: f64 = 5.0;
+/// Where the shaft meets the back of the head, measured from the tip.
+const NOTCH: f64 = HEAD_LENGTH * 0.7;
+/// Distance between the two shafts of equilibrium and retrosynthetic arrows.
+const SHAFT_SPACING: f64 = 6.0;
+/// How close to an atom a curved arrow end has to be dropped to attach to it.
+const ATOM_SNAP: f64 = 8.0;
+/// How close to the middle of a bond an end has to be to attach to it.
+const BOND_SNAP: f64 = 6.0;
+/// Arrow ends attached to an atom stop this far short of it.
+const ATOM_GAP: f64 = 9.0;
+/// Radius of the draggable handles on the points of a curved arrow.
+pub const HANDLE_RADIUS: f64 = 5.0;
+/// How far from an atom an arrow end can be and still attach to it or its
+/// electrons.
+pub const REACH: f64 = DECORATION_DISTANCE + ATOM_SNAP;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArrowHead {
+    /// Two barbs, moving an electron pair.
+    Full,
+    /// One barb (fishhook), moving a single electron.
+    Half,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArrowKind {
+    Forward,
+    Equilibrium,
+    Retrosynthetic,
+    Resonance,
+    /// Electron-pushing arrow following the whole curve.
+    Curved(ArrowHead),
+}
+
+/// What an end of a curved arrow is attached to. `concept` indexes the
+/// document's concepts and `molecule` the list returned by
+/// [`Concept::molecules`].
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Anchor {
+    Atom {
+        concept: usize,
+        molecule: usize,
+        atom: usize,
+    },
+    /// The middle of a bond.
+    Bond {
+        concept: usize,
+        molecule: usize,
+        bond: usize,
+    },
+    /// A lone pair of `atom`, in the direction `angle` (radians) from it.
+    LonePair {
+        concept: usize,
+        molecule: usize,
+        atom: usize,
+        angle: f64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Arrow {
+    pub kind: ArrowKind,
+    /// Straight arrows only use the end points, curved arrows the whole curve.
+    #[serde(with = "CubicBezDef")]
+    pub path: CubicBez,
+    #[serde(default)]
+    pub tail_anchor: Option<Anchor>,
+    #[serde(default)]
+    pub head_anchor: Option<Anchor>,
+    #[serde(default, skip_serializing_if = "Appearance::is_default")]
+    pub appearance: Appearance,
+}
+
+/// Backend independent outline of an arrow, in document coordinates.
+#[derive(Clone, Debug, Default)]
+pub struct ArrowGeometry {
+    pub strokes: Vec<BezPath>,
+    pub fills: Vec<BezPath>,
+}
+
+impl Arrow {
+    pub fn new(kind: ArrowKind, tail: Point, head: Point) -> Self {
+        let path = match kind {
+            // Curved arrows start out bowing to the left of their direction.
+            ArrowKind::Curved(_) => {
+                let d = head - tail;
+                let bow = Vec2::new(d.y, -d.x) * 0.4;
+                CubicBez::new(tail, tail + d / 3.0 + bow, tail + d * 2.0 / 3.0 + bow, head)
+            }
+            _ => CubicBez::new(
+                tail,
+                tail.lerp(head, 1.0 / 3.0),
+                tail.lerp(head, 2.0 / 3.0),
+                head,
+            ),
+        };
+        Self {
+            kind,
+            path,
+            tail_anchor: None,
+            head_anchor: None,
+            appearance: Appearance::default(),
+        }
+    }
+
+    /// Like [`Arrow::new`], but curved arrows attach their ends to whatever
+    /// atom, bond or lone pair of `concepts` they start or end on.
+    pub fn anchored(kind: ArrowKind, tail: Point, head: Point, concepts: &[Concept]) -> Self {
+        let mut arrow = Self::new(kind, tail, head);
+        if let ArrowKind::Curved(_) = kind {
+            arrow.tail_anchor = Anchor::at(concepts, tail);
+            arrow.head_anchor = Anchor::at(concepts, head);
+            arrow.follow_anchors(concepts);
+        }
+        arrow
+    }
+
+    /// Moves the anchored ends onto their atoms, bonds or lone pairs, keeping
+    /// the control points where they were relative to their end. Anchors
+    /// whose target no longer exists are dropped.
+    pub fn follow_anchors(&mut self, concepts: &[Concept]) {
+        if let Some(anchor) = self.tail_anchor {
+            match anchor.position(concepts, self.path.p1) {
+                Some(tail) => {
+                    self.path.p1 += tail - self.path.p0;
+                    self.path.p0 = tail;
+                }
+                None => self.tail_anchor = None,
+            }
+        }
+        if let Some(anchor) = self.head_anchor {
+            match anchor.position(concepts, self.path.p2) {
+                Some(head) => {
+                    self.path.p2 += head - self.path.p3;
+                    self.path.p3 = head;
+                }
+                None => self.head_anchor = None,
+            }
+        }
+    }
+
+    /// Index of the curve point whose handle is under `point`, preferring the
+    /// control points over the ends they may overlap.
+    pub fn handle_at(&self, point: Point) -> Option<usize> {
+        [1, 2, 0, 3].into_iter().find(|index| {
+            let handle = [self.path.p0, self.path.p1, self.path.p2, self.path.p3][*index];
+            handle.distance(point) <= HANDLE_RADIUS
+        })
+    }
+
+    /// The four points of the curve, tail, control points and head.
+    pub fn points_mut(&mut self) -> [&mut Point; 4] {
+        let path = &mut self.path;
+        [&mut path.p0, &mut path.p1, &mut path.p2, &mut path.p3]
+    }
+
+    pub fn tail(&self) -> Point {
+        self.path.p0
+    }
+
+    pub fn head(&self) -> Point {
+        self.path.p3
+    }
+
+    pub fn geometry(&self) -> ArrowGeometry {
+        let mut geometry = ArrowGeometry::default();
+        let (tail, head) = (self.tail(), self.head());
+        let Some(direction) = unit(head - tail) else {
+            return geometry;
+        };
+        let normal = Vec2::new(direction.y, -direction.x);
+
+        match self.kind {
+            ArrowKind::Forward => {
+                geometry.strokes.push(line(tail, head - direction * NOTCH));
+                geometry.fills.push(full_head(head, direction));
+            }
+            ArrowKind::Resonance => {
+                geometry
+                    .strokes
+                    .push(line(tail + direction * NOTCH, head - direction * NOTCH));
+                geometry.fills.push(full_head(head, direction));
+                geometry.fills.push(full_head(tail, -direction));
+            }
+            ArrowKind::Equilibrium => {
+                let offset = normal * (SHAFT_SPACING / 2.0);
+                geometry.strokes.push(line(tail + offset, head + offset));
+                geometry.strokes.push(line(tail - offset, head - offset));
+                geometry
+                    .fills
+                    .push(half_head(head + offset, direction, normal));
+                geometry
+                    .fills
+                    .push(half_head(tail - offset, -direction, -normal));
+            }
+            ArrowKind::Retrosynthetic => {
+                let offset = normal * (SHAFT_SPACING / 2.0);
+                let stop = head - direction * (SHAFT_SPACING / 2.0);
+                geometry.strokes.push(line(tail + offset, stop + offset));
+                geometry.strokes.push(line(tail - offset, stop - offset));
+                let back = head - direction * HEAD_LENGTH;
+                let mut chevron = BezPath::new();
+                chevron.move_to(back + normal * (SHAFT_SPACING + HEAD_WIDTH / 2.0));
+                chevron.line_to(head);
+                chevron.line_to(back - normal * (SHAFT_SPACING + HEAD_WIDTH / 2.0));
+                geometry.strokes.push(chevron);
+            }
+            ArrowKind::Curved(head_kind) => {
+                let mut curve = BezPath::new();
+                curve.move_to(self.path.p0);
+                let shortened = self.path.subsegment(0.0..self.shaft_end());
+                curve.curve_to(shortened.p1, shortened.p2, shortened.p3);
+                geometry.strokes.push(curve);
+
+                let tangent = unit(head - self.path.eval(0.95)).unwrap_or(direction);
+                let tangent_normal = Vec2::new(tangent.y, -tangent.x);
+                geometry.fills.push(match head_kind {
+                    ArrowHead::Full => full_head(head, tangent),
+                    ArrowHead::Half => half_head(head, tangent, tangent_normal),
+                });
+            }
+        }
+
+        geometry
+    }
+
+    /// Curve parameter where the shaft of a curved arrow meets its head.
+    fn shaft_end(&self) -> f64 {
+        let length = self.path.p0.distance(self.path.p3).max(HEAD_LENGTH);
+        (1.0 - HEAD_LENGTH / length * 0.8).clamp(0.5, 1.0)
+    }
+}
+
+impl Anchor {
+    /// The closest atom, bond middle or lone pair of a heteroatom near `point`,
+    /// in that order of preference.
+    pub fn at(concepts: &[Concept], point: Point) -> Option<Self> {
+        Self::among(concepts, 0..concepts.len(), point)
+    }
+
+    /// Like [`Anchor::at`], only looking at the concepts numbered
+    /// `candidates`, e.g. those a spatial index finds within [`REACH`] of
+    /// `point`.
+    pub fn among(
+        concepts: &[Concept],
+        candidates: impl IntoIterator<Item = usize>,
+        point: Point,
+    ) -> Option<Self> {
+        let molecules = candidates.into_iter().flat_map(|concept| {
+            concepts[concept]
+                .molecules()
+                .into_iter()
+                .enumerate()
+                .map(move |(molecule, m)| (concept, molecule, m))
+        });
+        let mut atom: Option<(f64, Self)> = None;
+        let mut bond: Option<(f64, Self)> = None;
+        let mut lone_pair: Option<(f64, Self)> = None;
+        let closer =
+            |best: &Option<(f64, Self)>, distance: f64| best.is_none_or(|(d, _)| distance < d);
+
+        for (concept, molecule, m) in molecules {
+            for (index, data) in m.atoms.iter().enumerate() {
+                let offset = point - data.position;
+                let distance = offset.hypot();
+                if distance <= ATOM_SNAP && closer(&atom, distance) {
+                    atom = Some((
+                        distance,
+                        Self::Atom {
+                            concept,
+                            molecule,
+                            atom: index,
+                        },
+                    ));
+                }
+                // Electrons drawn on the atom catch arrows near them.
+                let drawn = data
+                    .decorations
+                    .iter()
+                    .filter(|decoration| {
+                        matches!(
+                            decoration.kind,
+                            DecorationKind::LonePair | DecorationKind::Radical
+                        )
+                    })
+                    .map(|decoration| {
+                        let position = decoration.position(data.position);
+                        (point.distance(position), decoration.angle)
+                    })
+                    .filter(|(from_pair, _)| *from_pair <= ATOM_SNAP)
+                    .min_by(|a, b| a.0.total_cmp(&b.0));
+                let (from_pair, angle) =
+                    drawn.unwrap_or(((distance - DECORATION_DISTANCE).abs(), offset.atan2()));
+                if (data.element != Element::C || drawn.is_some())
+                    && distance > ATOM_SNAP
+                    && from_pair <= ATOM_SNAP
+                    && closer(&lone_pair, from_pair)
+                {
+                    lone_pair = Some((
+                        from_pair,
+                        Self::LonePair {
+                            concept,
+                            molecule,
+                            atom: index,
+                            angle,
+                        },
+                    ));
+                }
+            }
+            for (index, data) in m.bonds.iter().enumerate() {
+                let (p0, p1) = (m.atoms[data.begin].position, m.atoms[data.end].position);
+                let distance = point.distance(p0.midpoint(p1));
+                if distance <= BOND_SNAP && closer(&bond, distance) {
+                    bond = Some((
+                        distance,
+                        Self::Bond {
+                            concept,
+                            molecule,
+                            bond: index,
+                        },
+                    ));
+                }
+            }
+        }
+
+        atom.or(bond).or(lone_pair).map(|(_, anchor)| anchor)
+    }
+
+    /// Where an arrow end attached here goes, given the control point next to
+    /// that end. `None` if the anchored object no longer exists.
+    pub fn position(&self, concepts: &[Concept], control: Point) -> Option<Point> {
+        match *self {
+            Self::Atom {
+                concept,
+                molecule,
+                atom,
+            } => {
+                let center = molecule_of(concepts, concept, molecule)?
+                    .atoms
+                    .get(atom)?
+                    .position;
+                Some(match unit(control - center) {
+                    Some(direction) => center + direction * ATOM_GAP,
+                    None => center,
+                })
+            }
+            Self::Bond {
+                concept,
+                molecule,
+                bond,
+            } => {
+                let m = molecule_of(concepts, concept, molecule)?;
+                let bond = m.bonds.get(bond)?;
+                Some(
+                    m.atoms[bond.begin]
+                        .position
+                        .midpoint(m.atoms[bond.end].position),
+                )
+            }
+            Self::LonePair {
+                concept,
+                molecule,
+                atom,
+                angle,
+            } => {
+                let center = molecule_of(concepts, concept, molecule)?
+                    .atoms
+                    .get(atom)?
+                    .position;
+                Some(center + Vec2::from_angle(angle) * DECORATION_DISTANCE)
+            }
+        }
+    }
+
+    /// The same anchor after the concepts were rearranged from `old` into
+    /// `new`, found by where the anchored atom or bond sits. Rearranging
+    /// concepts never moves the molecules themselves.
+    pub fn relocate(&self, old: &[Concept], new: &[Concept]) -> Option<Self> {
+        let (concept, molecule) = self.molecule();
+        let before = molecule_of(old, concept, molecule)?;
+        let same = |m: &Molecule| {
+            m.atoms.len() == before.atoms.len()
+                && m.bonds.len() == before.bonds.len()
+                && m.atoms
+                    .iter()
+                    .zip(&before.atoms)
+                    .all(|(a, b)| a.position == b.position)
+        };
+
+        new.iter().enumerate().find_map(|(concept, c)| {
+            let molecule = c.molecules().into_iter().position(same)?;
+            let mut anchor = *self;
+            match &mut anchor {
+                Self::Atom {
+                    concept: c,
+                    molecule: m,
+                    ..
+                }
+                | Self::Bond {
+                    concept: c,
+                    molecule: m,
+                    ..
+                }
+                | Self::LonePair {
+                    concept: c,
+                    molecule: m,
+                    ..
+                } => (*c, *m) = (concept, molecule),
+            }
+            Some(anchor)
+        })
+    }
+
+    pub fn concept(&self) -> usize {
+        self.molecule().0
+    }
+
+    /// The same anchor with its concept index moved up by `offset`, for
+    /// concepts appended after others.
+    pub fn shifted(mut self, offset: usize) -> Self {
+        match &mut self {
+            Self::Atom { concept, .. }
+            | Self::Bond { concept, .. }
+            | Self::LonePair { concept, .. } => *concept += offset,
+        }
+        self
+    }
+
+    fn molecule(&self) -> (usize, usize) {
+        match *self {
+            Self::Atom {
+                concept, molecule, ..
+            }
+            | Self::Bond {
+                concept, molecule, ..
+            }
+            | Self::LonePair {
+                concept, molecule, ..
+            } => (concept, molecule),
+        }
+    }
+}
+
+fn molecule_of(concepts: &[Concept], concept: usize, molecule: usize) -> Option<&Molecule> {
+    concepts.get(concept)?.molecules().get(molecule).copied()
+}
+
+fn unit(v: Vec2) -> Option<Vec2> {
+    let length = v.hypot();
+    (length > f64::EPSILON).then(|| v / length)
+}
+
+fn line(from: Point, to: Point) -> BezPath {
+    let mut path = BezPath::new();
+    path.move_to(from);
+    path.line_to(to);
+    path
+}
+
+fn full_head(tip: Point, direction: Vec2) -> BezPath {
+    let back = tip - direction * HEAD_LENGTH;
+    let normal = Vec2::new(direction.y, -direction.x);
+    let mut path = BezPath::new();
+    path.move_to(tip);
+    path.line_to(back + normal * HEAD_WIDTH);
+    path.line_to(tip - direction * NOTCH);
+    path.line_to(back - normal * HEAD_WIDTH);
+    path.close_path();
+    path
+}
+
+/// Single barb on the `side` of the shaft.
+fn half_head(tip: Point, direction: Vec2, side: Vec2) -> BezPath {
+    let mut path = BezPath::new();
+    path.move_to(tip);
+    path.line_to(tip - direction * HEAD_LENGTH + side * HEAD_WIDTH);
+    path.line_to(tip - direction * NOTCH);
+    path.close_path();
+    path
+}
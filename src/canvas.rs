@@ -1,23 +1,45 @@
+use iced::advanced::graphics::geometry::Renderer as _;
 use iced::advanced::mouse::Click;
+use iced::advanced::widget::Tree;
+use iced::advanced::{Layout, Renderer as _, Widget, layout, renderer};
+use iced::alignment;
 use iced::widget::canvas::{self, Frame, Path, Text};
-use iced::{Color, Rectangle, Renderer, Theme};
+use iced::widget::text::Alignment;
+use iced::{Color, Element, Length, Rectangle, Renderer, Theme};
 use iced::{Point, mouse};
 
+use molecular::annotation::Script;
+use molecular::arrow::{self, Arrow, ArrowKind};
+use molecular::figure::Figure;
+use molecular::grid::Grid;
+use molecular::page::Page;
+use molecular::render::{HorizontalAlignment, Primitive, Scene, VerticalAlignment};
+use molecular::shape::Shape;
+use molecular::spatial::SpatialIndex;
+use molecular::{Document, Item};
+
+use crate::editor::{Edit, Editor, Emphasis, Motion};
 use crate::message::Message;
-use crate::shape::Shape;
-use crate::tool::Tool;
+use crate::tool::{self, Drag, Tool};
+
+const HANDLE_COLOR: Color = Color::from_rgb(0.2, 0.5, 0.9);
+/// Outlines items reaching past the margins of the page.
+const OFF_PAGE_COLOR: Color = Color::from_rgb(0.95, 0.5, 0.1);
 
 // First, we define the data we need for drawing
 #[derive(Debug)]
 pub struct Canvas<'a> {
-    pub(crate) shapes: &'a Vec<Shape>,
+    pub(crate) document: &'a Document,
+    pub(crate) index: &'a SpatialIndex,
     pub(crate) coords: Point,
     pub(crate) panning_starting_pos: &'a Option<iced::Point>,
     pub(crate) drawing_starting_pos: &'a Option<iced::Point>,
     pub(crate) drawing_ending_pos: &'a Option<iced::Point>,
     pub(crate) selected_tool: &'a Tool,
+    pub(crate) selection: &'a [Item],
+    pub(crate) dragging: Option<Drag>,
     pub(crate) previous_click: &'a Option<Click>,
-    pub(crate) current_text_field: Option<Text>,
+    pub(crate) current_text_field: Option<&'a Editor>,
 }
 
 // Then, we implement the `Program` trait
@@ -33,58 +55,141 @@ impl<'a> canvas::Program<Message> for Canvas<'a> {
         bounds: Rectangle,
         _cursor: mouse::Cursor,
     ) -> Vec<canvas::Geometry> {
+        // The origin of the document is at the center of the canvas.
+        let translation = iced::Vector::new(
+            bounds.width / 2.0 + self.coords.x,
+            bounds.height / 2.0 + self.coords.y,
+        );
+
         let mut frame = Frame::new(renderer, bounds.size());
-        let center = frame.center();
+        frame.translate(translation);
+        let mut scene = Scene::new(self.document.style.clone());
 
-        frame.translate(iced::Vector::new(
-            center.x + self.coords.x,
-            center.y + self.coords.y,
-        ));
+        if let Some(draw_start) = self.drawing_starting_pos
+            && let Some(draw_end) = self.drawing_ending_pos
+            && let Tool::Arrow(kind) = self.selected_tool
+        {
+            let arrow = Arrow::anchored(
+                *kind,
+                kurbo::Point::new(draw_start.x as f64, draw_start.y as f64),
+                kurbo::Point::new(draw_end.x as f64, draw_end.y as f64),
+                &self.document.concepts,
+            );
+            scene.arrow(&arrow);
+        } else if let Some(draw_start) = self.drawing_starting_pos
+            && let Some(draw_end) = self.drawing_ending_pos
+            && let Tool::Figure(kind) = self.selected_tool
+        {
+            scene.figure(&Figure::new(
+                *kind,
+                kurbo::Point::new(draw_start.x as f64, draw_start.y as f64),
+                kurbo::Point::new(draw_end.x as f64, draw_end.y as f64),
+            ));
+        } else if let Some(draw_start) = self.drawing_starting_pos
+            && let Some(draw_end) = self.drawing_ending_pos
+            && let Tool::Draw = self.selected_tool
+        {
+            let path = Path::line(*draw_start, *draw_end);
+            let stroke = canvas::Stroke {
+                style: canvas::Style::Solid(self.document.style.color),
+                width: self.document.style.line_width as f32,
+                line_cap: canvas::LineCap::Round,
+                ..Default::default()
+            };
+            frame.stroke(&path, stroke);
+        }
 
-        if let Some(draw_start) = self.drawing_starting_pos {
-            if let Some(draw_end) = self.drawing_ending_pos {
-                let path = Path::line(*draw_start, *draw_end);
-                let stroke = canvas::Stroke {
-                    style: canvas::Style::Solid(Color::BLACK),
-                    width: 5.0,
-                    line_cap: canvas::LineCap::Round,
-                    ..Default::default()
-                };
-                frame.stroke(&path, stroke);
+        if let Some(editor) = self.current_text_field {
+            for rect in editor.selection_rects() {
+                frame.fill(
+                    &Path::rectangle(
+                        to_iced(rect.origin()),
+                        iced::Size::new(rect.width() as f32, rect.height() as f32),
+                    ),
+                    Color {
+                        a: 0.3,
+                        ..HANDLE_COLOR
+                    },
+                );
             }
+            scene.annotation(&editor.annotation);
         }
 
-        match &self.current_text_field {
-            Some(current_text) => {
-                current_text.draw_with(|path, color| frame.fill(&path, color));
-            }
-            _ => (),
+        draw_scene(&mut frame, &scene);
+
+        if let Some(editor) = self.current_text_field
+            && let Tool::Typing = self.selected_tool
+        {
+            let caret = editor.caret_position();
+            frame.stroke(
+                &Path::line(
+                    to_iced(caret),
+                    to_iced(caret + kurbo::Vec2::new(0.0, editor.caret_height())),
+                ),
+                canvas::Stroke {
+                    style: canvas::Style::Solid(editor.annotation.text.color),
+                    width: 1.0,
+                    ..Default::default()
+                },
+            );
         }
 
-        for shape in self.shapes {
-            match shape {
-                Shape::Point(point) => {
-                    let path = Path::circle(iced::Point::new(point.x as f32, point.y as f32), 5.0);
-                    frame.fill(&path, Color::BLACK);
-                }
-                Shape::Line(line) => {
-                    let path = Path::line(
-                        iced::Point::new(line.p0.x as f32, line.p0.y as f32),
-                        iced::Point::new(line.p1.x as f32, line.p1.y as f32),
-                    );
-                    let stroke = canvas::Stroke {
-                        style: canvas::Style::Solid(Color::BLACK),
-                        width: 5.0,
-                        line_cap: canvas::LineCap::Round,
-                        ..Default::default()
-                    };
-                    frame.stroke(&path, stroke);
+        let selection_stroke = canvas::Stroke {
+            style: canvas::Style::Solid(HANDLE_COLOR),
+            width: 1.0,
+            ..Default::default()
+        };
+        for item in self.selection {
+            let Some(bounds) = self.index.bounds(*item) else {
+                continue;
+            };
+            let bounds = bounds.inflate(3.0, 3.0);
+            frame.stroke(
+                &Path::rectangle(
+                    to_iced(bounds.origin()),
+                    iced::Size::new(bounds.width() as f32, bounds.height() as f32),
+                ),
+                selection_stroke,
+            );
+        }
+        if let Some(Drag::Selection { start, end }) = self.dragging {
+            let rect = kurbo::Rect::from_points(start, end);
+            frame.stroke(
+                &Path::rectangle(
+                    to_iced(rect.origin()),
+                    iced::Size::new(rect.width() as f32, rect.height() as f32),
+                ),
+                selection_stroke,
+            );
+        }
+
+        if let Tool::Pick = self.selected_tool {
+            if let Some(bounds) = self.index.bounds_of(self.selection) {
+                let handle = tool::rotation_handle(bounds);
+                let top = kurbo::Point::new(handle.x, bounds.y0 - 3.0);
+                frame.stroke(&Path::line(to_iced(top), to_iced(handle)), selection_stroke);
+                let circle = Path::circle(to_iced(handle), arrow::HANDLE_RADIUS as f32);
+                frame.fill(&circle, Color::WHITE);
+                frame.stroke(&circle, selection_stroke);
+            }
+            let is_editable = self.document.editable();
+            for (index, shape) in self.document.shapes.iter().enumerate() {
+                if let Shape::Arrow(arrow) = shape
+                    && let ArrowKind::Curved(_) = arrow.kind
+                    && is_editable(Item::Shape(index))
+                {
+                    draw_handles(&mut frame, arrow);
                 }
-                Shape::Text(text) => text.draw_with(|path, color| frame.fill(&path, color)),
             }
         }
 
-        vec![frame.into_geometry()]
+        let mut layers = vec![frame.into_geometry()];
+        if self.document.grid.rulers {
+            let mut rulers = Frame::new(renderer, bounds.size());
+            draw_rulers(&mut rulers, translation);
+            layers.push(rulers.into_geometry());
+        }
+        layers
     }
 
     fn update(
@@ -94,15 +199,36 @@ impl<'a> canvas::Program<Message> for Canvas<'a> {
         bounds: Rectangle,
         cursor: iced::advanced::mouse::Cursor,
     ) -> Option<canvas::Action<Message>> {
+        if !matches!(self.selected_tool, Tool::Typing)
+            && let Some(message) = self.clipboard_shortcut(event, bounds, cursor)
+        {
+            return Some(canvas::Action::publish(message));
+        }
+
         match self.selected_tool {
             Tool::Pick => {
                 // Only process events if the cursor is within the canvas bounds
-                let Some(cursor_position) = cursor.position_in(bounds) else {
-                    return None;
-                };
+                let cursor_position = cursor.position_in(bounds)?;
+
+                let normalized_position = iced::Point::new(
+                    (cursor_position.x - bounds.width / 2.0) - self.coords.x,
+                    (cursor_position.y - bounds.height / 2.0) - self.coords.y,
+                );
 
                 match event {
                     canvas::Event::Mouse(mouse_event) => match mouse_event {
+                        mouse::Event::ButtonPressed(mouse::Button::Left) => Some(
+                            canvas::Action::publish(Message::StartedDragging(normalized_position)),
+                        ),
+                        mouse::Event::ButtonReleased(mouse::Button::Left)
+                            if self.dragging.is_some() =>
+                        {
+                            Some(canvas::Action::publish(Message::StoppedDragging))
+                        }
+                        mouse::Event::CursorMoved { .. } if self.dragging.is_some() => Some(
+                            canvas::Action::publish(Message::Dragged(normalized_position)),
+                        ),
+
                         // 1. Detect Middle Click Press
                         mouse::Event::ButtonPressed(mouse::Button::Middle) => Some(
                             canvas::Action::publish(Message::StartedPanning(cursor_position)),
@@ -138,11 +264,9 @@ impl<'a> canvas::Program<Message> for Canvas<'a> {
                     _ => None,
                 }
             }
-            Tool::Draw => {
+            Tool::Draw | Tool::Arrow(_) | Tool::Figure(_) | Tool::Decorate(_) => {
                 // Only process events if the cursor is within the canvas bounds
-                let Some(cursor_position) = cursor.position_in(bounds) else {
-                    return None;
-                };
+                let cursor_position = cursor.position_in(bounds)?;
 
                 let normalized_position = iced::Point::new(
                     (cursor_position.x - bounds.width / 2.0) - self.coords.x,
@@ -153,13 +277,25 @@ impl<'a> canvas::Program<Message> for Canvas<'a> {
                     canvas::Event::Mouse(mouse_event) => match mouse_event {
                         // 1. Detect Left Click Press
                         mouse::Event::ButtonPressed(mouse::Button::Left) => {
-                            println!("previous click: {:?}", self.previous_click);
                             let new_click = Click::new(
                                 normalized_position,
                                 mouse::Button::Left,
                                 *self.previous_click,
                             );
                             match new_click.kind() {
+                                // Arrows, figures and decorations are drawn
+                                // on every click, only the bond tool starts
+                                // typing on double clicks.
+                                _ if matches!(
+                                    self.selected_tool,
+                                    Tool::Arrow(_) | Tool::Figure(_) | Tool::Decorate(_)
+                                ) =>
+                                {
+                                    Some(canvas::Action::publish(Message::StartedDrawing(
+                                        normalized_position,
+                                        new_click,
+                                    )))
+                                }
                                 iced::advanced::mouse::click::Kind::Single => {
                                     Some(canvas::Action::publish(Message::StartedDrawing(
                                         normalized_position,
@@ -211,38 +347,526 @@ impl<'a> canvas::Program<Message> for Canvas<'a> {
                     _ => None,
                 }
             }
-            Tool::Typing => match event {
-                iced::Event::Keyboard(event) => match event {
-                    iced::keyboard::Event::KeyPressed {
-                        key,
-                        modified_key: _,
-                        physical_key: _,
-                        location: _,
-                        modifiers: _,
-                        text: _,
-                        repeat: _,
-                    } => match key {
-                        iced::keyboard::Key::Named(nk) => match nk {
-                            iced::keyboard::key::Named::Escape => {
-                                Some(canvas::Action::publish(Message::Escape))
-                            }
-                            iced::keyboard::key::Named::Backspace => {
-                                Some(canvas::Action::publish(Message::Backspace))
-                            }
-                            iced::keyboard::key::Named::Space => {
-                                Some(canvas::Action::publish(Message::Whitespace))
+            Tool::Typing => self
+                .typing(event, bounds, cursor)
+                .map(canvas::Action::publish),
+        }
+    }
+}
+
+impl Canvas<'_> {
+    /// Keys, input method text and mouse presses while a text is edited.
+    /// Pressing the mouse outside the text finishes it.
+    fn typing(
+        &self,
+        event: &iced::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Option<Message> {
+        use iced::keyboard::key::Named;
+
+        let editor = self.current_text_field?;
+        let point = cursor.position_in(bounds).map(|position| {
+            kurbo::Point::new(
+                ((position.x - bounds.width / 2.0) - self.coords.x) as f64,
+                ((position.y - bounds.height / 2.0) - self.coords.y) as f64,
+            )
+        });
+        let edit = match event {
+            iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                key,
+                modifiers,
+                text,
+                ..
+            }) => {
+                let motion = |motion, word| {
+                    let motion = match (motion, modifiers.command()) {
+                        (Motion::Left, true) => word,
+                        (Motion::Right, true) => word,
+                        (Motion::Home, true) => Motion::DocumentStart,
+                        (Motion::End, true) => Motion::DocumentEnd,
+                        (motion, _) => motion,
+                    };
+                    if modifiers.shift() {
+                        Edit::Select(motion)
+                    } else {
+                        Edit::Move(motion)
+                    }
+                };
+                match key {
+                    iced::keyboard::Key::Named(Named::Escape) => {
+                        return Some(Message::FinishedTyping);
+                    }
+                    iced::keyboard::Key::Named(Named::Enter) => Edit::Insert("\n".to_string()),
+                    iced::keyboard::Key::Named(Named::Backspace) => Edit::Backspace,
+                    iced::keyboard::Key::Named(Named::Delete) => Edit::Delete,
+                    iced::keyboard::Key::Named(Named::ArrowLeft) => {
+                        motion(Motion::Left, Motion::WordLeft)
+                    }
+                    iced::keyboard::Key::Named(Named::ArrowRight) => {
+                        motion(Motion::Right, Motion::WordRight)
+                    }
+                    iced::keyboard::Key::Named(Named::ArrowUp) => motion(Motion::Up, Motion::Up),
+                    iced::keyboard::Key::Named(Named::ArrowDown) => {
+                        motion(Motion::Down, Motion::Down)
+                    }
+                    iced::keyboard::Key::Named(Named::Home) => motion(Motion::Home, Motion::Home),
+                    iced::keyboard::Key::Named(Named::End) => motion(Motion::End, Motion::End),
+                    iced::keyboard::Key::Character(c) if modifiers.command() => {
+                        match c.as_str() {
+                            "a" => Edit::SelectAll,
+                            "b" => Edit::Format(Emphasis::Bold),
+                            "i" => Edit::Format(Emphasis::Italic),
+                            // Ctrl+= subscripts, Ctrl+Shift+= (Ctrl++) superscripts.
+                            "=" if modifiers.shift() => {
+                                Edit::Format(Emphasis::Script(Script::Super))
                             }
-                            _ => None,
+                            "+" => Edit::Format(Emphasis::Script(Script::Super)),
+                            "=" => Edit::Format(Emphasis::Script(Script::Sub)),
+                            "g" => Edit::Greek,
+                            _ => return None,
+                        }
+                    }
+                    _ if modifiers.command() => return None,
+                    _ => {
+                        let text = text.as_ref()?;
+                        if text.chars().any(char::is_control) {
+                            return None;
+                        }
+                        Edit::Insert(text.to_string())
+                    }
+                }
+            }
+            iced::Event::InputMethod(iced::advanced::input_method::Event::Commit(text)) => {
+                Edit::Insert(text.clone())
+            }
+            iced::Event::InputMethod(iced::advanced::input_method::Event::Preedit(text, _)) => {
+                Edit::Preedit(text.clone())
+            }
+            iced::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let point = point?;
+                if !editor.bounds().contains(point) {
+                    return Some(Message::FinishedTyping);
+                }
+                Edit::Press(point)
+            }
+            iced::Event::Mouse(mouse::Event::CursorMoved { .. }) if editor.is_selecting() => {
+                Edit::Drag(point?)
+            }
+            iced::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => Edit::Release,
+            _ => return None,
+        };
+        Some(Message::Edited(edit))
+    }
+
+    /// Ctrl+C, Ctrl+X and Ctrl+V (Cmd on macOS). Pastes land under the
+    /// cursor, or in the middle of the view when it is elsewhere.
+    fn clipboard_shortcut(
+        &self,
+        event: &iced::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Option<Message> {
+        let iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+            key: iced::keyboard::Key::Character(c),
+            modifiers,
+            ..
+        }) = event
+        else {
+            return None;
+        };
+        if !modifiers.command() {
+            return None;
+        }
+        match c.as_str() {
+            "c" => Some(Message::Copy),
+            "x" => Some(Message::Cut),
+            "v" => {
+                let position = cursor
+                    .position_in(bounds)
+                    .unwrap_or(Point::new(bounds.width / 2.0, bounds.height / 2.0));
+                Some(Message::Paste(Point::new(
+                    (position.x - bounds.width / 2.0) - self.coords.x,
+                    (position.y - bounds.height / 2.0) - self.coords.y,
+                )))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn to_iced(point: kurbo::Point) -> Point {
+    Point::new(point.x as f32, point.y as f32)
+}
+
+fn to_path(bez: &kurbo::BezPath) -> Path {
+    Path::new(|b| {
+        for element in bez.elements() {
+            match *element {
+                kurbo::PathEl::MoveTo(p) => b.move_to(to_iced(p)),
+                kurbo::PathEl::LineTo(p) => b.line_to(to_iced(p)),
+                kurbo::PathEl::QuadTo(c, p) => b.quadratic_curve_to(to_iced(c), to_iced(p)),
+                kurbo::PathEl::CurveTo(c1, c2, p) => {
+                    b.bezier_curve_to(to_iced(c1), to_iced(c2), to_iced(p))
+                }
+                kurbo::PathEl::ClosePath => b.close(),
+            }
+        }
+    })
+}
+
+/// The document under the canvas, drawn in document coordinates so that
+/// panning only moves what was drawn. Everything else is drawn every frame
+/// by [`Canvas`] on top of it.
+pub struct Sheet<'a> {
+    pub(crate) document: &'a Document,
+    pub(crate) index: &'a SpatialIndex,
+    /// The document as last drawn, cleared whenever it changes.
+    pub(crate) cache: &'a canvas::Cache,
+    pub(crate) coords: Point,
+}
+
+impl<Message> Widget<Message, Theme, Renderer> for Sheet<'_> {
+    fn size(&self) -> iced::Size<Length> {
+        iced::Size::new(Length::Fill, Length::Fill)
+    }
+
+    fn layout(
+        &mut self,
+        _tree: &mut Tree,
+        _renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        layout::atomic(limits, Length::Fill, Length::Fill)
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        // The origin of the document is at the center of the canvas.
+        let translation = iced::Vector::new(
+            bounds.width / 2.0 + self.coords.x,
+            bounds.height / 2.0 + self.coords.y,
+        );
+        let origin = kurbo::Point::new(-translation.x as f64, -translation.y as f64);
+        let viewport =
+            kurbo::Rect::from_origin_size(origin, (bounds.width as f64, bounds.height as f64));
+        let region = drawn_region(viewport);
+
+        // Drawn again only when the document changed or the view left the
+        // region, which is redrawn around it then.
+        let document = self.cache.draw_with_bounds(
+            renderer,
+            Rectangle::new(
+                to_iced(region.origin()),
+                iced::Size::new(region.width() as f32, region.height() as f32),
+            ),
+            |frame| {
+                if let Some(page) = &self.document.page {
+                    draw_page(frame, page);
+                }
+                if self.document.grid.shown {
+                    draw_grid(frame, &self.document.grid, region);
+                }
+                let items = self.index.query(region);
+                let mut scene = Scene::new(self.document.style.clone());
+                for item in &items {
+                    self.document.draw(&mut scene, *item);
+                }
+                draw_scene(frame, &scene);
+
+                // Whatever printers could cut off.
+                if let Some(printable) = self.document.page.map(|page| page.printable()) {
+                    for bounds in items.iter().filter_map(|item| self.index.bounds(*item)) {
+                        if printable.union(bounds) != printable {
+                            let bounds = bounds.inflate(2.0, 2.0);
+                            frame.stroke(
+                                &Path::rectangle(
+                                    to_iced(bounds.origin()),
+                                    iced::Size::new(bounds.width() as f32, bounds.height() as f32),
+                                ),
+                                canvas::Stroke {
+                                    style: canvas::Style::Solid(OFF_PAGE_COLOR),
+                                    width: 1.0,
+                                    ..Default::default()
+                                },
+                            );
+                        }
+                    }
+                }
+            },
+        );
+
+        renderer.with_layer(bounds, |renderer| {
+            renderer.with_translation(
+                iced::Vector::new(bounds.x, bounds.y) + translation,
+                |renderer| renderer.draw_geometry(document),
+            );
+        });
+    }
+}
+
+impl<'a, Message: 'a> From<Sheet<'a>> for Element<'a, Message> {
+    fn from(sheet: Sheet<'a>) -> Self {
+        Element::new(sheet)
+    }
+}
+
+/// The part of the document drawn for `viewport`: half a viewport more on
+/// every side, moving in steps of half a viewport so that panning within it
+/// keeps what was drawn.
+pub(crate) fn drawn_region(viewport: kurbo::Rect) -> kurbo::Rect {
+    let step = kurbo::Vec2::new(viewport.width() / 2.0, viewport.height() / 2.0);
+    if step.x <= 0.0 || step.y <= 0.0 {
+        return viewport;
+    }
+    let corner = kurbo::Point::new(
+        ((viewport.x0 / step.x).floor() - 1.0) * step.x,
+        ((viewport.y0 / step.y).floor() - 1.0) * step.y,
+    );
+    kurbo::Rect::from_origin_size(corner, viewport.size() + (step * 3.0).to_size())
+}
+
+/// Draws `scene` with the iced renderer.
+fn draw_scene(frame: &mut Frame, scene: &Scene) {
+    for primitive in &scene.primitives {
+        match primitive {
+            Primitive::Stroke {
+                path,
+                width,
+                color,
+                dash,
+            } => {
+                let segments: Vec<f32> = dash.iter().map(|d| *d as f32).collect();
+                let stroke = canvas::Stroke {
+                    style: canvas::Style::Solid(*color),
+                    width: *width as f32,
+                    line_cap: canvas::LineCap::Round,
+                    line_join: canvas::LineJoin::Round,
+                    line_dash: canvas::LineDash {
+                        segments: &segments,
+                        offset: 0,
+                    },
+                };
+                frame.stroke(&to_path(path), stroke);
+            }
+            Primitive::Fill { path, color } => frame.fill(&to_path(path), *color),
+            Primitive::Text {
+                content,
+                position,
+                size,
+                color,
+                align_x,
+                align_y,
+                font,
+                style,
+            } => {
+                let text = Text {
+                    content: content.clone(),
+                    position: to_iced(*position),
+                    color: *color,
+                    size: (*size as f32).into(),
+                    align_x: match align_x {
+                        HorizontalAlignment::Left => Alignment::Left,
+                        HorizontalAlignment::Center => Alignment::Center,
+                        HorizontalAlignment::Right => Alignment::Right,
+                    },
+                    align_y: match align_y {
+                        VerticalAlignment::Top => alignment::Vertical::Top,
+                        VerticalAlignment::Center => alignment::Vertical::Center,
+                        VerticalAlignment::Bottom => alignment::Vertical::Bottom,
+                    },
+                    // The typeface it was measured with, which iced is given
+                    // as well.
+                    font: iced::Font {
+                        family: iced::font::Family::Name(font.typeface.family()),
+                        weight: if style.bold {
+                            iced::font::Weight::Bold
+                        } else {
+                            iced::font::Weight::Normal
+                        },
+                        style: if style.italic {
+                            iced::font::Style::Italic
+                        } else {
+                            iced::font::Style::Normal
                         },
-                        iced::keyboard::Key::Character(c) => Some(canvas::Action::publish(
-                            Message::Typing(c.chars().nth(0).unwrap()),
-                        )),
-                        iced::keyboard::Key::Unidentified => None,
+                        ..iced::Font::default()
                     },
-                    _ => None,
-                },
-                _ => None,
+                    ..Default::default()
+                };
+                text.draw_with(|path, color| frame.fill(&path, color));
+            }
+        }
+    }
+}
+
+/// The sheet of `page` with a shadow, and its margins dashed.
+fn draw_page(frame: &mut Frame, page: &Page) {
+    let rectangle = |rect: kurbo::Rect| {
+        Path::rectangle(
+            to_iced(rect.origin()),
+            iced::Size::new(rect.width() as f32, rect.height() as f32),
+        )
+    };
+    let sheet = page.rect();
+    frame.fill(
+        &rectangle(sheet + kurbo::Vec2::new(4.0, 4.0)),
+        Color::from_rgba(0.0, 0.0, 0.0, 0.15),
+    );
+    frame.fill(&rectangle(sheet), Color::WHITE);
+    frame.stroke(
+        &rectangle(sheet),
+        canvas::Stroke {
+            style: canvas::Style::Solid(Color::from_rgb(0.6, 0.6, 0.6)),
+            width: 1.0,
+            ..Default::default()
+        },
+    );
+    frame.stroke(
+        &rectangle(page.printable()),
+        canvas::Stroke {
+            style: canvas::Style::Solid(Color::from_rgb(0.75, 0.8, 0.9)),
+            width: 1.0,
+            line_dash: canvas::LineDash {
+                segments: &[4.0, 4.0],
+                offset: 0,
             },
+            ..Default::default()
+        },
+    );
+}
+
+/// Width of the rulers along the top and left edges of the canvas.
+const RULER_WIDTH: f32 = 16.0;
+/// Document units between the ticks of the rulers, and between the labelled
+/// ones.
+const RULER_TICK: f32 = 10.0;
+const RULER_LABEL: f32 = 100.0;
+
+/// Lines of `grid` across `viewport`, under the document.
+fn draw_grid(frame: &mut Frame, grid: &Grid, viewport: kurbo::Rect) {
+    let stroke = canvas::Stroke {
+        style: canvas::Style::Solid(Color::from_rgb(0.88, 0.88, 0.88)),
+        width: 0.5,
+        ..Default::default()
+    };
+    for line in grid.lines(viewport) {
+        frame.stroke(&Path::line(to_iced(line.p0), to_iced(line.p1)), stroke);
+    }
+}
+
+/// Rulers in document units along the top and left edges of `frame`, whose
+/// document origin is at `translation`.
+fn draw_rulers(frame: &mut Frame, translation: iced::Vector) {
+    let size = frame.size();
+    let background = Color::from_rgb(0.95, 0.95, 0.95);
+    frame.fill_rectangle(
+        Point::ORIGIN,
+        iced::Size::new(size.width, RULER_WIDTH),
+        background,
+    );
+    frame.fill_rectangle(
+        Point::ORIGIN,
+        iced::Size::new(RULER_WIDTH, size.height),
+        background,
+    );
+    let stroke = canvas::Stroke {
+        style: canvas::Style::Solid(Color::from_rgb(0.4, 0.4, 0.4)),
+        width: 1.0,
+        ..Default::default()
+    };
+    let label = |content: String, position: Point| Text {
+        content,
+        position,
+        color: Color::from_rgb(0.3, 0.3, 0.3),
+        size: 9.0.into(),
+        ..Default::default()
+    };
+
+    // Ticks at every multiple of `RULER_TICK` in view, from the screen
+    // position of the first one.
+    let ticks = |origin: f32, length: f32| {
+        let first = ((-origin) / RULER_TICK).ceil() as i64;
+        let last = ((length - origin) / RULER_TICK).floor() as i64;
+        (first..=last).map(move |tick| {
+            let value = tick as f32 * RULER_TICK;
+            (value, origin + value)
+        })
+    };
+    for (value, x) in ticks(translation.x, size.width) {
+        let major = value % RULER_LABEL == 0.0;
+        let length = if major {
+            RULER_WIDTH
+        } else {
+            RULER_WIDTH / 4.0
+        };
+        frame.stroke(
+            &Path::line(
+                Point::new(x, RULER_WIDTH - length),
+                Point::new(x, RULER_WIDTH),
+            ),
+            stroke,
+        );
+        if major {
+            frame.fill_text(label(value.to_string(), Point::new(x + 2.0, 1.0)));
+        }
+    }
+    for (value, y) in ticks(translation.y, size.height) {
+        let major = value % RULER_LABEL == 0.0;
+        let length = if major {
+            RULER_WIDTH
+        } else {
+            RULER_WIDTH / 4.0
+        };
+        frame.stroke(
+            &Path::line(
+                Point::new(RULER_WIDTH - length, y),
+                Point::new(RULER_WIDTH, y),
+            ),
+            stroke,
+        );
+        if major {
+            frame.fill_text(label(value.to_string(), Point::new(1.0, y + 2.0)));
+        }
+    }
+}
+
+/// Control polygon of a curved arrow with a handle on each of its points,
+/// filled where an end is anchored.
+fn draw_handles(frame: &mut Frame, arrow: &Arrow) {
+    let path = arrow.path;
+    let stroke = canvas::Stroke {
+        style: canvas::Style::Solid(HANDLE_COLOR),
+        width: 1.0,
+        ..Default::default()
+    };
+    frame.stroke(&Path::line(to_iced(path.p0), to_iced(path.p1)), stroke);
+    frame.stroke(&Path::line(to_iced(path.p3), to_iced(path.p2)), stroke);
+
+    let anchored = [
+        arrow.tail_anchor.is_some(),
+        false,
+        false,
+        arrow.head_anchor.is_some(),
+    ];
+    for (point, anchored) in [path.p0, path.p1, path.p2, path.p3]
+        .into_iter()
+        .zip(anchored)
+    {
+        let handle = Path::circle(to_iced(point), arrow::HANDLE_RADIUS as f32);
+        if anchored {
+            frame.fill(&handle, HANDLE_COLOR);
+        } else {
+            frame.fill(&handle, Color::WHITE);
+            frame.stroke(&handle, stroke);
         }
     }
 }
@@ -0,0 +1,517 @@
+//! Cahn-Ingold-Prelog stereodescriptors.
+//!
+//! Priorities are ranked on the hierarchical digraph of the molecule using
+//! rule 1a (atomic number) and rule 2 (atomic mass). Tetrahedral centers are
+//! read from wedge and hash bonds starting at the center, double bonds from the
+//! 2D coordinates of their substituents. Aromatic bonds are treated as single
+//! bonds when building the digraph.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::element::Element;
+use crate::molecule::{BondOrder, BondStereo, Molecule};
+
+/// Stops runaway digraphs of large polycyclic systems.
+const MAX_NODES: usize = 20_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Descriptor {
+    R,
+    S,
+    E,
+    Z,
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::R => "R",
+            Self::S => "S",
+            Self::E => "E",
+            Self::Z => "Z",
+        };
+        write!(f, "({s})")
+    }
+}
+
+/// A stereogenic unit of a molecule. `descriptor` is `None` when the unit is
+/// stereogenic but the drawing does not define its configuration.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StereoUnit {
+    Center {
+        atom: usize,
+        descriptor: Option<Descriptor>,
+    },
+    DoubleBond {
+        bond: usize,
+        descriptor: Option<Descriptor>,
+    },
+}
+
+impl StereoUnit {
+    pub fn descriptor(&self) -> Option<Descriptor> {
+        match self {
+            Self::Center { descriptor, .. } | Self::DoubleBond { descriptor, .. } => *descriptor,
+        }
+    }
+}
+
+/// Finds all stereocenters and stereogenic double bonds of `molecule` and
+/// assigns their descriptors.
+pub fn assign(molecule: &Molecule) -> Vec<StereoUnit> {
+    let mut units = Vec::new();
+
+    for atom in 0..molecule.atoms.len() {
+        if let Some(descriptor) = center_descriptor(molecule, atom) {
+            units.push(StereoUnit::Center { atom, descriptor });
+        }
+    }
+
+    for (bond, data) in molecule.bonds.iter().enumerate() {
+        if data.order != BondOrder::Double {
+            continue;
+        }
+        if let Some(descriptor) = double_bond_descriptor(molecule, bond) {
+            units.push(StereoUnit::DoubleBond { bond, descriptor });
+        }
+    }
+
+    units
+}
+
+#[derive(Copy, Clone)]
+struct Vec3 {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl Vec3 {
+    fn sub(self, o: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x - o.x,
+            y: self.y - o.y,
+            z: self.z - o.z,
+        }
+    }
+
+    fn cross(self, o: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.y * o.z - self.z * o.y,
+            y: self.z * o.x - self.x * o.z,
+            z: self.x * o.y - self.y * o.x,
+        }
+    }
+
+    fn dot(self, o: Vec3) -> f64 {
+        self.x * o.x + self.y * o.y + self.z * o.z
+    }
+}
+
+/// `None` if `atom` is not a stereocenter, `Some(None)` if it is one without
+/// a defined configuration.
+fn center_descriptor(molecule: &Molecule, atom: usize) -> Option<Option<Descriptor>> {
+    let element = molecule.atoms[atom].element;
+    if ![Element::C, Element::SI, Element::N, Element::P, Element::S].contains(&element) {
+        return None;
+    }
+
+    let neighbors: Vec<(usize, usize)> = molecule.neighbors(atom).collect();
+    let hydrogens = molecule.implicit_hydrogens(atom) as usize;
+    if neighbors.len() + hydrogens != 4 || hydrogens > 1 {
+        return None;
+    }
+    if neighbors
+        .iter()
+        .any(|(b, _)| molecule.bonds[*b].order != BondOrder::Single)
+    {
+        return None;
+    }
+    // Trivalent nitrogen inverts, only ammonium centers are stable.
+    if element == Element::N && molecule.atoms[atom].charge <= 0 {
+        return None;
+    }
+
+    let mut graph = Digraph::new(molecule, atom);
+    let branches = graph.expand(0);
+    let order = graph.rank(&branches)?;
+
+    if neighbors.iter().any(|(b, _)| {
+        let bond = &molecule.bonds[*b];
+        bond.stereo == BondStereo::Wavy && bond.begin == atom
+    }) {
+        return Some(None);
+    }
+
+    let atoms: Vec<Option<usize>> = order
+        .iter()
+        .map(|i| graph.nodes[branches[*i]].atom)
+        .collect();
+    // Looking from the lowest priority towards the center, R runs anticlockwise.
+    let anticlockwise = anticlockwise(molecule, atom, &[atoms[3], atoms[0], atoms[1], atoms[2]]);
+
+    Some(anticlockwise.map(|anticlockwise| {
+        if anticlockwise {
+            Descriptor::R
+        } else {
+            Descriptor::S
+        }
+    }))
+}
+
+/// Sense of rotation of the last three of four `neighbors` of a tetrahedral
+/// `center` when looking from the first one towards the center, as read from
+/// wedges and hashes starting at the center. `None` stands for the implicit
+/// hydrogen. Returns `None` if the drawing does not define the configuration.
+pub(crate) fn anticlockwise(
+    molecule: &Molecule,
+    center: usize,
+    neighbors: &[Option<usize>],
+) -> Option<bool> {
+    // Coordinates with y pointing up and z towards the viewer.
+    let origin = molecule.atoms[center].position;
+    let mut vectors: Vec<Option<Vec3>> = neighbors
+        .iter()
+        .map(|neighbor| {
+            let neighbor = (*neighbor)?;
+            let bond = &molecule.bonds[molecule.bond_between(center, neighbor)?];
+            let d = molecule.atoms[neighbor].position - origin;
+            let length = d.hypot().max(f64::EPSILON);
+            let z = match bond.stereo {
+                BondStereo::Wedge if bond.begin == center => 1.0,
+                BondStereo::Hash if bond.begin == center => -1.0,
+                _ => 0.0,
+            };
+            Some(Vec3 {
+                x: d.x / length,
+                y: -d.y / length,
+                z,
+            })
+        })
+        .collect();
+
+    if vectors.iter().flatten().all(|v| v.z == 0.0) {
+        return None;
+    }
+
+    // The implicit hydrogen points opposite to the drawn substituents.
+    if let Some(missing) = vectors.iter().position(Option::is_none) {
+        let sum = vectors.iter().flatten().fold(
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            |acc, v| Vec3 {
+                x: acc.x + v.x,
+                y: acc.y + v.y,
+                z: acc.z + v.z,
+            },
+        );
+        vectors[missing] = Some(Vec3 {
+            x: -sum.x,
+            y: -sum.y,
+            z: -sum.z,
+        });
+    }
+
+    let v: Vec<Vec3> = vectors.into_iter().collect::<Option<_>>()?;
+    if v.len() != 4 {
+        return None;
+    }
+    let det = v[1].sub(v[0]).dot(v[2].sub(v[0]).cross(v[3].sub(v[0])));
+
+    (det.abs() >= 1e-6).then_some(det < 0.0)
+}
+
+fn double_bond_descriptor(molecule: &Molecule, bond: usize) -> Option<Option<Descriptor>> {
+    // Small rings can only be cis.
+    if molecule.smallest_ring(bond).is_some_and(|size| size < 8) {
+        return None;
+    }
+
+    let data = &molecule.bonds[bond];
+    let (a, b) = (data.begin, data.end);
+    let top_a = top_substituent(molecule, a, b)?;
+    let top_b = top_substituent(molecule, b, a)?;
+
+    if data.stereo == BondStereo::Either {
+        return Some(None);
+    }
+
+    let (pa, pb) = (molecule.atoms[a].position, molecule.atoms[b].position);
+    let axis = pb - pa;
+    let side = |(atom, flipped): (usize, bool)| {
+        let cross = axis.cross(molecule.atoms[atom].position - pa);
+        if cross.abs() < 1e-6 {
+            0.0
+        } else if flipped {
+            -cross.signum()
+        } else {
+            cross.signum()
+        }
+    };
+
+    let product = side(top_a) * side(top_b);
+    Some(if product == 0.0 {
+        None
+    } else if product > 0.0 {
+        Some(Descriptor::Z)
+    } else {
+        Some(Descriptor::E)
+    })
+}
+
+/// Picks the drawn substituent of `atom` (not `partner`) used to read the
+/// double bond geometry. The flag is set when the drawn substituent is the
+/// lower-ranked one, so its side has to be mirrored.
+fn top_substituent(molecule: &Molecule, atom: usize, partner: usize) -> Option<(usize, bool)> {
+    let mut graph = Digraph::new(molecule, atom);
+    let children = graph.expand(0);
+    let substituents: Vec<usize> = children
+        .into_iter()
+        .filter(|n| graph.nodes[*n].atom != Some(partner) && !graph.nodes[*n].duplicate)
+        .collect();
+
+    match substituents.as_slice() {
+        // A lone pair ranks below the only substituent.
+        [only] => Some((graph.nodes[*only].atom?, false)),
+        [first, second] => {
+            let (first, second) = (*first, *second);
+            match graph.compare(first, second) {
+                Ordering::Equal => None,
+                ordering => {
+                    let (top, low) = if ordering == Ordering::Greater {
+                        (first, second)
+                    } else {
+                        (second, first)
+                    };
+                    match (graph.nodes[top].atom, graph.nodes[low].atom) {
+                        (Some(atom), _) => Some((atom, false)),
+                        (None, Some(atom)) => Some((atom, true)),
+                        (None, None) => None,
+                    }
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+struct Node {
+    /// Atom this node stands for, `None` for implicit hydrogens.
+    atom: Option<usize>,
+    number: u8,
+    mass: f64,
+    parent: Option<usize>,
+    duplicate: bool,
+    children: Option<Vec<usize>>,
+}
+
+#[derive(Copy, Clone)]
+enum Rule {
+    AtomicNumber,
+    Mass,
+}
+
+/// Hierarchical digraph rooted at a stereogenic atom, expanded lazily.
+struct Digraph<'a> {
+    molecule: &'a Molecule,
+    nodes: Vec<Node>,
+}
+
+impl<'a> Digraph<'a> {
+    fn new(molecule: &'a Molecule, root: usize) -> Self {
+        let mut graph = Self {
+            molecule,
+            nodes: Vec::new(),
+        };
+        graph.push(Some(root), None, false);
+        graph
+    }
+
+    fn push(&mut self, atom: Option<usize>, parent: Option<usize>, duplicate: bool) -> usize {
+        let (number, mass) = match atom {
+            Some(a) => {
+                let data = &self.molecule.atoms[a];
+                let mass = match data.isotope {
+                    Some(isotope) => isotope as f64,
+                    None => data.element.mass(),
+                };
+                (data.element.atomic_number(), mass)
+            }
+            None => (1, Element::H.mass()),
+        };
+        self.nodes.push(Node {
+            atom,
+            number,
+            mass,
+            parent,
+            duplicate,
+            children: if duplicate || atom.is_none() {
+                Some(Vec::new())
+            } else {
+                None
+            },
+        });
+        self.nodes.len() - 1
+    }
+
+    fn is_ancestor(&self, node: usize, atom: usize) -> bool {
+        let mut current = self.nodes[node].parent;
+        while let Some(n) = current {
+            if self.nodes[n].atom == Some(atom) {
+                return true;
+            }
+            current = self.nodes[n].parent;
+        }
+        false
+    }
+
+    fn expand(&mut self, node: usize) -> Vec<usize> {
+        if let Some(children) = &self.nodes[node].children {
+            return children.clone();
+        }
+        if self.nodes.len() > MAX_NODES {
+            return Vec::new();
+        }
+
+        let molecule = self.molecule;
+        let atom = self.nodes[node].atom.unwrap();
+        let parent_atom = self.nodes[node].parent.and_then(|p| self.nodes[p].atom);
+        let mut children = Vec::new();
+
+        for (bond, neighbor) in molecule.neighbors(atom) {
+            let duplicates = match molecule.bonds[bond].order {
+                BondOrder::Double => 1,
+                BondOrder::Triple => 2,
+                BondOrder::Single | BondOrder::Aromatic => 0,
+            };
+            if Some(neighbor) == parent_atom {
+                for _ in 0..duplicates {
+                    children.push(self.push(Some(neighbor), Some(node), true));
+                }
+                continue;
+            }
+            // Ring closures end in a duplicate of the atom already on the path.
+            let closes_ring = neighbor == atom || self.is_ancestor(node, neighbor);
+            children.push(self.push(Some(neighbor), Some(node), closes_ring));
+            for _ in 0..duplicates {
+                children.push(self.push(Some(neighbor), Some(node), true));
+            }
+        }
+        for _ in 0..molecule.implicit_hydrogens(atom) {
+            children.push(self.push(None, Some(node), false));
+        }
+
+        self.sort(&mut children);
+        self.nodes[node].children = Some(children.clone());
+        children
+    }
+
+    fn key(&self, node: usize, rule: Rule) -> f64 {
+        match rule {
+            Rule::AtomicNumber => self.nodes[node].number as f64,
+            Rule::Mass => self.nodes[node].mass,
+        }
+    }
+
+    /// Atomic numbers a node would get as children, read straight from the
+    /// molecule so ordering siblings does not expand the digraph.
+    fn substituent_numbers(&self, node: usize) -> Vec<u8> {
+        let data = &self.nodes[node];
+        let Some(atom) = data.atom.filter(|_| !data.duplicate) else {
+            return Vec::new();
+        };
+        let parent_atom = data.parent.and_then(|p| self.nodes[p].atom);
+        let mut numbers: Vec<u8> = self
+            .molecule
+            .neighbors(atom)
+            .flat_map(|(bond, neighbor)| {
+                let count = match self.molecule.bonds[bond].order {
+                    BondOrder::Double => 2,
+                    BondOrder::Triple => 3,
+                    BondOrder::Single | BondOrder::Aromatic => 1,
+                } - usize::from(Some(neighbor) == parent_atom);
+                let number = self.molecule.atoms[neighbor].element.atomic_number();
+                std::iter::repeat_n(number, count)
+            })
+            .collect();
+        numbers.extend(std::iter::repeat_n(
+            1,
+            self.molecule.implicit_hydrogens(atom) as usize,
+        ));
+        numbers.sort_by(|a, b| b.cmp(a));
+        numbers
+    }
+
+    /// Orders siblings highest first by their own atomic number, then by the
+    /// atomic numbers of their substituents.
+    fn sort(&self, nodes: &mut [usize]) {
+        nodes.sort_by_cached_key(|n| {
+            std::cmp::Reverse((self.nodes[*n].number, self.substituent_numbers(*n)))
+        });
+    }
+
+    /// Compares two branches sphere by sphere, rule 1a exhaustively first and
+    /// rule 2 only when the whole digraph ties.
+    fn compare(&mut self, a: usize, b: usize) -> Ordering {
+        self.compare_by(a, b, Rule::AtomicNumber)
+            .then_with(|| self.compare_by(a, b, Rule::Mass))
+    }
+
+    fn compare_by(&mut self, a: usize, b: usize, rule: Rule) -> Ordering {
+        let ordering = self.key(a, rule).total_cmp(&self.key(b, rule));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+
+        let mut sphere_a = vec![a];
+        let mut sphere_b = vec![b];
+        while !sphere_a.is_empty() || !sphere_b.is_empty() {
+            let groups_a: Vec<Vec<usize>> = sphere_a.iter().map(|n| self.expand(*n)).collect();
+            let groups_b: Vec<Vec<usize>> = sphere_b.iter().map(|n| self.expand(*n)).collect();
+
+            for i in 0..groups_a.len().max(groups_b.len()) {
+                let empty = Vec::new();
+                let ga = groups_a.get(i).unwrap_or(&empty);
+                let gb = groups_b.get(i).unwrap_or(&empty);
+                let mut ka: Vec<f64> = ga.iter().map(|n| self.key(*n, rule)).collect();
+                let mut kb: Vec<f64> = gb.iter().map(|n| self.key(*n, rule)).collect();
+                ka.sort_by(|x, y| y.total_cmp(x));
+                kb.sort_by(|x, y| y.total_cmp(x));
+                // Missing positions are phantom atoms of atomic number zero.
+                for j in 0..ka.len().max(kb.len()) {
+                    let x = ka.get(j).copied().unwrap_or(0.0);
+                    let y = kb.get(j).copied().unwrap_or(0.0);
+                    let ordering = x.total_cmp(&y);
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+            }
+
+            sphere_a = groups_a.into_iter().flatten().collect();
+            sphere_b = groups_b.into_iter().flatten().collect();
+            if self.nodes.len() > MAX_NODES {
+                break;
+            }
+        }
+
+        Ordering::Equal
+    }
+
+    /// Ranks `branches` highest priority first, returning their indices into
+    /// `branches`, or `None` if two of them tie.
+    fn rank(&mut self, branches: &[usize]) -> Option<Vec<usize>> {
+        let mut order: Vec<usize> = (0..branches.len()).collect();
+        let mut tie = false;
+        order.sort_by(|x, y| {
+            let ordering = self.compare(branches[*y], branches[*x]);
+            tie |= ordering == Ordering::Equal;
+            ordering
+        });
+        (!tie).then_some(order)
+    }
+}
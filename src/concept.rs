@@ -1,4 +1,57 @@
-#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
+use crate::molecule::Molecule;
+use crate::reaction::Reaction;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum Concept {
-    Molecule(),
+    Molecule(Molecule),
+    Reaction(Box<Reaction>),
+}
+
+impl Concept {
+    /// Every molecule of the concept, in a stable order that anchors index into.
+    pub fn molecules(&self) -> Vec<&Molecule> {
+        match self {
+            Self::Molecule(molecule) => vec![molecule],
+            Self::Reaction(reaction) => reaction.molecules().collect(),
+        }
+    }
+
+    pub fn molecules_mut(&mut self) -> Vec<&mut Molecule> {
+        match self {
+            Self::Molecule(molecule) => vec![molecule],
+            Self::Reaction(reaction) => reaction.molecules_mut().collect(),
+        }
+    }
+
+    /// Median of the median bond lengths of its molecules.
+    pub fn median_bond_length(&self) -> Option<f64> {
+        let mut lengths: Vec<f64> = self
+            .molecules()
+            .iter()
+            .filter_map(|molecule| molecule.median_bond_length())
+            .collect();
+        lengths.sort_by(f64::total_cmp);
+        lengths.get(lengths.len() / 2).copied()
+    }
+
+    pub fn bounding_box(&self) -> Option<kurbo::Rect> {
+        self.molecules()
+            .iter()
+            .filter_map(|molecule| molecule.bounding_box())
+            .reduce(|a, b| a.union(b))
+    }
+
+    pub fn translate(&mut self, offset: kurbo::Vec2) {
+        match self {
+            Self::Molecule(molecule) => molecule.translate(offset),
+            Self::Reaction(reaction) => reaction.translate(offset),
+        }
+    }
+
+    pub fn transform(&mut self, affine: kurbo::Affine) {
+        match self {
+            Self::Molecule(molecule) => molecule.transform(affine),
+            Self::Reaction(reaction) => reaction.transform(affine),
+        }
+    }
 }
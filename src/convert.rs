@@ -0,0 +1,336 @@
+//! Conversion between documents, structure files and images, shared by the
+//! import and export menu entries and the headless `convert` command.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::concept::Concept;
+use crate::document::{Document, Item};
+use crate::font;
+use crate::io::{self, ParseError};
+use crate::molecule::Molecule;
+use crate::page::{Orientation, Paper};
+use crate::style::{Preset, Style};
+
+const USAGE: &str = "\
+usage: molecular convert <input> <output> [options]
+
+Converts between Molecular documents (.json), molfiles (.mol), SD files
+(.sdf), SMILES (.smi), RXN files (.rxn) and images (.svg, .png, .pdf, .eps).
+Formats follow the file extensions; `-` reads standard input or writes
+standard output and needs the format given explicitly.
+
+options:
+  --from <format>     format of the input, an extension such as `smi`
+  --to <format>       format of the output
+  --style <preset>    acs, rsc or wiley drawing style for new documents
+  --dpi <number>      resolution of PNG images (default 300)
+  --transparent       PNG images without a white background
+  --page <paper>      lay images out on a page: A5, A4, A3, Letter, Legal,
+                      Tabloid or the poster sizes A2, A1 and A0
+  --landscape         turn the page on its side, an A4 one unless given
+  --font <file>       TrueType or OpenType font the document names, may be
+                      given several times; missing fonts are drawn in the
+                      bundled Fira Sans";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Document,
+    Molfile,
+    Sdf,
+    Smiles,
+    Rxn,
+    Svg,
+    Png,
+    Pdf,
+    Eps,
+}
+
+impl Format {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Some(match extension.to_ascii_lowercase().as_str() {
+            "json" => Self::Document,
+            "mol" => Self::Molfile,
+            "sdf" | "sd" => Self::Sdf,
+            "smi" | "smiles" | "rsmi" => Self::Smiles,
+            "rxn" => Self::Rxn,
+            "svg" => Self::Svg,
+            "png" => Self::Png,
+            "pdf" => Self::Pdf,
+            "eps" => Self::Eps,
+            _ => return None,
+        })
+    }
+
+    pub fn of(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(Self::from_extension)
+    }
+}
+
+/// Why a conversion failed.
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Parse(ParseError),
+    Document(serde_json::Error),
+    /// A request the formats cannot fulfill, with the reason.
+    Unsupported(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Parse(e) => write!(f, "{e}"),
+            Self::Document(e) => write!(f, "broken document: {e}"),
+            Self::Unsupported(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Document(e)
+    }
+}
+
+/// The structures in `text`. Every line of a SMILES file and every record
+/// of an SD file is a structure of its own.
+pub fn read_structures(text: &str, format: Format) -> Result<Vec<Concept>, Error> {
+    let concepts = match format {
+        Format::Molfile => vec![Concept::Molecule(io::molfile::parse(text)?)],
+        Format::Sdf => io::sdf::parse(text)?
+            .into_iter()
+            .map(Concept::Molecule)
+            .collect(),
+        Format::Rxn => vec![Concept::Reaction(Box::new(io::rxn::parse(text)?))],
+        Format::Smiles => text
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                // Anything after the SMILES is the name of the structure.
+                let smiles = line.split_whitespace().next()?;
+                let concept = if smiles.contains('>') {
+                    io::smiles::parse_reaction(smiles)
+                        .map(|reaction| Concept::Reaction(Box::new(reaction)))
+                } else {
+                    io::smiles::parse(smiles).map(Concept::Molecule)
+                };
+                Some(concept.map_err(|e| {
+                    ParseError::new(
+                        index + 1,
+                        format!("{} (character {})", e.message, e.position),
+                    )
+                }))
+            })
+            .collect::<Result<_, _>>()?,
+        Format::Document => {
+            return Err(Error::Unsupported(
+                "documents are opened, not imported".to_string(),
+            ));
+        }
+        Format::Svg | Format::Png | Format::Pdf | Format::Eps => {
+            return Err(Error::Unsupported("images cannot be read".to_string()));
+        }
+    };
+    Ok(concepts)
+}
+
+/// `document` in `format`. Images show `items`, or everything when there are
+/// none; structure formats always take the whole document.
+pub fn write(document: &Document, items: &[Item], format: Format) -> Result<Vec<u8>, Error> {
+    let concepts = &document.concepts;
+    let molecules = || concepts.iter().flat_map(Concept::molecules);
+    let merged = || {
+        let mut merged = Molecule::default();
+        molecules().for_each(|m| merged.merge(m));
+        merged
+    };
+    let reaction = concepts.iter().find_map(|concept| match concept {
+        Concept::Reaction(reaction) => Some(reaction),
+        _ => None,
+    });
+
+    let bytes = match format {
+        Format::Document => serde_json::to_string_pretty(document)?.into_bytes(),
+        Format::Molfile => {
+            let molecule = merged();
+            io::molfile::write(&molecule, io::molfile::Version::for_molecule(&molecule))
+                .into_bytes()
+        }
+        Format::Sdf => io::sdf::write(molecules()).into_bytes(),
+        Format::Smiles => {
+            let mut out = String::new();
+            for concept in concepts {
+                match concept {
+                    Concept::Molecule(m) => out.push_str(&io::smiles::write(m)),
+                    Concept::Reaction(r) => out.push_str(&io::smiles::write_reaction(r)),
+                }
+                out.push('\n');
+            }
+            out.into_bytes()
+        }
+        Format::Rxn => {
+            let reaction = reaction.ok_or_else(|| {
+                Error::Unsupported("the document has no reaction to write".to_string())
+            })?;
+            let largest = reaction.molecules().max_by_key(|m| m.atoms.len());
+            let version = largest.map_or(io::molfile::Version::V2000, |m| {
+                io::molfile::Version::for_molecule(m)
+            });
+            io::rxn::write(reaction, version).into_bytes()
+        }
+        Format::Svg => io::svg::write(&document.scene(items)).into_bytes(),
+        Format::Png => {
+            if document.export_dpi == 0 {
+                return Err(Error::Unsupported(
+                    "the export resolution must be above 0 dpi".to_string(),
+                ));
+            }
+            let dpi = document.export_dpi as f64;
+            io::png::write(&document.scene(items), dpi, document.transparent_export)
+        }
+        Format::Pdf => io::pdf::write(&document.scene(items)),
+        Format::Eps => io::eps::write(&document.scene(items)).into_bytes(),
+    };
+    Ok(bytes)
+}
+
+/// Runs `molecular convert` with the arguments after the subcommand.
+pub fn run(args: &[String]) -> Result<(), Error> {
+    let usage = |problem: &str| Error::Unsupported(format!("{problem}\n\n{USAGE}"));
+
+    let mut paths = Vec::new();
+    let (mut from, mut to, mut preset) = (None, None, None);
+    let (mut dpi, mut transparent) = (None, false);
+    let (mut paper, mut landscape) = (None, false);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| usage(&format!("{name} needs a value")))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            "--from" => from = Some(format_named(value("--from")?)?),
+            "--to" => to = Some(format_named(value("--to")?)?),
+            "--style" => {
+                preset = Some(match value("--style")?.to_ascii_lowercase().as_str() {
+                    "acs" | "acs1996" => Preset::Acs1996,
+                    "rsc" => Preset::Rsc,
+                    "wiley" => Preset::Wiley,
+                    other => return Err(usage(&format!("unknown style `{other}`"))),
+                })
+            }
+            "--dpi" => {
+                let value = value("--dpi")?;
+                dpi = Some(
+                    value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|dpi| *dpi > 0)
+                        .ok_or_else(|| usage(&format!("`{value}` is not a resolution")))?,
+                );
+            }
+            "--transparent" => transparent = true,
+            "--page" => {
+                let value = value("--page")?;
+                paper = Some(
+                    Paper::named(value)
+                        .ok_or_else(|| usage(&format!("unknown paper `{value}`")))?,
+                );
+            }
+            "--landscape" => landscape = true,
+            "--font" => {
+                let path = value("--font")?;
+                font::load(std::fs::read(path)?)
+                    .map_err(|e| Error::Unsupported(format!("cannot load font `{path}`: {e}")))?;
+            }
+            flag if flag.starts_with("--") => {
+                return Err(usage(&format!("unknown option `{flag}`")));
+            }
+            path => paths.push(path),
+        }
+    }
+    let [input, output] = paths[..] else {
+        return Err(usage("expected an input and an output"));
+    };
+
+    let format_of = |path: &str, given: Option<Format>| {
+        given
+            .or_else(|| Format::of(Path::new(path)))
+            .ok_or_else(|| usage(&format!("cannot tell the format of `{path}`")))
+    };
+    let (from, to) = (format_of(input, from)?, format_of(output, to)?);
+
+    let text = if input == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(input)?
+    };
+    let mut document = if from == Format::Document {
+        serde_json::from_str(&text)?
+    } else {
+        let mut document = Document {
+            style: Style::preset(preset.unwrap_or_default()),
+            ..Document::default()
+        };
+        document.import(read_structures(&text, from)?);
+        document
+    };
+    if let (Some(preset), Format::Document) = (preset, from) {
+        document.set_style(Style::preset(preset));
+    }
+    if let Some(dpi) = dpi {
+        document.export_dpi = dpi;
+    }
+    document.transparent_export |= transparent;
+    if paper.is_some() || landscape {
+        let page = document.page.get_or_insert_default();
+        page.paper = paper.unwrap_or(page.paper);
+        if landscape {
+            page.orientation = Orientation::Landscape;
+        }
+    }
+    if matches!(to, Format::Svg | Format::Png | Format::Pdf | Format::Eps) {
+        for family in document.missing_fonts() {
+            eprintln!(
+                "molecular: font `{family}` is not loaded, using {}",
+                font::bundled().family()
+            );
+        }
+    }
+
+    let bytes = write(&document, &[], to)?;
+    if output == "-" {
+        use std::io::Write;
+        std::io::stdout().write_all(&bytes)?;
+    } else {
+        std::fs::write(output, bytes)?;
+    }
+    Ok(())
+}
+
+fn format_named(name: &str) -> Result<Format, Error> {
+    Format::from_extension(name.trim_start_matches('.'))
+        .ok_or_else(|| Error::Unsupported(format!("unknown format `{name}`")))
+}
@@ -0,0 +1,946 @@
+//! A drawing: free shapes, chemical concepts and the style they are drawn
+//! in, organized in layers and groups, with the editing operations that keep
+//! arrow anchors, layers and groups consistent.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::annotation::Annotation;
+use crate::arrow::ArrowKind;
+use crate::concept::Concept;
+use crate::grid::Grid;
+use crate::io::clipboard::Fragment;
+use crate::molecule::{Atom, DECORATION_DISTANCE, Decoration, DecorationKind};
+use crate::page::Page;
+use crate::reaction::{Agent, Reaction};
+use crate::render::{Primitive, Scene};
+use crate::shape::Shape;
+use crate::spatial::SpatialIndex;
+use crate::style::{Appearance, Style};
+
+/// An item of a document, by index into its shapes or concepts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Item {
+    Shape(usize),
+    Concept(usize),
+}
+
+/// Items drawn together, above the items of the layers before it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    pub name: String,
+    /// Hidden layers are neither drawn nor exported.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub hidden: bool,
+    /// Items on locked layers are drawn but cannot be picked.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub locked: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<Item>,
+}
+
+impl Layer {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            hidden: false,
+            locked: false,
+            items: Vec::new(),
+        }
+    }
+}
+
+/// The edge or center line that [`Document::align`] lines items up on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+    Top,
+    Middle,
+    Bottom,
+}
+
+impl Alignment {
+    pub const ALL: [Alignment; 6] = [
+        Alignment::Left,
+        Alignment::Center,
+        Alignment::Right,
+        Alignment::Top,
+        Alignment::Middle,
+        Alignment::Bottom,
+    ];
+}
+
+impl std::fmt::Display for Alignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Alignment::Left => "left",
+            Alignment::Center => "center",
+            Alignment::Right => "right",
+            Alignment::Top => "top",
+            Alignment::Middle => "middle",
+            Alignment::Bottom => "bottom",
+        })
+    }
+}
+
+/// A direction to distribute items along or to flip them in. Flipping
+/// horizontally mirrors left and right.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    #[serde(rename = "shape_storage")]
+    pub shapes: Vec<Shape>,
+    #[serde(rename = "concept_storage")]
+    pub concepts: Vec<Concept>,
+    /// Drawing settings of the whole document.
+    #[serde(default)]
+    pub style: Style,
+    #[serde(default)]
+    pub show_stereo_descriptors: bool,
+    /// Resolution of exported PNG images.
+    #[serde(default = "default_export_dpi")]
+    pub export_dpi: u32,
+    #[serde(default)]
+    pub transparent_export: bool,
+    /// Bottom layer first. Items on none of them, like those of documents
+    /// saved before layers, are on the first.
+    #[serde(default = "default_layers")]
+    pub layers: Vec<Layer>,
+    /// The layer new items go on.
+    #[serde(default)]
+    pub active_layer: usize,
+    /// Items that are selected and moved together.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Vec<Item>>,
+    #[serde(default, skip_serializing_if = "Grid::is_default")]
+    pub grid: Grid,
+    /// The paper the document is laid out on, none for an endless canvas.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<Page>,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self {
+            shapes: Vec::new(),
+            concepts: Vec::new(),
+            style: Style::default(),
+            show_stereo_descriptors: false,
+            export_dpi: default_export_dpi(),
+            transparent_export: false,
+            layers: default_layers(),
+            active_layer: 0,
+            groups: Vec::new(),
+            grid: Grid::default(),
+            page: None,
+        }
+    }
+}
+
+impl Document {
+    /// `items`, or the whole document if there are none, as exporters draw
+    /// it. The whole document is exported on its page, if it has one.
+    pub fn scene(&self, items: &[Item]) -> Scene {
+        let mut scene = Scene::new(self.style.clone());
+        if items.is_empty() {
+            scene.page = self.page.map(|page| page.rect());
+        }
+        for item in self.draw_order() {
+            if items.is_empty() || items.contains(&item) {
+                self.draw(&mut scene, item);
+            }
+        }
+        scene
+    }
+
+    /// Adds the primitives of `item` to `scene`.
+    pub fn draw(&self, scene: &mut Scene, item: Item) {
+        match item {
+            Item::Shape(index) => scene.shape(&self.shapes[index]),
+            Item::Concept(index) => {
+                scene.concept(&self.concepts[index], self.show_stereo_descriptors)
+            }
+        }
+    }
+
+    /// The items of the visible layers, bottom first. Within a layer, shapes
+    /// are drawn below concepts.
+    pub fn draw_order(&self) -> Vec<Item> {
+        let shapes = (0..self.shapes.len()).map(Item::Shape);
+        let concepts = (0..self.concepts.len()).map(Item::Concept);
+        let layer_of = self.layer_map();
+        let mut layers = vec![Vec::new(); self.layers.len().max(1)];
+        for item in shapes.chain(concepts) {
+            layers[layer_of.get(&item).copied().unwrap_or(0)].push(item);
+        }
+        layers
+            .into_iter()
+            .enumerate()
+            .filter(|(layer, _)| self.layers.get(*layer).is_none_or(|l| !l.hidden))
+            .flat_map(|(_, items)| items)
+            .collect()
+    }
+
+    /// Index of the layer `item` is on.
+    pub fn layer_of(&self, item: Item) -> usize {
+        self.layers
+            .iter()
+            .position(|layer| layer.items.contains(&item))
+            .unwrap_or(0)
+    }
+
+    /// The layer of every item on one, lower layers winning for items on
+    /// several.
+    fn layer_map(&self) -> HashMap<Item, usize> {
+        let mut map = HashMap::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            for item in &layer.items {
+                map.entry(*item).or_insert(index);
+            }
+        }
+        map
+    }
+
+    /// Whether `item` is on a visible, unlocked layer.
+    pub fn is_editable(&self, item: Item) -> bool {
+        self.layers
+            .get(self.layer_of(item))
+            .is_none_or(|layer| !layer.hidden && !layer.locked)
+    }
+
+    /// [`Document::is_editable`] for many items, finding the layers of all
+    /// items once.
+    pub fn editable(&self) -> impl Fn(Item) -> bool + '_ {
+        let layer_of = self.layer_map();
+        move |item| {
+            self.layers
+                .get(layer_of.get(&item).copied().unwrap_or(0))
+                .is_none_or(|layer| !layer.hidden && !layer.locked)
+        }
+    }
+
+    /// Puts `items` on `layer`.
+    pub fn set_layer(&mut self, items: &[Item], layer: usize) {
+        for other in &mut self.layers {
+            other.items.retain(|item| !items.contains(item));
+        }
+        if let Some(layer) = self.layers.get_mut(layer) {
+            layer.items.extend_from_slice(items);
+        }
+    }
+
+    /// Adds a layer above the others and makes it the active one.
+    pub fn add_layer(&mut self, name: impl Into<String>) -> usize {
+        self.layers.push(Layer::new(name));
+        self.active_layer = self.layers.len() - 1;
+        self.active_layer
+    }
+
+    /// Adds `shape` on top of the active layer.
+    pub fn add_shape(&mut self, shape: Shape) -> Item {
+        let item = Item::Shape(self.shapes.len());
+        self.shapes.push(shape);
+        self.set_layer(&[item], self.active_layer);
+        item
+    }
+
+    /// Takes the shape at `index` out of the document.
+    pub fn remove_shape(&mut self, index: usize) -> Shape {
+        let shape = self.shapes[index].clone();
+        self.delete(&[Item::Shape(index)]);
+        shape
+    }
+
+    /// Makes `items` one group, taking them out of their groups.
+    pub fn group(&mut self, items: &[Item]) {
+        self.ungroup(items);
+        if items.len() > 1 {
+            self.groups.push(items.to_vec());
+        }
+    }
+
+    /// Dissolves every group that any of `items` is in.
+    pub fn ungroup(&mut self, items: &[Item]) {
+        self.groups
+            .retain(|group| !group.iter().any(|item| items.contains(item)));
+    }
+
+    /// `items` with the rest of their groups.
+    pub fn with_groups(&self, items: &[Item]) -> Vec<Item> {
+        let mut all = items.to_vec();
+        for group in &self.groups {
+            if group.iter().any(|item| items.contains(item)) {
+                for item in group {
+                    if !all.contains(item) {
+                        all.push(*item);
+                    }
+                }
+            }
+        }
+        all
+    }
+
+    /// Draws `items` above the other items of their layers, returning where
+    /// they are now.
+    pub fn bring_to_front(&mut self, items: &[Item]) -> Vec<Item> {
+        self.reorder(items, true)
+    }
+
+    /// Draws `items` below the other items of their layers, returning where
+    /// they are now.
+    pub fn send_to_back(&mut self, items: &[Item]) -> Vec<Item> {
+        self.reorder(items, false)
+    }
+
+    pub fn bounds(&self, item: Item) -> Option<kurbo::Rect> {
+        let mut scene = Scene::new(self.style.clone());
+        match item {
+            Item::Shape(index) => scene.shape(self.shapes.get(index)?),
+            Item::Concept(index) => {
+                scene.concept(self.concepts.get(index)?, self.show_stereo_descriptors)
+            }
+        }
+        scene.bounds()
+    }
+
+    /// Families the document names that are not loaded, drawn in the bundled
+    /// font instead.
+    pub fn missing_fonts(&self) -> Vec<&'static str> {
+        let mut missing: Vec<&'static str> = Vec::new();
+        for primitive in self.scene(&[]).primitives {
+            if let Primitive::Text { font, .. } = primitive
+                && font.is_missing()
+                && !missing.contains(&font.family)
+            {
+                missing.push(font.family);
+            }
+        }
+        missing
+    }
+
+    /// The topmost item under `point`. Concepts are hit on their atoms and
+    /// bonds, shapes anywhere inside their bounds.
+    /// Items on hidden or locked layers are never hit. Builds an index for
+    /// the one query, keep a [`SpatialIndex`] around to ask many.
+    pub fn item_at(&self, point: kurbo::Point) -> Option<Item> {
+        SpatialIndex::new(self).item_at(self, point)
+    }
+
+    /// The drawn items reaching past the margins of the page, none without
+    /// a page.
+    pub fn off_page(&self) -> Vec<Item> {
+        let Some(printable) = self.page.map(|page| page.printable()) else {
+            return Vec::new();
+        };
+        let index = SpatialIndex::new(self);
+        self.draw_order()
+            .into_iter()
+            .filter(|item| {
+                index
+                    .bounds(*item)
+                    .is_some_and(|bounds| printable.union(bounds) != printable)
+            })
+            .collect()
+    }
+
+    /// Every editable item lying entirely inside `rect`.
+    pub fn items_within(&self, rect: kurbo::Rect) -> Vec<Item> {
+        SpatialIndex::new(self).items_within(self, rect)
+    }
+
+    /// Moves `item` by `offset`. Arrows anchored to it follow on the next
+    /// [`Document::follow_anchors`].
+    pub fn translate(&mut self, item: Item, offset: kurbo::Vec2) {
+        match item {
+            Item::Shape(index) => self.shapes[index].translate(offset),
+            Item::Concept(index) => self.concepts[index].translate(offset),
+        }
+    }
+
+    /// Moves the anchored ends of every arrow after structures moved.
+    pub fn follow_anchors(&mut self) {
+        for shape in &mut self.shapes {
+            if let Shape::Arrow(arrow) = shape {
+                arrow.follow_anchors(&self.concepts);
+            }
+        }
+    }
+
+    /// The bounds of `items` together, `None` if none of them has any.
+    pub fn bounds_of(&self, items: &[Item]) -> Option<kurbo::Rect> {
+        items
+            .iter()
+            .filter_map(|item| self.bounds(*item))
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// Maps `item` through `affine`. Texts keep reading left to right, only
+    /// their center is mapped.
+    pub fn transform(&mut self, item: Item, affine: kurbo::Affine) {
+        match item {
+            Item::Shape(index) if matches!(self.shapes[index], Shape::Text(_)) => {
+                if let Some(center) = self.bounds(item).map(|bounds| bounds.center()) {
+                    self.shapes[index].translate(affine * center - center);
+                }
+            }
+            Item::Shape(index) => self.shapes[index].transform(affine),
+            Item::Concept(index) => self.concepts[index].transform(affine),
+        }
+    }
+
+    /// Turns `items` by `angle` radians, clockwise on screen, about `center`.
+    pub fn rotate(&mut self, items: &[Item], angle: f64, center: kurbo::Point) {
+        let affine = kurbo::Affine::translate(center.to_vec2())
+            * kurbo::Affine::rotate(angle)
+            * kurbo::Affine::translate(-center.to_vec2());
+        for item in items {
+            self.transform(*item, affine);
+        }
+        self.follow_anchors();
+    }
+
+    /// Mirrors `items` about the center of their bounds. Wedges and hashes
+    /// swap, so stereocenters keep their configuration.
+    pub fn flip(&mut self, items: &[Item], axis: Axis) {
+        let Some(center) = self.bounds_of(items).map(|bounds| bounds.center()) else {
+            return;
+        };
+        let mirror = match axis {
+            Axis::Horizontal => kurbo::Affine::FLIP_X,
+            Axis::Vertical => kurbo::Affine::FLIP_Y,
+        };
+        let affine = kurbo::Affine::translate(center.to_vec2())
+            * mirror
+            * kurbo::Affine::translate(-center.to_vec2());
+        for item in items {
+            self.transform(*item, affine);
+        }
+        self.follow_anchors();
+    }
+
+    /// Lines `items` up on an edge or center line of their bounds. Groups
+    /// move as one.
+    pub fn align(&mut self, items: &[Item], alignment: Alignment) {
+        let Some(target) = self.bounds_of(items) else {
+            return;
+        };
+        for (unit, bounds) in self.units(items) {
+            let offset = match alignment {
+                Alignment::Left => kurbo::Vec2::new(target.x0 - bounds.x0, 0.0),
+                Alignment::Center => kurbo::Vec2::new(target.center().x - bounds.center().x, 0.0),
+                Alignment::Right => kurbo::Vec2::new(target.x1 - bounds.x1, 0.0),
+                Alignment::Top => kurbo::Vec2::new(0.0, target.y0 - bounds.y0),
+                Alignment::Middle => kurbo::Vec2::new(0.0, target.center().y - bounds.center().y),
+                Alignment::Bottom => kurbo::Vec2::new(0.0, target.y1 - bounds.y1),
+            };
+            for item in unit {
+                self.translate(item, offset);
+            }
+        }
+        self.follow_anchors();
+    }
+
+    /// Spaces `items` evenly along `axis`, leaving the first and the last
+    /// where they are. Groups move as one.
+    pub fn distribute(&mut self, items: &[Item], axis: Axis) {
+        // The start and the size of bounds along the axis.
+        let extent = |bounds: kurbo::Rect| match axis {
+            Axis::Horizontal => (bounds.x0, bounds.width()),
+            Axis::Vertical => (bounds.y0, bounds.height()),
+        };
+        let mut units = self.units(items);
+        if units.len() < 3 {
+            return;
+        }
+        units.sort_by(|(_, a), (_, b)| extent(*a).0.total_cmp(&extent(*b).0));
+        let (start, _) = extent(units[0].1);
+        let end = units
+            .iter()
+            .map(|(_, bounds)| extent(*bounds))
+            .map(|(start, size)| start + size)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let sizes: f64 = units.iter().map(|(_, bounds)| extent(*bounds).1).sum();
+        let gap = (end - start - sizes) / (units.len() - 1) as f64;
+
+        let mut next = start;
+        for (unit, bounds) in units {
+            let (at, size) = extent(bounds);
+            let offset = match axis {
+                Axis::Horizontal => kurbo::Vec2::new(next - at, 0.0),
+                Axis::Vertical => kurbo::Vec2::new(0.0, next - at),
+            };
+            for item in unit {
+                self.translate(item, offset);
+            }
+            next += size + gap;
+        }
+        self.follow_anchors();
+    }
+
+    /// Copies of `items`. Arrows stay anchored to structures that are copied
+    /// with them.
+    pub fn fragment(&self, items: &[Item]) -> Fragment {
+        let concepts: Vec<Concept> = self
+            .concepts
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| items.contains(&Item::Concept(*index)))
+            .map(|(_, concept)| concept.clone())
+            .collect();
+        let shapes = self
+            .shapes
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| items.contains(&Item::Shape(*index)))
+            .map(|(_, shape)| {
+                let mut shape = shape.clone();
+                if let Shape::Arrow(arrow) = &mut shape {
+                    for anchor in [&mut arrow.tail_anchor, &mut arrow.head_anchor] {
+                        *anchor = anchor.and_then(|a| a.relocate(&self.concepts, &concepts));
+                    }
+                }
+                shape
+            })
+            .collect();
+        Fragment {
+            shapes,
+            concepts,
+            bond_length: Some(self.style.bond_length),
+        }
+    }
+
+    /// Removes `items`. Arrows anchored to removed structures keep their
+    /// position but lose the anchor.
+    pub fn delete(&mut self, items: &[Item]) {
+        let deleted: HashSet<Item> = items.iter().copied().collect();
+        // Later items move down by the number of deleted ones before them.
+        let deleted_before = |count: usize, wrap: fn(usize) -> Item| {
+            let mut before = Vec::with_capacity(count);
+            let mut removed = 0;
+            for index in 0..count {
+                before.push(removed);
+                removed += usize::from(deleted.contains(&wrap(index)));
+            }
+            before
+        };
+        let shapes_before = deleted_before(self.shapes.len(), Item::Shape);
+        let concepts_before = deleted_before(self.concepts.len(), Item::Concept);
+
+        let old = self.concepts.clone();
+        let mut index = 0;
+        self.concepts.retain(|_| {
+            index += 1;
+            !deleted.contains(&Item::Concept(index - 1))
+        });
+        let mut index = 0;
+        self.shapes.retain(|_| {
+            index += 1;
+            !deleted.contains(&Item::Shape(index - 1))
+        });
+        self.relocate_anchors(&old);
+        self.remap(|item| match item {
+            _ if deleted.contains(&item) => None,
+            Item::Shape(index) => Some(Item::Shape(index - shapes_before.get(index)?)),
+            Item::Concept(index) => Some(Item::Concept(index - concepts_before.get(index)?)),
+        });
+    }
+
+    /// Adds `fragment` centered on `position` at the bond length of the style,
+    /// returning the new items.
+    pub fn paste(&mut self, mut fragment: Fragment, position: kurbo::Point) -> Vec<Item> {
+        match fragment.bond_length {
+            Some(length) => {
+                let affine = kurbo::Affine::scale(self.style.bond_length / length);
+                fragment
+                    .concepts
+                    .iter_mut()
+                    .for_each(|c| c.transform(affine));
+                fragment.shapes.iter_mut().for_each(|s| s.transform(affine));
+            }
+            None => {
+                for concept in &mut fragment.concepts {
+                    self.fit_to_style(concept);
+                }
+            }
+        }
+
+        let mut scene = Scene::new(self.style.clone());
+        fragment.shapes.iter().for_each(|shape| scene.shape(shape));
+        fragment
+            .concepts
+            .iter()
+            .for_each(|concept| scene.concept(concept, false));
+        let Some(bounds) = scene.bounds() else {
+            return Vec::new();
+        };
+        let offset = position - bounds.center();
+
+        let mut items = Vec::new();
+        let concepts = self.concepts.len();
+        for mut concept in fragment.concepts {
+            concept.translate(offset);
+            items.push(Item::Concept(self.concepts.len()));
+            self.concepts.push(concept);
+        }
+        for mut shape in fragment.shapes {
+            shape.translate(offset);
+            if let Shape::Arrow(arrow) = &mut shape {
+                for anchor in [&mut arrow.tail_anchor, &mut arrow.head_anchor] {
+                    *anchor = anchor.map(|a| a.shifted(concepts));
+                }
+            }
+            items.push(Item::Shape(self.shapes.len()));
+            self.shapes.push(shape);
+        }
+        self.follow_anchors();
+        self.set_layer(&items, self.active_layer);
+        items
+    }
+
+    /// Adds structures read from a file, fitted to the style and lined up
+    /// side by side.
+    pub fn import(&mut self, mut concepts: Vec<Concept>) {
+        for concept in &mut concepts {
+            self.fit_to_style(concept);
+        }
+        crate::layout::side_by_side(&mut concepts, self.style.bond_length * 2.0);
+        let new: Vec<Item> = (self.concepts.len()..self.concepts.len() + concepts.len())
+            .map(Item::Concept)
+            .collect();
+        self.concepts.extend(concepts);
+        self.set_layer(&new, self.active_layer);
+    }
+
+    /// Turns the most recently drawn reaction arrow into a reaction, taking
+    /// the free molecules in line with the arrow and the text beside it,
+    /// within three bond lengths. Returns `false` when there is no such arrow.
+    pub fn assemble_reaction(&mut self) -> bool {
+        let (editable, shapes_editable) = {
+            let is_editable = self.editable();
+            let editable: Vec<bool> = (0..self.concepts.len())
+                .map(|index| is_editable(Item::Concept(index)))
+                .collect();
+            let shapes_editable: Vec<bool> = (0..self.shapes.len())
+                .map(|index| is_editable(Item::Shape(index)))
+                .collect();
+            (editable, shapes_editable)
+        };
+        let Some(index) = (0..self.shapes.len()).rev().find(|index| {
+            shapes_editable[*index]
+                && matches!(&self.shapes[*index],
+                    Shape::Arrow(arrow) if !matches!(arrow.kind, ArrowKind::Curved(_)))
+        }) else {
+            return false;
+        };
+        let layer = self.layer_of(Item::Shape(index));
+
+        // Where the items that are not taken into the reaction end up.
+        let mut moved = Vec::new();
+        let old = self.concepts.clone();
+        let mut components = Vec::new();
+        let mut concepts = Vec::new();
+        for (old_index, concept) in self.concepts.drain(..).enumerate() {
+            match concept {
+                Concept::Molecule(molecule) if editable[old_index] => {
+                    components.push(Agent::Molecule(molecule))
+                }
+                other => {
+                    moved.push((Item::Concept(old_index), Item::Concept(concepts.len())));
+                    concepts.push(other);
+                }
+            }
+        }
+        let mut arrow = None;
+        let mut shapes = Vec::new();
+        for (old_index, shape) in self.shapes.drain(..).enumerate() {
+            match shape {
+                Shape::Arrow(a) if old_index == index => arrow = Some(a),
+                Shape::Text(text) if shapes_editable[old_index] => {
+                    components.push(Agent::Text(text))
+                }
+                other => {
+                    moved.push((Item::Shape(old_index), Item::Shape(shapes.len())));
+                    shapes.push(other);
+                }
+            }
+        }
+
+        let (reaction, rest) = Reaction::assemble(
+            arrow.expect("arrow at index"),
+            components,
+            self.style.bond_length * 3.0,
+        );
+        let mut added = Vec::new();
+        for component in rest {
+            match component {
+                Agent::Molecule(molecule) => {
+                    added.push(Item::Concept(concepts.len()));
+                    concepts.push(Concept::Molecule(molecule));
+                }
+                Agent::Text(text) => {
+                    added.push(Item::Shape(shapes.len()));
+                    shapes.push(Shape::Text(text));
+                }
+            }
+        }
+        added.push(Item::Concept(concepts.len()));
+        concepts.push(Concept::Reaction(Box::new(reaction)));
+        self.concepts = concepts;
+        self.shapes = shapes;
+        self.relocate_anchors(&old);
+        self.remap(|item| {
+            moved
+                .iter()
+                .find(|(from, _)| *from == item)
+                .map(|(_, to)| *to)
+        });
+        // What the reaction did not take goes with it on the layer of the
+        // arrow.
+        self.set_layer(&added, layer);
+        true
+    }
+
+    /// Takes a decoration of `kind` off the atom near `point` when it has one
+    /// there, or adds one pointing at `point`. Returns `false` when there is
+    /// no editable atom near enough.
+    pub fn decorate(&mut self, point: kurbo::Point, kind: DecorationKind) -> bool {
+        let editable: Vec<bool> = {
+            let is_editable = self.editable();
+            (0..self.concepts.len())
+                .map(|index| is_editable(Item::Concept(index)))
+                .collect()
+        };
+        let mut nearest: Option<(f64, &mut Atom)> = None;
+        for (index, concept) in self.concepts.iter_mut().enumerate() {
+            if !editable[index] {
+                continue;
+            }
+            for molecule in concept.molecules_mut() {
+                for atom in &mut molecule.atoms {
+                    let distance = point.distance(atom.position);
+                    if distance <= DECORATION_DISTANCE * 2.0
+                        && nearest.as_ref().is_none_or(|(best, _)| distance < *best)
+                    {
+                        nearest = Some((distance, atom));
+                    }
+                }
+            }
+        }
+        let Some((_, atom)) = nearest else {
+            return false;
+        };
+        let existing = atom.decorations.iter().position(|decoration| {
+            decoration.kind == kind
+                && decoration.position(atom.position).distance(point) <= DECORATION_DISTANCE / 2.0
+        });
+        match existing {
+            Some(index) => {
+                atom.undecorate(index);
+            }
+            None => atom.decorate(Decoration {
+                kind,
+                angle: (point - atom.position).atan2(),
+            }),
+        }
+        true
+    }
+
+    /// Scales `concept` about its center to the bond length of the style.
+    pub fn fit_to_style(&self, concept: &mut Concept) {
+        let (Some(length), Some(bounds)) = (concept.median_bond_length(), concept.bounding_box())
+        else {
+            return;
+        };
+        let center = bounds.center().to_vec2();
+        concept.transform(
+            kurbo::Affine::translate(center)
+                * kurbo::Affine::scale(self.style.bond_length / length)
+                * kurbo::Affine::translate(-center),
+        );
+    }
+
+    /// How `item` is drawn where it differs from the style: the settings a
+    /// properties panel shows for it.
+    pub fn appearance(&self, item: Item) -> Appearance {
+        match item {
+            Item::Shape(index) => match &self.shapes[index] {
+                Shape::Point { appearance, .. } | Shape::Line { appearance, .. } => *appearance,
+                Shape::Arrow(arrow) => arrow.appearance,
+                Shape::Figure(figure) => figure.appearance,
+                Shape::Text(annotation) => Appearance {
+                    color: Some(annotation.text.color),
+                    ..Appearance::default()
+                },
+            },
+            Item::Concept(index) => match &self.concepts[index] {
+                Concept::Molecule(molecule) => molecule.appearance(),
+                Concept::Reaction(reaction) => reaction.arrow.appearance,
+            },
+        }
+    }
+
+    /// Applies `change` to the appearance of `items`. Texts only take its
+    /// color, falling back to the color of the style.
+    pub fn restyle(&mut self, items: &[Item], change: impl Fn(&mut Appearance)) {
+        let color = self.style.color;
+        let text = |annotation: &mut Annotation| {
+            let mut appearance = Appearance {
+                color: Some(annotation.text.color),
+                ..Appearance::default()
+            };
+            change(&mut appearance);
+            annotation.text.color = appearance.color.unwrap_or(color);
+        };
+        for item in items {
+            match *item {
+                Item::Shape(index) => match &mut self.shapes[index] {
+                    Shape::Point { appearance, .. } | Shape::Line { appearance, .. } => {
+                        change(appearance)
+                    }
+                    Shape::Arrow(arrow) => change(&mut arrow.appearance),
+                    Shape::Figure(figure) => change(&mut figure.appearance),
+                    Shape::Text(annotation) => text(annotation),
+                },
+                Item::Concept(index) => match &mut self.concepts[index] {
+                    Concept::Molecule(molecule) => molecule.restyle(&change),
+                    Concept::Reaction(reaction) => {
+                        reaction.molecules_mut().for_each(|m| m.restyle(&change));
+                        reaction.texts_mut().for_each(text);
+                        change(&mut reaction.arrow.appearance);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Switches to `style`, scaling the whole document and its grid about
+    /// the origin when the bond length changes.
+    pub fn set_style(&mut self, style: Style) {
+        let scale = style.bond_length / self.style.bond_length;
+        if scale.is_finite() && scale > 0.0 && scale != 1.0 {
+            self.grid.spacing *= scale;
+            let affine = kurbo::Affine::scale(scale);
+            for concept in &mut self.concepts {
+                concept.transform(affine);
+            }
+            for shape in &mut self.shapes {
+                shape.transform(affine);
+            }
+        }
+        self.style = style;
+    }
+
+    /// Moves `items` to the end of their storage, or to the start.
+    fn reorder(&mut self, items: &[Item], to_front: bool) -> Vec<Item> {
+        fn order(len: usize, selected: impl Fn(usize) -> bool, to_front: bool) -> Vec<usize> {
+            let (chosen, rest): (Vec<usize>, Vec<usize>) = (0..len).partition(|i| selected(*i));
+            if to_front {
+                rest.into_iter().chain(chosen).collect()
+            } else {
+                chosen.into_iter().chain(rest).collect()
+            }
+        }
+        /// Where each old index ends up in `order`.
+        fn inverse(order: &[usize]) -> Vec<usize> {
+            let mut inverse = vec![0; order.len()];
+            for (new, old) in order.iter().enumerate() {
+                inverse[*old] = new;
+            }
+            inverse
+        }
+        let moved: HashSet<Item> = items.iter().copied().collect();
+        let shapes = order(
+            self.shapes.len(),
+            |i| moved.contains(&Item::Shape(i)),
+            to_front,
+        );
+        let concepts = order(
+            self.concepts.len(),
+            |i| moved.contains(&Item::Concept(i)),
+            to_front,
+        );
+
+        let old = self.concepts.clone();
+        self.shapes = shapes.iter().map(|i| self.shapes[*i].clone()).collect();
+        self.concepts = concepts.iter().map(|i| old[*i].clone()).collect();
+        self.relocate_anchors(&old);
+        let (shapes, concepts) = (inverse(&shapes), inverse(&concepts));
+        let map = |item| {
+            Some(match item {
+                Item::Shape(index) => Item::Shape(*shapes.get(index)?),
+                Item::Concept(index) => Item::Concept(*concepts.get(index)?),
+            })
+        };
+        self.remap(map);
+        items.iter().filter_map(|item| map(*item)).collect()
+    }
+
+    /// `items` split into what moves as one when arranging, the items of a
+    /// group together, each with its bounds. Items without bounds are left
+    /// out.
+    fn units(&self, items: &[Item]) -> Vec<(Vec<Item>, kurbo::Rect)> {
+        let mut units: Vec<Vec<Item>> = Vec::new();
+        for item in items {
+            if units.iter().any(|unit| unit.contains(item)) {
+                continue;
+            }
+            let unit = match self.groups.iter().find(|group| group.contains(item)) {
+                Some(group) => group
+                    .iter()
+                    .filter(|item| items.contains(item))
+                    .copied()
+                    .collect(),
+                None => vec![*item],
+            };
+            units.push(unit);
+        }
+        units
+            .into_iter()
+            .filter_map(|unit| Some((unit.clone(), self.bounds_of(&unit)?)))
+            .collect()
+    }
+
+    /// Renames the items on layers and in groups after the storage was
+    /// rearranged, dropping the ones `map` has no new item for.
+    fn remap(&mut self, map: impl Fn(Item) -> Option<Item>) {
+        for layer in &mut self.layers {
+            layer.items = layer.items.iter().filter_map(|item| map(*item)).collect();
+        }
+        for group in &mut self.groups {
+            *group = group.iter().filter_map(|item| map(*item)).collect();
+        }
+        self.groups.retain(|group| group.len() > 1);
+    }
+
+    /// Points arrow anchors at the concepts they were on before the concepts
+    /// were rearranged from `old`.
+    fn relocate_anchors(&mut self, old: &[Concept]) {
+        for shape in &mut self.shapes {
+            if let Shape::Arrow(arrow) = shape {
+                for anchor in [&mut arrow.tail_anchor, &mut arrow.head_anchor] {
+                    *anchor = anchor.and_then(|a| a.relocate(old, &self.concepts));
+                }
+            }
+        }
+    }
+}
+
+fn default_export_dpi() -> u32 {
+    300
+}
+
+fn default_layers() -> Vec<Layer> {
+    vec![Layer::new("Layer 1")]
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
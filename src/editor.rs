@@ -0,0 +1,358 @@
+//! Editing of a text on the canvas: a caret, a selection, the keys that
+//! move them and the formatting of runs, measured with the same layout the
+//! text is drawn and exported with.
+
+use kurbo::{Point, Rect};
+
+use molecular::annotation::{self, Annotation, Format, Line, Script};
+
+/// A way of moving the caret.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Up,
+    Down,
+    /// The start of the line.
+    Home,
+    /// The end of the line.
+    End,
+    DocumentStart,
+    DocumentEnd,
+}
+
+/// A formatting change.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Emphasis {
+    Bold,
+    Italic,
+    /// Toggles between the script and normal text.
+    Script(Script),
+    Color(Option<iced::Color>),
+}
+
+/// A change to the text being edited.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    /// Replaces the selection with the text, which may span lines.
+    Insert(String),
+    /// Deletes the selection or the character before the caret.
+    Backspace,
+    /// Deletes the selection or the character after the caret.
+    Delete,
+    Move(Motion),
+    /// Moves the caret, extending the selection.
+    Select(Motion),
+    SelectAll,
+    /// The mouse button went down at a point on the canvas.
+    Press(Point),
+    /// The mouse moved to a point with the button still down.
+    Drag(Point),
+    Release,
+    /// Text an input method is composing, not part of the content yet.
+    Preedit(String),
+    /// Formats the selection, or what is typed next when nothing is
+    /// selected.
+    Format(Emphasis),
+    /// Swaps the selection, or the letter before the caret, between Latin
+    /// and Greek.
+    Greek,
+    AutoFormat(bool),
+    /// Sets the font family of the whole text.
+    Font(&'static str),
+}
+
+#[derive(Clone, Debug)]
+pub struct Editor {
+    pub(crate) annotation: Annotation,
+    /// Byte offset of the caret in the content.
+    caret: usize,
+    /// The other end of the selection, if there is one.
+    anchor: Option<usize>,
+    /// Whether the mouse is selecting.
+    selecting: bool,
+    /// Format of the next typed text, when set without a selection.
+    typing: Option<Format>,
+    pub(crate) preedit: String,
+}
+
+impl Editor {
+    /// Edits `annotation` with the caret at its end.
+    pub fn new(annotation: Annotation) -> Self {
+        Self {
+            caret: annotation.content().len(),
+            annotation,
+            anchor: None,
+            selecting: false,
+            typing: None,
+            preedit: String::new(),
+        }
+    }
+
+    pub fn content(&self) -> &str {
+        self.annotation.content()
+    }
+
+    /// The selected byte range, empty when nothing is selected.
+    pub fn selection(&self) -> std::ops::Range<usize> {
+        let anchor = self.anchor.unwrap_or(self.caret);
+        anchor.min(self.caret)..anchor.max(self.caret)
+    }
+
+    pub fn is_selecting(&self) -> bool {
+        self.selecting
+    }
+
+    /// The format typed text gets: of the selection, or of the text before
+    /// the caret.
+    pub fn format(&self) -> Format {
+        let selection = self.selection();
+        if selection.is_empty() {
+            self.typing
+                .unwrap_or_else(|| self.annotation.format_at(self.caret))
+        } else {
+            self.annotation.format_at(selection.start + 1)
+        }
+    }
+
+    /// The finished text, `None` if nothing was typed.
+    pub fn finish(self) -> Option<Annotation> {
+        (!self.content().trim().is_empty()).then_some(self.annotation)
+    }
+
+    pub fn apply(&mut self, edit: Edit) {
+        match edit {
+            Edit::Insert(text) => {
+                let format = self.format();
+                let selection = self.selection();
+                self.annotation.replace(selection.clone(), &text, format);
+                self.caret = selection.start + text.len();
+                self.anchor = None;
+                self.preedit.clear();
+                if self.annotation.auto_format {
+                    self.annotation.apply_chemistry();
+                }
+            }
+            Edit::Backspace | Edit::Delete if !self.selection().is_empty() => {
+                self.apply(Edit::Insert(String::new()))
+            }
+            Edit::Backspace => {
+                if self.caret > 0 {
+                    self.anchor = Some(self.moved(Motion::Left));
+                    self.apply(Edit::Insert(String::new()));
+                }
+            }
+            Edit::Delete => {
+                if self.caret < self.content().len() {
+                    self.anchor = Some(self.moved(Motion::Right));
+                    self.apply(Edit::Insert(String::new()));
+                }
+            }
+            Edit::Move(motion) => {
+                let selection = self.selection();
+                self.caret = match motion {
+                    // Collapsing a selection leaves the caret at its edge.
+                    Motion::Left if !selection.is_empty() => selection.start,
+                    Motion::Right if !selection.is_empty() => selection.end,
+                    motion => self.moved(motion),
+                };
+                self.anchor = None;
+                self.typing = None;
+            }
+            Edit::Select(motion) => {
+                self.anchor.get_or_insert(self.caret);
+                self.caret = self.moved(motion);
+            }
+            Edit::SelectAll => {
+                self.anchor = Some(0);
+                self.caret = self.content().len();
+            }
+            Edit::Press(point) => {
+                self.caret = self.offset_at(point);
+                self.anchor = None;
+                self.selecting = true;
+                self.typing = None;
+            }
+            Edit::Drag(point) if self.selecting => {
+                self.anchor.get_or_insert(self.caret);
+                self.caret = self.offset_at(point);
+            }
+            Edit::Drag(_) => (),
+            Edit::Release => {
+                self.selecting = false;
+                if self.anchor == Some(self.caret) {
+                    self.anchor = None;
+                }
+            }
+            Edit::Preedit(text) => self.preedit = text,
+            Edit::Format(emphasis) => {
+                let current = self.format();
+                let change = move |format: &mut Format| match emphasis {
+                    Emphasis::Bold => format.bold = !current.bold,
+                    Emphasis::Italic => format.italic = !current.italic,
+                    Emphasis::Script(script) if current.script == script => {
+                        format.script = Script::Normal
+                    }
+                    Emphasis::Script(script) => format.script = script,
+                    Emphasis::Color(color) => format.color = color,
+                };
+                let selection = self.selection();
+                if selection.is_empty() {
+                    let mut format = current;
+                    change(&mut format);
+                    self.typing = Some(format);
+                } else {
+                    self.annotation.set_format(selection, change);
+                }
+            }
+            Edit::Greek => {
+                let selected = !self.selection().is_empty();
+                let range = if selected {
+                    self.selection()
+                } else {
+                    self.moved(Motion::Left)..self.caret
+                };
+                let swapped: String = self.content()[range.clone()]
+                    .chars()
+                    .map(|c| annotation::greek(c).unwrap_or(c))
+                    .collect();
+                let format = self.annotation.format_at(range.start + 1);
+                self.annotation.replace(range.clone(), &swapped, format);
+                self.caret = range.start + swapped.len();
+                self.anchor = selected.then_some(range.start);
+            }
+            Edit::AutoFormat(on) => {
+                self.annotation.auto_format = on;
+                if on {
+                    self.annotation.apply_chemistry();
+                }
+            }
+            Edit::Font(family) => {
+                self.annotation.text.font.family = iced::font::Family::Name(family)
+            }
+        }
+    }
+
+    /// Where `motion` takes the caret.
+    fn moved(&self, motion: Motion) -> usize {
+        let content = self.content();
+        let lines = self.annotation.layout();
+        let line = line_of(&lines, self.caret);
+        match motion {
+            Motion::Left => content[..self.caret]
+                .chars()
+                .next_back()
+                .map_or(self.caret, |c| self.caret - c.len_utf8()),
+            Motion::Right => content[self.caret..]
+                .chars()
+                .next()
+                .map_or(self.caret, |c| self.caret + c.len_utf8()),
+            Motion::WordLeft => {
+                let before = content[..self.caret].trim_end();
+                before
+                    .rfind(char::is_whitespace)
+                    .map_or(0, |space| space + 1)
+            }
+            Motion::WordRight => {
+                let after = &content[self.caret..];
+                let word = after.len() - after.trim_start().len();
+                let rest = &after[word..];
+                self.caret + word + rest.find(char::is_whitespace).unwrap_or(rest.len())
+            }
+            Motion::Up | Motion::Down => {
+                let target = if motion == Motion::Up {
+                    line.checked_sub(1)
+                } else {
+                    Some(line + 1).filter(|next| *next < lines.len())
+                };
+                match target {
+                    Some(target) => {
+                        let x = lines[line].x_of(content, self.caret);
+                        lines[target].offset_at(content, x)
+                    }
+                    None if motion == Motion::Up => 0,
+                    None => content.len(),
+                }
+            }
+            Motion::Home => lines[line].range.start,
+            Motion::End => lines[line].range.end,
+            Motion::DocumentStart => 0,
+            Motion::DocumentEnd => content.len(),
+        }
+    }
+
+    /// The top of the caret in document units.
+    pub fn caret_position(&self) -> Point {
+        let lines = self.annotation.layout();
+        let line = &lines[line_of(&lines, self.caret)];
+        Point::new(line.x_of(self.content(), self.caret), line.top)
+    }
+
+    /// Height of the caret.
+    pub fn caret_height(&self) -> f64 {
+        self.annotation.line_height()
+    }
+
+    /// The byte offset closest to `point`.
+    pub fn offset_at(&self, point: Point) -> usize {
+        let lines = self.annotation.layout();
+        let line = lines
+            .iter()
+            .rev()
+            .find(|line| point.y >= line.top)
+            .unwrap_or(&lines[0]);
+        line.offset_at(self.content(), point.x)
+    }
+
+    /// One rectangle per line of the selection.
+    pub fn selection_rects(&self) -> Vec<Rect> {
+        let selection = self.selection();
+        if selection.is_empty() {
+            return Vec::new();
+        }
+        let height = self.annotation.line_height();
+        self.annotation
+            .layout()
+            .iter()
+            .filter(|line| line.range.start <= selection.end && selection.start <= line.range.end)
+            .map(|line| {
+                let left = line.x_of(self.content(), selection.start.max(line.range.start));
+                let right = line.x_of(self.content(), selection.end.min(line.range.end));
+                Rect::new(left, line.top, right, line.top + height)
+            })
+            .collect()
+    }
+
+    /// The area the text takes up, at least big enough to click into when
+    /// it is empty.
+    pub fn bounds(&self) -> Rect {
+        let height = self.annotation.line_height();
+        let caret = self.caret_position();
+        let empty = Rect::from_origin_size(
+            caret - kurbo::Vec2::new(self.annotation.size() / 4.0, 0.0),
+            (self.annotation.size() / 2.0, height),
+        );
+        self.annotation
+            .layout()
+            .iter()
+            .map(|line| {
+                Rect::new(
+                    line.left,
+                    line.top,
+                    line.left + line.width,
+                    line.top + height,
+                )
+            })
+            .fold(empty, |bounds, line| bounds.union(line))
+    }
+}
+
+/// The index of the line holding byte `offset`.
+fn line_of(lines: &[Line], offset: usize) -> usize {
+    lines
+        .iter()
+        .position(|line| offset <= line.range.end)
+        .unwrap_or(lines.len() - 1)
+}
@@ -0,0 +1,196 @@
+use iced::Color;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A chemical element, identified by its atomic number.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Element(u8);
+
+// (symbol, standard atomic weight)
+const TABLE: [(&str, f64); 86] = [
+    ("H", 1.008),
+    ("He", 4.0026),
+    ("Li", 6.94),
+    ("Be", 9.0122),
+    ("B", 10.81),
+    ("C", 12.011),
+    ("N", 14.007),
+    ("O", 15.999),
+    ("F", 18.998),
+    ("Ne", 20.180),
+    ("Na", 22.990),
+    ("Mg", 24.305),
+    ("Al", 26.982),
+    ("Si", 28.085),
+    ("P", 30.974),
+    ("S", 32.06),
+    ("Cl", 35.45),
+    ("Ar", 39.948),
+    ("K", 39.098),
+    ("Ca", 40.078),
+    ("Sc", 44.956),
+    ("Ti", 47.867),
+    ("V", 50.942),
+    ("Cr", 51.996),
+    ("Mn", 54.938),
+    ("Fe", 55.845),
+    ("Co", 58.933),
+    ("Ni", 58.693),
+    ("Cu", 63.546),
+    ("Zn", 65.38),
+    ("Ga", 69.723),
+    ("Ge", 72.630),
+    ("As", 74.922),
+    ("Se", 78.971),
+    ("Br", 79.904),
+    ("Kr", 83.798),
+    ("Rb", 85.468),
+    ("Sr", 87.62),
+    ("Y", 88.906),
+    ("Zr", 91.224),
+    ("Nb", 92.906),
+    ("Mo", 95.95),
+    ("Tc", 98.0),
+    ("Ru", 101.07),
+    ("Rh", 102.91),
+    ("Pd", 106.42),
+    ("Ag", 107.87),
+    ("Cd", 112.41),
+    ("In", 114.82),
+    ("Sn", 118.71),
+    ("Sb", 121.76),
+    ("Te", 127.60),
+    ("I", 126.90),
+    ("Xe", 131.29),
+    ("Cs", 132.91),
+    ("Ba", 137.33),
+    ("La", 138.91),
+    ("Ce", 140.12),
+    ("Pr", 140.91),
+    ("Nd", 144.24),
+    ("Pm", 145.0),
+    ("Sm", 150.36),
+    ("Eu", 151.96),
+    ("Gd", 157.25),
+    ("Tb", 158.93),
+    ("Dy", 162.50),
+    ("Ho", 164.93),
+    ("Er", 167.26),
+    ("Tm", 168.93),
+    ("Yb", 173.05),
+    ("Lu", 174.97),
+    ("Hf", 178.49),
+    ("Ta", 180.95),
+    ("W", 183.84),
+    ("Re", 186.21),
+    ("Os", 190.23),
+    ("Ir", 192.22),
+    ("Pt", 195.08),
+    ("Au", 196.97),
+    ("Hg", 200.59),
+    ("Tl", 204.38),
+    ("Pb", 207.2),
+    ("Bi", 208.98),
+    ("Po", 209.0),
+    ("At", 210.0),
+    ("Rn", 222.0),
+];
+
+impl Element {
+    pub const H: Element = Element(1);
+    pub const B: Element = Element(5);
+    pub const C: Element = Element(6);
+    pub const N: Element = Element(7);
+    pub const SI: Element = Element(14);
+    pub const P: Element = Element(15);
+    pub const S: Element = Element(16);
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        TABLE
+            .iter()
+            .position(|(s, _)| *s == symbol)
+            .map(|i| Self(i as u8 + 1))
+    }
+
+    pub fn atomic_number(self) -> u8 {
+        self.0
+    }
+
+    pub fn symbol(self) -> &'static str {
+        TABLE[self.0 as usize - 1].0
+    }
+
+    /// Standard atomic weight, used when no isotope is specified.
+    pub fn mass(self) -> f64 {
+        TABLE[self.0 as usize - 1].1
+    }
+
+    /// Valences of the organic subset, lowest first. Elements outside of it
+    /// never get implicit hydrogens.
+    pub fn default_valences(self) -> &'static [u8] {
+        match self.0 {
+            1 => &[1],
+            5 => &[3],
+            6 => &[4],
+            7 => &[3, 5],
+            8 => &[2],
+            9 | 17 | 35 | 53 => &[1],
+            15 => &[3, 5],
+            16 => &[2, 4, 6],
+            _ => &[],
+        }
+    }
+
+    /// Electrons in the outer shell of the elements with default valences.
+    pub fn valence_electrons(self) -> u8 {
+        match self.0 {
+            1 => 1,
+            5 => 3,
+            6 => 4,
+            7 | 15 => 5,
+            8 | 16 => 6,
+            9 | 17 | 35 | 53 => 7,
+            _ => 0,
+        }
+    }
+
+    /// CPK color of labels colored by element, darkened where the usual
+    /// color would not read on white. `None` for carbon and hydrogen, which
+    /// keep the color of the style.
+    pub fn color(self) -> Option<Color> {
+        let rgb = |r: u8, g: u8, b: u8| Some(Color::from_rgb8(r, g, b));
+        match self.0 {
+            1 | 6 => None,
+            7 => rgb(48, 80, 248),
+            8 => rgb(230, 13, 13),
+            9 | 17 => rgb(30, 170, 30),
+            35 => rgb(153, 34, 0),
+            53 => rgb(148, 0, 148),
+            // Noble gases.
+            2 | 10 | 18 | 36 | 54 | 86 => rgb(0, 160, 190),
+            // Alkali metals.
+            3 | 11 | 19 | 37 | 55 => rgb(119, 0, 221),
+            // Alkaline earth metals.
+            4 | 12 | 20 | 38 | 56 => rgb(0, 119, 0),
+            5 => rgb(220, 120, 100),
+            15 => rgb(255, 128, 0),
+            16 => rgb(200, 160, 0),
+            22 => rgb(128, 128, 128),
+            26 => rgb(221, 119, 0),
+            _ => rgb(221, 85, 153),
+        }
+    }
+}
+
+impl Serialize for Element {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.symbol())
+    }
+}
+
+impl<'de> Deserialize<'de> for Element {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let symbol = String::deserialize(d)?;
+        Element::from_symbol(&symbol)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown element {symbol}")))
+    }
+}
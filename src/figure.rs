@@ -0,0 +1,208 @@
+//! Figures drawn around structures: boxes, ellipses, polygons and the
+//! brackets of repeating units.
+
+use kurbo::{Affine, BezPath, Point, Rect, Shape as _};
+use serde::{Deserialize, Serialize};
+
+use crate::shape::{RectDef, points};
+use crate::style::Appearance;
+
+/// Corner radius of new rounded rectangles.
+const CORNER_RADIUS: f64 = 8.0;
+/// How far the ends of brackets reach in, at most.
+const BRACKET_ARM: f64 = 6.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BracketKind {
+    Square,
+    Round,
+    Curly,
+}
+
+/// What a figure drawn from one corner to the other becomes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FigureKind {
+    Rectangle,
+    RoundedRectangle,
+    Ellipse,
+    /// A regular polygon with this many corners, fitted to the box.
+    Polygon(u8),
+    Brackets(BracketKind),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Outline {
+    Rectangle {
+        #[serde(flatten, with = "RectDef")]
+        rect: Rect,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        corner_radius: f64,
+    },
+    /// The ellipse filling `rect`.
+    Ellipse {
+        #[serde(flatten, with = "RectDef")]
+        rect: Rect,
+    },
+    Polygon {
+        #[serde(with = "points")]
+        points: Vec<Point>,
+    },
+    /// A pair of brackets on the left and right of `rect`.
+    Brackets {
+        #[serde(flatten, with = "RectDef")]
+        rect: Rect,
+        kind: BracketKind,
+        /// Set at the lower right, like the "n" of a polymer.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        subscript: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Figure {
+    pub outline: Outline,
+    #[serde(default, skip_serializing_if = "Appearance::is_default")]
+    pub appearance: Appearance,
+}
+
+impl Figure {
+    /// A figure of `kind` filling the box between `start` and `end`.
+    pub fn new(kind: FigureKind, start: Point, end: Point) -> Self {
+        let rect = Rect::from_points(start, end);
+        let outline = match kind {
+            FigureKind::Rectangle => Outline::Rectangle {
+                rect,
+                corner_radius: 0.0,
+            },
+            FigureKind::RoundedRectangle => Outline::Rectangle {
+                rect,
+                corner_radius: CORNER_RADIUS,
+            },
+            FigureKind::Ellipse => Outline::Ellipse { rect },
+            FigureKind::Polygon(corners) => {
+                let corners = corners.max(3);
+                // On the unit circle with the first corner pointing up, then
+                // stretched to fill the box.
+                let unit: Vec<Point> = (0..corners)
+                    .map(|i| {
+                        let angle = std::f64::consts::TAU * i as f64 / corners as f64;
+                        Point::new(angle.sin(), -angle.cos())
+                    })
+                    .collect();
+                let bounds = unit
+                    .iter()
+                    .fold(Rect::from_points(unit[0], unit[0]), |bounds, point| {
+                        bounds.union_pt(*point)
+                    });
+                let fit = Affine::translate(rect.origin().to_vec2())
+                    * Affine::scale_non_uniform(
+                        rect.width() / bounds.width(),
+                        rect.height() / bounds.height(),
+                    )
+                    * Affine::translate(-bounds.origin().to_vec2());
+                Outline::Polygon {
+                    points: unit.into_iter().map(|point| fit * point).collect(),
+                }
+            }
+            FigureKind::Brackets(kind) => Outline::Brackets {
+                rect,
+                kind,
+                subscript: String::new(),
+            },
+        };
+        Self {
+            outline,
+            appearance: Appearance::default(),
+        }
+    }
+
+    /// Maps the figure through `affine`. Boxes stay upright, taking the
+    /// bounds of their mapped corners.
+    pub fn transform(&mut self, affine: Affine) {
+        match &mut self.outline {
+            Outline::Rectangle {
+                rect,
+                corner_radius,
+            } => {
+                *rect = affine.transform_rect_bbox(*rect);
+                *corner_radius *= affine.determinant().abs().sqrt();
+            }
+            Outline::Ellipse { rect } | Outline::Brackets { rect, .. } => {
+                *rect = affine.transform_rect_bbox(*rect)
+            }
+            Outline::Polygon { points } => {
+                for point in points {
+                    *point = affine * *point;
+                }
+            }
+        }
+    }
+
+    /// The outline as paths to stroke, in document coordinates.
+    pub fn strokes(&self) -> Vec<BezPath> {
+        match &self.outline {
+            Outline::Rectangle {
+                rect,
+                corner_radius,
+            } => {
+                let radius = corner_radius.min(rect.width().min(rect.height()) / 2.0);
+                vec![rect.to_rounded_rect(radius).to_path(0.1)]
+            }
+            Outline::Ellipse { rect } => vec![kurbo::Ellipse::from_rect(*rect).to_path(0.1)],
+            Outline::Polygon { points } => {
+                let mut path = BezPath::new();
+                for (i, point) in points.iter().enumerate() {
+                    if i == 0 {
+                        path.move_to(*point);
+                    } else {
+                        path.line_to(*point);
+                    }
+                }
+                path.close_path();
+                vec![path]
+            }
+            Outline::Brackets { rect, kind, .. } => {
+                let left = bracket(*rect, *kind);
+                // The right bracket mirrors the left one.
+                let mirror = Affine::new([-1.0, 0.0, 0.0, 1.0, rect.x0 + rect.x1, 0.0]);
+                vec![left.clone(), mirror * left]
+            }
+        }
+    }
+}
+
+/// The left bracket of `kind` spanning the height of `rect`.
+fn bracket(rect: Rect, kind: BracketKind) -> BezPath {
+    let Rect { x0, y0, y1, .. } = rect;
+    let arm = BRACKET_ARM.min(rect.width() / 4.0).min(rect.height() / 4.0);
+    let mut path = BezPath::new();
+    path.move_to((x0 + arm, y0));
+    match kind {
+        BracketKind::Square => {
+            path.line_to((x0, y0));
+            path.line_to((x0, y1));
+            path.line_to((x0 + arm, y1));
+        }
+        // Bulges out to touch the edge of the box halfway down.
+        BracketKind::Round => path.curve_to(
+            (x0 - arm / 3.0, y0 + rect.height() * 0.2),
+            (x0 - arm / 3.0, y1 - rect.height() * 0.2),
+            (x0 + arm, y1),
+        ),
+        BracketKind::Curly => {
+            let x = x0 + arm / 2.0;
+            let middle = rect.center().y;
+            path.quad_to((x, y0), (x, y0 + arm / 2.0));
+            path.line_to((x, middle - arm / 2.0));
+            path.quad_to((x, middle), (x0, middle));
+            path.quad_to((x, middle), (x, middle + arm / 2.0));
+            path.line_to((x, y1 - arm / 2.0));
+            path.quad_to((x, y1), (x0 + arm, y1));
+        }
+    }
+    path
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
+}
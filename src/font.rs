@@ -0,0 +1,341 @@
+//! The fonts labels and texts are measured and outlined with outside of iced.
+//!
+//! Documents name their fonts. A name resolves to a font loaded from a file
+//! or, when no font of that name is loaded, to the bundled Fira Sans, which
+//! the app hands to iced as well, so exported text matches the canvas.
+
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+use kurbo::{BezPath, Point, Vec2};
+use ttf_parser::{Face, GlyphId, OutlineBuilder, name_id};
+
+use crate::render::{HorizontalAlignment, VerticalAlignment};
+
+/// The TrueType file of the bundled font, for formats that embed it.
+pub const DATA: &[u8] = include_bytes!("../fonts/FiraSans-Regular.ttf");
+/// PostScript name of the bundled font.
+pub const NAME: &str = "FiraSans-Regular";
+
+/// Fonts loaded from files, in the order they were loaded.
+static LOADED: Mutex<Vec<&'static Typeface>> = Mutex::new(Vec::new());
+/// Family names read from documents, kept for the life of the program.
+static NAMES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// A parsed font file.
+pub struct Typeface {
+    family: &'static str,
+    data: &'static [u8],
+    face: Face<'static>,
+}
+
+/// A font family as a document names it, with the typeface drawing it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Font {
+    /// The name in the document, kept when the font is missing.
+    pub family: &'static str,
+    pub typeface: &'static Typeface,
+}
+
+/// Emphasis of a text. Fonts are loaded one face at a time, so outlines
+/// fake bold by overprinting and italic by slanting.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FontStyle {
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Why a font file could not be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    Invalid(ttf_parser::FaceParsingError),
+    /// The file names no family to refer to the font by.
+    Unnamed,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(e) => write!(f, "not a font: {e}"),
+            Self::Unnamed => f.write_str("the font has no family name"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Horizontal shear of slanted glyphs.
+const SLANT: f64 = 0.2;
+/// Offset of the overprint of bold glyphs, relative to the size.
+const EMBOLDEN: f64 = 0.04;
+
+pub fn bundled() -> &'static Typeface {
+    static BUNDLED: OnceLock<Typeface> = OnceLock::new();
+    BUNDLED.get_or_init(|| {
+        let face = Face::parse(DATA, 0).expect("bundled font is valid");
+        Typeface {
+            family: family_name(&face).map_or("Fira Sans", |name| intern(&name)),
+            data: DATA,
+            face,
+        }
+    })
+}
+
+/// Makes the font in `data` available to documents by its family name. A
+/// family that is loaded already keeps its first font.
+pub fn load(data: Vec<u8>) -> Result<&'static Typeface, LoadError> {
+    let family = {
+        let face = Face::parse(&data, 0).map_err(LoadError::Invalid)?;
+        family_name(&face).ok_or(LoadError::Unnamed)?
+    };
+    if let Some(typeface) = loaded(&family) {
+        return Ok(typeface);
+    }
+    let data: &'static [u8] = Vec::leak(data);
+    let typeface: &'static Typeface = Box::leak(Box::new(Typeface {
+        family: intern(&family),
+        data,
+        face: Face::parse(data, 0).map_err(LoadError::Invalid)?,
+    }));
+    LOADED.lock().unwrap().push(typeface);
+    Ok(typeface)
+}
+
+/// The typeface of `family`, ignoring case, if it is bundled or loaded.
+pub fn loaded(family: &str) -> Option<&'static Typeface> {
+    let bundled = bundled();
+    if bundled.family.eq_ignore_ascii_case(family) {
+        return Some(bundled);
+    }
+    let loaded = LOADED.lock().unwrap();
+    loaded
+        .iter()
+        .find(|typeface| typeface.family.eq_ignore_ascii_case(family))
+        .copied()
+}
+
+/// Families that can be drawn with their own font, the bundled one first.
+pub fn families() -> Vec<&'static str> {
+    let loaded = LOADED.lock().unwrap();
+    std::iter::once(bundled().family)
+        .chain(loaded.iter().map(|typeface| typeface.family))
+        .collect()
+}
+
+/// The font for `family`, falling back to the bundled one when it is not
+/// loaded.
+pub fn resolve(family: &str) -> Font {
+    Font {
+        family: intern(family),
+        typeface: loaded(family).unwrap_or_else(bundled),
+    }
+}
+
+/// The font for an iced family. Generic families are drawn with the bundled
+/// font.
+pub fn of(family: iced::font::Family) -> Font {
+    match family {
+        iced::font::Family::Name(name) => resolve(name),
+        _ => Font::default(),
+    }
+}
+
+/// `name` as a string that lives as long as the program, which iced font
+/// families need. Each distinct name is allocated once.
+pub fn intern(name: &str) -> &'static str {
+    let mut names = NAMES.lock().unwrap();
+    if let Some(interned) = names.iter().find(|interned| **interned == name) {
+        return interned;
+    }
+    let interned: &'static str = String::leak(name.to_string());
+    names.push(interned);
+    interned
+}
+
+fn family_name(face: &Face) -> Option<String> {
+    // The typographic family groups weights a legacy family name splits up.
+    [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+        .into_iter()
+        .find_map(|id| {
+            face.names()
+                .into_iter()
+                .filter(|name| name.name_id == id)
+                .find_map(|name| name.to_string())
+        })
+}
+
+impl Default for Font {
+    /// The bundled font.
+    fn default() -> Self {
+        let typeface = bundled();
+        Self {
+            family: typeface.family,
+            typeface,
+        }
+    }
+}
+
+impl Font {
+    /// Whether the document names a font that is not loaded.
+    pub fn is_missing(&self) -> bool {
+        !self.family.eq_ignore_ascii_case(self.typeface.family)
+    }
+}
+
+impl fmt::Debug for Typeface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Typeface").field(&self.family).finish()
+    }
+}
+
+impl PartialEq for Typeface {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Typeface {
+    pub fn family(&self) -> &'static str {
+        self.family
+    }
+
+    /// The font file.
+    pub fn data(&self) -> &'static [u8] {
+        self.data
+    }
+
+    pub fn face(&self) -> &Face<'static> {
+        &self.face
+    }
+
+    /// Glyph `c` maps to, the missing glyph if the font lacks it.
+    pub fn glyph(&self, c: char) -> GlyphId {
+        self.face.glyph_index(c).unwrap_or(GlyphId(0))
+    }
+
+    /// Horizontal advance of glyph `id` in font units.
+    pub fn advance(&self, id: GlyphId) -> u16 {
+        self.face.glyph_hor_advance(id).unwrap_or(0)
+    }
+
+    /// Width of a line of `text` at `size`, in document units.
+    pub fn width(&self, text: &str, size: f64) -> f64 {
+        let units: u32 = text
+            .chars()
+            .map(|c| self.advance(self.glyph(c)) as u32)
+            .sum();
+        units as f64 * self.scale(size)
+    }
+
+    /// Document units per font unit at `size`.
+    pub fn scale(&self, size: f64) -> f64 {
+        size / self.face.units_per_em() as f64
+    }
+
+    pub fn ascender(&self, size: f64) -> f64 {
+        self.face.ascender() as f64 * self.scale(size)
+    }
+
+    /// Negative, below the baseline.
+    pub fn descender(&self, size: f64) -> f64 {
+        self.face.descender() as f64 * self.scale(size)
+    }
+
+    /// Where the first glyph of a line of text aligned at `position` starts
+    /// on the baseline.
+    pub fn origin(
+        &self,
+        text: &str,
+        position: Point,
+        size: f64,
+        align_x: HorizontalAlignment,
+        align_y: VerticalAlignment,
+    ) -> Point {
+        let x = match align_x {
+            HorizontalAlignment::Left => position.x,
+            HorizontalAlignment::Center => position.x - self.width(text, size) / 2.0,
+            HorizontalAlignment::Right => position.x - self.width(text, size),
+        };
+        let y = match align_y {
+            VerticalAlignment::Top => position.y + self.ascender(size),
+            VerticalAlignment::Center => {
+                position.y + (self.ascender(size) + self.descender(size)) / 2.0
+            }
+            VerticalAlignment::Bottom => position.y + self.descender(size),
+        };
+        Point::new(x, y)
+    }
+
+    /// Outlines of a line of `text` with its baseline starting at `origin`.
+    pub fn outline(&self, text: &str, origin: Point, size: f64, style: FontStyle) -> BezPath {
+        let mut path = self.upright_outline(text, origin, size);
+        if style.italic {
+            // Shear about the baseline, leaning the tops of the glyphs right.
+            path.apply_affine(kurbo::Affine::new([
+                1.0,
+                0.0,
+                -SLANT,
+                1.0,
+                SLANT * origin.y,
+                0.0,
+            ]));
+        }
+        if style.bold {
+            let mut overprint = path.clone();
+            overprint.apply_affine(kurbo::Affine::translate((EMBOLDEN * size, 0.0)));
+            path.extend(overprint);
+        }
+        path
+    }
+
+    fn upright_outline(&self, text: &str, origin: Point, size: f64) -> BezPath {
+        let scale = self.scale(size);
+        let mut builder = Builder {
+            path: BezPath::new(),
+            origin,
+            scale,
+        };
+        for c in text.chars() {
+            let id = self.glyph(c);
+            self.face.outline_glyph(id, &mut builder);
+            builder.origin += Vec2::new(self.advance(id) as f64 * scale, 0.0);
+        }
+        builder.path
+    }
+}
+
+/// Collects glyph outlines, flipping them from the font's y-up space.
+struct Builder {
+    path: BezPath,
+    origin: Point,
+    scale: f64,
+}
+
+impl Builder {
+    fn point(&self, x: f32, y: f32) -> Point {
+        self.origin + Vec2::new(x as f64 * self.scale, -y as f64 * self.scale)
+    }
+}
+
+impl OutlineBuilder for Builder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.path.move_to(self.point(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.path.line_to(self.point(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.path.quad_to(self.point(x1, y1), self.point(x, y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.path
+            .curve_to(self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+    }
+
+    fn close(&mut self) {
+        self.path.close_path();
+    }
+}
@@ -0,0 +1,189 @@
+//! The background grid of a document, and snapping points and moves to it.
+
+use kurbo::{Line, Point, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::style::Style;
+
+/// Grids with more lines than this in view are not drawn, they would only
+/// gray out the drawing.
+const MAX_LINES: usize = 20_000;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GridKind {
+    #[default]
+    Square,
+    /// Hexagons with vertical sides, so bonds drawn along them meet at 120°.
+    Hexagonal,
+}
+
+impl GridKind {
+    pub const ALL: [GridKind; 2] = [GridKind::Square, GridKind::Hexagonal];
+}
+
+impl std::fmt::Display for GridKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GridKind::Square => "square",
+            GridKind::Hexagonal => "hexagonal",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Grid {
+    pub kind: GridKind,
+    /// Side of the squares, or of the hexagons, in document units.
+    pub spacing: f64,
+    /// Whether the grid is drawn behind the document.
+    pub shown: bool,
+    /// Whether drawing and moving stick to the grid.
+    pub snap: bool,
+    /// Whether rulers are shown along the edges of the canvas.
+    pub rulers: bool,
+}
+
+impl Default for Grid {
+    /// A hidden square grid one default bond long.
+    fn default() -> Self {
+        Self {
+            kind: GridKind::Square,
+            spacing: Style::default().bond_length,
+            shown: false,
+            snap: false,
+            rulers: false,
+        }
+    }
+}
+
+impl Grid {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The grid point closest to `point`. For hexagonal grids these are the
+    /// corners of the hexagons.
+    pub fn snap(&self, point: Point) -> Point {
+        match self.kind {
+            GridKind::Square => Point::new(
+                (point.x / self.spacing).round() * self.spacing,
+                (point.y / self.spacing).round() * self.spacing,
+            ),
+            GridKind::Hexagonal => {
+                // Two lattices of corners, the upper and the lower end of
+                // every vertical side.
+                let (a, b) = self.lattice();
+                let lower = Vec2::new(0.0, self.spacing);
+                let upper_corner = nearest(point, a, b, Vec2::ZERO);
+                let lower_corner = nearest(point, a, b, lower);
+                if point.distance(upper_corner) <= point.distance(lower_corner) {
+                    upper_corner
+                } else {
+                    lower_corner
+                }
+            }
+        }
+    }
+
+    /// The move closest to `offset` that keeps points on the grid on it.
+    pub fn snap_offset(&self, offset: Vec2) -> Vec2 {
+        match self.kind {
+            GridKind::Square => self.snap(offset.to_point()).to_vec2(),
+            GridKind::Hexagonal => {
+                let (a, b) = self.lattice();
+                nearest(offset.to_point(), a, b, Vec2::ZERO).to_vec2()
+            }
+        }
+    }
+
+    /// The lines of the grid crossing `rect`, none if there would be too
+    /// many to make out.
+    pub fn lines(&self, rect: Rect) -> Vec<Line> {
+        if !self.spacing.is_finite() || self.spacing <= 0.0 {
+            return Vec::new();
+        }
+        match self.kind {
+            GridKind::Square => {
+                let steps = |from: f64, to: f64| {
+                    let first = (from / self.spacing).ceil() as i64;
+                    let last = (to / self.spacing).floor() as i64;
+                    (first..=last).map(|step| step as f64 * self.spacing)
+                };
+                let count = (rect.width() + rect.height()) / self.spacing;
+                if count > MAX_LINES as f64 {
+                    return Vec::new();
+                }
+                let vertical =
+                    steps(rect.x0, rect.x1).map(|x| Line::new((x, rect.y0), (x, rect.y1)));
+                let horizontal =
+                    steps(rect.y0, rect.y1).map(|y| Line::new((rect.x0, y), (rect.x1, y)));
+                vertical.chain(horizontal).collect()
+            }
+            GridKind::Hexagonal => {
+                let (a, b) = self.lattice();
+                // Every upper corner has three sides: down to its lower
+                // corner and up to the lower corners above it on either side.
+                let lower = Vec2::new(0.0, self.spacing);
+                let sides = [lower, lower - b, lower - b + a];
+                let rows = (rect.height() / b.y).ceil() + 2.0;
+                let columns = (rect.width() / a.x).ceil() + 2.0;
+                if rows * columns * 3.0 > MAX_LINES as f64 {
+                    return Vec::new();
+                }
+                let first_row = (rect.y0 / b.y).floor() as i64 - 1;
+                let mut lines = Vec::new();
+                for row in first_row..first_row + rows as i64 {
+                    let start = b * row as f64;
+                    let first_column = ((rect.x0 - start.x) / a.x).floor() as i64 - 1;
+                    for column in first_column..first_column + columns as i64 {
+                        let corner = (start + a * column as f64).to_point();
+                        for side in sides {
+                            let line = Line::new(corner, corner + side);
+                            if overlaps(rect, line) {
+                                lines.push(line);
+                            }
+                        }
+                    }
+                }
+                lines
+            }
+        }
+    }
+
+    /// The two steps from an upper corner of a hexagonal grid to the next
+    /// ones, to the right and diagonally down to the right.
+    fn lattice(&self) -> (Vec2, Vec2) {
+        let width = 3f64.sqrt() * self.spacing;
+        (
+            Vec2::new(width, 0.0),
+            Vec2::new(width / 2.0, 1.5 * self.spacing),
+        )
+    }
+}
+
+/// The point of the lattice `origin + i * a + j * b` closest to `point`, for
+/// `a` horizontal.
+fn nearest(point: Point, a: Vec2, b: Vec2, origin: Vec2) -> Point {
+    let p = point - origin;
+    let j = p.y / b.y;
+    let i = (p.x - j * b.x) / a.x;
+    let mut best = origin.to_point();
+    let mut distance = f64::INFINITY;
+    for i in [i.floor(), i.ceil()] {
+        for j in [j.floor(), j.ceil()] {
+            let candidate = (origin + a * i + b * j).to_point();
+            if candidate.distance(point) < distance {
+                distance = candidate.distance(point);
+                best = candidate;
+            }
+        }
+    }
+    best
+}
+
+/// Whether `line` has any point in `rect`, as far as its bounds tell.
+fn overlaps(rect: Rect, line: Line) -> bool {
+    let bounds = Rect::from_points(line.p0, line.p1);
+    bounds.x0 <= rect.x1 && rect.x0 <= bounds.x1 && bounds.y0 <= rect.y1 && rect.y0 <= bounds.y1
+}
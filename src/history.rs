@@ -0,0 +1,93 @@
+use molecular::Document;
+
+/// Changes kept to undo, the oldest dropped first.
+const MAX_UNDO: usize = 100;
+
+/// Earlier and undone states of a document.
+#[derive(Debug)]
+pub struct History {
+    undo: Vec<Document>,
+    redo: Vec<Document>,
+    /// The document when the change being made, like a drag, started.
+    pending: Option<Document>,
+    /// How many changes there were to undo when the document was saved,
+    /// `None` once that state cannot be reached again.
+    saved: Option<usize>,
+}
+
+impl Default for History {
+    /// The history of a document as it was loaded, with nothing to save.
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            pending: None,
+            saved: Some(0),
+        }
+    }
+}
+
+impl History {
+    /// Remembers `before` to go back to, if it differs from `after`. Whether
+    /// it did.
+    pub fn record(&mut self, before: Document, after: &Document) -> bool {
+        if before == *after {
+            return false;
+        }
+        // The saved state was undone, and is dropped with the redo steps.
+        if self.saved.is_some_and(|saved| saved > self.undo.len()) {
+            self.saved = None;
+        }
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+            self.saved = self.saved.and_then(|saved| saved.checked_sub(1));
+        }
+        self.undo.push(before);
+        self.redo.clear();
+        true
+    }
+
+    /// Starts a change made over several messages, recorded as one by
+    /// [`History::end`].
+    pub fn begin(&mut self, document: &Document) {
+        self.pending = Some(document.clone());
+    }
+
+    /// Records the change since [`History::begin`], if any. Whether there
+    /// was one.
+    pub fn end(&mut self, document: &Document) -> bool {
+        self.pending
+            .take()
+            .is_some_and(|before| self.record(before, document))
+    }
+
+    /// Puts `document` back the way it was before the last change. Whether
+    /// there was one.
+    pub fn undo(&mut self, document: &mut Document) -> bool {
+        let Some(before) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(std::mem::replace(document, before));
+        true
+    }
+
+    /// Makes the last undone change again. Whether there was one.
+    pub fn redo(&mut self, document: &mut Document) -> bool {
+        let Some(after) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(std::mem::replace(document, after));
+        true
+    }
+
+    /// Marks the document as it is now as saved.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo.len());
+    }
+
+    /// Whether the document differs from the one last saved, undoing back to
+    /// it counting as no change.
+    pub fn changed(&self) -> bool {
+        self.saved != Some(self.undo.len())
+    }
+}
@@ -0,0 +1,165 @@
+//! Canvases don't ask the system for an input method, so composed text
+//! (accents, CJK, emoji pickers) never reaches them. This wraps the canvas
+//! and asks for one while a text is being edited.
+
+use iced::advanced::input_method::{self, InputMethod, Preedit};
+use iced::advanced::widget::{Operation, Tree};
+use iced::advanced::{Clipboard, Layout, Shell, Widget, layout, mouse, overlay, renderer};
+use iced::{Element, Event, Length, Rectangle, Size, Vector};
+
+pub struct InputMethodArea<'a, Message> {
+    content: Element<'a, Message>,
+    /// The caret, relative to the center of the content.
+    caret: Option<Rectangle>,
+    preedit: String,
+}
+
+impl<'a, Message> InputMethodArea<'a, Message> {
+    /// Wraps `content`, asking for an input method at `caret` when there is
+    /// one. `preedit` is the text being composed so far.
+    pub fn new(
+        content: impl Into<Element<'a, Message>>,
+        caret: Option<Rectangle>,
+        preedit: &str,
+    ) -> Self {
+        Self {
+            content: content.into(),
+            caret,
+            preedit: preedit.to_string(),
+        }
+    }
+}
+
+impl<Message> Widget<Message, iced::Theme, iced::Renderer> for InputMethodArea<'_, Message> {
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.content));
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(
+        &mut self,
+        tree: &mut Tree,
+        renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content
+            .as_widget_mut()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn operate(
+        &mut self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        operation: &mut dyn Operation,
+    ) {
+        self.content
+            .as_widget_mut()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn update(
+        &mut self,
+        tree: &mut Tree,
+        event: &Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget_mut().update(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        );
+
+        if let Some(caret) = self.caret {
+            let center = layout.bounds().center();
+            shell.request_input_method(&InputMethod::Enabled {
+                cursor: caret + Vector::new(center.x, center.y),
+                purpose: input_method::Purpose::Normal,
+                preedit: (!self.preedit.is_empty()).then_some(Preedit {
+                    content: self.preedit.as_str(),
+                    selection: None,
+                    text_size: None,
+                }),
+            });
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'b>,
+        renderer: &iced::Renderer,
+        viewport: &Rectangle,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, iced::Theme, iced::Renderer>> {
+        self.content.as_widget_mut().overlay(
+            &mut tree.children[0],
+            layout,
+            renderer,
+            viewport,
+            translation,
+        )
+    }
+}
+
+impl<'a, Message: 'a> From<InputMethodArea<'a, Message>> for Element<'a, Message> {
+    fn from(area: InputMethodArea<'a, Message>) -> Self {
+        Element::new(area)
+    }
+}
@@ -0,0 +1,105 @@
+//! Text put on and read from the system clipboard.
+//!
+//! The clipboard only carries text, so a copied fragment travels as one SD
+//! record: a molfile of its molecules titled with their SMILES, followed by
+//! data items with the SMILES and the fragment itself as JSON. Other programs
+//! pick up the molfile or SMILES, this one restores the exact fragment.
+//! Pasted text may also be a bare molfile, an RXN file or (reaction) SMILES.
+
+use serde::{Deserialize, Serialize};
+
+use crate::concept::Concept;
+use crate::io::{ParseError, molfile, rxn, smiles};
+use crate::molecule::Molecule;
+use crate::shape::Shape;
+
+/// Name of the data item holding the native fragment.
+const FRAGMENT_FIELD: &str = "MOLECULAR";
+const SMILES_FIELD: &str = "SMILES";
+/// Longest title line a V2000 molfile allows.
+const MAX_TITLE: usize = 80;
+
+/// Copied items of a document, with anchors numbered within the fragment.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Fragment {
+    pub shapes: Vec<Shape>,
+    pub concepts: Vec<Concept>,
+    /// Bond length of the document it was copied from, `None` for fragments
+    /// read from other formats.
+    pub bond_length: Option<f64>,
+}
+
+pub fn write(fragment: &Fragment) -> String {
+    let mut molecule = Molecule::default();
+    let mut molecules = Vec::new();
+    let mut reactions = Vec::new();
+    for concept in &fragment.concepts {
+        match concept {
+            Concept::Molecule(m) => {
+                molecule.merge(m);
+                molecules.push(smiles::write(m));
+            }
+            Concept::Reaction(r) => {
+                r.molecules().for_each(|m| molecule.merge(m));
+                reactions.push(smiles::write_reaction(r));
+            }
+        }
+    }
+    // Molecules share one SMILES, every reaction needs a line of its own.
+    let mut lines = Vec::new();
+    if !molecules.is_empty() {
+        lines.push(molecules.join("."));
+    }
+    lines.extend(reactions);
+    let smiles = lines.join("\n");
+
+    let title = match lines.as_slice() {
+        [line] if line.len() <= MAX_TITLE => line,
+        _ => "",
+    };
+    let mut out = title.to_string();
+    out.push_str(&molfile::write(
+        &molecule,
+        molfile::Version::for_molecule(&molecule),
+    ));
+    if !smiles.is_empty() {
+        out.push_str(&format!("> <{SMILES_FIELD}>\n{smiles}\n\n"));
+    }
+    let json = serde_json::to_string(fragment).expect("fragments serialize");
+    out.push_str(&format!("> <{FRAGMENT_FIELD}>\n{json}\n\n$$$$\n"));
+    out
+}
+
+/// Reads clipboard text in any of the supported formats, newest first: the
+/// native fragment, RXN, molfile, then SMILES on the first line.
+pub fn read(text: &str) -> Result<Fragment, ParseError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let header = format!("> <{FRAGMENT_FIELD}>");
+    if let Some(index) = lines.iter().position(|line| line.trim_end() == header) {
+        let json = lines.get(index + 1).copied().unwrap_or_default();
+        return serde_json::from_str(json)
+            .map_err(|e| ParseError::new(index + 2, format!("broken fragment: {e}")));
+    }
+
+    let concept = if text.trim_start().starts_with("$RXN") {
+        rxn::parse(text.trim_start()).map(|reaction| Concept::Reaction(Box::new(reaction)))?
+    } else if lines.iter().any(|line| line.starts_with("M  END")) {
+        molfile::parse(text).map(Concept::Molecule)?
+    } else {
+        let line = lines
+            .iter()
+            .map(|line| line.trim())
+            .find(|line| !line.is_empty())
+            .ok_or_else(|| ParseError::new(1, "nothing to paste"))?;
+        if line.contains('>') {
+            smiles::parse_reaction(line).map(|reaction| Concept::Reaction(Box::new(reaction)))?
+        } else {
+            smiles::parse(line).map(Concept::Molecule)?
+        }
+    };
+    Ok(Fragment {
+        shapes: Vec::new(),
+        concepts: vec![concept],
+        bond_length: None,
+    })
+}
@@ -0,0 +1,108 @@
+//! Encapsulated PostScript images of a scene, cropped to its content or
+//! laid out on its page.
+//!
+//! Labels are drawn as glyph outlines of their fonts, so the file does not
+//! depend on the fonts of the printer. Colors are opaque.
+
+use std::fmt::Write;
+
+use iced::Color;
+
+use crate::io::{number, postscript_path};
+use crate::render::{Primitive, Scene};
+
+/// Points per document unit.
+const POINTS: f64 = 0.75;
+const PATH_OPERATORS: [&str; 4] = ["moveto", "lineto", "curveto", "closepath"];
+
+pub fn write(scene: &Scene) -> String {
+    let bounds = scene.extent();
+    let (width, height) = (bounds.width() * POINTS, bounds.height() * POINTS);
+    let mut out = String::new();
+
+    write!(
+        out,
+        "%!PS-Adobe-3.0 EPSF-3.0\n\
+         %%Creator: Molecular\n\
+         %%BoundingBox: 0 0 {} {}\n\
+         %%HiResBoundingBox: 0 0 {} {}\n\
+         %%EndComments\n\
+         gsave\n\
+         0 {} translate {} {} scale {} {} translate\n\
+         1 setlinecap 1 setlinejoin\n",
+        width.ceil(),
+        height.ceil(),
+        number(width),
+        number(height),
+        number(height),
+        number(POINTS),
+        number(-POINTS),
+        number(-bounds.x0),
+        number(-bounds.y0),
+    )
+    .unwrap();
+
+    for primitive in &scene.primitives {
+        match primitive {
+            Primitive::Stroke {
+                path,
+                width,
+                color,
+                dash,
+            } => {
+                let dash: Vec<String> = dash.iter().map(|d| number(*d)).collect();
+                write!(
+                    out,
+                    "newpath\n{}{} setrgbcolor {} setlinewidth [{}] 0 setdash stroke\n",
+                    postscript_path(path, PATH_OPERATORS),
+                    rgb(*color),
+                    number(*width),
+                    dash.join(" "),
+                )
+                .unwrap();
+            }
+            Primitive::Fill { path, color } => fill(&mut out, path, *color),
+            Primitive::Text {
+                content,
+                position,
+                size,
+                color,
+                align_x,
+                align_y,
+                font,
+                style,
+            } => {
+                let origin = font
+                    .typeface
+                    .origin(content, *position, *size, *align_x, *align_y);
+                fill(
+                    &mut out,
+                    &font.typeface.outline(content, origin, *size, *style),
+                    *color,
+                );
+            }
+        }
+    }
+
+    out.push_str("grestore\nshowpage\n%%EOF\n");
+    out
+}
+
+fn fill(out: &mut String, path: &kurbo::BezPath, color: Color) {
+    write!(
+        out,
+        "newpath\n{}{} setrgbcolor fill\n",
+        postscript_path(path, PATH_OPERATORS),
+        rgb(color),
+    )
+    .unwrap();
+}
+
+fn rgb(color: Color) -> String {
+    format!(
+        "{} {} {}",
+        number(color.r as f64),
+        number(color.g as f64),
+        number(color.b as f64)
+    )
+}
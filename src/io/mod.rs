@@ -0,0 +1,100 @@
+//! Reading and writing structures in the formats other chemistry tools use,
+//! and exporting drawings as images.
+
+use std::fmt;
+
+use kurbo::{BezPath, PathEl, Point};
+
+pub mod clipboard;
+pub mod eps;
+pub mod molfile;
+pub mod pdf;
+pub mod png;
+pub mod rxn;
+pub mod sdf;
+pub mod smiles;
+pub mod svg;
+
+/// Why a structure could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line of a file, or character of a SMILES string.
+    pub position: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at {})", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Shortest decimal that keeps a hundredth of a unit.
+pub(crate) fn number(value: f64) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    if rounded == 0.0 {
+        "0".to_string()
+    } else {
+        rounded.to_string()
+    }
+}
+
+/// `path` in PostScript style operators, as used by PDF content streams and
+/// EPS files. `operators` names move, line, curve and close, in that order.
+/// Quadratic segments are raised to cubics, which both formats lack.
+pub(crate) fn postscript_path(path: &BezPath, operators: [&str; 4]) -> String {
+    let [move_to, line_to, curve_to, close] = operators;
+    let point = |p: Point| format!("{} {}", number(p.x), number(p.y));
+    let mut out = String::new();
+    let mut current = Point::ORIGIN;
+    let mut start = Point::ORIGIN;
+
+    for element in path.elements() {
+        match *element {
+            PathEl::MoveTo(p) => {
+                out.push_str(&format!("{} {move_to}\n", point(p)));
+                (current, start) = (p, p);
+            }
+            PathEl::LineTo(p) => {
+                out.push_str(&format!("{} {line_to}\n", point(p)));
+                current = p;
+            }
+            PathEl::QuadTo(c, p) => {
+                let cubic = kurbo::QuadBez::new(current, c, p).raise();
+                out.push_str(&format!(
+                    "{} {} {} {curve_to}\n",
+                    point(cubic.p1),
+                    point(cubic.p2),
+                    point(p)
+                ));
+                current = p;
+            }
+            PathEl::CurveTo(c1, c2, p) => {
+                out.push_str(&format!(
+                    "{} {} {} {curve_to}\n",
+                    point(c1),
+                    point(c2),
+                    point(p)
+                ));
+                current = p;
+            }
+            PathEl::ClosePath => {
+                out.push_str(close);
+                out.push('\n');
+                current = start;
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,606 @@
+//! MDL molfiles, V2000 and V3000.
+//!
+//! Molfile coordinates are in Ångström with y pointing up. They are scaled so
+//! bonds get the document bond length and flipped into screen coordinates.
+
+use kurbo::Point;
+
+use crate::element::Element;
+use crate::io::ParseError;
+use crate::layout::BOND_LENGTH;
+use crate::molecule::{Atom, Bond, BondOrder, BondStereo, Molecule};
+use crate::style::Appearance;
+
+/// Bond length written to files, the usual C-C distance.
+const FILE_BOND_LENGTH: f64 = 1.5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Version {
+    V2000,
+    V3000,
+}
+
+impl Version {
+    /// V2000 counts are limited to three digits.
+    pub fn for_molecule(molecule: &Molecule) -> Self {
+        if molecule.atoms.len() > 999 || molecule.bonds.len() > 999 {
+            Self::V3000
+        } else {
+            Self::V2000
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Molecule, ParseError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let counts = lines
+        .get(3)
+        .ok_or_else(|| ParseError::new(lines.len(), "molfile ends before the counts line"))?;
+
+    let mut molecule = if counts.contains("V3000") {
+        let ctab: Vec<(usize, String)> = v30_lines(&lines, 4);
+        parse_ctab_v3000(&ctab)?
+    } else {
+        parse_ctab_v2000(&lines, 3)?
+    };
+    normalize_coordinates(&mut molecule);
+    Ok(molecule)
+}
+
+pub fn write(molecule: &Molecule, version: Version) -> String {
+    let mut out = String::new();
+    out.push_str("\n  Molecular          2D\n\n");
+    match version {
+        Version::V2000 => write_ctab_v2000(molecule, &mut out),
+        Version::V3000 => {
+            out.push_str("  0  0  0     0  0            999 V3000\n");
+            write_ctab_v3000(molecule, &mut out);
+            out.push_str("M  END\n");
+        }
+    }
+    out
+}
+
+/// Scales file coordinates to the document bond length and flips y.
+pub(crate) fn normalize_coordinates(molecule: &mut Molecule) {
+    let median = molecule.median_bond_length().unwrap_or(FILE_BOND_LENGTH);
+    let scale = BOND_LENGTH / median;
+
+    for atom in &mut molecule.atoms {
+        atom.position = Point::new(atom.position.x * scale, -atom.position.y * scale);
+    }
+}
+
+/// Scale from document coordinates to file coordinates with bonds of the
+/// usual length.
+fn file_scale(molecule: &Molecule) -> f64 {
+    FILE_BOND_LENGTH / molecule.median_bond_length().unwrap_or(BOND_LENGTH)
+}
+
+fn file_coordinates(position: Point, scale: f64) -> (f64, f64) {
+    (position.x * scale, -position.y * scale)
+}
+
+fn field(line: &str, start: usize, end: usize) -> &str {
+    line.get(start.min(line.len())..end.min(line.len()))
+        .unwrap_or_default()
+        .trim()
+}
+
+fn number<T: std::str::FromStr + Default>(
+    line: &str,
+    start: usize,
+    end: usize,
+    index: usize,
+) -> Result<T, ParseError> {
+    let text = field(line, start, end);
+    if text.is_empty() {
+        return Ok(T::default());
+    }
+    text.parse()
+        .map_err(|_| ParseError::new(index + 1, format!("expected a number, found '{text}'")))
+}
+
+/// Element and isotope of an atom symbol, reading deuterium and tritium as
+/// hydrogen isotopes.
+fn element(symbol: &str, index: usize) -> Result<(Element, Option<u16>), ParseError> {
+    match symbol {
+        "D" => Ok((Element::H, Some(2))),
+        "T" => Ok((Element::H, Some(3))),
+        _ => Element::from_symbol(symbol)
+            .map(|element| (element, None))
+            .ok_or_else(|| {
+                ParseError::new(index + 1, format!("unsupported atom symbol '{symbol}'"))
+            }),
+    }
+}
+
+fn bond_order(code: u8, index: usize) -> Result<BondOrder, ParseError> {
+    match code {
+        1 => Ok(BondOrder::Single),
+        2 => Ok(BondOrder::Double),
+        3 => Ok(BondOrder::Triple),
+        4 => Ok(BondOrder::Aromatic),
+        _ => Err(ParseError::new(
+            index + 1,
+            format!("unsupported bond type {code}"),
+        )),
+    }
+}
+
+fn bond_code(order: BondOrder) -> u8 {
+    match order {
+        BondOrder::Single => 1,
+        BondOrder::Double => 2,
+        BondOrder::Triple => 3,
+        BondOrder::Aromatic => 4,
+    }
+}
+
+/// Integer word of a V3000 line, out of range values being errors.
+fn integer<T: std::str::FromStr>(text: &str, index: usize) -> Result<T, ParseError> {
+    text.parse()
+        .map_err(|_| ParseError::new(index + 1, format!("expected a number, found '{text}'")))
+}
+
+/// Rejects bonds that would join an atom to itself or repeat a bond.
+fn check_bond(
+    molecule: &Molecule,
+    begin: usize,
+    end: usize,
+    index: usize,
+) -> Result<(), ParseError> {
+    if begin == end {
+        return Err(ParseError::new(index + 1, "bond from an atom to itself"));
+    }
+    if molecule.bond_between(begin, end).is_some() {
+        return Err(ParseError::new(
+            index + 1,
+            "second bond between the same atoms",
+        ));
+    }
+    Ok(())
+}
+
+/// Sum of the bond orders at `atom`, saturating for absurd structures.
+fn bonded_valence(molecule: &Molecule, atom: usize) -> u8 {
+    molecule
+        .neighbors(atom)
+        .map(|(bond, _)| match molecule.bonds[bond].order {
+            BondOrder::Single | BondOrder::Aromatic => 1,
+            BondOrder::Double => 2,
+            BondOrder::Triple => 3,
+        })
+        .fold(0, u8::saturating_add)
+}
+
+/// Sets the hydrogen override of atoms with an explicit total valence.
+fn apply_valences(molecule: &mut Molecule, valences: &[(usize, u8)]) {
+    for (atom, valence) in valences {
+        let bonded = bonded_valence(molecule, *atom);
+        molecule.atoms[*atom].hydrogens = Some(valence.saturating_sub(bonded));
+    }
+}
+
+/// Total valence to write for atoms whose hydrogen count the reader's
+/// valence model would not reproduce.
+fn explicit_valence(molecule: &Molecule, atom: usize) -> Option<u8> {
+    let hydrogens = molecule.atoms[atom].hydrogens?;
+    if hydrogens == molecule.default_hydrogens(atom) {
+        return None;
+    }
+    Some(bonded_valence(molecule, atom).saturating_add(hydrogens))
+}
+
+/// Parses the V2000 connection table whose counts line is `lines[counts]`,
+/// returning the molecule in file coordinates.
+pub(crate) fn parse_ctab_v2000(lines: &[&str], counts: usize) -> Result<Molecule, ParseError> {
+    let line = |index: usize| {
+        lines
+            .get(index)
+            .copied()
+            .ok_or_else(|| ParseError::new(index + 1, "molfile ends early"))
+    };
+    let counts_line = line(counts)?;
+    let atom_count: usize = number(counts_line, 0, 3, counts)?;
+    let bond_count: usize = number(counts_line, 3, 6, counts)?;
+
+    let mut molecule = Molecule::default();
+    let mut valences = Vec::new();
+    for i in 0..atom_count {
+        let index = counts + 1 + i;
+        let text = line(index)?;
+        let x: f64 = number(text, 0, 10, index)?;
+        let y: f64 = number(text, 10, 20, index)?;
+        let (element, mut isotope) = element(field(text, 31, 34), index)?;
+        let mass_difference: i16 = number(text, 34, 36, index)?;
+        if mass_difference != 0 {
+            let mass = element.mass().round() as i16 + mass_difference;
+            isotope = Some(
+                u16::try_from(mass)
+                    .ok()
+                    .filter(|mass| *mass > 0)
+                    .ok_or_else(|| ParseError::new(index + 1, "isotope out of range"))?,
+            );
+        }
+        let charge_code: u8 = number(text, 36, 39, index)?;
+        let valence: u8 = number(text, 48, 51, index)?;
+        let map: u32 = number(text, 60, 63, index)?;
+
+        let mut atom = Atom::new(element, Point::new(x, y));
+        atom.isotope = isotope;
+        atom.charge = match charge_code {
+            1 => 3,
+            2 => 2,
+            3 => 1,
+            5 => -1,
+            6 => -2,
+            7 => -3,
+            _ => 0,
+        };
+        atom.map = (map != 0).then_some(map);
+        match valence {
+            0 => (),
+            15 => valences.push((i, 0)),
+            v => valences.push((i, v)),
+        }
+        molecule.atoms.push(atom);
+    }
+
+    for i in 0..bond_count {
+        let index = counts + 1 + atom_count + i;
+        let text = line(index)?;
+        let begin: usize = number(text, 0, 3, index)?;
+        let end: usize = number(text, 3, 6, index)?;
+        if begin == 0 || end == 0 || begin > atom_count || end > atom_count {
+            return Err(ParseError::new(index + 1, "bond to a missing atom"));
+        }
+        check_bond(&molecule, begin - 1, end - 1, index)?;
+        let order = bond_order(number(text, 6, 9, index)?, index)?;
+        let stereo_code: u8 = number(text, 9, 12, index)?;
+        let stereo = match (order, stereo_code) {
+            (BondOrder::Single, 1) => BondStereo::Wedge,
+            (BondOrder::Single, 4) => BondStereo::Wavy,
+            (BondOrder::Single, 6) => BondStereo::Hash,
+            (BondOrder::Double, 3) => BondStereo::Either,
+            _ => BondStereo::None,
+        };
+        molecule.add_bond(Bond {
+            begin: begin - 1,
+            end: end - 1,
+            order,
+            stereo,
+            appearance: Appearance::default(),
+        });
+    }
+
+    // Any CHG or ISO property replaces the values of the atom block.
+    let mut charges_reset = false;
+    let mut isotopes_reset = false;
+    for (index, text) in lines
+        .iter()
+        .enumerate()
+        .skip(counts + 1 + atom_count + bond_count)
+    {
+        if text.starts_with("M  END") || text.starts_with("$$$$") {
+            break;
+        }
+        let property = field(text, 3, 6);
+        if property != "CHG" && property != "ISO" {
+            continue;
+        }
+        let entries: usize = number(text, 6, 9, index)?;
+        if property == "CHG" && !charges_reset {
+            molecule.atoms.iter_mut().for_each(|a| a.charge = 0);
+            charges_reset = true;
+        }
+        if property == "ISO" && !isotopes_reset {
+            molecule.atoms.iter_mut().for_each(|a| a.isotope = None);
+            isotopes_reset = true;
+        }
+        for entry in 0..entries {
+            let start = 9 + entry * 8;
+            let atom: usize = number(text, start, start + 4, index)?;
+            let value: i16 = number(text, start + 4, start + 8, index)?;
+            let atom = molecule
+                .atoms
+                .get_mut(atom.wrapping_sub(1))
+                .ok_or_else(|| ParseError::new(index + 1, "property of a missing atom"))?;
+            let out_of_range =
+                || ParseError::new(index + 1, format!("{property} value out of range"));
+            if property == "CHG" {
+                atom.charge = i8::try_from(value).map_err(|_| out_of_range())?;
+            } else {
+                atom.isotope = Some(u16::try_from(value).map_err(|_| out_of_range())?);
+            }
+        }
+    }
+
+    apply_valences(&mut molecule, &valences);
+    Ok(molecule)
+}
+
+pub(crate) fn write_ctab_v2000(molecule: &Molecule, out: &mut String) {
+    out.push_str(&format!(
+        "{:>3}{:>3}  0  0  0  0  0  0  0  0999 V2000\n",
+        molecule.atoms.len(),
+        molecule.bonds.len()
+    ));
+
+    let scale = file_scale(molecule);
+    for (i, atom) in molecule.atoms.iter().enumerate() {
+        let (x, y) = file_coordinates(atom.position, scale);
+        let charge_code = match atom.charge {
+            3 => 1,
+            2 => 2,
+            1 => 3,
+            -1 => 5,
+            -2 => 6,
+            -3 => 7,
+            _ => 0,
+        };
+        let valence = match explicit_valence(molecule, i) {
+            Some(0) => 15,
+            Some(v) => v,
+            None => 0,
+        };
+        out.push_str(&format!(
+            "{x:>10.4}{y:>10.4}{:>10.4} {:<3} 0{charge_code:>3}  0  0  0{valence:>3}  0  0  0{:>3}  0  0\n",
+            0.0,
+            atom.element.symbol(),
+            atom.map.unwrap_or(0),
+        ));
+    }
+
+    for bond in &molecule.bonds {
+        let stereo = match bond.stereo {
+            BondStereo::Wedge => 1,
+            BondStereo::Wavy => 4,
+            BondStereo::Hash => 6,
+            BondStereo::Either => 3,
+            BondStereo::None => 0,
+        };
+        out.push_str(&format!(
+            "{:>3}{:>3}{:>3}{:>3}  0  0  0\n",
+            bond.begin + 1,
+            bond.end + 1,
+            bond_code(bond.order),
+            stereo
+        ));
+    }
+
+    let charges: Vec<(usize, i16)> = molecule
+        .atoms
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| a.charge != 0)
+        .map(|(i, a)| (i, a.charge as i16))
+        .collect();
+    let isotopes: Vec<(usize, i16)> = molecule
+        .atoms
+        .iter()
+        .enumerate()
+        .filter_map(|(i, a)| Some((i, a.isotope? as i16)))
+        .collect();
+    for (property, entries) in [("CHG", charges), ("ISO", isotopes)] {
+        for chunk in entries.chunks(8) {
+            out.push_str(&format!("M  {property}{:>3}", chunk.len()));
+            for (atom, value) in chunk {
+                out.push_str(&format!("{:>4}{value:>4}", atom + 1));
+            }
+            out.push('\n');
+        }
+    }
+    out.push_str("M  END\n");
+}
+
+/// Logical `M  V30` lines from `start` on, with continuation lines joined.
+/// Each line keeps its 0-based line index for error messages.
+pub(crate) fn v30_lines(lines: &[&str], start: usize) -> Vec<(usize, String)> {
+    let mut logical: Vec<(usize, String)> = Vec::new();
+    let mut continued = false;
+    for (index, text) in lines.iter().enumerate().skip(start) {
+        let Some(content) = text.strip_prefix("M  V30 ") else {
+            if text.starts_with("M  END") {
+                break;
+            }
+            continue;
+        };
+        let (content, continues) = match content.trim_end().strip_suffix('-') {
+            Some(stripped) => (stripped, true),
+            None => (content.trim_end(), false),
+        };
+        if continued {
+            logical.last_mut().unwrap().1.push_str(content);
+        } else {
+            logical.push((index, content.to_string()));
+        }
+        continued = continues;
+    }
+    logical
+}
+
+/// Splits a V3000 line into words, keeping parenthesized lists and quoted
+/// strings together.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            _ => (),
+        }
+        if c.is_whitespace() && depth == 0 && !quoted {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Parses a V3000 connection table from `BEGIN CTAB` to `END CTAB`.
+pub(crate) fn parse_ctab_v3000(lines: &[(usize, String)]) -> Result<Molecule, ParseError> {
+    #[derive(PartialEq)]
+    enum Block {
+        None,
+        Atoms,
+        Bonds,
+    }
+
+    let parse_number = |text: &str, index: usize| {
+        text.parse::<f64>()
+            .map_err(|_| ParseError::new(index + 1, format!("expected a number, found '{text}'")))
+    };
+
+    let mut molecule = Molecule::default();
+    let mut ids: Vec<usize> = Vec::new();
+    let mut valences = Vec::new();
+    let mut block = Block::None;
+
+    for (index, line) in lines {
+        let index = *index;
+        match line.trim() {
+            "BEGIN ATOM" => block = Block::Atoms,
+            "BEGIN BOND" => block = Block::Bonds,
+            "END ATOM" | "END BOND" => block = Block::None,
+            "END CTAB" => break,
+            _ if block == Block::Atoms => {
+                let words = words(line);
+                if words.len() < 6 {
+                    return Err(ParseError::new(index + 1, "incomplete atom line"));
+                }
+                let (element, isotope) = element(&words[1], index)?;
+                let x = parse_number(&words[2], index)?;
+                let y = parse_number(&words[3], index)?;
+                let map: u32 = integer(&words[5], index)?;
+                let mut atom = Atom::new(element, Point::new(x, y));
+                atom.isotope = isotope;
+                atom.map = (map != 0).then_some(map);
+                for property in &words[6..] {
+                    let Some((key, value)) = property.split_once('=') else {
+                        continue;
+                    };
+                    match key {
+                        "CHG" => atom.charge = integer(value, index)?,
+                        "MASS" => atom.isotope = Some(integer(value, index)?),
+                        "VAL" => {
+                            let value: i16 = integer(value, index)?;
+                            let value = u8::try_from(value.max(0)).map_err(|_| {
+                                ParseError::new(index + 1, "VAL value out of range")
+                            })?;
+                            valences.push((molecule.atoms.len(), value));
+                        }
+                        _ => (),
+                    }
+                }
+                ids.push(integer(&words[0], index)?);
+                molecule.atoms.push(atom);
+            }
+            _ if block == Block::Bonds => {
+                let words = words(line);
+                if words.len() < 4 {
+                    return Err(ParseError::new(index + 1, "incomplete bond line"));
+                }
+                let order = bond_order(integer(&words[1], index)?, index)?;
+                let atom = |word: &str| -> Result<usize, ParseError> {
+                    let id: usize = integer(word, index)?;
+                    ids.iter()
+                        .position(|i| *i == id)
+                        .ok_or_else(|| ParseError::new(index + 1, "bond to a missing atom"))
+                };
+                let (begin, end) = (atom(&words[2])?, atom(&words[3])?);
+                check_bond(&molecule, begin, end, index)?;
+                let mut stereo = BondStereo::None;
+                for property in &words[4..] {
+                    if let Some(value) = property.strip_prefix("CFG=") {
+                        stereo = match (order, value) {
+                            (BondOrder::Single, "1") => BondStereo::Wedge,
+                            (BondOrder::Single, "2") => BondStereo::Wavy,
+                            (BondOrder::Single, "3") => BondStereo::Hash,
+                            (BondOrder::Double, "2") => BondStereo::Either,
+                            _ => BondStereo::None,
+                        };
+                    }
+                }
+                molecule.add_bond(Bond {
+                    begin,
+                    end,
+                    order,
+                    stereo,
+                    appearance: Appearance::default(),
+                });
+            }
+            _ => (),
+        }
+    }
+
+    apply_valences(&mut molecule, &valences);
+    Ok(molecule)
+}
+
+pub(crate) fn write_ctab_v3000(molecule: &Molecule, out: &mut String) {
+    out.push_str("M  V30 BEGIN CTAB\n");
+    out.push_str(&format!(
+        "M  V30 COUNTS {} {} 0 0 0\n",
+        molecule.atoms.len(),
+        molecule.bonds.len()
+    ));
+
+    out.push_str("M  V30 BEGIN ATOM\n");
+    let scale = file_scale(molecule);
+    for (i, atom) in molecule.atoms.iter().enumerate() {
+        let (x, y) = file_coordinates(atom.position, scale);
+        out.push_str(&format!(
+            "M  V30 {} {} {x:.4} {y:.4} 0 {}",
+            i + 1,
+            atom.element.symbol(),
+            atom.map.unwrap_or(0)
+        ));
+        if atom.charge != 0 {
+            out.push_str(&format!(" CHG={}", atom.charge));
+        }
+        if let Some(isotope) = atom.isotope {
+            out.push_str(&format!(" MASS={isotope}"));
+        }
+        match explicit_valence(molecule, i) {
+            Some(0) => out.push_str(" VAL=-1"),
+            Some(v) => out.push_str(&format!(" VAL={v}")),
+            None => (),
+        }
+        out.push('\n');
+    }
+    out.push_str("M  V30 END ATOM\n");
+
+    out.push_str("M  V30 BEGIN BOND\n");
+    for (i, bond) in molecule.bonds.iter().enumerate() {
+        out.push_str(&format!(
+            "M  V30 {} {} {} {}",
+            i + 1,
+            bond_code(bond.order),
+            bond.begin + 1,
+            bond.end + 1
+        ));
+        let configuration = match bond.stereo {
+            BondStereo::Wedge => Some(1),
+            BondStereo::Wavy | BondStereo::Either => Some(2),
+            BondStereo::Hash => Some(3),
+            BondStereo::None => None,
+        };
+        if let Some(configuration) = configuration {
+            out.push_str(&format!(" CFG={configuration}"));
+        }
+        out.push('\n');
+    }
+    out.push_str("M  V30 END BOND\n");
+    out.push_str("M  V30 END CTAB\n");
+}
@@ -0,0 +1,270 @@
+//! Single page PDF documents of a scene, cropped to its content or the
+//! size of its page.
+//!
+//! Document units are CSS pixels, three quarters of a point. Labels are real
+//! text set in the embedded bundled font; text in loaded fonts, bold or
+//! italic text and text with characters outside of WinAnsi (Latin-1) is drawn
+//! as glyph outlines instead. Colors are opaque.
+
+use std::fmt::Write;
+
+use iced::Color;
+
+use crate::font::{self, FontStyle};
+use crate::io::{number, postscript_path};
+use crate::render::{Primitive, Scene};
+
+/// Points per document unit.
+const POINTS: f64 = 0.75;
+const PATH_OPERATORS: [&str; 4] = ["m", "l", "c", "h"];
+
+pub fn write(scene: &Scene) -> Vec<u8> {
+    let bounds = scene.extent();
+    let (width, height) = (bounds.width() * POINTS, bounds.height() * POINTS);
+
+    // Flip to the y-down document space, with the content's corner at the origin.
+    let mut content = format!(
+        "{} 0 0 {} {} {} cm\n1 J 1 j\n",
+        number(POINTS),
+        number(-POINTS),
+        number(-bounds.x0 * POINTS),
+        number(bounds.y1 * POINTS),
+    );
+    let mut uses_font = false;
+
+    for primitive in &scene.primitives {
+        match primitive {
+            Primitive::Stroke {
+                path,
+                width,
+                color,
+                dash,
+            } => {
+                let dash: Vec<String> = dash.iter().map(|d| number(*d)).collect();
+                write!(
+                    content,
+                    "{} RG {} w [{}] 0 d\n{}S\n",
+                    rgb(*color),
+                    number(*width),
+                    dash.join(" "),
+                    postscript_path(path, PATH_OPERATORS),
+                )
+                .unwrap();
+            }
+            Primitive::Fill { path, color } => {
+                write!(
+                    content,
+                    "{} rg\n{}f\n",
+                    rgb(*color),
+                    postscript_path(path, PATH_OPERATORS)
+                )
+                .unwrap();
+            }
+            Primitive::Text {
+                content: text,
+                position,
+                size,
+                color,
+                align_x,
+                align_y,
+                font,
+                style,
+            } => {
+                let origin = font
+                    .typeface
+                    .origin(text, *position, *size, *align_x, *align_y);
+                // The embedded font has no bold or italic face.
+                let embedded = font.typeface == font::bundled() && *style == FontStyle::default();
+                match win_ansi(text).filter(|_| embedded) {
+                    Some(bytes) => {
+                        uses_font = true;
+                        // The text matrix flips glyphs back upright.
+                        write!(
+                            content,
+                            "{} rg\nBT /F1 {} Tf 1 0 0 -1 {} {} Tm ({}) Tj ET\n",
+                            rgb(*color),
+                            number(*size),
+                            number(origin.x),
+                            number(origin.y),
+                            escape(&bytes),
+                        )
+                        .unwrap();
+                    }
+                    None => {
+                        write!(
+                            content,
+                            "{} rg\n{}f\n",
+                            rgb(*color),
+                            postscript_path(
+                                &font.typeface.outline(text, origin, *size, *style),
+                                PATH_OPERATORS
+                            ),
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    let mut pdf = Pdf::default();
+    let resources = if uses_font {
+        let font = pdf.font();
+        format!("/Resources << /Font << /F1 {font} 0 R >> >>")
+    } else {
+        "/Resources << >>".to_string()
+    };
+    let contents = pdf.stream("", content.as_bytes());
+    // Catalog, pages and page refer to each other, so their numbers are fixed
+    // up front.
+    let (catalog, pages, page) = (pdf.next(), pdf.next() + 1, pdf.next() + 2);
+    pdf.object(format!("<< /Type /Catalog /Pages {pages} 0 R >>"));
+    pdf.object(format!("<< /Type /Pages /Kids [{page} 0 R] /Count 1 >>"));
+    pdf.object(format!(
+        "<< /Type /Page /Parent {pages} 0 R /MediaBox [0 0 {} {}] {resources} /Contents {contents} 0 R >>",
+        number(width),
+        number(height),
+    ));
+    pdf.finish(catalog)
+}
+
+/// Objects of a PDF file as they are written, with their byte offsets.
+struct Pdf {
+    out: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl Default for Pdf {
+    fn default() -> Self {
+        Self {
+            // The comment with high bytes marks the file as binary.
+            out: b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec(),
+            offsets: Vec::new(),
+        }
+    }
+}
+
+impl Pdf {
+    /// Number the next object will get.
+    fn next(&self) -> usize {
+        self.offsets.len() + 1
+    }
+
+    fn object(&mut self, body: String) -> usize {
+        self.begin();
+        self.out.extend(body.as_bytes());
+        self.out.extend(b"\nendobj\n");
+        self.offsets.len()
+    }
+
+    /// A compressed stream object with the extra dictionary entries `entries`.
+    fn stream(&mut self, entries: &str, data: &[u8]) -> usize {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+        self.begin();
+        self.out.extend(
+            format!(
+                "<< /Length {} /Filter /FlateDecode {entries} >>\nstream\n",
+                compressed.len()
+            )
+            .as_bytes(),
+        );
+        self.out.extend(&compressed);
+        self.out.extend(b"\nendstream\nendobj\n");
+        self.offsets.len()
+    }
+
+    fn begin(&mut self) {
+        self.offsets.push(self.out.len());
+        self.out
+            .extend(format!("{} 0 obj\n", self.offsets.len()).as_bytes());
+    }
+
+    /// Embeds the bundled font as a WinAnsi encoded TrueType font.
+    fn font(&mut self) -> usize {
+        let typeface = font::bundled();
+        let face = typeface.face();
+        let per_mille = |units: f64| (units * 1000.0 / face.units_per_em() as f64).round();
+        let bbox = face.global_bounding_box();
+
+        let file = self.stream(&format!("/Length1 {}", font::DATA.len()), font::DATA);
+        let descriptor = self.object(format!(
+            "<< /Type /FontDescriptor /FontName /{} /Flags 32 /FontBBox [{} {} {} {}] \
+             /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /FontFile2 {file} 0 R >>",
+            font::NAME,
+            per_mille(bbox.x_min as f64),
+            per_mille(bbox.y_min as f64),
+            per_mille(bbox.x_max as f64),
+            per_mille(bbox.y_max as f64),
+            per_mille(face.ascender() as f64),
+            per_mille(face.descender() as f64),
+            per_mille(face.capital_height().unwrap_or(face.ascender()) as f64),
+        ));
+        let widths: Vec<String> = (32u8..=255)
+            .map(|code| match char_of(code) {
+                Some(c) => per_mille(typeface.advance(typeface.glyph(c)) as f64).to_string(),
+                None => "0".to_string(),
+            })
+            .collect();
+        self.object(format!(
+            "<< /Type /Font /Subtype /TrueType /BaseFont /{} /FirstChar 32 /LastChar 255 \
+             /Widths [{}] /Encoding /WinAnsiEncoding /FontDescriptor {descriptor} 0 R >>",
+            font::NAME,
+            widths.join(" "),
+        ))
+    }
+
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        let xref = self.out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            writeln!(table, "{offset:010} 00000 n ").unwrap();
+        }
+        write!(
+            table,
+            "trailer\n<< /Size {} /Root {root} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.offsets.len() + 1
+        )
+        .unwrap();
+        self.out.extend(table.as_bytes());
+        self.out
+    }
+}
+
+fn rgb(color: Color) -> String {
+    format!(
+        "{} {} {}",
+        number(color.r as f64),
+        number(color.g as f64),
+        number(color.b as f64)
+    )
+}
+
+/// Character WinAnsi code `code` stands for, for the printable ASCII and
+/// Latin-1 ranges where both agree.
+fn char_of(code: u8) -> Option<char> {
+    matches!(code, 32..=126 | 160..=255).then_some(code as char)
+}
+
+fn win_ansi(text: &str) -> Option<Vec<u8>> {
+    text.chars()
+        .map(|c| {
+            let code = u8::try_from(c as u32).ok()?;
+            char_of(code).map(|_| code)
+        })
+        .collect()
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for byte in bytes {
+        match byte {
+            b'(' | b')' | b'\\' => {
+                escaped.push('\\');
+                escaped.push(*byte as char);
+            }
+            32..=126 => escaped.push(*byte as char),
+            _ => write!(escaped, "\\{byte:03o}").unwrap(),
+        }
+    }
+    escaped
+}
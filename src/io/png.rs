@@ -0,0 +1,142 @@
+//! PNG images of a scene, rasterized on the CPU.
+//!
+//! Document units are CSS pixels, so 96 DPI draws them one to one. Labels are
+//! filled from the glyph outlines of their fonts.
+
+use tiny_skia::{
+    FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Stroke, StrokeDash, Transform,
+};
+
+use crate::render::{Primitive, Scene};
+
+/// Resolution at which one document unit is one pixel.
+pub const BASE_DPI: f64 = 96.0;
+/// Largest width or height of an image; larger requests lower the resolution.
+const MAX_SIDE: f64 = 16384.0;
+
+/// Draws `scene` cropped to its content plus padding, or to its page, at
+/// `dpi`, on white unless `transparent` is set.
+pub fn write(scene: &Scene, dpi: f64, transparent: bool) -> Vec<u8> {
+    let bounds = scene.extent();
+    let scale = (dpi / BASE_DPI)
+        .min(MAX_SIDE / bounds.width())
+        .min(MAX_SIDE / bounds.height());
+    let width = (bounds.width() * scale).ceil().max(1.0) as u32;
+    let height = (bounds.height() * scale).ceil().max(1.0) as u32;
+
+    let mut pixmap = Pixmap::new(width, height).expect("image size is within limits");
+    if !transparent {
+        pixmap.fill(tiny_skia::Color::WHITE);
+    }
+    let transform = Transform::from_scale(scale as f32, scale as f32)
+        .pre_translate(-bounds.x0 as f32, -bounds.y0 as f32);
+
+    for primitive in &scene.primitives {
+        match primitive {
+            Primitive::Stroke {
+                path,
+                width,
+                color,
+                dash,
+            } => {
+                let Some(path) = to_path(path) else {
+                    continue;
+                };
+                let stroke = Stroke {
+                    width: *width as f32,
+                    line_cap: LineCap::Round,
+                    line_join: LineJoin::Round,
+                    dash: StrokeDash::new(dash.iter().map(|d| *d as f32).collect(), 0.0),
+                    ..Default::default()
+                };
+                pixmap.stroke_path(&path, &paint(*color), &stroke, transform, None);
+            }
+            Primitive::Fill { path, color } => {
+                if let Some(path) = to_path(path) {
+                    pixmap.fill_path(&path, &paint(*color), FillRule::Winding, transform, None);
+                }
+            }
+            Primitive::Text {
+                content,
+                position,
+                size,
+                color,
+                align_x,
+                align_y,
+                font,
+                style,
+            } => {
+                let origin = font
+                    .typeface
+                    .origin(content, *position, *size, *align_x, *align_y);
+                if let Some(path) = to_path(&font.typeface.outline(content, origin, *size, *style))
+                {
+                    pixmap.fill_path(&path, &paint(*color), FillRule::Winding, transform, None);
+                }
+            }
+        }
+    }
+
+    encode(&pixmap, scale * BASE_DPI)
+}
+
+fn paint(color: iced::Color) -> Paint<'static> {
+    let [r, g, b, a] = color.into_rgba8();
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    paint
+}
+
+fn to_path(path: &kurbo::BezPath) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    for element in path.elements() {
+        match *element {
+            kurbo::PathEl::MoveTo(p) => builder.move_to(p.x as f32, p.y as f32),
+            kurbo::PathEl::LineTo(p) => builder.line_to(p.x as f32, p.y as f32),
+            kurbo::PathEl::QuadTo(c, p) => {
+                builder.quad_to(c.x as f32, c.y as f32, p.x as f32, p.y as f32)
+            }
+            kurbo::PathEl::CurveTo(c1, c2, p) => builder.cubic_to(
+                c1.x as f32,
+                c1.y as f32,
+                c2.x as f32,
+                c2.y as f32,
+                p.x as f32,
+                p.y as f32,
+            ),
+            kurbo::PathEl::ClosePath => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+/// Straight alpha RGBA PNG of `pixmap`, tagged with its resolution.
+fn encode(pixmap: &Pixmap, dpi: f64) -> Vec<u8> {
+    let rgba: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, pixmap.width(), pixmap.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let pixels_per_meter = (dpi / 0.0254).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: pixels_per_meter,
+        yppu: pixels_per_meter,
+        unit: png::Unit::Meter,
+    }));
+    // Writing to memory cannot fail, and the data fits the header.
+    let mut writer = encoder.write_header().expect("PNG header is valid");
+    writer
+        .write_image_data(&rgba)
+        .expect("pixels match the image size");
+    writer.finish().expect("PNG is complete");
+    out
+}
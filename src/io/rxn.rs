@@ -0,0 +1,198 @@
+//! MDL RXN reaction files, V2000 and V3000.
+//!
+//! Agents are read from and written to the optional third count, the way
+//! most current tools extend the format. Text agents have no representation
+//! and are left out.
+
+use kurbo::Point;
+
+use crate::arrow::{Arrow, ArrowKind};
+use crate::io::ParseError;
+use crate::io::molfile::{self, Version};
+use crate::layout;
+use crate::molecule::Molecule;
+use crate::reaction::{Agent, Reaction};
+
+pub fn parse(text: &str) -> Result<Reaction, ParseError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let header = lines
+        .first()
+        .ok_or_else(|| ParseError::new(1, "empty reaction file"))?;
+    if !header.starts_with("$RXN") {
+        return Err(ParseError::new(1, "reaction files start with $RXN"));
+    }
+
+    let (reactants, products, agents) = if header.contains("V3000") {
+        parse_v3000(&lines)?
+    } else {
+        parse_v2000(&lines)?
+    };
+
+    let mut reaction = Reaction {
+        reactants,
+        agents_above: agents.into_iter().map(Agent::Molecule).collect(),
+        agents_below: Vec::new(),
+        products,
+        arrow: Arrow::new(ArrowKind::Forward, Point::ORIGIN, Point::ORIGIN),
+    };
+    layout::arrange_reaction(&mut reaction);
+    Ok(reaction)
+}
+
+pub fn write(reaction: &Reaction, version: Version) -> String {
+    let agents: Vec<&Molecule> = reaction
+        .agents_above
+        .iter()
+        .chain(&reaction.agents_below)
+        .filter_map(|agent| match agent {
+            Agent::Molecule(molecule) => Some(molecule),
+            Agent::Text(_) => None,
+        })
+        .collect();
+    let mut out = String::new();
+
+    match version {
+        Version::V2000 => {
+            out.push_str("$RXN\n\n  Molecular\n\n");
+            out.push_str(&format!(
+                "{:>3}{:>3}",
+                reaction.reactants.len(),
+                reaction.products.len()
+            ));
+            if !agents.is_empty() {
+                out.push_str(&format!("{:>3}", agents.len()));
+            }
+            out.push('\n');
+            let molecules = reaction
+                .reactants
+                .iter()
+                .chain(&reaction.products)
+                .chain(agents.iter().copied());
+            for molecule in molecules {
+                out.push_str("$MOL\n");
+                out.push_str(&molfile::write(molecule, Version::V2000));
+            }
+        }
+        Version::V3000 => {
+            out.push_str("$RXN V3000\n\n  Molecular\n\n");
+            out.push_str(&format!(
+                "M  V30 COUNTS {} {}",
+                reaction.reactants.len(),
+                reaction.products.len()
+            ));
+            if !agents.is_empty() {
+                out.push_str(&format!(" {}", agents.len()));
+            }
+            out.push('\n');
+            let blocks: [(&str, Vec<&Molecule>); 3] = [
+                ("REACTANT", reaction.reactants.iter().collect()),
+                ("PRODUCT", reaction.products.iter().collect()),
+                ("AGENT", agents),
+            ];
+            for (name, molecules) in blocks {
+                if molecules.is_empty() {
+                    continue;
+                }
+                out.push_str(&format!("M  V30 BEGIN {name}\n"));
+                for molecule in molecules {
+                    molfile::write_ctab_v3000(molecule, &mut out);
+                }
+                out.push_str(&format!("M  V30 END {name}\n"));
+            }
+            out.push_str("M  END\n");
+        }
+    }
+
+    out
+}
+
+type Components = (Vec<Molecule>, Vec<Molecule>, Vec<Molecule>);
+
+fn parse_v2000(lines: &[&str]) -> Result<Components, ParseError> {
+    let counts = lines
+        .get(4)
+        .ok_or_else(|| ParseError::new(lines.len(), "reaction file ends before its counts"))?;
+    let count = |start: usize| -> Result<usize, ParseError> {
+        let text = counts
+            .get(start..(start + 3).min(counts.len()))
+            .unwrap_or("")
+            .trim();
+        if text.is_empty() {
+            return Ok(0);
+        }
+        text.parse()
+            .map_err(|_| ParseError::new(5, format!("expected a count, found '{text}'")))
+    };
+    let (reactant_count, product_count, agent_count) = (count(0)?, count(3)?, count(6)?);
+
+    let starts: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with("$MOL"))
+        .map(|(i, _)| i)
+        .collect();
+    let expected = reactant_count + product_count + agent_count;
+    if starts.len() < expected {
+        return Err(ParseError::new(
+            lines.len(),
+            format!("expected {expected} molecules, found {}", starts.len()),
+        ));
+    }
+
+    let mut molecules = Vec::new();
+    for (i, start) in starts.iter().take(expected).enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+        let block = &lines[start + 1..end];
+        // The counts line of each molfile is its fourth line.
+        let mut molecule = molfile::parse_ctab_v2000(block, 3)
+            .map_err(|e| ParseError::new(e.position + start + 1, e.message))?;
+        molfile::normalize_coordinates(&mut molecule);
+        molecules.push(molecule);
+    }
+
+    let agents = molecules.split_off(reactant_count + product_count);
+    let products = molecules.split_off(reactant_count);
+    Ok((molecules, products, agents))
+}
+
+fn parse_v3000(lines: &[&str]) -> Result<Components, ParseError> {
+    let logical = molfile::v30_lines(lines, 1);
+    let mut reactants = Vec::new();
+    let mut products = Vec::new();
+    let mut agents = Vec::new();
+    let mut role: Option<&str> = None;
+    let mut ctab: Option<Vec<(usize, String)>> = None;
+
+    for (index, line) in logical {
+        let trimmed = line.trim().to_string();
+        if let Some(lines) = ctab.as_mut() {
+            lines.push((index, line));
+            if trimmed == "END CTAB" {
+                let mut molecule = molfile::parse_ctab_v3000(&ctab.take().unwrap())?;
+                molfile::normalize_coordinates(&mut molecule);
+                match role {
+                    Some("REACTANT") => reactants.push(molecule),
+                    Some("PRODUCT") => products.push(molecule),
+                    Some("AGENT") => agents.push(molecule),
+                    _ => {
+                        return Err(ParseError::new(
+                            index + 1,
+                            "connection table outside of a reactant, product or agent block",
+                        ));
+                    }
+                }
+            }
+            continue;
+        }
+        match trimmed.as_str() {
+            "BEGIN REACTANT" => role = Some("REACTANT"),
+            "BEGIN PRODUCT" => role = Some("PRODUCT"),
+            "BEGIN AGENT" => role = Some("AGENT"),
+            "END REACTANT" | "END PRODUCT" | "END AGENT" => role = None,
+            "BEGIN CTAB" => ctab = Some(Vec::new()),
+            _ => (),
+        }
+    }
+
+    Ok((reactants, products, agents))
+}
@@ -0,0 +1,35 @@
+//! SD files: molfiles separated by `$$$$` lines. Data items after a record's
+//! `M  END` are skipped when reading and not written.
+
+use crate::io::ParseError;
+use crate::io::molfile::{self, Version};
+use crate::molecule::Molecule;
+
+pub fn parse(text: &str) -> Result<Vec<Molecule>, ParseError> {
+    let mut molecules = Vec::new();
+    let mut record = Vec::new();
+    let mut start = 0;
+    for (index, line) in text.lines().chain(["$$$$"]).enumerate() {
+        if !line.starts_with("$$$$") {
+            record.push(line);
+            continue;
+        }
+        if record.iter().any(|line| !line.trim().is_empty()) {
+            let molecule = molfile::parse(&record.join("\n"))
+                .map_err(|e| ParseError::new(start + e.position, e.message))?;
+            molecules.push(molecule);
+        }
+        record.clear();
+        start = index + 1;
+    }
+    Ok(molecules)
+}
+
+pub fn write<'a>(molecules: impl IntoIterator<Item = &'a Molecule>) -> String {
+    let mut out = String::new();
+    for molecule in molecules {
+        out.push_str(&molfile::write(molecule, Version::for_molecule(molecule)));
+        out.push_str("$$$$\n");
+    }
+    out
+}
@@ -0,0 +1,843 @@
+//! SMILES and reaction SMILES.
+//!
+//! Parsed structures are laid out with [`crate::layout`], tetrahedral
+//! configurations become wedges and double bond configurations are enforced
+//! on the generated coordinates. Written SMILES read their stereo back from
+//! wedges, hashes and 2D geometry.
+
+use std::collections::HashMap;
+
+use kurbo::Point;
+
+use crate::arrow::{Arrow, ArrowKind};
+use crate::cip::{self, StereoUnit};
+use crate::element::Element;
+use crate::io::ParseError;
+use crate::layout;
+use crate::molecule::{Atom, BondOrder, BondStereo, Molecule};
+use crate::reaction::{Agent, Reaction};
+use crate::style::Appearance;
+
+const ORGANIC: [&str; 10] = ["B", "C", "N", "O", "P", "S", "F", "Cl", "Br", "I"];
+const AROMATIC: [&str; 8] = ["b", "c", "n", "o", "p", "s", "se", "as"];
+
+pub fn parse(smiles: &str) -> Result<Molecule, ParseError> {
+    let smiles = smiles.split_whitespace().next().unwrap_or_default();
+    let mut parser = Parser::new(smiles);
+    parser.run()?;
+    Ok(parser.finish())
+}
+
+/// Reads `reactants>agents>products`, each part a dot-separated list.
+pub fn parse_reaction(smiles: &str) -> Result<Reaction, ParseError> {
+    let smiles = smiles.split_whitespace().next().unwrap_or_default();
+    let parts: Vec<&str> = smiles.split('>').collect();
+    let [reactants, agents, products] = parts.as_slice() else {
+        return Err(ParseError::new(
+            1,
+            "reaction SMILES needs exactly two '>' separators",
+        ));
+    };
+
+    let mut offset = 0;
+    let mut fragments = |part: &str| -> Result<Vec<Molecule>, ParseError> {
+        let start = offset;
+        offset += part.chars().count() + 1;
+        if part.is_empty() {
+            return Ok(Vec::new());
+        }
+        let molecule = parse(part).map_err(|e| ParseError::new(e.position + start, e.message))?;
+        Ok(molecule
+            .components()
+            .iter()
+            .map(|atoms| molecule.extract(atoms))
+            .collect())
+    };
+
+    let mut reaction = Reaction {
+        reactants: fragments(reactants)?,
+        agents_above: fragments(agents)?
+            .into_iter()
+            .map(Agent::Molecule)
+            .collect(),
+        agents_below: Vec::new(),
+        products: fragments(products)?,
+        arrow: Arrow::new(ArrowKind::Forward, Point::ORIGIN, Point::ORIGIN),
+    };
+    layout::arrange_reaction(&mut reaction);
+    Ok(reaction)
+}
+
+pub fn write(molecule: &Molecule) -> String {
+    Writer::new(molecule).run()
+}
+
+/// Writes the molecules of `reaction`; text agents have no SMILES and are
+/// left out.
+pub fn write_reaction(reaction: &Reaction) -> String {
+    fn join<'a>(molecules: impl Iterator<Item = &'a Molecule>) -> String {
+        molecules.map(write).collect::<Vec<_>>().join(".")
+    }
+
+    let agents = reaction
+        .agents_above
+        .iter()
+        .chain(&reaction.agents_below)
+        .filter_map(|agent| match agent {
+            Agent::Molecule(molecule) => Some(molecule),
+            Agent::Text(_) => None,
+        });
+    format!(
+        "{}>{}>{}",
+        join(reaction.reactants.iter()),
+        join(agents),
+        join(reaction.products.iter()),
+    )
+}
+
+/// Neighbor of a chiral atom in the order SMILES lists them.
+#[derive(Copy, Clone, Debug)]
+enum Slot {
+    Atom(usize),
+    Hydrogen,
+    /// Ring bond whose partner is not parsed yet.
+    Pending,
+}
+
+#[derive(Copy, Clone, Default)]
+struct BondSpec {
+    order: Option<BondOrder>,
+    /// `Some(true)` for `/`: the atom after the bond is above the one before.
+    up: Option<bool>,
+}
+
+struct OpenRing {
+    atom: usize,
+    spec: BondSpec,
+    slot: usize,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    molecule: Molecule,
+    aromatic: Vec<bool>,
+    /// `Some(true)` for `@`, anticlockwise.
+    chirality: Vec<Option<bool>>,
+    slots: Vec<Vec<Slot>>,
+    /// `(bond, from, up)`: the bond was written from `from` with `/` if `up`.
+    directions: Vec<(usize, usize, bool)>,
+    previous: Option<usize>,
+    bond: BondSpec,
+    branches: Vec<usize>,
+    rings: HashMap<u32, OpenRing>,
+}
+
+impl Parser {
+    fn new(smiles: &str) -> Self {
+        Self {
+            chars: smiles.chars().collect(),
+            position: 0,
+            molecule: Molecule::default(),
+            aromatic: Vec::new(),
+            chirality: Vec::new(),
+            slots: Vec::new(),
+            directions: Vec::new(),
+            previous: None,
+            bond: BondSpec::default(),
+            branches: Vec::new(),
+            rings: HashMap::new(),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::new(self.position + 1, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn run(&mut self) -> Result<(), ParseError> {
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => {
+                    let previous = self
+                        .previous
+                        .ok_or_else(|| self.error("branch without an atom before it"))?;
+                    self.branches.push(previous);
+                    self.position += 1;
+                }
+                ')' => {
+                    self.previous = Some(
+                        self.branches
+                            .pop()
+                            .ok_or_else(|| self.error("unmatched ')'"))?,
+                    );
+                    self.position += 1;
+                }
+                '-' | '=' | '#' | ':' | '/' | '\\' => {
+                    self.bond = match c {
+                        '-' => BondSpec {
+                            order: Some(BondOrder::Single),
+                            up: None,
+                        },
+                        '=' => BondSpec {
+                            order: Some(BondOrder::Double),
+                            up: None,
+                        },
+                        '#' => BondSpec {
+                            order: Some(BondOrder::Triple),
+                            up: None,
+                        },
+                        ':' => BondSpec {
+                            order: Some(BondOrder::Aromatic),
+                            up: None,
+                        },
+                        '/' => BondSpec {
+                            order: Some(BondOrder::Single),
+                            up: Some(true),
+                        },
+                        _ => BondSpec {
+                            order: Some(BondOrder::Single),
+                            up: Some(false),
+                        },
+                    };
+                    self.position += 1;
+                }
+                '.' => {
+                    self.previous = None;
+                    self.position += 1;
+                }
+                '0'..='9' | '%' => {
+                    let number = self.ring_number()?;
+                    self.ring_bond(number)?;
+                }
+                '[' => self.bracket_atom()?,
+                _ => self.organic_atom()?,
+            }
+        }
+
+        if !self.branches.is_empty() {
+            return Err(self.error("unclosed branch"));
+        }
+        if let Some(number) = self.rings.keys().next() {
+            return Err(self.error(format!("ring bond {number} is never closed")));
+        }
+        Ok(())
+    }
+
+    fn ring_number(&mut self) -> Result<u32, ParseError> {
+        if self.peek() == Some('%') {
+            self.position += 1;
+            let digits: String = self.chars[self.position..]
+                .iter()
+                .take(2)
+                .filter(|c| c.is_ascii_digit())
+                .collect();
+            if digits.len() != 2 {
+                return Err(self.error("'%' needs two digits"));
+            }
+            self.position += 2;
+            Ok(digits.parse().unwrap())
+        } else {
+            let digit = self.peek().unwrap().to_digit(10).unwrap();
+            self.position += 1;
+            Ok(digit)
+        }
+    }
+
+    fn ring_bond(&mut self, number: u32) -> Result<(), ParseError> {
+        let atom = self
+            .previous
+            .ok_or_else(|| self.error("ring bond without an atom before it"))?;
+        let spec = std::mem::take(&mut self.bond);
+
+        match self.rings.remove(&number) {
+            Some(open) => {
+                if open.atom == atom {
+                    return Err(self.error("ring bond to the atom itself"));
+                }
+                if self.molecule.bond_between(open.atom, atom).is_some() {
+                    return Err(self.error("ring bond between atoms already bonded"));
+                }
+                let (spec, from) = if spec.order.is_some() {
+                    (spec, atom)
+                } else {
+                    (open.spec, open.atom)
+                };
+                self.add_bond(open.atom, atom, spec, from);
+                self.slots[open.atom][open.slot] = Slot::Atom(atom);
+                self.slots[atom].push(Slot::Atom(open.atom));
+            }
+            None => {
+                let slot = self.slots[atom].len();
+                self.slots[atom].push(Slot::Pending);
+                self.rings.insert(number, OpenRing { atom, spec, slot });
+            }
+        }
+        Ok(())
+    }
+
+    fn add_bond(&mut self, a: usize, b: usize, spec: BondSpec, from: usize) -> usize {
+        let order = spec
+            .order
+            .unwrap_or(if self.aromatic[a] && self.aromatic[b] {
+                BondOrder::Aromatic
+            } else {
+                BondOrder::Single
+            });
+        let bond = self.molecule.add_bond(crate::molecule::Bond {
+            begin: a,
+            end: b,
+            order,
+            stereo: BondStereo::None,
+            appearance: Appearance::default(),
+        });
+        if let Some(up) = spec.up {
+            self.directions.push((bond, from, up));
+        }
+        bond
+    }
+
+    fn push_atom(&mut self, atom: Atom, aromatic: bool, chirality: Option<bool>) -> usize {
+        self.molecule.atoms.push(atom);
+        self.aromatic.push(aromatic);
+        self.chirality.push(chirality);
+        self.slots.push(Vec::new());
+        let index = self.molecule.atoms.len() - 1;
+
+        if let Some(previous) = self.previous {
+            let spec = std::mem::take(&mut self.bond);
+            self.add_bond(previous, index, spec, previous);
+            self.slots[previous].push(Slot::Atom(index));
+            self.slots[index].push(Slot::Atom(previous));
+        }
+        self.previous = Some(index);
+        index
+    }
+
+    fn organic_atom(&mut self) -> Result<(), ParseError> {
+        let rest: String = self.chars[self.position..].iter().take(2).collect();
+        let symbol = ["Cl", "Br"]
+            .into_iter()
+            .chain(ORGANIC)
+            .chain(AROMATIC.into_iter().take(6))
+            .find(|s| rest.starts_with(s))
+            .ok_or_else(|| self.error(format!("unexpected '{}'", self.peek().unwrap())))?;
+        self.position += symbol.len();
+
+        let aromatic = symbol.chars().next().unwrap().is_lowercase();
+        let element = element_of(symbol).unwrap();
+        self.push_atom(Atom::new(element, Point::ORIGIN), aromatic, None);
+        Ok(())
+    }
+
+    /// The digits at the current position as a `T`, `None` without digits.
+    fn number<T: TryFrom<u32>>(&mut self, what: &str) -> Result<Option<T>, ParseError> {
+        let digits: String = self.chars[self.position..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        if digits.is_empty() {
+            return Ok(None);
+        }
+        let number = digits
+            .parse::<u32>()
+            .ok()
+            .and_then(|n| T::try_from(n).ok())
+            .ok_or_else(|| self.error(format!("{what} {digits} is out of range")))?;
+        self.position += digits.len();
+        Ok(Some(number))
+    }
+
+    fn bracket_atom(&mut self) -> Result<(), ParseError> {
+        self.position += 1;
+        let isotope = self.number::<u16>("isotope")?;
+
+        let rest: Vec<char> = self.chars[self.position..]
+            .iter()
+            .take(2)
+            .copied()
+            .collect();
+        let symbol = (1..=rest.len())
+            .rev()
+            .map(|length| rest[..length].iter().collect::<String>())
+            .find(|s| {
+                let aromatic = s.starts_with(|c: char| c.is_lowercase());
+                element_of(s).is_some() && (!aromatic || AROMATIC.contains(&s.as_str()))
+            })
+            .ok_or_else(|| self.error("unknown element"))?;
+        self.position += symbol.len();
+        let aromatic = symbol.chars().next().unwrap().is_lowercase();
+        let mut atom = Atom::new(element_of(&symbol).unwrap(), Point::ORIGIN);
+        atom.isotope = isotope;
+
+        let mut chirality = None;
+        if self.peek() == Some('@') {
+            self.position += 1;
+            chirality = Some(true);
+            if self.peek() == Some('@') {
+                self.position += 1;
+                chirality = Some(false);
+            }
+            if self
+                .peek()
+                .is_some_and(|c| c.is_ascii_uppercase() && c != 'H')
+            {
+                return Err(self.error("only @ and @@ chirality is supported"));
+            }
+        }
+
+        let mut hydrogens = 0;
+        if self.peek() == Some('H') {
+            self.position += 1;
+            hydrogens = self.number::<u8>("hydrogen count")?.unwrap_or(1);
+        }
+        atom.hydrogens = Some(hydrogens);
+
+        while let Some(sign @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            let magnitude = self.number::<u8>("charge")?.unwrap_or(1);
+            atom.charge = if sign == '+' {
+                atom.charge.checked_add_unsigned(magnitude)
+            } else {
+                atom.charge.checked_sub_unsigned(magnitude)
+            }
+            .ok_or_else(|| self.error("charge is out of range"))?;
+        }
+
+        if self.peek() == Some(':') {
+            self.position += 1;
+            atom.map = Some(
+                self.number::<u32>("atom class")?
+                    .ok_or_else(|| self.error("atom class needs a number"))?,
+            );
+        }
+
+        if self.peek() != Some(']') {
+            return Err(self.error("expected ']'"));
+        }
+        self.position += 1;
+
+        let index = self.push_atom(atom, aromatic, chirality);
+        if hydrogens > 0 && chirality.is_some() {
+            self.slots[index].push(Slot::Hydrogen);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Molecule {
+        layout::generate(&mut self.molecule);
+        self.apply_double_bonds();
+        self.apply_centers();
+        self.molecule
+    }
+
+    /// Whether the substituent across `bond` is above `end`, from `/` and `\`.
+    fn is_up(&self, bond: usize, end: usize) -> Option<bool> {
+        self.directions
+            .iter()
+            .find(|(b, _, _)| *b == bond)
+            .map(|(_, from, up)| if *from == end { *up } else { !*up })
+    }
+
+    /// Mirrors substituents that the layout put on the wrong side of a
+    /// double bond.
+    fn apply_double_bonds(&mut self) {
+        for bond in 0..self.molecule.bonds.len() {
+            let data = &self.molecule.bonds[bond];
+            if data.order != BondOrder::Double {
+                continue;
+            }
+            let (a, b) = (data.begin, data.end);
+            let substituent = |end: usize, partner: usize| {
+                self.molecule
+                    .neighbors(end)
+                    .filter(|(_, neighbor)| *neighbor != partner)
+                    .find_map(|(bond, neighbor)| Some((neighbor, self.is_up(bond, end)?)))
+            };
+            let (Some((sa, up_a)), Some((sb, up_b))) = (substituent(a, b), substituent(b, a))
+            else {
+                continue;
+            };
+
+            let position = |atom: usize| self.molecule.atoms[atom].position;
+            let axis = position(b) - position(a);
+            let side_a = axis.cross(position(sa) - position(a));
+            let side_b = axis.cross(position(sb) - position(a));
+            if (side_a * side_b > 0.0) == (up_a == up_b) {
+                continue;
+            }
+
+            // Reflect everything hanging off `b` across the bond axis.
+            let Some(side) = self.side_of(b, bond) else {
+                continue;
+            };
+            let origin = position(a);
+            let unit = axis / axis.hypot().max(f64::EPSILON);
+            for atom in side {
+                let d = self.molecule.atoms[atom].position - origin;
+                let along = unit * d.dot(unit);
+                self.molecule.atoms[atom].position = origin + along * 2.0 - d;
+            }
+        }
+    }
+
+    /// Atoms reachable from `start` without crossing `bond`, `None` if the
+    /// bond is in a ring.
+    fn side_of(&self, start: usize, bond: usize) -> Option<Vec<usize>> {
+        let other = self.molecule.bonds[bond].other(start);
+        let mut seen = vec![start];
+        let mut stack = vec![start];
+        while let Some(atom) = stack.pop() {
+            for (b, neighbor) in self.molecule.neighbors(atom) {
+                if b == bond || seen.contains(&neighbor) {
+                    continue;
+                }
+                if neighbor == other {
+                    return None;
+                }
+                seen.push(neighbor);
+                stack.push(neighbor);
+            }
+        }
+        Some(seen)
+    }
+
+    /// Draws one wedge or hash per chiral atom so the drawing has the parsed
+    /// configuration.
+    fn apply_centers(&mut self) {
+        for atom in 0..self.molecule.atoms.len() {
+            let Some(wanted) = self.chirality[atom] else {
+                continue;
+            };
+            let order: Vec<Option<usize>> = self.slots[atom]
+                .iter()
+                .map(|slot| match slot {
+                    Slot::Atom(a) => Some(*a),
+                    _ => None,
+                })
+                .collect();
+            if order.len() != 4 {
+                continue;
+            }
+
+            let mut candidates: Vec<(usize, usize)> = self
+                .molecule
+                .neighbors(atom)
+                .filter(|(bond, _)| {
+                    let data = &self.molecule.bonds[*bond];
+                    data.order == BondOrder::Single && data.stereo == BondStereo::None
+                })
+                .collect();
+            // Prefer bonds to plain chain atoms, those are the least confusing.
+            candidates.sort_by_key(|(bond, neighbor)| {
+                (
+                    self.chirality[*neighbor].is_some(),
+                    self.molecule.smallest_ring(*bond).is_some(),
+                    self.molecule.neighbors(*neighbor).count(),
+                )
+            });
+
+            for (bond, neighbor) in candidates {
+                let data = &mut self.molecule.bonds[bond];
+                let previous = (data.begin, data.end);
+                data.begin = atom;
+                data.end = neighbor;
+                data.stereo = BondStereo::Wedge;
+                match cip::anticlockwise(&self.molecule, atom, &order) {
+                    Some(actual) => {
+                        if actual != wanted {
+                            self.molecule.bonds[bond].stereo = BondStereo::Hash;
+                        }
+                        break;
+                    }
+                    None => {
+                        let data = &mut self.molecule.bonds[bond];
+                        (data.begin, data.end) = previous;
+                        data.stereo = BondStereo::None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn element_of(symbol: &str) -> Option<Element> {
+    let mut chars = symbol.chars();
+    let first = chars.next()?.to_ascii_uppercase();
+    Element::from_symbol(&std::iter::once(first).chain(chars).collect::<String>())
+}
+
+struct Writer<'a> {
+    molecule: &'a Molecule,
+    out: String,
+    visited: Vec<bool>,
+    parent: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    /// Ring bonds opened and closed at each atom, in the order written.
+    ring_opens: Vec<Vec<usize>>,
+    ring_closes: Vec<Vec<usize>>,
+    ring_digits: HashMap<usize, u32>,
+    free_digits: Vec<bool>,
+    aromatic: Vec<bool>,
+    chiral: Vec<bool>,
+    /// `bond -> (end, up)`: seen from `end`, the other atom is above.
+    directions: HashMap<usize, (usize, bool)>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(molecule: &'a Molecule) -> Self {
+        let n = molecule.atoms.len();
+        let aromatic = (0..n)
+            .map(|atom| {
+                let symbol = molecule.atoms[atom].element.symbol().to_ascii_lowercase();
+                AROMATIC.contains(&symbol.as_str())
+                    && molecule
+                        .neighbors(atom)
+                        .any(|(b, _)| molecule.bonds[b].order == BondOrder::Aromatic)
+            })
+            .collect();
+
+        let units = cip::assign(molecule);
+        let mut chiral = vec![false; n];
+        for unit in &units {
+            if let StereoUnit::Center {
+                atom,
+                descriptor: Some(_),
+            } = unit
+            {
+                chiral[*atom] = true;
+            }
+        }
+
+        let mut writer = Self {
+            molecule,
+            out: String::new(),
+            visited: vec![false; n],
+            parent: vec![None; n],
+            children: vec![Vec::new(); n],
+            ring_opens: vec![Vec::new(); n],
+            ring_closes: vec![Vec::new(); n],
+            ring_digits: HashMap::new(),
+            free_digits: vec![true; 100],
+            aromatic,
+            chiral,
+            directions: HashMap::new(),
+        };
+        writer.assign_directions(&units);
+        writer
+    }
+
+    fn run(mut self) -> String {
+        let mut fragments = Vec::new();
+        for start in 0..self.molecule.atoms.len() {
+            if self.visited[start] {
+                continue;
+            }
+            self.search(start, None);
+            self.write_atom(start);
+            fragments.push(std::mem::take(&mut self.out));
+        }
+        fragments.join(".")
+    }
+
+    /// Depth first pass that decides the spanning tree and ring bonds.
+    fn search(&mut self, atom: usize, from_bond: Option<usize>) {
+        self.visited[atom] = true;
+        for (bond, neighbor) in self.molecule.neighbors(atom) {
+            if Some(bond) == from_bond {
+                continue;
+            }
+            if self.visited[neighbor] {
+                // Seen from the descendant first, the ancestor opened it.
+                if !self.ring_closes[neighbor].contains(&bond)
+                    && !self.ring_opens[atom].contains(&bond)
+                {
+                    self.ring_opens[neighbor].push(bond);
+                    self.ring_closes[atom].push(bond);
+                }
+                continue;
+            }
+            self.parent[neighbor] = Some(atom);
+            self.children[atom].push(neighbor);
+            self.search(neighbor, Some(bond));
+        }
+    }
+
+    fn write_atom(&mut self, atom: usize) {
+        let molecule = self.molecule;
+        let mut order: Vec<Option<usize>> = self.parent[atom].into_iter().map(Some).collect();
+        let hydrogens = molecule.implicit_hydrogens(atom);
+        if self.chiral[atom] && hydrogens == 1 {
+            order.push(None);
+        }
+
+        let mut rings = String::new();
+        let closes = self.ring_closes[atom].clone();
+        for bond in closes {
+            let digit = self.ring_digits[&bond];
+            self.free_digits[digit as usize] = true;
+            rings.push_str(&ring_label(digit));
+            order.push(Some(molecule.bonds[bond].other(atom)));
+        }
+        let opens = self.ring_opens[atom].clone();
+        for bond in opens {
+            let digit = (1..100).find(|d| self.free_digits[*d]).unwrap() as u32;
+            self.free_digits[digit as usize] = false;
+            self.ring_digits.insert(bond, digit);
+            let other = molecule.bonds[bond].other(atom);
+            rings.push_str(&self.bond_symbol(bond, atom, other));
+            rings.push_str(&ring_label(digit));
+            order.push(Some(other));
+        }
+        let children = self.children[atom].clone();
+        order.extend(children.iter().map(|c| Some(*c)));
+
+        let chirality = if self.chiral[atom] && order.len() == 4 {
+            cip::anticlockwise(molecule, atom, &order)
+        } else {
+            None
+        };
+        self.out.push_str(&self.atom_symbol(atom, chirality));
+        self.out.push_str(&rings);
+
+        for (i, child) in children.iter().enumerate() {
+            let bond = molecule.bond_between(atom, *child).unwrap();
+            let branch = i + 1 < children.len();
+            if branch {
+                self.out.push('(');
+            }
+            let symbol = self.bond_symbol(bond, atom, *child);
+            self.out.push_str(&symbol);
+            self.write_atom(*child);
+            if branch {
+                self.out.push(')');
+            }
+        }
+    }
+
+    fn atom_symbol(&self, atom: usize, chirality: Option<bool>) -> String {
+        let molecule = self.molecule;
+        let data = &molecule.atoms[atom];
+        let mut symbol = data.element.symbol().to_string();
+        if self.aromatic[atom] {
+            symbol = symbol.to_ascii_lowercase();
+        }
+
+        let hydrogens = molecule.implicit_hydrogens(atom);
+        let bare = ORGANIC.contains(&data.element.symbol())
+            && data.charge == 0
+            && data.isotope.is_none()
+            && data.map.is_none()
+            && chirality.is_none()
+            && hydrogens == molecule.default_hydrogens(atom);
+        if bare {
+            return symbol;
+        }
+
+        let mut out = String::from("[");
+        if let Some(isotope) = data.isotope {
+            out.push_str(&isotope.to_string());
+        }
+        out.push_str(&symbol);
+        match chirality {
+            Some(true) => out.push('@'),
+            Some(false) => out.push_str("@@"),
+            None => (),
+        }
+        match hydrogens {
+            0 => (),
+            1 => out.push('H'),
+            n => out.push_str(&format!("H{n}")),
+        }
+        match data.charge {
+            0 => (),
+            1 => out.push('+'),
+            -1 => out.push('-'),
+            c if c > 0 => out.push_str(&format!("+{c}")),
+            c => out.push_str(&format!("-{}", c.unsigned_abs())),
+        }
+        if let Some(map) = data.map {
+            out.push_str(&format!(":{map}"));
+        }
+        out.push(']');
+        out
+    }
+
+    /// Symbol for `bond` written from `from` to `to`.
+    fn bond_symbol(&self, bond: usize, from: usize, to: usize) -> String {
+        let both_aromatic = self.aromatic[from] && self.aromatic[to];
+        if let Some((end, up)) = self.directions.get(&bond) {
+            let slash = if *end == from { *up } else { !*up };
+            return if slash { "/" } else { "\\" }.to_string();
+        }
+        match self.molecule.bonds[bond].order {
+            BondOrder::Single if both_aromatic => "-",
+            BondOrder::Single => "",
+            BondOrder::Double => "=",
+            BondOrder::Triple => "#",
+            BondOrder::Aromatic if both_aromatic => "",
+            BondOrder::Aromatic => ":",
+        }
+        .to_string()
+    }
+
+    /// Chooses `/` and `\` for the single bonds next to every double bond with
+    /// a defined configuration.
+    fn assign_directions(&mut self, units: &[StereoUnit]) {
+        let molecule = self.molecule;
+        for unit in units {
+            let StereoUnit::DoubleBond {
+                bond,
+                descriptor: Some(_),
+            } = unit
+            else {
+                continue;
+            };
+            let (a, b) = (molecule.bonds[*bond].begin, molecule.bonds[*bond].end);
+
+            let pick = |end: usize, partner: usize| {
+                let mut options: Vec<(usize, usize)> = molecule
+                    .neighbors(end)
+                    .filter(|(bond, neighbor)| {
+                        *neighbor != partner && molecule.bonds[*bond].order == BondOrder::Single
+                    })
+                    .collect();
+                options.sort_by_key(|(bond, _)| !self.directions.contains_key(bond));
+                options.first().copied()
+            };
+            let (Some((bond_a, sub_a)), Some((bond_b, sub_b))) = (pick(a, b), pick(b, a)) else {
+                continue;
+            };
+
+            let position = |atom: usize| molecule.atoms[atom].position;
+            let axis = position(b) - position(a);
+            let cis = axis.cross(position(sub_a) - position(a))
+                * axis.cross(position(sub_b) - position(a))
+                > 0.0;
+
+            let seen_from = |directions: &HashMap<usize, (usize, bool)>, bond, end| {
+                directions
+                    .get(&bond)
+                    .map(|(e, up)| if *e == end { *up } else { !*up })
+            };
+            let up_a = seen_from(&self.directions, bond_a, a).unwrap_or(true);
+            let up_b = if cis { up_a } else { !up_a };
+            if seen_from(&self.directions, bond_b, b).is_some_and(|up| up != up_b) {
+                continue;
+            }
+            self.directions.insert(bond_a, (a, up_a));
+            self.directions.insert(bond_b, (b, up_b));
+        }
+    }
+}
+
+fn ring_label(digit: u32) -> String {
+    if digit < 10 {
+        digit.to_string()
+    } else {
+        format!("%{digit}")
+    }
+}
@@ -0,0 +1,158 @@
+//! SVG images of a scene, cropped to its content or laid out on its page.
+//!
+//! One document unit becomes one SVG user unit (a CSS pixel). Labels are kept
+//! as `<text>` in the fonts the document names, so they stay editable in
+//! vector editors. A missing font falls back to the bundled one it was
+//! measured with, then to any sans-serif font.
+
+use std::fmt::Write;
+
+use iced::Color;
+use kurbo::{BezPath, PathEl, Point};
+
+use crate::font::Font;
+use crate::io::number;
+use crate::render::{HorizontalAlignment, Primitive, Scene, VerticalAlignment};
+
+pub fn write(scene: &Scene) -> String {
+    let bounds = scene.extent();
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="{x} {y} {w} {h}">"#,
+        x = number(bounds.x0),
+        y = number(bounds.y0),
+        w = number(bounds.width()),
+        h = number(bounds.height()),
+    )
+    .unwrap();
+
+    for primitive in &scene.primitives {
+        match primitive {
+            Primitive::Stroke {
+                path,
+                width,
+                color,
+                dash,
+            } => {
+                write!(
+                    out,
+                    r#"  <path d="{}" fill="none"{} stroke-width="{}" stroke-linecap="round" stroke-linejoin="round""#,
+                    path_data(path),
+                    paint("stroke", *color),
+                    number(*width),
+                )
+                .unwrap();
+                if !dash.is_empty() {
+                    let dash: Vec<String> = dash.iter().map(|d| number(*d)).collect();
+                    write!(out, r#" stroke-dasharray="{}""#, dash.join(" ")).unwrap();
+                }
+                out.push_str("/>\n");
+            }
+            Primitive::Fill { path, color } => {
+                writeln!(
+                    out,
+                    r#"  <path d="{}"{}/>"#,
+                    path_data(path),
+                    paint("fill", *color)
+                )
+                .unwrap();
+            }
+            Primitive::Text {
+                content,
+                position,
+                size,
+                color,
+                align_x,
+                align_y,
+                font,
+                style,
+            } => {
+                let anchor = match align_x {
+                    HorizontalAlignment::Left => "start",
+                    HorizontalAlignment::Center => "middle",
+                    HorizontalAlignment::Right => "end",
+                };
+                let baseline = match align_y {
+                    VerticalAlignment::Top => "text-before-edge",
+                    VerticalAlignment::Center => "central",
+                    VerticalAlignment::Bottom => "text-after-edge",
+                };
+                writeln!(
+                    out,
+                    r#"  <text x="{}" y="{}" font-family="{}" font-size="{}"{}{} text-anchor="{anchor}" dominant-baseline="{baseline}"{}>{}</text>"#,
+                    number(position.x),
+                    number(position.y),
+                    escape(&font_family(*font)),
+                    number(*size),
+                    if style.bold { r#" font-weight="bold""# } else { "" },
+                    if style.italic { r#" font-style="italic""# } else { "" },
+                    paint("fill", *color),
+                    escape(content),
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+fn font_family(font: Font) -> String {
+    if font.is_missing() {
+        format!(
+            "'{}', '{}', sans-serif",
+            font.family,
+            font.typeface.family()
+        )
+    } else {
+        format!("'{}', sans-serif", font.family)
+    }
+}
+
+fn path_data(path: &BezPath) -> String {
+    let point = |p: Point| format!("{},{}", number(p.x), number(p.y));
+    let commands: Vec<String> = path
+        .elements()
+        .iter()
+        .map(|element| match *element {
+            PathEl::MoveTo(p) => format!("M{}", point(p)),
+            PathEl::LineTo(p) => format!("L{}", point(p)),
+            PathEl::QuadTo(c, p) => format!("Q{} {}", point(c), point(p)),
+            PathEl::CurveTo(c1, c2, p) => format!("C{} {} {}", point(c1), point(c2), point(p)),
+            PathEl::ClosePath => "Z".to_string(),
+        })
+        .collect();
+    commands.join(" ")
+}
+
+/// ` fill="#rrggbb"` or ` stroke="#rrggbb"`, with an opacity if needed.
+fn paint(attribute: &str, color: Color) -> String {
+    let [r, g, b, a] = color.into_rgba8();
+    let mut paint = format!(r##" {attribute}="#{r:02x}{g:02x}{b:02x}""##);
+    if a < 255 {
+        write!(
+            paint,
+            r#" {attribute}-opacity="{}""#,
+            number(color.a as f64)
+        )
+        .unwrap();
+    }
+    paint
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,317 @@
+//! 2D coordinates for structures that arrive without any, such as SMILES.
+//!
+//! Atoms are placed by stress majorization: every pair of atoms is pulled
+//! towards the distance it would have in an ideal drawing, zig-zag for chains
+//! and regular polygons for rings, starting from a classical scaling of the
+//! graph distances.
+
+use std::collections::VecDeque;
+
+use kurbo::{Point, Vec2};
+
+use crate::arrow::{Arrow, ArrowKind};
+use crate::concept::Concept;
+use crate::molecule::Molecule;
+use crate::reaction::{Agent, Reaction};
+
+/// Bond length of generated drawings, in document units.
+pub const BOND_LENGTH: f64 = 30.0;
+
+const ITERATIONS: usize = 300;
+
+/// Gives every atom of `molecule` a position, laying out disconnected
+/// fragments side by side around the origin.
+pub fn generate(molecule: &mut Molecule) {
+    let components = molecule.components();
+    let mut x = 0.0;
+
+    for component in &components {
+        let positions = layout_component(molecule, component);
+        let min_x = positions.iter().map(|p| p.x).fold(f64::INFINITY, f64::min);
+        let max_x = positions
+            .iter()
+            .map(|p| p.x)
+            .fold(f64::NEG_INFINITY, f64::max);
+        for (atom, position) in component.iter().zip(positions) {
+            molecule.atoms[*atom].position = position + Vec2::new(x - min_x, 0.0);
+        }
+        x += max_x - min_x + BOND_LENGTH * 1.5;
+    }
+
+    if let Some(bounds) = molecule.bounding_box() {
+        molecule.translate(Point::ORIGIN - bounds.center());
+    }
+}
+
+/// Lines a reaction up from left to right: reactants, the arrow with its
+/// agents stacked above and below it, then products.
+pub fn arrange_reaction(reaction: &mut Reaction) {
+    let gap = BOND_LENGTH;
+    let mut x = 0.0;
+
+    for molecule in &mut reaction.reactants {
+        x = place(molecule, x, 0.0) + gap;
+    }
+
+    let width = |agents: &[Agent]| {
+        agents
+            .iter()
+            .filter_map(|agent| match agent {
+                Agent::Molecule(molecule) => molecule.bounding_box().map(|b| b.width()),
+                Agent::Text(annotation) => annotation
+                    .layout()
+                    .iter()
+                    .map(|line| line.width)
+                    .reduce(f64::max),
+            })
+            .fold(0.0, f64::max)
+    };
+    let length = width(&reaction.agents_above)
+        .max(width(&reaction.agents_below))
+        .max(BOND_LENGTH * 2.0)
+        + gap;
+    let tail = Point::new(x, 0.0);
+    let head = Point::new(x + length, 0.0);
+    let kind = match reaction.arrow.kind {
+        ArrowKind::Curved(_) => ArrowKind::Forward,
+        kind => kind,
+    };
+    reaction.arrow = Arrow::new(kind, tail, head);
+
+    let center = x + length / 2.0;
+    let mut y = -gap / 2.0;
+    for agent in &mut reaction.agents_above {
+        y = stack(agent, center, y, -1.0);
+    }
+    let mut y = gap / 2.0;
+    for agent in &mut reaction.agents_below {
+        y = stack(agent, center, y, 1.0);
+    }
+
+    let mut x = head.x + gap;
+    for molecule in &mut reaction.products {
+        x = place(molecule, x, 0.0) + gap;
+    }
+}
+
+/// Lines `concepts` up from left to right around the origin, `gap` apart.
+pub fn side_by_side(concepts: &mut [Concept], gap: f64) {
+    let mut x = 0.0;
+    for concept in concepts.iter_mut() {
+        let Some(bounds) = concept.bounding_box() else {
+            continue;
+        };
+        concept.translate(Vec2::new(x - bounds.x0, -bounds.center().y));
+        x += bounds.width() + gap;
+    }
+    let shift = Vec2::new(-(x - gap).max(0.0) / 2.0, 0.0);
+    concepts
+        .iter_mut()
+        .for_each(|concept| concept.translate(shift));
+}
+
+/// Moves `molecule` so its left edge is at `x` and it is centered on `y`,
+/// returning its right edge.
+fn place(molecule: &mut Molecule, x: f64, y: f64) -> f64 {
+    let Some(bounds) = molecule.bounding_box() else {
+        return x;
+    };
+    molecule.translate(Vec2::new(x - bounds.x0, y - bounds.center().y));
+    x + bounds.width()
+}
+
+/// Puts `agent` centered on `x` next to `y` in `direction`, returning where
+/// the next agent goes.
+fn stack(agent: &mut Agent, x: f64, y: f64, direction: f64) -> f64 {
+    match agent {
+        Agent::Molecule(molecule) => {
+            let Some(bounds) = molecule.bounding_box() else {
+                return y;
+            };
+            let height = bounds.height() + BOND_LENGTH / 2.0;
+            let target = Point::new(x, y + direction * height / 2.0);
+            molecule.translate(target - bounds.center());
+            y + direction * height
+        }
+        Agent::Text(annotation) => {
+            let height = annotation.line_height() * annotation.layout().len() as f64;
+            let text = &mut annotation.text;
+            text.position = iced::Point::new(x as f32, (y + direction * height / 2.0) as f32);
+            text.align_x = iced::widget::text::Alignment::Center;
+            text.align_y = iced::alignment::Vertical::Center;
+            y + direction * height
+        }
+    }
+}
+
+fn layout_component(molecule: &Molecule, atoms: &[usize]) -> Vec<Point> {
+    let n = atoms.len();
+    if n == 1 {
+        return vec![Point::ORIGIN];
+    }
+
+    let index_of = |atom: usize| atoms.iter().position(|a| *a == atom).unwrap();
+    let targets = target_distances(molecule, atoms, &index_of);
+    let mut positions = classical_scaling(&targets);
+
+    for _ in 0..ITERATIONS {
+        for i in 0..n {
+            let mut sum = Vec2::ZERO;
+            let mut weights = 0.0;
+            for j in 0..n {
+                if i == j {
+                    continue;
+                }
+                let d = positions[i] - positions[j];
+                let distance = d.hypot().max(1e-3);
+                let weight = 1.0 / (targets[i][j] * targets[i][j]);
+                sum += (positions[j].to_vec2() + d * (targets[i][j] / distance)) * weight;
+                weights += weight;
+            }
+            positions[i] = (sum / weights).to_point();
+        }
+    }
+
+    positions
+}
+
+/// Ideal distances between all atom pairs of a component.
+fn target_distances(
+    molecule: &Molecule,
+    atoms: &[usize],
+    index_of: &dyn Fn(usize) -> usize,
+) -> Vec<Vec<f64>> {
+    let n = atoms.len();
+    let mut hops = vec![vec![0usize; n]; n];
+    for (i, start) in atoms.iter().enumerate() {
+        let mut distance = vec![usize::MAX; n];
+        distance[i] = 0;
+        let mut queue = VecDeque::from([*start]);
+        while let Some(atom) = queue.pop_front() {
+            let d = distance[index_of(atom)];
+            for (_, neighbor) in molecule.neighbors(atom) {
+                let j = index_of(neighbor);
+                if distance[j] == usize::MAX {
+                    distance[j] = d + 1;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        hops[i] = distance;
+    }
+
+    // Zig-zag chains at 120 degrees.
+    let mut targets: Vec<Vec<f64>> = hops
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|k| match k {
+                    0 => 0.0,
+                    1 => BOND_LENGTH,
+                    k => BOND_LENGTH * *k as f64 * 0.866,
+                })
+                .collect()
+        })
+        .collect();
+
+    // Ring members sit on regular polygons.
+    for ring in rings(molecule, atoms) {
+        let size = ring.len();
+        let radius = BOND_LENGTH / (2.0 * (std::f64::consts::PI / size as f64).sin());
+        for (a, atom_a) in ring.iter().enumerate() {
+            for (b, atom_b) in ring.iter().enumerate().skip(a + 1) {
+                let steps = (b - a).min(size - (b - a));
+                let chord =
+                    2.0 * radius * (std::f64::consts::PI * steps as f64 / size as f64).sin();
+                let (i, j) = (index_of(*atom_a), index_of(*atom_b));
+                targets[i][j] = chord;
+                targets[j][i] = chord;
+            }
+        }
+    }
+
+    targets
+}
+
+/// Smallest ring through every ring bond of the component, as atom cycles.
+fn rings(molecule: &Molecule, atoms: &[usize]) -> Vec<Vec<usize>> {
+    let mut rings: Vec<Vec<usize>> = Vec::new();
+
+    for (bond, data) in molecule.bonds.iter().enumerate() {
+        if !atoms.contains(&data.begin) {
+            continue;
+        }
+        let Some(path) = molecule.shortest_path_without(data.begin, data.end, bond) else {
+            continue;
+        };
+        let mut sorted = path.clone();
+        sorted.sort_unstable();
+        if !rings.iter().any(|ring| {
+            let mut other = ring.clone();
+            other.sort_unstable();
+            other == sorted
+        }) {
+            rings.push(path);
+        }
+    }
+
+    rings
+}
+
+/// Places the atoms along the two main axes of the distance matrix, found by
+/// power iteration on the double centered squared distances.
+fn classical_scaling(targets: &[Vec<f64>]) -> Vec<Point> {
+    let n = targets.len();
+    let squared: Vec<Vec<f64>> = targets
+        .iter()
+        .map(|row| row.iter().map(|d| d * d).collect())
+        .collect();
+    let row_means: Vec<f64> = squared
+        .iter()
+        .map(|row| row.iter().sum::<f64>() / n as f64)
+        .collect();
+    let total_mean = row_means.iter().sum::<f64>() / n as f64;
+    let b: Vec<Vec<f64>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| -0.5 * (squared[i][j] - row_means[i] - row_means[j] + total_mean))
+                .collect()
+        })
+        .collect();
+
+    let mut axes: Vec<(Vec<f64>, f64)> = Vec::new();
+    for axis in 0..2 {
+        // Deterministic start that is not orthogonal to typical eigenvectors.
+        let mut v: Vec<f64> = (0..n)
+            .map(|i| ((i * (axis + 2) + 1) as f64).sin())
+            .collect();
+        let mut eigenvalue = 0.0;
+        for _ in 0..100 {
+            let mut next: Vec<f64> = (0..n)
+                .map(|i| (0..n).map(|j| b[i][j] * v[j]).sum())
+                .collect();
+            for (previous, _) in &axes {
+                let projection: f64 = next.iter().zip(previous).map(|(a, b)| a * b).sum();
+                for (x, p) in next.iter_mut().zip(previous) {
+                    *x -= projection * p;
+                }
+            }
+            let norm = next.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm < 1e-9 {
+                break;
+            }
+            eigenvalue = norm;
+            v = next.into_iter().map(|x| x / norm).collect();
+        }
+        axes.push((v, eigenvalue));
+    }
+
+    (0..n)
+        .map(|i| {
+            let x = axes[0].0[i] * axes[0].1.sqrt();
+            // Nudge perfectly straight chains off the line so they can fold.
+            let y = axes[1].0[i] * axes[1].1.sqrt() + if i % 2 == 0 { 0.5 } else { -0.5 };
+            Point::new(x, y)
+        })
+        .collect()
+}
@@ -0,0 +1,37 @@
+//! Chemical drawings: the document model, the chemistry behind it, reading
+//! and writing structure files and rendering to images.
+//!
+//! The Molecular app is a front-end over this crate; scripts can use it
+//! directly, e.g. to turn SMILES into SVG:
+//!
+//! ```
+//! use molecular::convert::{self, Format};
+//! use molecular::Document;
+//!
+//! let mut document = Document::default();
+//! document.import(convert::read_structures("CCO ethanol", Format::Smiles).unwrap());
+//! let svg = convert::write(&document, &[], Format::Svg).unwrap();
+//! assert!(svg.starts_with(b"<svg"));
+//! ```
+
+pub mod annotation;
+pub mod arrow;
+pub mod cip;
+pub mod concept;
+pub mod convert;
+pub mod document;
+pub mod element;
+pub mod figure;
+pub mod font;
+pub mod grid;
+pub mod io;
+pub mod layout;
+pub mod molecule;
+pub mod page;
+pub mod reaction;
+pub mod render;
+pub mod shape;
+pub mod spatial;
+pub mod style;
+
+pub use document::{Alignment, Axis, Document, Item, Layer};
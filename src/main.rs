@@ -1,32 +1,178 @@
 use std::fs::File;
-use std::io::{BufReader, Read, Write};
+use std::io::BufReader;
+use std::path::PathBuf;
 
+use iced::Element;
 use iced::widget::canvas::Text;
-use iced::widget::{button, column, container, row, space, text};
-use iced::{self, Element};
-use iced::{Color, Length};
+use iced::widget::{
+    button, checkbox, column, container, pick_list, row, scrollable, space, text, text_input,
+};
+use iced::{Color, Length, Task};
 
 mod app;
 mod canvas;
-mod concept;
+mod editor;
+mod history;
+mod ime;
 mod message;
-mod shape;
+mod tabs;
+#[cfg(test)]
+mod tests;
 mod tool;
 
 use message::Message;
 use rfd::FileDialog;
-use serde::Serialize;
 
-use crate::shape::Shape;
+use molecular::annotation::{Annotation, Script};
+use molecular::arrow::{Anchor, Arrow, ArrowHead, ArrowKind, HANDLE_RADIUS};
+use molecular::figure::{BracketKind, Figure, FigureKind, Outline};
+use molecular::grid::GridKind;
+use molecular::molecule::{DecorationKind, Orbital};
+use molecular::page::{Orientation, Page, Paper, UNITS_PER_MM};
+use molecular::shape::Shape;
+use molecular::spatial::SpatialIndex;
+use molecular::style::{self, Preset, Style};
+use molecular::{Alignment, Axis, Item, convert, font, io};
 
 fn main() -> iced::Result {
-    iced::run(update, view)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|command| command == "convert") {
+        if let Err(e) = convert::run(&args[1..]) {
+            eprintln!("molecular: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // Text is measured with the bundled font, so the canvas draws with it too.
+    iced::application(tabs::Tabs::default, tabs::update, tabs::view)
+        .subscription(tabs::subscription)
+        .exit_on_close_request(false)
+        .font(font::DATA)
+        .run()
+}
+
+/// `point` on the nearest grid point, when snapping to the grid and drawing
+/// with a tool that snaps. Curved arrows go to atoms and electrons instead.
+fn snap(state: &app::App, point: iced::Point) -> iced::Point {
+    let snaps = matches!(
+        state.selected_tool,
+        tool::Tool::Draw | tool::Tool::Figure(_)
+    ) || matches!(state.selected_tool, tool::Tool::Arrow(kind) if !matches!(kind, ArrowKind::Curved(_)));
+    if !state.document.grid.snap || !snaps {
+        return point;
+    }
+    let snapped = state
+        .document
+        .grid
+        .snap(kurbo::Point::new(point.x.into(), point.y.into()));
+    iced::Point::new(snapped.x as f32, snapped.y as f32)
+}
+
+/// Puts the text being edited into the document, unless it is empty.
+fn finish_typing(state: &mut app::App) {
+    if let Some(text) = state
+        .current_text_field
+        .take()
+        .and_then(editor::Editor::finish)
+    {
+        state.document.add_shape(Shape::Text(text));
+    }
+}
+
+fn update(state: &mut app::App, message: Message) -> Task<Message> {
+    // Panning, previews, typing, picking, rubber bands, panels and writing
+    // files leave the document as it is, and are not compared with it.
+    let keeps_document = match message {
+        Message::CanvasMoved(..)
+        | Message::StartedPanning(_)
+        | Message::StoppedPanning
+        | Message::DrawingMoved(..)
+        | Message::Edited(_)
+        | Message::StartedDragging(_)
+        | Message::Copy
+        | Message::Paste(_)
+        | Message::Save
+        | Message::Export
+        | Message::ToggleStylePanel
+        | Message::ToggleLayersPanel => true,
+        Message::SelectedTool(_) => state.current_text_field.is_none(),
+        Message::Dragged(_) => matches!(state.dragging, Some(tool::Drag::Selection { .. })),
+        _ => false,
+    };
+    let drag = match message {
+        Message::Dragged(_) | Message::StoppedDragging => state.dragging,
+        _ => None,
+    };
+    let undoes = matches!(message, Message::Undo | Message::Redo);
+    // A drag is undone as a whole, and undoing records nothing.
+    let before = match message {
+        Message::StartedDragging(_) => {
+            state.history.begin(&state.document);
+            None
+        }
+        Message::Dragged(_) | Message::Undo | Message::Redo => None,
+        Message::StoppedDragging => {
+            state.history.end(&state.document);
+            None
+        }
+        _ => (!keeps_document).then(|| state.document.clone()),
+    };
+    let task = handle(state, message);
+    // The index follows the items that changed, and starts over when the
+    // whole document is swapped. The document is drawn again only if it
+    // changed.
+    let changed = if let Some(before) = before {
+        state.index.refresh(&before, &state.document);
+        state.history.record(before, &state.document)
+    } else if undoes {
+        state.index = SpatialIndex::new(&state.document);
+        true
+    } else if let Some(drag) = drag {
+        let items = dragged_items(state, drag);
+        state.index.refresh_items(&state.document, &items);
+        !items.is_empty()
+    } else {
+        false
+    };
+    if changed {
+        state.cache.clear();
+    }
+    task
+}
+
+/// The items a step of `drag` can change: the dragged arrow, or the
+/// selection and the arrows that may follow it.
+fn dragged_items(state: &app::App, drag: tool::Drag) -> Vec<Item> {
+    match drag {
+        tool::Drag::ArrowPoint { shape, .. } => vec![Item::Shape(shape)],
+        tool::Drag::Move { .. } | tool::Drag::Rotate { .. } => {
+            let anchored = state
+                .document
+                .shapes
+                .iter()
+                .enumerate()
+                .filter(|(_, shape)| {
+                    matches!(shape, Shape::Arrow(arrow)
+                        if arrow.tail_anchor.is_some() || arrow.head_anchor.is_some())
+                })
+                .map(|(index, _)| Item::Shape(index));
+            state.selection.iter().copied().chain(anchored).collect()
+        }
+        tool::Drag::Selection { .. } => Vec::new(),
+    }
 }
 
-fn update(mut state: &mut app::App, message: Message) {
+fn handle(state: &mut app::App, message: Message) -> Task<Message> {
+    // Notices stay until the next action, not the next move of the mouse.
+    if !matches!(
+        message,
+        Message::CanvasMoved(..) | Message::DrawingMoved(..) | Message::Dragged(_)
+    ) {
+        state.notice = None;
+    }
     match message {
         Message::SelectedTool(t) => {
-            println!("Selected tool: {t:?}");
+            finish_typing(state);
             state.selected_tool = t;
         }
         Message::CanvasMoved(new_point, delta) => {
@@ -35,110 +181,502 @@ fn update(mut state: &mut app::App, message: Message) {
         }
         Message::StartedPanning(point) => state.panning_starting_pos = Some(point),
         Message::StoppedPanning => state.panning_starting_pos = None,
+        Message::StartedDragging(point) => {
+            let point = kurbo::Point::new(point.x.into(), point.y.into());
+            let rotation = state
+                .index
+                .bounds_of(&state.selection)
+                .filter(|bounds| tool::rotation_handle(*bounds).distance(point) <= HANDLE_RADIUS)
+                .map(|bounds| tool::Drag::Rotate {
+                    center: bounds.center(),
+                    angle: (point - bounds.center()).atan2(),
+                });
+            let handle = rotation.or_else(|| {
+                let is_editable = state.document.editable();
+                state
+                    .document
+                    .shapes
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .filter(|(shape, _)| is_editable(Item::Shape(*shape)))
+                    .find_map(|(shape, s)| match s {
+                        Shape::Arrow(arrow) if matches!(arrow.kind, ArrowKind::Curved(_)) => arrow
+                            .handle_at(point)
+                            .map(|point| tool::Drag::ArrowPoint { shape, point }),
+                        _ => None,
+                    })
+            });
+            state.dragging = match (handle, state.index.item_at(&state.document, point)) {
+                (Some(handle), _) => Some(handle),
+                (None, Some(item)) => {
+                    if !state.selection.contains(&item) {
+                        state.selection = state.document.with_groups(&[item]);
+                    }
+                    Some(tool::Drag::Move { last: point })
+                }
+                (None, None) => {
+                    state.selection.clear();
+                    Some(tool::Drag::Selection {
+                        start: point,
+                        end: point,
+                    })
+                }
+            };
+        }
+        Message::Dragged(point) => {
+            let point = kurbo::Point::new(point.x.into(), point.y.into());
+            match &mut state.dragging {
+                Some(tool::Drag::ArrowPoint {
+                    shape,
+                    point: index,
+                }) => {
+                    if let Some(Shape::Arrow(arrow)) = state.document.shapes.get_mut(*shape) {
+                        // A dragged end lets go of its anchor until it is dropped.
+                        match index {
+                            0 => arrow.tail_anchor = None,
+                            3 => arrow.head_anchor = None,
+                            _ => (),
+                        }
+                        *arrow.points_mut()[*index] = point;
+                    }
+                }
+                Some(tool::Drag::Move { last }) => {
+                    let mut offset = point - *last;
+                    // On the grid, the selection jumps from grid point to
+                    // grid point as the cursor gets closer to the next.
+                    if state.document.grid.snap {
+                        offset = state.document.grid.snap_offset(offset);
+                    }
+                    *last += offset;
+                    for item in state.selection.clone() {
+                        state.document.translate(item, offset);
+                    }
+                    state.document.follow_anchors();
+                }
+                Some(tool::Drag::Rotate { center, angle }) => {
+                    let to = (point - *center).atan2();
+                    state
+                        .document
+                        .rotate(&state.selection, to - *angle, *center);
+                    *angle = to;
+                }
+                Some(tool::Drag::Selection { end, .. }) => *end = point,
+                None => (),
+            }
+        }
+        Message::StoppedDragging => match state.dragging.take() {
+            Some(tool::Drag::ArrowPoint { shape, point }) => {
+                if let Some(Shape::Arrow(arrow)) = state.document.shapes.get_mut(shape) {
+                    match point {
+                        0 => arrow.tail_anchor = Anchor::at(&state.document.concepts, arrow.tail()),
+                        3 => arrow.head_anchor = Anchor::at(&state.document.concepts, arrow.head()),
+                        _ => (),
+                    }
+                    arrow.follow_anchors(&state.document.concepts);
+                }
+            }
+            Some(tool::Drag::Selection { start, end }) => {
+                let within = state
+                    .index
+                    .items_within(&state.document, kurbo::Rect::from_points(start, end));
+                state.selection = state.document.with_groups(&within);
+            }
+            Some(tool::Drag::Move { .. } | tool::Drag::Rotate { .. }) | None => (),
+        },
         Message::StartedDrawing(point, click) => {
-            state.drawing_starting_pos = Some(point);
+            state.drawing_starting_pos = Some(snap(state, point));
             state.previous_click = Some(click);
         }
-        Message::DrawingMoved(point, vector) => state.drawing_ending_pos = Some(point + vector),
+        Message::DrawingMoved(point, vector) => {
+            state.drawing_ending_pos = Some(snap(state, point + vector))
+        }
         Message::StoppedDrawing => {
-            if let Some(ending_point) = state.drawing_ending_pos {
-                if ending_point.distance(state.drawing_starting_pos.unwrap()) >= 10.0 {
-                    state.shape_storage.push(Shape::Line(kurbo::Line {
-                        p0: {
-                            let point = state.drawing_starting_pos.unwrap();
-                            kurbo::Point::new(point.x.into(), point.y.into())
-                        },
-                        p1: kurbo::Point::new(ending_point.x.into(), ending_point.y.into()),
-                    }));
-                } else {
-                    state.shape_storage.push(Shape::Point(kurbo::Point::new(
-                        state.drawing_starting_pos.unwrap().x as f64,
-                        state.drawing_starting_pos.unwrap().y as f64,
+            // The release of a click that ended typing started nothing.
+            let Some(start) = state.drawing_starting_pos else {
+                state.drawing_ending_pos = None;
+                return Task::none();
+            };
+            if let tool::Tool::Arrow(kind) = state.selected_tool {
+                if let Some(end) = state.drawing_ending_pos
+                    && end.distance(start) >= 10.0
+                {
+                    state.document.add_shape(Shape::Arrow(Arrow::anchored(
+                        kind,
+                        kurbo::Point::new(start.x.into(), start.y.into()),
+                        kurbo::Point::new(end.x.into(), end.y.into()),
+                        &state.document.concepts,
                     )));
                 }
+            } else if let tool::Tool::Decorate(kind) = state.selected_tool {
+                if !state
+                    .document
+                    .decorate(kurbo::Point::new(start.x.into(), start.y.into()), kind)
+                {
+                    state.notice = Some("No atom to decorate here".to_string());
+                }
+            } else if let tool::Tool::Figure(kind) = state.selected_tool {
+                if let Some(end) = state.drawing_ending_pos
+                    && end.distance(start) >= 10.0
+                {
+                    state.document.add_shape(Shape::Figure(Figure::new(
+                        kind,
+                        kurbo::Point::new(start.x.into(), start.y.into()),
+                        kurbo::Point::new(end.x.into(), end.y.into()),
+                    )));
+                }
+            } else if let Some(ending_point) = state.drawing_ending_pos
+                && ending_point.distance(start) >= 10.0
+            {
+                state.document.add_shape(Shape::line(kurbo::Line {
+                    p0: kurbo::Point::new(start.x.into(), start.y.into()),
+                    p1: kurbo::Point::new(ending_point.x.into(), ending_point.y.into()),
+                }));
             } else {
-                state.shape_storage.push(Shape::Point(kurbo::Point::new(
-                    state.drawing_starting_pos.unwrap().x as f64,
-                    state.drawing_starting_pos.unwrap().y as f64,
+                state.document.add_shape(Shape::point(kurbo::Point::new(
+                    start.x as f64,
+                    start.y as f64,
                 )));
             }
             state.drawing_starting_pos = None;
             state.drawing_ending_pos = None;
         }
         Message::StartedTyping(point) => {
-            state.current_text_field = Some({
-                let mut text = Text::default();
-                text.position = point;
-                text
+            finish_typing(state);
+            // The first click of the double click drew a point, which the
+            // text replaces. Anything else drawn last stays.
+            let first_click = kurbo::Point::new(point.x.into(), point.y.into());
+            if let Some(Shape::Point { point: p, .. }) = state.document.shapes.last()
+                && p.distance(first_click) < 1.0
+            {
+                state.document.remove_shape(state.document.shapes.len() - 1);
+            }
+            // Double clicking a text edits it again.
+            let existing = {
+                let is_editable = state.document.editable();
+                (0..state.document.shapes.len()).rev().find(|index| {
+                    is_editable(Item::Shape(*index))
+                        && matches!(&state.document.shapes[*index], Shape::Text(annotation)
+                            if editor::Editor::new(annotation.clone()).bounds().contains(first_click))
+                })
+            };
+            state.current_text_field = Some(match existing {
+                Some(index) => {
+                    let Shape::Text(annotation) = state.document.remove_shape(index) else {
+                        unreachable!()
+                    };
+                    state.selection.clear();
+                    let mut editor = editor::Editor::new(annotation);
+                    editor.apply(editor::Edit::Press(first_click));
+                    editor.apply(editor::Edit::Release);
+                    editor
+                }
+                None => editor::Editor::new(Annotation::new(Text {
+                    position: point,
+                    ..Default::default()
+                })),
             });
-            state.shape_storage.pop();
             state.selected_tool = tool::Tool::Typing
         }
         Message::DoubleClick(_point) => (),
-        Message::Typing(c) => {
-            state.current_text_field = Some({
-                let mut text = Text::default();
-                text.position = state.current_text_field.clone().unwrap().position;
-                text.content = state.current_text_field.clone().unwrap().content;
-                text.content.push(c);
-                text.position -= iced::Vector::new(text.size.0 / 4.0, 0.0);
-                text
-            })
+        Message::Edited(edit) => {
+            if let Some(editor) = &mut state.current_text_field {
+                editor.apply(edit);
+            }
         }
-        Message::Escape => {
+        Message::FinishedTyping => {
+            finish_typing(state);
             state.selected_tool = tool::Tool::Draw;
-            state
-                .shape_storage
-                .push(Shape::Text(state.current_text_field.clone().unwrap()));
-            state.current_text_field = None;
-        }
-        Message::Backspace => {
-            state.current_text_field = Some({
-                let mut text = Text::default();
-                text.position = state.current_text_field.clone().unwrap().position;
-                text.content = state.current_text_field.clone().unwrap().content;
-                text.content.pop();
-                text
-            })
         }
-        Message::Whitespace => {
-            state.current_text_field = Some({
-                let mut text = Text::default();
-                text.position = state.current_text_field.clone().unwrap().position;
-                text.content = state.current_text_field.clone().unwrap().content;
-                text.content.push(' ');
-                text
+        Message::Save => {
+            save(state);
+        }
+        Message::Undo => {
+            if state.history.undo(&mut state.document) {
+                state.selection.clear();
+            }
+        }
+        Message::Redo => {
+            if state.history.redo(&mut state.document) {
+                state.selection.clear();
+            }
+        }
+        // Opening, switching and closing documents is up to the tabs.
+        Message::Load
+        | Message::NewTab
+        | Message::SelectedTab(_)
+        | Message::NextTab
+        | Message::PreviousTab
+        | Message::CloseTab(_)
+        | Message::SaveAndClose
+        | Message::DiscardAndClose
+        | Message::CancelClose
+        | Message::CloseRequested => (),
+        Message::LoadFont => return load_font(state),
+        Message::Import => import(state),
+        Message::Export => export(state),
+        Message::ExportDpiChanged(dpi) => match dpi.parse() {
+            Ok(dpi) if dpi > 0 => state.document.export_dpi = dpi,
+            _ => {
+                state.notice = Some(format!(
+                    "The export resolution must be a whole number above 0, keeping {} dpi",
+                    state.document.export_dpi
+                ))
+            }
+        },
+        Message::ToggleTransparentExport(transparent) => {
+            state.document.transparent_export = transparent
+        }
+        Message::AssembleReaction => {
+            state.selection.clear();
+            if !state.document.assemble_reaction() {
+                state.notice = Some("No reaction arrow to assemble around".to_string());
+            }
+        }
+        Message::ToggleStereoDescriptors => {
+            state.document.show_stereo_descriptors = !state.document.show_stereo_descriptors
+        }
+        Message::SelectedStylePreset(preset) => {
+            let style = match preset {
+                // Keeps the current settings, only dropping the journal name.
+                Preset::Custom => Style {
+                    preset,
+                    ..state.document.style.clone()
+                },
+                preset => Style::preset(preset),
+            };
+            state.document.set_style(style);
+            state.style_draft = None;
+        }
+        Message::ToggleStylePanel => state.show_style_panel = !state.show_style_panel,
+        Message::StyleChanged(field, value) => {
+            if let Some(style) = state.document.style.set(field, &value) {
+                state.document.set_style(style);
+            }
+            state.style_draft = Some((field, value));
+        }
+        Message::ToggleElementColors(on) => {
+            let style = Style {
+                element_colors: on,
+                ..state.document.style.clone()
+            };
+            state.document.set_style(style);
+        }
+        Message::ToggleGrid(shown) => state.document.grid.shown = shown,
+        Message::SelectedGridKind(kind) => state.document.grid.kind = kind,
+        Message::SelectedGridSpacing(spacing) => state.document.grid.spacing = spacing,
+        Message::ToggleSnapToGrid(snap) => state.document.grid.snap = snap,
+        Message::ToggleRulers(rulers) => state.document.grid.rulers = rulers,
+        Message::SelectedPaper(paper) => {
+            state.document.page = paper.map(|paper| Page {
+                paper,
+                ..state.document.page.unwrap_or_default()
             })
         }
-        Message::Save => {
-            println!("saving whole state");
-            let json = serde_json::to_string_pretty(state).unwrap();
-            println!("{json}");
-            let mut file = File::create("saved.json").expect("could create file");
-            file.write_all(json.as_bytes()).unwrap();
-        }
-        Message::Load => {
-            println!("loading whole state");
-            let files = FileDialog::new()
-                .add_filter("text", &["json"])
-                .set_directory("/")
-                .pick_file();
-
-            match files {
-                Some(f) => {
-                    let serialized = File::open(f).unwrap();
-                    let reader = BufReader::new(serialized);
-                    let app: app::App = serde_json::from_reader(reader).unwrap();
-                    *state = app;
+        Message::SelectedOrientation(orientation) => {
+            if let Some(page) = &mut state.document.page {
+                page.orientation = orientation;
+            }
+        }
+        Message::SelectedMargin(margin) => {
+            if let Some(page) = &mut state.document.page {
+                page.margin = margin;
+            }
+        }
+        Message::SetColor(color) => state
+            .document
+            .restyle(&state.selection, |appearance| appearance.color = color),
+        Message::SetLineWidth(width) => state
+            .document
+            .restyle(&state.selection, |appearance| appearance.line_width = width),
+        Message::SetDash(dash) => state
+            .document
+            .restyle(&state.selection, |appearance| appearance.dash = dash),
+        Message::SetSubscript(subscript) => {
+            for item in &state.selection {
+                if let Item::Shape(index) = *item
+                    && let Shape::Figure(figure) = &mut state.document.shapes[index]
+                    && let Outline::Brackets { subscript: s, .. } = &mut figure.outline
+                {
+                    s.clone_from(&subscript);
                 }
-                None => println!("Couldnt find file!"),
             }
         }
+        Message::Group => state.document.group(&state.selection),
+        Message::Ungroup => state.document.ungroup(&state.selection),
+        Message::BringToFront => state.selection = state.document.bring_to_front(&state.selection),
+        Message::SendToBack => state.selection = state.document.send_to_back(&state.selection),
+        Message::Align(alignment) => state.document.align(&state.selection, alignment),
+        Message::Distribute(axis) => state.document.distribute(&state.selection, axis),
+        Message::Flip(axis) => state.document.flip(&state.selection, axis),
+        Message::ToggleLayersPanel => state.show_layers_panel = !state.show_layers_panel,
+        Message::AddLayer => {
+            let name = format!("Layer {}", state.document.layers.len() + 1);
+            state.document.add_layer(name);
+        }
+        Message::SelectedLayer(layer) => state.document.active_layer = layer,
+        Message::RenamedLayer(layer, name) => state.document.layers[layer].name = name,
+        Message::ToggleLayerHidden(layer, hidden) => {
+            state.document.layers[layer].hidden = hidden;
+            let is_editable = state.document.editable();
+            state.selection.retain(|item| is_editable(*item));
+        }
+        Message::ToggleLayerLocked(layer, locked) => {
+            state.document.layers[layer].locked = locked;
+            let is_editable = state.document.editable();
+            state.selection.retain(|item| is_editable(*item));
+        }
+        Message::MoveToLayer(layer) => {
+            state.document.set_layer(&state.selection, layer);
+            let is_editable = state.document.editable();
+            state.selection.retain(|item| is_editable(*item));
+        }
+        Message::Copy if !state.selection.is_empty() => {
+            let fragment = state.document.fragment(&state.selection);
+            return iced::clipboard::write(io::clipboard::write(&fragment));
+        }
+        Message::Cut if !state.selection.is_empty() => {
+            let fragment = state.document.fragment(&state.selection);
+            state.document.delete(&state.selection);
+            state.selection.clear();
+            return iced::clipboard::write(io::clipboard::write(&fragment));
+        }
+        Message::Copy | Message::Cut => (),
+        Message::Paste(point) => {
+            return iced::clipboard::read().map(move |text| Message::Pasted(point, text));
+        }
+        Message::Pasted(point, text) => {
+            match io::clipboard::read(text.as_deref().unwrap_or_default()) {
+                Ok(fragment) => {
+                    let point = kurbo::Point::new(point.x.into(), point.y.into());
+                    state.selection = state.document.paste(fragment, point);
+                }
+                Err(e) => state.notice = Some(format!("Cannot paste: {e}")),
+            }
+        }
+    }
+    Task::none()
+}
+
+/// Loads a font file for documents to name, handing it to iced as well.
+fn load_font(state: &mut app::App) -> Task<Message> {
+    let Some(path) = FileDialog::new()
+        .add_filter("fonts", &["ttf", "otf", "ttc"])
+        .pick_file()
+    else {
+        return Task::none();
+    };
+    let loaded = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| font::load(data).map_err(|e| e.to_string()));
+    match loaded {
+        Ok(typeface) => {
+            state.notice = Some(format!("Loaded font {}", typeface.family()));
+            iced::font::load(typeface.data()).discard()
+        }
+        Err(e) => {
+            state.notice = Some(format!("Cannot load font {}: {e}", path.display()));
+            Task::none()
+        }
+    }
+}
+
+/// Reads a molfile, RXN file or (reaction) SMILES file into the document.
+fn import(state: &mut app::App) {
+    let Some(path) = FileDialog::new()
+        .add_filter(
+            "structures",
+            &["mol", "sdf", "rxn", "smi", "smiles", "rsmi"],
+        )
+        .pick_file()
+    else {
+        return;
+    };
+    let Some(format) = convert::Format::of(&path) else {
+        state.notice = Some(format!("Unknown format of {}", path.display()));
+        return;
+    };
+    let concepts = std::fs::read_to_string(&path)
+        .map_err(convert::Error::from)
+        .and_then(|text| convert::read_structures(&text, format));
+    match concepts {
+        Ok(concepts) => state.document.import(concepts),
+        Err(e) => state.notice = Some(format!("Cannot import {}: {e}", path.display())),
+    }
+}
+
+/// Writes the document to the file it came from, or to one picked for it.
+/// Whether it was saved.
+pub(crate) fn save(state: &mut app::App) -> bool {
+    let Some(path) = state.path.clone().or_else(|| {
+        FileDialog::new()
+            .add_filter("text", &["json"])
+            .set_file_name(format!("{}.json", state.title()))
+            .save_file()
+    }) else {
+        return false;
+    };
+    let saved = serde_json::to_string_pretty(state)
+        .map_err(std::io::Error::from)
+        .and_then(|json| std::fs::write(&path, json));
+    if let Err(e) = saved {
+        state.notice = Some(format!("Cannot save {}: {e}", path.display()));
+        return false;
+    }
+    state.path = Some(path);
+    state.history.mark_saved();
+    true
+}
+
+/// The document saved at `path`, ready to be shown in a tab, or why it
+/// cannot be.
+pub(crate) fn open(path: PathBuf) -> Result<app::App, String> {
+    let loaded = File::open(&path)
+        .map(BufReader::new)
+        .map_err(serde_json::Error::io)
+        .and_then(serde_json::from_reader::<_, app::App>);
+    let mut state = loaded.map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+    let missing = state.document.missing_fonts();
+    if !missing.is_empty() {
+        state.notice = Some(format!(
+            "Fonts not loaded, using {} instead: {}",
+            font::bundled().family(),
+            missing.join(", ")
+        ));
+    }
+    state.index = SpatialIndex::new(&state.document);
+    state.path = Some(path);
+    Ok(state)
+}
+
+/// Writes the document in the format picked by the file extension. Images
+/// show the selection, or the whole document when nothing is selected.
+fn export(state: &mut app::App) {
+    let Some(path) = FileDialog::new()
+        .add_filter("molfile", &["mol"])
+        .add_filter("SD file", &["sdf"])
+        .add_filter("RXN file", &["rxn"])
+        .add_filter("SMILES", &["smi", "rsmi"])
+        .add_filter("SVG image", &["svg"])
+        .add_filter("PNG image", &["png"])
+        .add_filter("PDF document", &["pdf"])
+        .add_filter("EPS image", &["eps"])
+        .save_file()
+    else {
+        return;
+    };
+
+    let format = convert::Format::of(&path).unwrap_or(convert::Format::Molfile);
+    let written = convert::write(&state.document, &state.selection, format)
+        .and_then(|bytes| std::fs::write(&path, bytes).map_err(convert::Error::from));
+    if let Err(e) = written {
+        state.notice = Some(format!("Cannot export {}: {e}", path.display()));
     }
 }
 
 fn view(state: &app::App) -> Element<'_, Message> {
     // Top Menu
+    let dpi = state.document.export_dpi.to_string();
     let top_menu = container(
         row![
             text("Molecular").size(20),
@@ -146,6 +684,38 @@ fn view(state: &app::App) -> Element<'_, Message> {
             button("save").on_press(Message::Save),
             space(),
             button("load").on_press(Message::Load),
+            space(),
+            button("undo").on_press(Message::Undo),
+            button("redo").on_press(Message::Redo),
+            space(),
+            button("font").on_press(Message::LoadFont),
+            space(),
+            button("import").on_press(Message::Import),
+            space(),
+            button("export").on_press(Message::Export),
+            space(),
+            text_input("dpi", &dpi)
+                .on_input(Message::ExportDpiChanged)
+                .width(Length::Fixed(60.0)),
+            text("dpi"),
+            space(),
+            checkbox(state.document.transparent_export)
+                .label("transparent")
+                .on_toggle(Message::ToggleTransparentExport),
+            space(),
+            button("rxn").on_press(Message::AssembleReaction),
+            space(),
+            button("R/S").on_press(Message::ToggleStereoDescriptors),
+            space(),
+            pick_list(
+                Preset::ALL,
+                Some(state.document.style.preset),
+                Message::SelectedStylePreset
+            ),
+            space(),
+            button("style").on_press(Message::ToggleStylePanel),
+            space(),
+            button("layers").on_press(Message::ToggleLayersPanel),
         ]
         .padding(10)
         .align_y(iced::Alignment::Center),
@@ -157,15 +727,73 @@ fn view(state: &app::App) -> Element<'_, Message> {
     });
 
     // Sidebar with Hover Listeners
-    let sidebar = container(
+    let sidebar = container(scrollable(
         column![
             button("P").on_press(Message::SelectedTool(tool::Tool::Pick)),
             button("D").on_press(Message::SelectedTool(tool::Tool::Draw)),
+            button("->").on_press(Message::SelectedTool(tool::Tool::Arrow(ArrowKind::Forward))),
+            button("<=>").on_press(Message::SelectedTool(tool::Tool::Arrow(
+                ArrowKind::Equilibrium
+            ))),
+            button("=>").on_press(Message::SelectedTool(tool::Tool::Arrow(
+                ArrowKind::Retrosynthetic
+            ))),
+            button("<->").on_press(Message::SelectedTool(tool::Tool::Arrow(
+                ArrowKind::Resonance
+            ))),
+            button("(>").on_press(Message::SelectedTool(tool::Tool::Arrow(ArrowKind::Curved(
+                ArrowHead::Full
+            )))),
+            button("(/").on_press(Message::SelectedTool(tool::Tool::Arrow(ArrowKind::Curved(
+                ArrowHead::Half
+            )))),
+            button("[]").on_press(Message::SelectedTool(tool::Tool::Figure(
+                FigureKind::Rectangle
+            ))),
+            button("()").on_press(Message::SelectedTool(tool::Tool::Figure(
+                FigureKind::RoundedRectangle
+            ))),
+            button("O").on_press(Message::SelectedTool(tool::Tool::Figure(
+                FigureKind::Ellipse
+            ))),
+            button("<>").on_press(Message::SelectedTool(tool::Tool::Figure(
+                FigureKind::Polygon(6)
+            ))),
+            button("[ ]").on_press(Message::SelectedTool(tool::Tool::Figure(
+                FigureKind::Brackets(BracketKind::Square)
+            ))),
+            button("( )").on_press(Message::SelectedTool(tool::Tool::Figure(
+                FigureKind::Brackets(BracketKind::Round)
+            ))),
+            button("{ }").on_press(Message::SelectedTool(tool::Tool::Figure(
+                FigureKind::Brackets(BracketKind::Curly)
+            ))),
+            button(":").on_press(Message::SelectedTool(tool::Tool::Decorate(
+                DecorationKind::LonePair
+            ))),
+            button(".").on_press(Message::SelectedTool(tool::Tool::Decorate(
+                DecorationKind::Radical
+            ))),
+            button("(+)").on_press(Message::SelectedTool(tool::Tool::Decorate(
+                DecorationKind::Charge(1)
+            ))),
+            button("(-)").on_press(Message::SelectedTool(tool::Tool::Decorate(
+                DecorationKind::Charge(-1)
+            ))),
+            button("s").on_press(Message::SelectedTool(tool::Tool::Decorate(
+                DecorationKind::Orbital(Orbital::S)
+            ))),
+            button("p").on_press(Message::SelectedTool(tool::Tool::Decorate(
+                DecorationKind::Orbital(Orbital::P)
+            ))),
+            button("sp3").on_press(Message::SelectedTool(tool::Tool::Decorate(
+                DecorationKind::Orbital(Orbital::Sp3)
+            ))),
             // Add your tool buttons here
         ]
-        .spacing(20)
+        .spacing(10)
         .padding(10),
-    )
+    ))
     .width(Length::Fixed(50.0))
     .height(Length::Fill)
     .style(|_| container::Style {
@@ -173,9 +801,441 @@ fn view(state: &app::App) -> Element<'_, Message> {
         ..Default::default()
     });
 
-    let main_content = container(state.view())
+    let canvas = match &state.current_text_field {
+        Some(editor) if matches!(state.selected_tool, tool::Tool::Typing) => {
+            column![format_bar(editor), state.view()].into()
+        }
+        _ => state.view(),
+    };
+    let main_content = container(canvas).width(Length::Fill).height(Length::Fill);
+
+    let mut content = row![sidebar, main_content];
+    if let Some(&item) = state.selection.first() {
+        content = content.push(properties_panel(&state.document, item));
+    }
+    if state.show_style_panel {
+        content = content.push(style_panel(state));
+    }
+    if state.show_layers_panel {
+        content = content.push(layers_panel(state));
+    }
+
+    let mut page = column![top_menu];
+    if let Some(notice) = &state.notice {
+        page = page.push(
+            container(text(notice).size(12))
+                .width(Length::Fill)
+                .padding([4, 10])
+                .style(|_| container::Style {
+                    background: Some(Color::from_rgb(0.95, 0.85, 0.5).into()),
+                    ..Default::default()
+                }),
+        );
+    }
+    page.push(content).into()
+}
+
+/// Colors offered for runs of text, the first one leaving the text color.
+const TEXT_COLORS: [Option<Color>; 5] = [
+    None,
+    Some(Color::from_rgb(0.8, 0.1, 0.1)),
+    Some(Color::from_rgb(0.1, 0.3, 0.8)),
+    Some(Color::from_rgb(0.1, 0.55, 0.2)),
+    Some(Color::from_rgb(0.85, 0.45, 0.0)),
+];
+
+/// Formatting of the selection or of what is typed next, with the chemistry
+/// auto-format switch.
+fn format_bar(editor: &editor::Editor) -> Element<'_, Message> {
+    use editor::{Edit, Emphasis};
+
+    let format = editor.format();
+    let toggle = |label, active: bool, emphasis| {
+        button(text(label))
+            .style(if active {
+                button::primary
+            } else {
+                button::secondary
+            })
+            .on_press(Message::Edited(Edit::Format(emphasis)))
+    };
+    let mut bar = row![
+        pick_list(
+            font::families(),
+            Some(editor.annotation.font().family),
+            |family| Message::Edited(Edit::Font(family))
+        )
+        .text_size(12),
+        toggle("B", format.bold, Emphasis::Bold),
+        toggle("I", format.italic, Emphasis::Italic),
+        toggle(
+            "x₂",
+            format.script == Script::Sub,
+            Emphasis::Script(Script::Sub)
+        ),
+        toggle(
+            "x²",
+            format.script == Script::Super,
+            Emphasis::Script(Script::Super)
+        ),
+        button(text("αβ")).on_press(Message::Edited(Edit::Greek)),
+    ]
+    .spacing(5)
+    .padding(5)
+    .align_y(iced::Alignment::Center);
+    for color in TEXT_COLORS {
+        let swatch = text("A").color(color.unwrap_or(editor.annotation.text.color));
+        bar = bar.push(
+            button(swatch)
+                .style(if format.color == color {
+                    button::secondary
+                } else {
+                    button::text
+                })
+                .on_press(Message::Edited(Edit::Format(Emphasis::Color(color)))),
+        );
+    }
+    bar = bar.push(
+        checkbox(editor.annotation.auto_format)
+            .label("chemistry")
+            .on_toggle(|on| Message::Edited(Edit::AutoFormat(on))),
+    );
+
+    container(bar)
         .width(Length::Fill)
-        .height(Length::Fill);
+        .style(|_| container::Style {
+            background: Some(Color::from_rgb(0.9, 0.9, 0.9).into()),
+            ..Default::default()
+        })
+        .into()
+}
+
+/// Colors offered for selected objects, the first one leaving the style
+/// color.
+const COLORS: [Option<Color>; 9] = [
+    None,
+    Some(Color::BLACK),
+    Some(Color::from_rgb(0.5, 0.5, 0.5)),
+    Some(Color::from_rgb(0.8, 0.1, 0.1)),
+    Some(Color::from_rgb(0.85, 0.45, 0.0)),
+    Some(Color::from_rgb(0.1, 0.55, 0.2)),
+    Some(Color::from_rgb(0.0, 0.55, 0.6)),
+    Some(Color::from_rgb(0.1, 0.3, 0.8)),
+    Some(Color::from_rgb(0.5, 0.1, 0.6)),
+];
+
+/// Line widths offered for selected objects, in points.
+const LINE_WIDTHS: [Option<f64>; 7] = [
+    None,
+    Some(0.25),
+    Some(0.5),
+    Some(0.75),
+    Some(1.0),
+    Some(1.5),
+    Some(2.0),
+];
+
+/// A line width in points as shown in a pick list, `None` for the width of
+/// the style.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct LineWidth(Option<f64>);
+
+impl std::fmt::Display for LineWidth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(points) => write!(f, "{} pt", (points * 100.0).round() / 100.0),
+            None => f.write_str("style"),
+        }
+    }
+}
+
+/// Color, line width and dashes of the selection, and the subscript of
+/// brackets, shown for its first item.
+fn properties_panel(document: &molecular::Document, item: Item) -> Element<'_, Message> {
+    let appearance = document.appearance(item);
+    let swatches = COLORS.map(|color| {
+        let swatch: Element<'_, Message> = match color {
+            Some(color) => container(space())
+                .width(14)
+                .height(14)
+                .style(move |_| container::Style {
+                    background: Some(color.into()),
+                    ..Default::default()
+                })
+                .into(),
+            None => text("auto").size(12).into(),
+        };
+        button(swatch)
+            .padding(3)
+            .style(if appearance.color == color {
+                button::primary
+            } else {
+                button::text
+            })
+            .on_press(Message::SetColor(color))
+            .into()
+    });
+    let subscript = match item {
+        Item::Shape(index) => match &document.shapes[index] {
+            Shape::Figure(Figure {
+                outline: Outline::Brackets { subscript, .. },
+                ..
+            }) => Some(subscript.as_str()),
+            _ => None,
+        },
+        Item::Concept(_) => None,
+    };
+    let width = LineWidth(
+        appearance
+            .line_width
+            .map(|width| width / style::UNITS_PER_POINT),
+    );
+
+    container(scrollable(
+        column![
+            text("color").size(12),
+            row(swatches).spacing(2).wrap(),
+            text("line width").size(12),
+            pick_list(LINE_WIDTHS.map(LineWidth), Some(width), |width| {
+                Message::SetLineWidth(width.0.map(|points| points * style::UNITS_PER_POINT))
+            })
+            .text_size(12),
+            text("dash").size(12),
+            pick_list(style::Dash::ALL, Some(appearance.dash), Message::SetDash).text_size(12),
+            text("arrange").size(12),
+            row![
+                button(text("front").size(12)).on_press(Message::BringToFront),
+                button(text("back").size(12)).on_press(Message::SendToBack),
+            ]
+            .spacing(2),
+            row![
+                button(text("group").size(12)).on_press(Message::Group),
+                button(text("ungroup").size(12)).on_press(Message::Ungroup),
+            ]
+            .spacing(2),
+            text("align").size(12),
+            row(Alignment::ALL.map(|alignment| {
+                button(text(alignment.to_string()).size(12))
+                    .on_press(Message::Align(alignment))
+                    .into()
+            }))
+            .spacing(2)
+            .wrap(),
+            text("distribute").size(12),
+            row![
+                button(text("across").size(12)).on_press(Message::Distribute(Axis::Horizontal)),
+                button(text("down").size(12)).on_press(Message::Distribute(Axis::Vertical)),
+            ]
+            .spacing(2),
+            text("flip").size(12),
+            row![
+                button(text("horizontal").size(12)).on_press(Message::Flip(Axis::Horizontal)),
+                button(text("vertical").size(12)).on_press(Message::Flip(Axis::Vertical)),
+            ]
+            .spacing(2),
+        ]
+        .push(subscript.map(|subscript| {
+            column![
+                text("subscript").size(12),
+                text_input("n", subscript).on_input(Message::SetSubscript),
+            ]
+            .spacing(5)
+        }))
+        .spacing(5)
+        .padding(10),
+    ))
+    .width(Length::Fixed(160.0))
+    .height(Length::Fill)
+    .style(|_| container::Style {
+        background: Some(Color::from_rgb(0.15, 0.15, 0.15).into()),
+        text_color: Some(Color::WHITE),
+        ..Default::default()
+    })
+    .into()
+}
+
+/// The layers of the document, top layer first, with the active one
+/// highlighted.
+fn layers_panel(state: &app::App) -> Element<'_, Message> {
+    let document = &state.document;
+    let mut layers = column![button(text("add layer").size(12)).on_press(Message::AddLayer)]
+        .spacing(10)
+        .padding(10);
+    for (index, layer) in document.layers.iter().enumerate().rev() {
+        let mut controls = row![
+            checkbox(!layer.hidden)
+                .label("show")
+                .on_toggle(move |shown| Message::ToggleLayerHidden(index, !shown)),
+            checkbox(layer.locked)
+                .label("lock")
+                .on_toggle(move |locked| Message::ToggleLayerLocked(index, locked)),
+        ]
+        .spacing(5);
+        if !state.selection.is_empty() {
+            controls = controls
+                .push(button(text("move here").size(12)).on_press(Message::MoveToLayer(index)));
+        }
+        layers = layers.push(
+            column![
+                row![
+                    button(text(" ").size(12))
+                        .style(if index == document.active_layer {
+                            button::primary
+                        } else {
+                            button::secondary
+                        })
+                        .on_press(Message::SelectedLayer(index)),
+                    text_input("name", &layer.name)
+                        .on_input(move |name| Message::RenamedLayer(index, name)),
+                ]
+                .spacing(5)
+                .align_y(iced::Alignment::Center),
+                controls,
+            ]
+            .spacing(5),
+        );
+    }
+
+    container(scrollable(layers))
+        .width(Length::Fixed(240.0))
+        .height(Length::Fill)
+        .style(|_| container::Style {
+            background: Some(Color::from_rgb(0.15, 0.15, 0.15).into()),
+            text_color: Some(Color::WHITE),
+            ..Default::default()
+        })
+        .into()
+}
+
+/// Grid spacings offered, in points.
+const GRID_SPACINGS: [f64; 7] = [5.0, 7.2, 10.0, 14.4, 20.0, 28.8, 36.0];
+
+/// A grid spacing in points as shown in a pick list.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct GridSpacing(f64);
+
+impl std::fmt::Display for GridSpacing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} pt", (self.0 * 100.0).round() / 100.0)
+    }
+}
+
+/// Page margins offered, in millimetres.
+const MARGINS: [f64; 6] = [0.0, 5.0, 10.0, 15.0, 20.0, 25.0];
+
+/// A paper size as shown in a pick list, `None` for no page.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct PaperChoice(Option<Paper>);
 
-    column![top_menu, row![sidebar, main_content]].into()
+impl std::fmt::Display for PaperChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(paper) => paper.fmt(f),
+            None => f.write_str("no page"),
+        }
+    }
+}
+
+/// A page margin in millimetres as shown in a pick list.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Margin(f64);
+
+impl std::fmt::Display for Margin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} mm", (self.0 * 10.0).round() / 10.0)
+    }
+}
+
+/// Settings of the document style, lengths in points, of its grid and of
+/// its page.
+fn style_panel(state: &app::App) -> Element<'_, Message> {
+    let fields = style::Field::ALL.into_iter().map(|field| {
+        let value = match &state.style_draft {
+            Some((draft, value)) if *draft == field => value.clone(),
+            _ => state.document.style.get(field),
+        };
+        column![
+            text(field.name()).size(12),
+            text_input("", &value).on_input(move |value| Message::StyleChanged(field, value)),
+        ]
+        .spacing(2)
+        .into()
+    });
+
+    let element_colors = checkbox(state.document.style.element_colors)
+        .label("element colors")
+        .on_toggle(Message::ToggleElementColors);
+
+    let grid = &state.document.grid;
+    let grid = column![
+        text("grid").size(12),
+        checkbox(grid.shown)
+            .label("show grid")
+            .on_toggle(Message::ToggleGrid),
+        pick_list(GridKind::ALL, Some(grid.kind), Message::SelectedGridKind).text_size(12),
+        pick_list(
+            GRID_SPACINGS.map(GridSpacing),
+            Some(GridSpacing(grid.spacing / style::UNITS_PER_POINT)),
+            |spacing| Message::SelectedGridSpacing(spacing.0 * style::UNITS_PER_POINT)
+        )
+        .text_size(12),
+        checkbox(grid.snap)
+            .label("snap to grid")
+            .on_toggle(Message::ToggleSnapToGrid),
+        checkbox(grid.rulers)
+            .label("rulers")
+            .on_toggle(Message::ToggleRulers),
+    ]
+    .spacing(5);
+
+    let page = state.document.page;
+    let papers = std::iter::once(None)
+        .chain(Paper::ALL.map(Some))
+        .map(PaperChoice)
+        .collect::<Vec<_>>();
+    let page = column![
+        text("page").size(12),
+        pick_list(
+            papers,
+            Some(PaperChoice(page.map(|page| page.paper))),
+            |paper| { Message::SelectedPaper(paper.0) }
+        )
+        .text_size(12),
+    ]
+    .push(page.map(|page| {
+        column![
+            pick_list(
+                Orientation::ALL,
+                Some(page.orientation),
+                Message::SelectedOrientation
+            )
+            .text_size(12),
+            text("margins").size(12),
+            pick_list(
+                MARGINS.map(Margin),
+                Some(Margin(page.margin / UNITS_PER_MM)),
+                |margin| Message::SelectedMargin(margin.0 * UNITS_PER_MM)
+            )
+            .text_size(12),
+        ]
+        .spacing(5)
+    }))
+    .spacing(5);
+
+    container(scrollable(
+        column(fields)
+            .push(element_colors)
+            .push(grid)
+            .push(page)
+            .spacing(10)
+            .padding(10),
+    ))
+    .width(Length::Fixed(160.0))
+    .height(Length::Fill)
+    .style(|_| container::Style {
+        background: Some(Color::from_rgb(0.15, 0.15, 0.15).into()),
+        text_color: Some(Color::WHITE),
+        ..Default::default()
+    })
+    .into()
 }
@@ -1,5 +1,11 @@
 use iced::advanced::mouse::Click;
 
+use molecular::grid::GridKind;
+use molecular::page::{Orientation, Paper};
+use molecular::style;
+use molecular::{Alignment, Axis};
+
+use crate::editor::Edit;
 use crate::tool;
 
 #[derive(Debug, Clone)]
@@ -11,6 +17,11 @@ pub enum Message {
     StartedPanning(iced::Point),
     StoppedPanning,
 
+    // PICKING
+    StartedDragging(iced::Point),
+    Dragged(iced::Point),
+    StoppedDragging,
+
     // DRAWING
     StartedDrawing(iced::Point, Click),
     DrawingMoved(iced::Point, iced::Vector),
@@ -18,15 +29,85 @@ pub enum Message {
 
     // TEXT
     StartedTyping(iced::Point),
-    Typing(char),
-    Escape,
-    Backspace,
-    Whitespace,
+    Edited(Edit),
+    FinishedTyping,
+
+    // CLIPBOARD
+    Copy,
+    Cut,
+    Paste(iced::Point),
+    Pasted(iced::Point, Option<String>),
 
     // MISCELLANEOUS
+    #[allow(dead_code)]
     DoubleClick(iced::Point),
 
+    // HISTORY
+    Undo,
+    Redo,
+
+    // TABS
+    NewTab,
+    SelectedTab(usize),
+    NextTab,
+    PreviousTab,
+    CloseTab(usize),
+    /// Answers to closing a tab with unsaved changes.
+    SaveAndClose,
+    DiscardAndClose,
+    CancelClose,
+    /// The window is asked to close, which closes every tab first.
+    CloseRequested,
+
     // MENU
     Save,
     Load,
+    LoadFont,
+    Import,
+    Export,
+    ExportDpiChanged(String),
+    ToggleTransparentExport(bool),
+    AssembleReaction,
+    ToggleStereoDescriptors,
+    SelectedStylePreset(style::Preset),
+    ToggleStylePanel,
+    StyleChanged(style::Field, String),
+    ToggleElementColors(bool),
+
+    // GRID
+    ToggleGrid(bool),
+    SelectedGridKind(GridKind),
+    SelectedGridSpacing(f64),
+    ToggleSnapToGrid(bool),
+    ToggleRulers(bool),
+
+    // PAGE
+    /// `None` for an endless canvas.
+    SelectedPaper(Option<Paper>),
+    SelectedOrientation(Orientation),
+    SelectedMargin(f64),
+
+    // PROPERTIES
+    SetColor(Option<iced::Color>),
+    SetLineWidth(Option<f64>),
+    SetDash(style::Dash),
+    SetSubscript(String),
+
+    // ARRANGING
+    Group,
+    Ungroup,
+    BringToFront,
+    SendToBack,
+    Align(Alignment),
+    Distribute(Axis),
+    Flip(Axis),
+
+    // LAYERS
+    ToggleLayersPanel,
+    AddLayer,
+    SelectedLayer(usize),
+    RenamedLayer(usize, String),
+    ToggleLayerHidden(usize, bool),
+    ToggleLayerLocked(usize, bool),
+    MoveToLayer(usize),
 }
@@ -0,0 +1,478 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::element::Element;
+use crate::shape::{PointDef, option_color};
+use crate::style::Appearance;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Atom {
+    pub element: Element,
+    #[serde(with = "PointDef")]
+    pub position: kurbo::Point,
+    #[serde(default)]
+    pub charge: i8,
+    /// Mass number, `None` for the natural isotope mixture.
+    #[serde(default)]
+    pub isotope: Option<u16>,
+    /// Overrides the implicit hydrogen count derived from the valence model.
+    #[serde(default)]
+    pub hydrogens: Option<u8>,
+    /// Atom-atom mapping number tying reactant atoms to product atoms.
+    #[serde(default)]
+    pub map: Option<u32>,
+    /// Color of the label, overriding the style.
+    #[serde(
+        default,
+        with = "option_color",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub color: Option<iced::Color>,
+    /// Electrons, charges and orbitals drawn around the atom.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub decorations: Vec<Decoration>,
+}
+
+/// Decorations sit this far from the center of their atom.
+pub const DECORATION_DISTANCE: f64 = 14.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orbital {
+    S,
+    P,
+    Sp3,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecorationKind {
+    LonePair,
+    /// An unpaired electron.
+    Radical,
+    /// This part of the charge of the atom, drawn in a circle instead of in
+    /// the label.
+    Charge(i8),
+    Orbital(Orbital),
+}
+
+/// Something drawn around an atom, in the direction `angle` (radians) from
+/// it, so it moves along with the atom.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Decoration {
+    pub kind: DecorationKind,
+    pub angle: f64,
+}
+
+impl Decoration {
+    /// Where the decoration sits around an atom at `center`.
+    pub fn position(&self, center: kurbo::Point) -> kurbo::Point {
+        center + kurbo::Vec2::from_angle(self.angle) * DECORATION_DISTANCE
+    }
+}
+
+impl Atom {
+    pub fn new(element: Element, position: kurbo::Point) -> Self {
+        Self {
+            element,
+            position,
+            charge: 0,
+            isotope: None,
+            hydrogens: None,
+            map: None,
+            color: None,
+            decorations: Vec::new(),
+        }
+    }
+
+    /// Number of unpaired electrons drawn on the atom.
+    pub fn radicals(&self) -> u8 {
+        self.count(DecorationKind::Radical)
+    }
+
+    pub fn lone_pairs(&self) -> u8 {
+        self.count(DecorationKind::LonePair)
+    }
+
+    /// The part of the charge drawn in circles.
+    pub fn circled_charge(&self) -> i8 {
+        self.decorations
+            .iter()
+            .map(|decoration| match decoration.kind {
+                DecorationKind::Charge(charge) => charge,
+                _ => 0,
+            })
+            .fold(0, i8::saturating_add)
+    }
+
+    /// Adds `decoration`, taking a circled charge into the charge of the atom.
+    pub fn decorate(&mut self, decoration: Decoration) {
+        if let DecorationKind::Charge(charge) = decoration.kind {
+            self.charge = self.charge.saturating_add(charge);
+        }
+        self.decorations.push(decoration);
+    }
+
+    /// Removes the decoration at `index`, with the charge it stood for.
+    pub fn undecorate(&mut self, index: usize) -> Decoration {
+        let decoration = self.decorations.remove(index);
+        if let DecorationKind::Charge(charge) = decoration.kind {
+            self.charge = self.charge.saturating_sub(charge);
+        }
+        decoration
+    }
+
+    fn count(&self, kind: DecorationKind) -> u8 {
+        self.decorations
+            .iter()
+            .filter(|decoration| decoration.kind == kind)
+            .count() as u8
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BondOrder {
+    Single,
+    Double,
+    Triple,
+    Aromatic,
+}
+
+impl BondOrder {
+    /// Twice the bond order, so aromatic bonds can count as 1.5.
+    fn valence_x2(self) -> u8 {
+        match self {
+            Self::Single => 2,
+            Self::Double => 4,
+            Self::Triple => 6,
+            Self::Aromatic => 3,
+        }
+    }
+}
+
+/// How a bond is drawn with respect to the plane of the drawing. Wedges and
+/// hashes point away from `begin`, which is the stereocenter they describe.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BondStereo {
+    #[default]
+    None,
+    /// Solid wedge, towards the viewer.
+    Wedge,
+    /// Hashed wedge, away from the viewer.
+    Hash,
+    /// Wavy bond, explicitly unknown configuration at `begin`.
+    Wavy,
+    /// Crossed double bond, explicitly unknown E/Z configuration.
+    Either,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bond {
+    pub begin: usize,
+    pub end: usize,
+    pub order: BondOrder,
+    #[serde(default)]
+    pub stereo: BondStereo,
+    #[serde(default, skip_serializing_if = "Appearance::is_default")]
+    pub appearance: Appearance,
+}
+
+impl Bond {
+    pub fn other(&self, atom: usize) -> usize {
+        if self.begin == atom {
+            self.end
+        } else {
+            self.begin
+        }
+    }
+
+    pub fn contains(&self, atom: usize) -> bool {
+        self.begin == atom || self.end == atom
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Parts")]
+pub struct Molecule {
+    pub atoms: Vec<Atom>,
+    /// Add bonds with [`Molecule::add_bond`], which keeps track of the
+    /// neighbors of their atoms.
+    pub bonds: Vec<Bond>,
+    /// `(bond index, neighbor atom index)` pairs of each atom, in bond order.
+    #[serde(skip)]
+    adjacency: Vec<Vec<(usize, usize)>>,
+}
+
+/// The saved fields of a [`Molecule`], from which it finds the neighbors of
+/// its atoms again.
+#[derive(Deserialize)]
+struct Parts {
+    atoms: Vec<Atom>,
+    bonds: Vec<Bond>,
+}
+
+impl From<Parts> for Molecule {
+    fn from(parts: Parts) -> Self {
+        Self::new(parts.atoms, parts.bonds)
+    }
+}
+
+impl Molecule {
+    pub fn new(atoms: Vec<Atom>, bonds: Vec<Bond>) -> Self {
+        let mut molecule = Self {
+            atoms,
+            bonds: Vec::with_capacity(bonds.len()),
+            adjacency: Vec::new(),
+        };
+        for bond in bonds {
+            molecule.add_bond(bond);
+        }
+        molecule
+    }
+
+    /// Adds `bond` and returns its index.
+    pub fn add_bond(&mut self, bond: Bond) -> usize {
+        let index = self.bonds.len();
+        let last = bond.begin.max(bond.end);
+        if self.adjacency.len() <= last {
+            self.adjacency.resize_with(last + 1, Vec::new);
+        }
+        self.adjacency[bond.begin].push((index, bond.end));
+        if bond.end != bond.begin {
+            self.adjacency[bond.end].push((index, bond.begin));
+        }
+        self.bonds.push(bond);
+        index
+    }
+
+    /// How the molecule is drawn, as far as its first bond and first colored
+    /// atom tell.
+    pub fn appearance(&self) -> Appearance {
+        let mut appearance = self
+            .bonds
+            .first()
+            .map(|bond| bond.appearance)
+            .unwrap_or_default();
+        appearance.color = appearance
+            .color
+            .or_else(|| self.atoms.iter().find_map(|atom| atom.color));
+        appearance
+    }
+
+    /// Applies `change` to every bond, and its color to every atom label.
+    pub fn restyle(&mut self, change: &impl Fn(&mut Appearance)) {
+        for bond in &mut self.bonds {
+            change(&mut bond.appearance);
+        }
+        for atom in &mut self.atoms {
+            let mut appearance = Appearance {
+                color: atom.color,
+                ..Appearance::default()
+            };
+            change(&mut appearance);
+            atom.color = appearance.color;
+        }
+    }
+
+    /// Iterates over `(bond index, neighbor atom index)` pairs of `atom`.
+    pub fn neighbors(&self, atom: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.adjacency.get(atom).into_iter().flatten().copied()
+    }
+
+    pub fn bond_between(&self, a: usize, b: usize) -> Option<usize> {
+        self.neighbors(a).find(|(_, n)| *n == b).map(|(i, _)| i)
+    }
+
+    /// Number of hydrogens attached to `atom` that are not drawn as atoms.
+    pub fn implicit_hydrogens(&self, atom: usize) -> u8 {
+        self.atoms[atom]
+            .hydrogens
+            .unwrap_or_else(|| self.default_hydrogens(atom))
+    }
+
+    /// Hydrogen count the valence model gives `atom`, ignoring any override.
+    pub fn default_hydrogens(&self, atom: usize) -> u8 {
+        let atom_data = &self.atoms[atom];
+        let valences = atom_data.element.default_valences();
+        if valences.is_empty() {
+            return 0;
+        }
+
+        let bonded_x2 = self
+            .neighbors(atom)
+            .map(|(b, _)| self.bonds[b].order.valence_x2())
+            .fold(0u8, u8::saturating_add);
+        let bonded = bonded_x2.div_ceil(2) as i16;
+
+        // Charged atoms take the valence of their isoelectronic neighbor, so
+        // N+ behaves like C and O- like F.
+        let charge = atom_data.charge as i16;
+        let shift = match atom_data.element {
+            Element::B | Element::C => -charge.abs(),
+            _ => charge,
+        };
+
+        // Radicals take a bond each, and lone pairs beyond the ones the
+        // valence leaves two, so a carbene carbon only has two bonds.
+        let electrons = atom_data.element.valence_electrons() as i16 - charge;
+        let radicals = atom_data.radicals() as i16;
+        let lone_pairs = atom_data.lone_pairs() as i16;
+        valences
+            .iter()
+            .map(|v| {
+                let v = *v as i16 + shift;
+                let usual_pairs = ((electrons - v) / 2).max(0);
+                v - radicals - 2 * (lone_pairs - usual_pairs).max(0)
+            })
+            .find(|v| *v >= bonded)
+            .map(|v| (v - bonded) as u8)
+            .unwrap_or(0)
+    }
+
+    /// Size of the smallest ring containing `bond`, if it is part of one.
+    pub fn smallest_ring(&self, bond: usize) -> Option<usize> {
+        let Bond { begin, end, .. } = self.bonds[bond];
+        self.shortest_path_without(begin, end, bond)
+            .map(|path| path.len())
+    }
+
+    /// Atoms on the shortest path from `from` to `to` that avoids `bond`,
+    /// both ends included.
+    pub fn shortest_path_without(&self, from: usize, to: usize, bond: usize) -> Option<Vec<usize>> {
+        let mut previous = vec![usize::MAX; self.atoms.len()];
+        let mut queue = VecDeque::from([from]);
+        previous[from] = from;
+
+        while let Some(atom) = queue.pop_front() {
+            if atom == to {
+                let mut path = vec![to];
+                while *path.last().unwrap() != from {
+                    path.push(previous[*path.last().unwrap()]);
+                }
+                return Some(path);
+            }
+            for (b, n) in self.neighbors(atom) {
+                if b == bond || previous[n] != usize::MAX {
+                    continue;
+                }
+                previous[n] = atom;
+                queue.push_back(n);
+            }
+        }
+
+        None
+    }
+
+    pub fn translate(&mut self, offset: kurbo::Vec2) {
+        for atom in &mut self.atoms {
+            atom.position += offset;
+        }
+    }
+
+    /// Maps the atoms through `affine`. A mirror image of a drawing shows
+    /// the other enantiomer, so mirroring swaps wedges and hashes to keep
+    /// the molecule the same.
+    pub fn transform(&mut self, affine: kurbo::Affine) {
+        let linear = kurbo::Affine::new({
+            let [a, b, c, d, _, _] = affine.as_coeffs();
+            [a, b, c, d, 0.0, 0.0]
+        });
+        if affine.determinant() < 0.0 {
+            for bond in &mut self.bonds {
+                bond.stereo = match bond.stereo {
+                    BondStereo::Wedge => BondStereo::Hash,
+                    BondStereo::Hash => BondStereo::Wedge,
+                    stereo => stereo,
+                };
+            }
+        }
+        for atom in &mut self.atoms {
+            atom.position = affine * atom.position;
+            for decoration in &mut atom.decorations {
+                let direction =
+                    linear * kurbo::Point::new(decoration.angle.cos(), decoration.angle.sin());
+                decoration.angle = direction.to_vec2().atan2();
+            }
+        }
+    }
+
+    /// Median length of the bonds that have one, `None` without bonds.
+    pub fn median_bond_length(&self) -> Option<f64> {
+        let mut lengths: Vec<f64> = self
+            .bonds
+            .iter()
+            .map(|bond| {
+                self.atoms[bond.begin]
+                    .position
+                    .distance(self.atoms[bond.end].position)
+            })
+            .filter(|length| *length > 1e-4)
+            .collect();
+        lengths.sort_by(f64::total_cmp);
+        lengths.get(lengths.len() / 2).copied()
+    }
+
+    pub fn bounding_box(&self) -> Option<kurbo::Rect> {
+        self.atoms
+            .iter()
+            .map(|a| kurbo::Rect::from_points(a.position, a.position))
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// Atom indices of every connected fragment.
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.atoms.len()];
+        let mut components = Vec::new();
+
+        for start in 0..self.atoms.len() {
+            if seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut component = vec![start];
+            let mut queue = VecDeque::from([start]);
+            while let Some(atom) = queue.pop_front() {
+                for (_, neighbor) in self.neighbors(atom) {
+                    if !seen[neighbor] {
+                        seen[neighbor] = true;
+                        component.push(neighbor);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        components
+    }
+
+    /// Copies `atoms` and the bonds between them into a new molecule.
+    pub fn extract(&self, atoms: &[usize]) -> Molecule {
+        let index_of = |atom: usize| atoms.iter().position(|a| *a == atom);
+        Molecule::new(
+            atoms.iter().map(|a| self.atoms[*a].clone()).collect(),
+            self.bonds
+                .iter()
+                .filter_map(|bond| {
+                    Some(Bond {
+                        begin: index_of(bond.begin)?,
+                        end: index_of(bond.end)?,
+                        ..bond.clone()
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Adds all atoms and bonds of `other` to this molecule.
+    pub fn merge(&mut self, other: &Molecule) {
+        let offset = self.atoms.len();
+        self.atoms.extend(other.atoms.iter().cloned());
+        for bond in &other.bonds {
+            self.add_bond(Bond {
+                begin: bond.begin + offset,
+                end: bond.end + offset,
+                ..bond.clone()
+            });
+        }
+    }
+}
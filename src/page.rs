@@ -0,0 +1,145 @@
+//! The paper a document is laid out on, for print and for posters.
+
+use kurbo::{Point, Rect, Size};
+use serde::{Deserialize, Serialize};
+
+/// Document units per millimetre, at 96 units to the inch.
+pub const UNITS_PER_MM: f64 = 96.0 / 25.4;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Paper {
+    A5,
+    #[default]
+    A4,
+    A3,
+    Letter,
+    Legal,
+    Tabloid,
+    /// Poster sizes.
+    A2,
+    A1,
+    A0,
+}
+
+impl Paper {
+    pub const ALL: [Paper; 9] = [
+        Paper::A5,
+        Paper::A4,
+        Paper::A3,
+        Paper::Letter,
+        Paper::Legal,
+        Paper::Tabloid,
+        Paper::A2,
+        Paper::A1,
+        Paper::A0,
+    ];
+
+    /// Width and height standing upright, in millimetres.
+    pub fn millimetres(self) -> (f64, f64) {
+        match self {
+            Paper::A5 => (148.0, 210.0),
+            Paper::A4 => (210.0, 297.0),
+            Paper::A3 => (297.0, 420.0),
+            Paper::Letter => (215.9, 279.4),
+            Paper::Legal => (215.9, 355.6),
+            Paper::Tabloid => (279.4, 431.8),
+            Paper::A2 => (420.0, 594.0),
+            Paper::A1 => (594.0, 841.0),
+            Paper::A0 => (841.0, 1189.0),
+        }
+    }
+
+    /// The paper named `name`, any case, as the command line takes it.
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|paper| paper.to_string().eq_ignore_ascii_case(name))
+    }
+}
+
+impl std::fmt::Display for Paper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Paper::A5 => "A5",
+            Paper::A4 => "A4",
+            Paper::A3 => "A3",
+            Paper::Letter => "Letter",
+            Paper::Legal => "Legal",
+            Paper::Tabloid => "Tabloid",
+            Paper::A2 => "A2",
+            Paper::A1 => "A1",
+            Paper::A0 => "A0",
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    #[default]
+    Portrait,
+    Landscape,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 2] = [Orientation::Portrait, Orientation::Landscape];
+}
+
+impl std::fmt::Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Orientation::Portrait => "portrait",
+            Orientation::Landscape => "landscape",
+        })
+    }
+}
+
+/// A sheet of paper centered on the origin of the document.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Page {
+    pub paper: Paper,
+    pub orientation: Orientation,
+    /// Blank space kept on every side, in document units.
+    pub margin: f64,
+}
+
+impl Default for Page {
+    /// Upright A4 with 10 mm margins.
+    fn default() -> Self {
+        Self {
+            paper: Paper::default(),
+            orientation: Orientation::default(),
+            margin: 10.0 * UNITS_PER_MM,
+        }
+    }
+}
+
+impl Page {
+    /// Width and height in document units.
+    pub fn size(&self) -> Size {
+        let (width, height) = self.paper.millimetres();
+        let size = Size::new(width * UNITS_PER_MM, height * UNITS_PER_MM);
+        match self.orientation {
+            Orientation::Portrait => size,
+            Orientation::Landscape => Size::new(size.height, size.width),
+        }
+    }
+
+    /// The whole sheet, as exported.
+    pub fn rect(&self) -> Rect {
+        Rect::from_center_size(Point::ZERO, self.size())
+    }
+
+    /// The sheet inside the margins, where drawings are safe from being cut
+    /// by printers. Margins wider than the sheet leave a line through its
+    /// middle.
+    pub fn printable(&self) -> Rect {
+        let size = self.size();
+        let margin = self
+            .margin
+            .max(0.0)
+            .min(size.width / 2.0)
+            .min(size.height / 2.0);
+        self.rect().inset(-margin)
+    }
+}
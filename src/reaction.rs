@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+
+use crate::annotation::Annotation;
+use crate::arrow::Arrow;
+use crate::molecule::Molecule;
+
+/// Reagents, catalysts and conditions written along the arrow.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Agent {
+    Molecule(Molecule),
+    Text(Annotation),
+}
+
+impl Agent {
+    /// Atom bounds of a molecule, the anchor point of text.
+    fn bounds(&self) -> Option<kurbo::Rect> {
+        match self {
+            Self::Molecule(molecule) => molecule.bounding_box(),
+            Self::Text(annotation) => {
+                let position = kurbo::Point::new(
+                    annotation.text.position.x as f64,
+                    annotation.text.position.y as f64,
+                );
+                Some(kurbo::Rect::from_points(position, position))
+            }
+        }
+    }
+}
+
+/// A reaction scheme step: everything left of the arrow reacts to everything
+/// right of it, with agents written above and below the arrow.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reaction {
+    pub reactants: Vec<Molecule>,
+    pub agents_above: Vec<Agent>,
+    pub agents_below: Vec<Agent>,
+    pub products: Vec<Molecule>,
+    pub arrow: Arrow,
+}
+
+/// Where a component sits relative to a reaction arrow.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Role {
+    Reactant,
+    AgentAbove,
+    AgentBelow,
+    Product,
+}
+
+impl Reaction {
+    /// Groups `components` around `arrow` by their position along it. Only
+    /// components within `reach` of the arrow, or of the line through it no
+    /// further than `reach` past either end, are taken, text only beside the
+    /// arrow itself; the others are handed back.
+    pub fn assemble(arrow: Arrow, components: Vec<Agent>, reach: f64) -> (Self, Vec<Agent>) {
+        let mut reaction = Self {
+            reactants: Vec::new(),
+            agents_above: Vec::new(),
+            agents_below: Vec::new(),
+            products: Vec::new(),
+            arrow,
+        };
+        let mut rest = Vec::new();
+
+        let mut placed = Vec::new();
+        for component in components {
+            match component.bounds() {
+                Some(bounds) if reaction.distance_from_arrow(bounds) <= reach => {
+                    let (t, role) = reaction.role_of(bounds.center());
+                    placed.push((t, role, component));
+                }
+                _ => rest.push(component),
+            }
+        }
+        placed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (_, role, component) in placed {
+            match (role, component) {
+                (Role::Reactant, Agent::Molecule(molecule)) => reaction.reactants.push(molecule),
+                (Role::Product, Agent::Molecule(molecule)) => reaction.products.push(molecule),
+                (Role::AgentAbove, component) => reaction.agents_above.push(component),
+                (Role::AgentBelow, component) => reaction.agents_below.push(component),
+                (Role::Reactant | Role::Product, text) => rest.push(text),
+            }
+        }
+
+        (reaction, rest)
+    }
+
+    /// Classifies `point` by projecting it onto the arrow. Returns the
+    /// position along the arrow, 0 at the tail and 1 at the head.
+    pub fn role_of(&self, point: kurbo::Point) -> (f64, Role) {
+        let (tail, head) = (self.arrow.tail(), self.arrow.head());
+        let axis = head - tail;
+        let length_squared = axis.hypot2().max(f64::EPSILON);
+        let t = (point - tail).dot(axis) / length_squared;
+
+        let role = if t < 0.0 {
+            Role::Reactant
+        } else if t > 1.0 {
+            Role::Product
+        } else if axis.cross(point - tail) < 0.0 {
+            // Screen y points down, so negative is above for a left-to-right arrow.
+            Role::AgentAbove
+        } else {
+            Role::AgentBelow
+        };
+
+        (t, role)
+    }
+
+    /// How far `rect` is from the arrow, measured across the arrow or past
+    /// its ends, whichever is further; 0 if it touches the arrow.
+    pub fn distance_from_arrow(&self, rect: kurbo::Rect) -> f64 {
+        let (tail, head) = (self.arrow.tail(), self.arrow.head());
+        let axis = head - tail;
+        let length = axis.hypot().max(f64::EPSILON);
+        let corners = [
+            rect.origin(),
+            kurbo::Point::new(rect.x1, rect.y0),
+            kurbo::Point::new(rect.x0, rect.y1),
+            kurbo::Point::new(rect.x1, rect.y1),
+        ];
+        let range = |offsets: [f64; 4]| {
+            offsets
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), offset| {
+                    (min.min(*offset), max.max(*offset))
+                })
+        };
+        let (min, max) = range(corners.map(|corner| axis.cross(corner - tail) / length));
+        let across = if min <= 0.0 && max >= 0.0 {
+            0.0
+        } else {
+            min.abs().min(max.abs())
+        };
+        let (first, last) = range(corners.map(|corner| axis.dot(corner - tail) / length));
+        let along = (-last).max(first - length).max(0.0);
+        across.max(along)
+    }
+
+    pub fn translate(&mut self, offset: kurbo::Vec2) {
+        for molecule in self.reactants.iter_mut().chain(&mut self.products) {
+            molecule.translate(offset);
+        }
+        for agent in self.agents_above.iter_mut().chain(&mut self.agents_below) {
+            match agent {
+                Agent::Molecule(molecule) => molecule.translate(offset),
+                Agent::Text(annotation) => {
+                    annotation.text.position += iced::Vector::new(offset.x as f32, offset.y as f32)
+                }
+            }
+        }
+        for point in self.arrow.points_mut() {
+            *point += offset;
+        }
+    }
+
+    pub fn transform(&mut self, affine: kurbo::Affine) {
+        for molecule in self.reactants.iter_mut().chain(&mut self.products) {
+            molecule.transform(affine);
+        }
+        for agent in self.agents_above.iter_mut().chain(&mut self.agents_below) {
+            match agent {
+                Agent::Molecule(molecule) => molecule.transform(affine),
+                Agent::Text(annotation) => {
+                    let text = &mut annotation.text;
+                    let position =
+                        affine * kurbo::Point::new(text.position.x as f64, text.position.y as f64);
+                    text.position = iced::Point::new(position.x as f32, position.y as f32);
+                }
+            }
+        }
+        for point in self.arrow.points_mut() {
+            *point = affine * *point;
+        }
+    }
+
+    pub fn molecules(&self) -> impl Iterator<Item = &Molecule> {
+        let agents = self
+            .agents_above
+            .iter()
+            .chain(&self.agents_below)
+            .filter_map(|agent| match agent {
+                Agent::Molecule(molecule) => Some(molecule),
+                Agent::Text(_) => None,
+            });
+        self.reactants.iter().chain(agents).chain(&self.products)
+    }
+
+    pub fn texts(&self) -> impl Iterator<Item = &Annotation> {
+        self.agents_above
+            .iter()
+            .chain(&self.agents_below)
+            .filter_map(|agent| match agent {
+                Agent::Text(text) => Some(text),
+                Agent::Molecule(_) => None,
+            })
+    }
+
+    pub fn molecules_mut(&mut self) -> impl Iterator<Item = &mut Molecule> {
+        let agents = self
+            .agents_above
+            .iter_mut()
+            .chain(&mut self.agents_below)
+            .filter_map(|agent| match agent {
+                Agent::Molecule(molecule) => Some(molecule),
+                Agent::Text(_) => None,
+            });
+        self.reactants
+            .iter_mut()
+            .chain(agents)
+            .chain(&mut self.products)
+    }
+
+    pub fn texts_mut(&mut self) -> impl Iterator<Item = &mut Annotation> {
+        self.agents_above
+            .iter_mut()
+            .chain(&mut self.agents_below)
+            .filter_map(|agent| match agent {
+                Agent::Text(text) => Some(text),
+                Agent::Molecule(_) => None,
+            })
+    }
+}
@@ -0,0 +1,596 @@
+//! Backend independent description of a drawing.
+//!
+//! Everything in a document is turned into a flat list of strokes, fills and
+//! text runs in document coordinates. The canvas draws that list with iced and
+//! the exporters translate it into their own formats, so all of them agree on
+//! what a bond or an arrow looks like.
+
+use iced::Color;
+use iced::widget::canvas::Text;
+use kurbo::{BezPath, Point, Rect, Shape as _, Vec2};
+
+use crate::annotation::Annotation;
+use crate::arrow::Arrow;
+use crate::cip::{self, StereoUnit};
+use crate::concept::Concept;
+use crate::figure::{Figure, Outline};
+use crate::font::{self, Font, FontStyle};
+use crate::molecule::{BondOrder, BondStereo, Decoration, DecorationKind, Molecule, Orbital};
+use crate::shape::Shape;
+use crate::style::{Dash, Style};
+
+/// Room left around the content of exported images, in document units.
+pub const PADDING: f64 = 10.0;
+const DESCRIPTOR_SIZE: f64 = 11.0;
+const DESCRIPTOR_COLOR: Color = Color::from_rgb(0.1, 0.3, 0.8);
+const UNDEFINED_COLOR: Color = Color::from_rgb(0.85, 0.1, 0.1);
+const CHARGE_RADIUS: f64 = 4.5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HorizontalAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerticalAlignment {
+    Top,
+    Center,
+    Bottom,
+}
+
+#[derive(Clone, Debug)]
+pub enum Primitive {
+    /// A stroke with round caps and joins. An empty `dash` is solid.
+    Stroke {
+        path: BezPath,
+        width: f64,
+        color: Color,
+        dash: Vec<f64>,
+    },
+    /// A filled path, using the non-zero winding rule.
+    Fill { path: BezPath, color: Color },
+    /// A single line of text anchored at `position`.
+    Text {
+        content: String,
+        position: Point,
+        size: f64,
+        color: Color,
+        align_x: HorizontalAlignment,
+        align_y: VerticalAlignment,
+        font: Font,
+        style: FontStyle,
+    },
+}
+
+/// Primitives drawn with the settings of one document style.
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    pub primitives: Vec<Primitive>,
+    pub style: Style,
+    /// The sheet exporters lay the scene out on, instead of cropping it to
+    /// its content.
+    pub page: Option<Rect>,
+}
+
+impl Primitive {
+    pub fn bounds(&self) -> Rect {
+        match self {
+            Self::Stroke { path, width, .. } => {
+                path.bounding_box().inflate(width / 2.0, width / 2.0)
+            }
+            Self::Fill { path, .. } => path.bounding_box(),
+            Self::Text {
+                content,
+                position,
+                size,
+                align_x,
+                align_y,
+                font,
+                ..
+            } => text_bounds(*font, content, *position, *size, *align_x, *align_y),
+        }
+    }
+}
+
+impl Scene {
+    pub fn new(style: Style) -> Self {
+        Self {
+            primitives: Vec::new(),
+            style,
+            page: None,
+        }
+    }
+
+    /// The area exporters show: the page, or the content with padding around
+    /// it.
+    pub fn extent(&self) -> Rect {
+        self.page
+            .unwrap_or_else(|| self.bounds().unwrap_or_default().inflate(PADDING, PADDING))
+    }
+
+    /// Union of the bounds of all primitives, `None` for an empty scene.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.primitives
+            .iter()
+            .map(Primitive::bounds)
+            .reduce(|a, b| a.union(b))
+    }
+
+    pub fn shape(&mut self, shape: &Shape) {
+        match shape {
+            Shape::Point { point, appearance } => self.fill(
+                kurbo::Circle::new(*point, self.style.bold_width).to_path(0.1),
+                appearance.color(&self.style),
+            ),
+            Shape::Line { line, appearance } => self.stroke(
+                line.to_path(0.1),
+                appearance.line_width(&self.style),
+                appearance.color(&self.style),
+                appearance.dash,
+            ),
+            Shape::Text(annotation) => self.annotation(annotation),
+            Shape::Arrow(arrow) => self.arrow(arrow),
+            Shape::Figure(figure) => self.figure(figure),
+        }
+    }
+
+    pub fn concept(&mut self, concept: &Concept, show_stereo_descriptors: bool) {
+        match concept {
+            Concept::Molecule(molecule) => self.molecule(molecule, show_stereo_descriptors),
+            Concept::Reaction(reaction) => {
+                for molecule in reaction.molecules() {
+                    self.molecule(molecule, show_stereo_descriptors);
+                }
+                for annotation in reaction.texts() {
+                    self.annotation(annotation);
+                }
+                self.arrow(&reaction.arrow);
+            }
+        }
+    }
+
+    pub fn arrow(&mut self, arrow: &Arrow) {
+        let geometry = arrow.geometry();
+        let color = arrow.appearance.color(&self.style);
+        let width = arrow.appearance.line_width(&self.style);
+        for path in geometry.strokes {
+            self.stroke(path, width, color, arrow.appearance.dash);
+        }
+        for path in geometry.fills {
+            self.fill(path, color);
+        }
+    }
+
+    /// The outline of `figure`, with the subscript of brackets in italics
+    /// at their lower right.
+    pub fn figure(&mut self, figure: &Figure) {
+        let color = figure.appearance.color(&self.style);
+        let width = figure.appearance.line_width(&self.style);
+        for path in figure.strokes() {
+            self.stroke(path, width, color, figure.appearance.dash);
+        }
+        if let Outline::Brackets {
+            rect, subscript, ..
+        } = &figure.outline
+            && !subscript.is_empty()
+        {
+            self.primitives.push(Primitive::Text {
+                content: subscript.clone(),
+                position: Point::new(rect.x1 + width * 2.0, rect.y1),
+                size: self.style.label_size * 0.8,
+                color,
+                align_x: HorizontalAlignment::Left,
+                align_y: VerticalAlignment::Center,
+                font: font::resolve(&self.style.label_font),
+                style: FontStyle {
+                    bold: false,
+                    italic: true,
+                },
+            });
+        }
+    }
+
+    /// A plain text, one primitive per line.
+    pub fn text(&mut self, text: &Text) {
+        self.annotation(&Annotation::new(text.clone()));
+    }
+
+    /// A formatted text, one primitive per run on each line.
+    pub fn annotation(&mut self, annotation: &Annotation) {
+        let content = annotation.content();
+        for line in annotation.layout() {
+            for segment in line.segments {
+                // Centered on the glyph box, which iced centers its line box
+                // on as well.
+                let typeface = segment.font.typeface;
+                let center =
+                    (typeface.ascender(segment.size) + typeface.descender(segment.size)) / 2.0;
+                self.primitives.push(Primitive::Text {
+                    content: content[segment.range].to_string(),
+                    position: segment.origin - Vec2::new(0.0, center),
+                    size: segment.size,
+                    color: segment.format.color.unwrap_or(annotation.text.color),
+                    align_x: HorizontalAlignment::Left,
+                    align_y: VerticalAlignment::Center,
+                    font: segment.font,
+                    style: segment.format.font_style(),
+                });
+            }
+        }
+    }
+
+    pub fn molecule(&mut self, molecule: &Molecule, show_stereo_descriptors: bool) {
+        let Style {
+            bold_width,
+            hash_spacing,
+            margin_width,
+            label_size,
+            color,
+            ..
+        } = self.style;
+        let spacing = self.style.bond_offset();
+        let label_font = font::resolve(&self.style.label_font);
+        let labels: Vec<Option<String>> = (0..molecule.atoms.len())
+            .map(|atom| atom_label(molecule, atom))
+            .collect();
+        // Bonds stop `margin_width` short of the box around an atom label.
+        let trim = |atom: usize, direction: Vec2| {
+            labels[atom].as_ref().map_or(0.0, |label| {
+                let bounds = text_bounds(
+                    label_font,
+                    label,
+                    molecule.atoms[atom].position,
+                    label_size,
+                    HorizontalAlignment::Center,
+                    VerticalAlignment::Center,
+                );
+                let exit = (bounds.width() / 2.0 / direction.x.abs())
+                    .min(bounds.height() / 2.0 / direction.y.abs());
+                exit + margin_width
+            })
+        };
+
+        for bond in &molecule.bonds {
+            let color = bond.appearance.color(&self.style);
+            let line_width = bond.appearance.line_width(&self.style);
+            let dash = bond.appearance.dash;
+            let mut p0 = molecule.atoms[bond.begin].position;
+            let mut p1 = molecule.atoms[bond.end].position;
+            let length = (p1 - p0).hypot();
+            if length < f64::EPSILON {
+                continue;
+            }
+            let direction = (p1 - p0) / length;
+            p0 += direction * trim(bond.begin, direction);
+            p1 -= direction * trim(bond.end, direction);
+            let length = (p1 - p0).hypot();
+            let normal = Vec2::new(-direction.y, direction.x);
+            let offset = |p: Point, d: f64| p + normal * d;
+
+            match (bond.order, bond.stereo) {
+                (BondOrder::Single, BondStereo::Wedge) => {
+                    let half = bold_width / 2.0;
+                    let mut path = BezPath::new();
+                    path.move_to(p0);
+                    path.line_to(offset(p1, half));
+                    path.line_to(offset(p1, -half));
+                    path.close_path();
+                    self.fill(path, color);
+                }
+                (BondOrder::Single, BondStereo::Hash) => {
+                    let hashes = (length / hash_spacing).max(3.0) as usize;
+                    for i in 0..=hashes {
+                        let t = i as f64 / hashes as f64;
+                        let center = p0.lerp(p1, t);
+                        let half = (bold_width / 2.0) * t.max(0.15);
+                        self.stroke(
+                            line(offset(center, half), offset(center, -half)),
+                            line_width,
+                            color,
+                            Dash::Solid,
+                        );
+                    }
+                }
+                (BondOrder::Single, BondStereo::Wavy) => {
+                    let waves = (length / spacing).max(2.0) as usize;
+                    let mut path = BezPath::new();
+                    path.move_to(p0);
+                    for i in 0..waves {
+                        let t0 = i as f64 / waves as f64;
+                        let t1 = (i + 1) as f64 / waves as f64;
+                        let side = if i % 2 == 0 { spacing } else { -spacing };
+                        path.quad_to(
+                            offset(p0.lerp(p1, (t0 + t1) / 2.0), side / 2.0),
+                            p0.lerp(p1, t1),
+                        );
+                    }
+                    self.stroke(path, line_width, color, dash);
+                }
+                (BondOrder::Double, BondStereo::Either) => {
+                    let half = spacing / 2.0;
+                    self.stroke(
+                        line(offset(p0, half), offset(p1, -half)),
+                        line_width,
+                        color,
+                        dash,
+                    );
+                    self.stroke(
+                        line(offset(p0, -half), offset(p1, half)),
+                        line_width,
+                        color,
+                        dash,
+                    );
+                }
+                (BondOrder::Double, _) => {
+                    let half = spacing / 2.0;
+                    self.stroke(
+                        line(offset(p0, half), offset(p1, half)),
+                        line_width,
+                        color,
+                        dash,
+                    );
+                    self.stroke(
+                        line(offset(p0, -half), offset(p1, -half)),
+                        line_width,
+                        color,
+                        dash,
+                    );
+                }
+                (BondOrder::Triple, _) => {
+                    for d in [-spacing, 0.0, spacing] {
+                        self.stroke(line(offset(p0, d), offset(p1, d)), line_width, color, dash);
+                    }
+                }
+                (BondOrder::Aromatic, _) => {
+                    self.stroke(line(p0, p1), line_width, color, dash);
+                    self.primitives.push(Primitive::Stroke {
+                        path: line(offset(p0, spacing), offset(p1, spacing)),
+                        width: line_width,
+                        color,
+                        dash: vec![spacing * 0.8, spacing * 0.6],
+                    });
+                }
+                (BondOrder::Single, _) => self.stroke(line(p0, p1), line_width, color, dash),
+            }
+        }
+
+        for (atom, label) in labels.iter().enumerate() {
+            let Some(content) = label else {
+                continue;
+            };
+            let data = &molecule.atoms[atom];
+            let element_color = self
+                .style
+                .element_colors
+                .then(|| data.element.color())
+                .flatten();
+            self.primitives.push(Primitive::Text {
+                content: content.clone(),
+                position: data.position,
+                size: label_size,
+                color: data.color.or(element_color).unwrap_or(color),
+                align_x: HorizontalAlignment::Center,
+                align_y: VerticalAlignment::Center,
+                font: label_font,
+                style: FontStyle::default(),
+            });
+        }
+
+        for data in &molecule.atoms {
+            let element_color = self
+                .style
+                .element_colors
+                .then(|| data.element.color())
+                .flatten();
+            for decoration in &data.decorations {
+                self.decoration(
+                    decoration,
+                    data.position,
+                    data.color.or(element_color).unwrap_or(color),
+                );
+            }
+        }
+
+        if show_stereo_descriptors {
+            self.stereo_descriptors(molecule);
+        }
+    }
+
+    /// Electrons and circled charges beside the atom at `center`, or
+    /// orbital lobes through it.
+    fn decoration(&mut self, decoration: &Decoration, center: Point, color: Color) {
+        let width = self.style.line_width;
+        let dot = (width * 1.5).max(1.0);
+        let position = decoration.position(center);
+        let direction = Vec2::from_angle(decoration.angle);
+        let normal = Vec2::new(-direction.y, direction.x);
+        let shaded = Color { a: 0.25, ..color };
+        match decoration.kind {
+            DecorationKind::LonePair => {
+                for side in [-1.0, 1.0] {
+                    let dot_center = position + normal * side * dot * 1.8;
+                    self.fill(kurbo::Circle::new(dot_center, dot).to_path(0.1), color);
+                }
+            }
+            DecorationKind::Radical => {
+                self.fill(kurbo::Circle::new(position, dot).to_path(0.1), color)
+            }
+            DecorationKind::Charge(charge) => {
+                let radius = CHARGE_RADIUS;
+                self.stroke(
+                    kurbo::Circle::new(position, radius).to_path(0.1),
+                    width,
+                    color,
+                    Dash::Solid,
+                );
+                let arm = radius * 0.55;
+                let mut sign = BezPath::new();
+                sign.move_to(position - Vec2::new(arm, 0.0));
+                sign.line_to(position + Vec2::new(arm, 0.0));
+                if charge > 0 {
+                    sign.move_to(position - Vec2::new(0.0, arm));
+                    sign.line_to(position + Vec2::new(0.0, arm));
+                }
+                self.stroke(sign, width, color, Dash::Solid);
+                if charge.unsigned_abs() > 1 {
+                    self.primitives.push(Primitive::Text {
+                        content: charge.unsigned_abs().to_string(),
+                        position: position - Vec2::new(radius + width, 0.0),
+                        size: self.style.label_size * 0.8,
+                        color,
+                        align_x: HorizontalAlignment::Right,
+                        align_y: VerticalAlignment::Center,
+                        font: font::resolve(&self.style.label_font),
+                        style: FontStyle::default(),
+                    });
+                }
+            }
+            DecorationKind::Orbital(orbital) => {
+                let length = self.style.bond_length * 0.6;
+                let lobes = match orbital {
+                    Orbital::S => {
+                        let circle = kurbo::Circle::new(center, length * 0.5).to_path(0.1);
+                        self.fill(circle.clone(), shaded);
+                        self.stroke(circle, width, color, Dash::Solid);
+                        return;
+                    }
+                    // The shaded lobe shows the phase.
+                    Orbital::P => [(direction, length, true), (-direction, length, false)],
+                    Orbital::Sp3 => [
+                        (direction, length, true),
+                        (-direction, length * 0.35, false),
+                    ],
+                };
+                for (direction, length, shade) in lobes {
+                    let lobe = lobe(center, direction, length);
+                    if shade {
+                        self.fill(lobe.clone(), shaded);
+                    }
+                    self.stroke(lobe, width, color, Dash::Solid);
+                }
+            }
+        }
+    }
+
+    /// Labels stereocenters and stereogenic double bonds, marking the ones
+    /// whose configuration the drawing leaves undefined.
+    fn stereo_descriptors(&mut self, molecule: &Molecule) {
+        for unit in cip::assign(molecule) {
+            let position = match unit {
+                StereoUnit::Center { atom, .. } => {
+                    molecule.atoms[atom].position + Vec2::new(10.0, -12.0)
+                }
+                StereoUnit::DoubleBond { bond, .. } => {
+                    let bond = &molecule.bonds[bond];
+                    let (p0, p1) = (
+                        molecule.atoms[bond.begin].position,
+                        molecule.atoms[bond.end].position,
+                    );
+                    let d = p1 - p0;
+                    let normal = Vec2::new(-d.y, d.x).normalize();
+                    p0.midpoint(p1) + normal * 12.0
+                }
+            };
+            let (content, color) = match unit.descriptor() {
+                Some(descriptor) => (descriptor.to_string(), DESCRIPTOR_COLOR),
+                None => ("(?)".to_string(), UNDEFINED_COLOR),
+            };
+            self.primitives.push(Primitive::Text {
+                content,
+                position,
+                size: DESCRIPTOR_SIZE,
+                color,
+                align_x: HorizontalAlignment::Center,
+                align_y: VerticalAlignment::Center,
+                font: Font::default(),
+                style: FontStyle::default(),
+            });
+        }
+    }
+
+    fn stroke(&mut self, path: BezPath, width: f64, color: Color, dash: Dash) {
+        self.primitives.push(Primitive::Stroke {
+            path,
+            width,
+            color,
+            dash: dash.pattern(width),
+        });
+    }
+
+    fn fill(&mut self, path: BezPath, color: Color) {
+        self.primitives.push(Primitive::Fill { path, color });
+    }
+}
+
+fn line(from: Point, to: Point) -> BezPath {
+    let mut path = BezPath::new();
+    path.move_to(from);
+    path.line_to(to);
+    path
+}
+
+/// Box from the ascender to the descender of a line of text.
+fn text_bounds(
+    font: Font,
+    content: &str,
+    position: Point,
+    size: f64,
+    align_x: HorizontalAlignment,
+    align_y: VerticalAlignment,
+) -> Rect {
+    let typeface = font.typeface;
+    let origin = typeface.origin(content, position, size, align_x, align_y);
+    Rect::new(
+        origin.x,
+        origin.y - typeface.ascender(size),
+        origin.x + typeface.width(content, size),
+        origin.y - typeface.descender(size),
+    )
+}
+
+/// A teardrop from `center` reaching `length` along `direction`.
+fn lobe(center: Point, direction: Vec2, length: f64) -> BezPath {
+    let normal = Vec2::new(-direction.y, direction.x);
+    let at = |along: f64, across: f64| center + (direction * along + normal * across) * length;
+    let mut path = BezPath::new();
+    path.move_to(center);
+    path.curve_to(at(0.25, 0.55), at(1.05, 0.45), at(1.0, 0.0));
+    path.curve_to(at(1.05, -0.45), at(0.25, -0.55), center);
+    path.close_path();
+    path
+}
+
+fn atom_label(molecule: &Molecule, atom: usize) -> Option<String> {
+    let data = &molecule.atoms[atom];
+    let isolated = molecule.neighbors(atom).next().is_none();
+    // Circled charges are drawn on their own.
+    let charge = data.charge.saturating_sub(data.circled_charge());
+    if data.element == crate::element::Element::C
+        && charge == 0
+        && data.isotope.is_none()
+        && !isolated
+    {
+        return None;
+    }
+
+    let mut label = String::new();
+    if let Some(isotope) = data.isotope {
+        label.push_str(&isotope.to_string());
+    }
+    label.push_str(data.element.symbol());
+    match molecule.implicit_hydrogens(atom) {
+        0 => (),
+        1 => label.push('H'),
+        n => label.push_str(&format!("H{n}")),
+    }
+    match charge {
+        0 => (),
+        1 => label.push('+'),
+        -1 => label.push('-'),
+        c if c > 0 => label.push_str(&format!("{c}+")),
+        c => label.push_str(&format!("{}-", c.unsigned_abs())),
+    }
+    Some(label)
+}
@@ -1,5 +1,5 @@
 use iced::{
-    advanced::{graphics::text::cosmic_text::skrifa::raw::types::Offset32, text::Alignment},
+    advanced::text::Alignment,
     alignment,
     font::{Family, Stretch, Style, Weight},
     widget::{
@@ -7,12 +7,16 @@ use iced::{
         text::{LineHeight, Shaping},
     },
 };
-use kurbo;
 use serde::{Deserialize, Serialize, Serializer};
 
+use crate::annotation::Annotation;
+use crate::arrow::Arrow;
+use crate::figure::Figure;
+use crate::style::Appearance;
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 #[serde(remote = "kurbo::Point")]
-struct PointDef {
+pub(crate) struct PointDef {
     x: f64,
     y: f64,
 }
@@ -26,6 +30,49 @@ struct LineDef {
     p1: kurbo::Point,
 }
 
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(remote = "kurbo::Rect")]
+pub(crate) struct RectDef {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+}
+
+/// A list of points, each in the layout of [`PointDef`].
+pub(crate) mod points {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::PointDef;
+
+    #[derive(Serialize, Deserialize)]
+    struct Point(#[serde(with = "PointDef")] kurbo::Point);
+
+    pub fn serialize<S: Serializer>(points: &[kurbo::Point], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(points.iter().map(|point| Point(*point)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<kurbo::Point>, D::Error> {
+        Ok(Vec::<Point>::deserialize(d)?
+            .into_iter()
+            .map(|point| point.0)
+            .collect())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(remote = "kurbo::CubicBez")]
+pub(crate) struct CubicBezDef {
+    #[serde(with = "PointDef")]
+    p0: kurbo::Point,
+    #[serde(with = "PointDef")]
+    p1: kurbo::Point,
+    #[serde(with = "PointDef")]
+    p2: kurbo::Point,
+    #[serde(with = "PointDef")]
+    p3: kurbo::Point,
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 #[serde(remote = "iced::Point")]
 struct IcedPointDef {
@@ -35,13 +82,31 @@ struct IcedPointDef {
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 #[serde(remote = "iced::Color")]
-struct ColorDef {
+pub(crate) struct ColorDef {
     r: f32,
     g: f32,
     b: f32,
     a: f32,
 }
 
+/// An optional color in the layout of [`ColorDef`].
+pub(crate) mod option_color {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::ColorDef;
+
+    #[derive(Serialize, Deserialize)]
+    struct Color(#[serde(with = "ColorDef")] iced::Color);
+
+    pub fn serialize<S: Serializer>(color: &Option<iced::Color>, s: S) -> Result<S::Ok, S::Error> {
+        color.map(Color).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<iced::Color>, D::Error> {
+        Ok(Option::<Color>::deserialize(d)?.map(|color| color.0))
+    }
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 #[serde(remote = "iced::Pixels")]
 struct PixelsDef(pub f32);
@@ -57,12 +122,12 @@ enum LineHeightDef {
     Absolute(iced::Pixels),
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
-#[serde(remote = "Family")]
+/// [`Family`] with an owned name, as deserialized names cannot borrow from
+/// the document.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 enum FamilyDef {
     /// The name of a font family of choice.
-    #[serde(skip_serializing, skip_deserializing)]
-    Name(&'static str),
+    Name(String),
 
     /// Serif fonts represent the formal text style for a script.
     Serif,
@@ -86,6 +151,38 @@ enum FamilyDef {
     Monospace,
 }
 
+mod family {
+    use iced::font::Family;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::FamilyDef;
+    use crate::font;
+
+    pub fn serialize<S: Serializer>(family: &Family, serializer: S) -> Result<S::Ok, S::Error> {
+        match *family {
+            Family::Name(name) => FamilyDef::Name(name.to_string()),
+            Family::Serif => FamilyDef::Serif,
+            Family::SansSerif => FamilyDef::SansSerif,
+            Family::Cursive => FamilyDef::Cursive,
+            Family::Fantasy => FamilyDef::Fantasy,
+            Family::Monospace => FamilyDef::Monospace,
+        }
+        .serialize(serializer)
+    }
+
+    /// Names live as long as the program, as iced fonts need them to.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Family, D::Error> {
+        Ok(match FamilyDef::deserialize(deserializer)? {
+            FamilyDef::Name(name) => Family::Name(font::intern(&name)),
+            FamilyDef::Serif => Family::Serif,
+            FamilyDef::SansSerif => Family::SansSerif,
+            FamilyDef::Cursive => Family::Cursive,
+            FamilyDef::Fantasy => Family::Fantasy,
+            FamilyDef::Monospace => Family::Monospace,
+        })
+    }
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 #[serde(remote = "Weight")]
 enum WeightDef {
@@ -123,11 +220,11 @@ enum StyleDef {
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
-//#[serde(bound(deserialize = "'de: 'static"))]
 #[serde(remote = "iced::Font")]
 struct FontDef {
-    /// The [`Family`] of the [`Font`].
-    #[serde(skip_serializing, skip_deserializing)]
+    /// The [`Family`] of the [`Font`], sans-serif in documents saved
+    /// before families were kept.
+    #[serde(with = "family", default)]
     pub family: Family,
     /// The [`Weight`] of the [`Font`].
     #[serde(with = "WeightDef")]
@@ -192,7 +289,7 @@ where
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(remote = "Text")]
-struct TextDef {
+pub(crate) struct TextDef {
     /// The contents of the text
     pub content: String,
     /// The position of the text relative to the alignment properties.
@@ -236,12 +333,77 @@ struct TextDef {
     pub shaping: Shaping,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum Shape {
-    #[serde(with = "PointDef")]
-    Point(kurbo::Point),
-    #[serde(with = "LineDef")]
-    Line(kurbo::Line),
-    #[serde(with = "TextDef")]
-    Text(Text),
+    Point {
+        #[serde(flatten, with = "PointDef")]
+        point: kurbo::Point,
+        #[serde(default, skip_serializing_if = "Appearance::is_default")]
+        appearance: Appearance,
+    },
+    Line {
+        #[serde(flatten, with = "LineDef")]
+        line: kurbo::Line,
+        #[serde(default, skip_serializing_if = "Appearance::is_default")]
+        appearance: Appearance,
+    },
+    Text(Annotation),
+    Arrow(Arrow),
+    Figure(Figure),
+}
+
+impl Shape {
+    /// A point in the style of the document.
+    pub fn point(point: kurbo::Point) -> Self {
+        Shape::Point {
+            point,
+            appearance: Appearance::default(),
+        }
+    }
+
+    /// A line in the style of the document.
+    pub fn line(line: kurbo::Line) -> Self {
+        Shape::Line {
+            line,
+            appearance: Appearance::default(),
+        }
+    }
+
+    pub fn translate(&mut self, offset: kurbo::Vec2) {
+        match self {
+            Shape::Point { point, .. } => *point += offset,
+            Shape::Line { line, .. } => {
+                line.p0 += offset;
+                line.p1 += offset;
+            }
+            Shape::Text(annotation) => {
+                annotation.text.position += iced::Vector::new(offset.x as f32, offset.y as f32)
+            }
+            Shape::Arrow(arrow) => {
+                for point in arrow.points_mut() {
+                    *point += offset;
+                }
+            }
+            Shape::Figure(figure) => figure.transform(kurbo::Affine::translate(offset)),
+        }
+    }
+
+    pub fn transform(&mut self, affine: kurbo::Affine) {
+        match self {
+            Shape::Point { point, .. } => *point = affine * *point,
+            Shape::Line { line, .. } => *line = affine * *line,
+            Shape::Text(annotation) => {
+                let text = &mut annotation.text;
+                let position =
+                    affine * kurbo::Point::new(text.position.x as f64, text.position.y as f64);
+                text.position = iced::Point::new(position.x as f32, position.y as f32);
+            }
+            Shape::Arrow(arrow) => {
+                for point in arrow.points_mut() {
+                    *point = affine * *point;
+                }
+            }
+            Shape::Figure(figure) => figure.transform(affine),
+        }
+    }
 }
@@ -0,0 +1,366 @@
+//! A grid over the parts of the items of a document, so hit-testing,
+//! snapping and drawing only look at the items near a point or inside the
+//! viewport instead of at all of them. Concepts are put in the grid atom by
+//! atom and bond by bond, so a large molecule is only found where it is
+//! drawn.
+
+use std::collections::HashMap;
+
+use kurbo::{Point, Rect};
+
+use crate::arrow::{self, Anchor};
+use crate::concept::Concept;
+use crate::document::{Document, Item};
+use crate::molecule::DECORATION_DISTANCE;
+use crate::render::Scene;
+
+/// Side of the square cells of the grid, in document units.
+const CELL_SIZE: f64 = 64.0;
+/// Parts covering more cells than this are kept out of the grid and checked
+/// on every query, rather than filling thousands of cells.
+const MAX_CELLS: i64 = 1024;
+
+/// The bounds of the visible items of a document, bucketed into a grid.
+/// Update the items that change, or build a new one when items are added,
+/// removed or reordered.
+#[derive(Clone, Debug, Default)]
+pub struct SpatialIndex {
+    /// Every indexed item, bottom first, `None` for removed ones.
+    entries: Vec<Option<Entry>>,
+    /// Where each item is in `entries`.
+    positions: HashMap<Item, usize>,
+    /// Entries overlapping each cell, by position in `entries`.
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    /// Entries with parts too large for the grid.
+    large: Vec<usize>,
+}
+
+/// An indexed item with its bounds, and the parts of it the grid points at.
+#[derive(Clone, Debug)]
+struct Entry {
+    item: Item,
+    bounds: Rect,
+    parts: Vec<Rect>,
+}
+
+impl SpatialIndex {
+    /// Indexes the items of `document` that are drawn, in drawing order.
+    pub fn new(document: &Document) -> Self {
+        let mut index = Self::default();
+        for item in document.draw_order() {
+            if let Some(bounds) = document.bounds(item) {
+                index.insert_entry(Entry {
+                    item,
+                    bounds,
+                    parts: parts(document, item, bounds),
+                });
+            }
+        }
+        index
+    }
+
+    /// Adds `item` above the items indexed so far, as a whole.
+    pub fn insert(&mut self, item: Item, bounds: Rect) {
+        self.insert_entry(Entry {
+            item,
+            bounds,
+            parts: vec![bounds],
+        });
+    }
+
+    fn insert_entry(&mut self, entry: Entry) {
+        self.remove(entry.item);
+        let position = self.entries.len();
+        self.positions.insert(entry.item, position);
+        self.entries.push(Some(entry));
+        self.link(position);
+    }
+
+    /// Gives `item` new bounds, keeping its place in the drawing order. Items
+    /// not indexed yet go on top.
+    pub fn update(&mut self, item: Item, bounds: Rect) {
+        self.update_entry(Entry {
+            item,
+            bounds,
+            parts: vec![bounds],
+        });
+    }
+
+    fn update_entry(&mut self, entry: Entry) {
+        let Some(&position) = self.positions.get(&entry.item) else {
+            self.insert_entry(entry);
+            return;
+        };
+        self.unlink(position);
+        self.entries[position] = Some(entry);
+        self.link(position);
+    }
+
+    /// Takes `item` out of the index.
+    pub fn remove(&mut self, item: Item) {
+        if let Some(entry) = self.positions.remove(&item) {
+            self.unlink(entry);
+            self.entries[entry] = None;
+        }
+    }
+
+    /// Updates the items of `document` that differ from `before`. Adding,
+    /// removing or reordering items, or changing the layers or the style,
+    /// rebuilds the whole index.
+    pub fn refresh(&mut self, before: &Document, document: &Document) {
+        let same_items = before.shapes.len() == document.shapes.len()
+            && before.concepts.len() == document.concepts.len()
+            && before.layers == document.layers
+            && before.style == document.style
+            && before.show_stereo_descriptors == document.show_stereo_descriptors;
+        if !same_items {
+            *self = Self::new(document);
+            return;
+        }
+        let shapes = (0..document.shapes.len())
+            .filter(|index| before.shapes[*index] != document.shapes[*index])
+            .map(Item::Shape);
+        let concepts = (0..document.concepts.len())
+            .filter(|index| before.concepts[*index] != document.concepts[*index])
+            .map(Item::Concept);
+        let changed: Vec<Item> = shapes.chain(concepts).collect();
+        // Items that were not drawn before would have to go in their place
+        // in the drawing order.
+        if changed
+            .iter()
+            .any(|item| !self.positions.contains_key(item) && document.bounds(*item).is_some())
+        {
+            *self = Self::new(document);
+            return;
+        }
+        self.refresh_items(document, &changed);
+    }
+
+    /// Updates the bounds of `items`, which are already in the index unless
+    /// they are not drawn.
+    pub fn refresh_items(&mut self, document: &Document, items: &[Item]) {
+        for item in items {
+            if !self.positions.contains_key(item) {
+                continue;
+            }
+            match document.bounds(*item) {
+                Some(bounds) => self.update_entry(Entry {
+                    item: *item,
+                    bounds,
+                    parts: parts(document, *item, bounds),
+                }),
+                None => self.remove(*item),
+            }
+        }
+    }
+
+    /// The cells `bounds` covers, `None` if there are too many.
+    fn cells_of(bounds: Rect) -> Option<((i64, i64), (i64, i64))> {
+        let ((x0, y0), (x1, y1)) = (
+            cell(bounds.origin()),
+            cell(Point::new(bounds.x1, bounds.y1)),
+        );
+        ((x1 - x0 + 1) * (y1 - y0 + 1) <= MAX_CELLS).then_some(((x0, y0), (x1, y1)))
+    }
+
+    /// Puts `entry` in the cells its parts cover.
+    fn link(&mut self, entry: usize) {
+        let Some(Entry { parts, .. }) = &self.entries[entry] else {
+            return;
+        };
+        for part in parts {
+            let Some(((x0, y0), (x1, y1))) = Self::cells_of(*part) else {
+                if !self.large.contains(&entry) {
+                    self.large.push(entry);
+                }
+                continue;
+            };
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    let entries = self.cells.entry((x, y)).or_default();
+                    // Parts next to each other share cells.
+                    if entries.last() != Some(&entry) {
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Takes `entry` out of the cells it is in.
+    fn unlink(&mut self, entry: usize) {
+        let Some(Entry { parts, .. }) = &self.entries[entry] else {
+            return;
+        };
+        for part in parts {
+            let Some(((x0, y0), (x1, y1))) = Self::cells_of(*part) else {
+                self.large.retain(|large| *large != entry);
+                continue;
+            };
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    if let Some(entries) = self.cells.get_mut(&(x, y)) {
+                        entries.retain(|other| *other != entry);
+                        if entries.is_empty() {
+                            self.cells.remove(&(x, y));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The bounds `item` was indexed with, `None` if it is not drawn.
+    pub fn bounds(&self, item: Item) -> Option<Rect> {
+        let entry = self.positions.get(&item)?;
+        self.entries[*entry].as_ref().map(|entry| entry.bounds)
+    }
+
+    /// The bounds of `items` together, like [`Document::bounds_of`].
+    pub fn bounds_of(&self, items: &[Item]) -> Option<Rect> {
+        items
+            .iter()
+            .filter_map(|item| self.bounds(*item))
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// The items with a part overlapping `rect`, bottom first: an atom or
+    /// bond of a concept, or anywhere inside the bounds of a shape.
+    pub fn query(&self, rect: Rect) -> Vec<Item> {
+        self.entries_in(rect)
+            .into_iter()
+            .map(|(item, _)| item)
+            .collect()
+    }
+
+    /// The items with a part overlapping `rect` and their bounds, bottom
+    /// first.
+    fn entries_in(&self, rect: Rect) -> Vec<(Item, Rect)> {
+        let ((x0, y0), (x1, y1)) = (cell(rect.origin()), cell(Point::new(rect.x1, rect.y1)));
+        let mut found: Vec<usize> = self.large.clone();
+        if (x1 - x0 + 1) * (y1 - y0 + 1) > self.cells.len() as i64 {
+            // Looking at every cell in the rectangle would take longer than
+            // looking at every cell there is.
+            for (&(x, y), entries) in &self.cells {
+                if (x0..=x1).contains(&x) && (y0..=y1).contains(&y) {
+                    found.extend(entries);
+                }
+            }
+        } else {
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    if let Some(entries) = self.cells.get(&(x, y)) {
+                        found.extend(entries);
+                    }
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+            .into_iter()
+            .filter_map(|entry| self.entries[entry].as_ref())
+            .filter(|entry| entry.parts.iter().any(|part| overlap(*part, rect)))
+            .map(|entry| (entry.item, entry.bounds))
+            .collect()
+    }
+
+    /// The topmost item of `document` under `point`, like
+    /// [`Document::item_at`].
+    pub fn item_at(&self, document: &Document, point: Point) -> Option<Item> {
+        let near = self.entries_in(Rect::from_center_size(
+            point,
+            (2.0 * arrow::REACH, 2.0 * arrow::REACH),
+        ));
+        let is_editable = document.editable();
+        let concepts = near.iter().filter_map(|(item, _)| match item {
+            Item::Concept(index) if is_editable(*item) => Some(*index),
+            _ => None,
+        });
+        if let Some(anchor) = Anchor::among(&document.concepts, concepts, point) {
+            return Some(Item::Concept(anchor.concept()));
+        }
+        near.into_iter()
+            .rev()
+            .find(|(item, bounds)| {
+                matches!(item, Item::Shape(_)) && is_editable(*item) && bounds.contains(point)
+            })
+            .map(|(item, _)| item)
+    }
+
+    /// Every editable item of `document` lying entirely inside `rect`, like
+    /// [`Document::items_within`].
+    pub fn items_within(&self, document: &Document, rect: Rect) -> Vec<Item> {
+        let is_editable = document.editable();
+        self.entries_in(rect)
+            .into_iter()
+            .filter(|(item, bounds)| is_editable(*item) && rect.union(*bounds) == rect)
+            .map(|(item, _)| item)
+            .collect()
+    }
+}
+
+/// Where `item` is drawn, within its `bounds`: each atom with room for its
+/// label and decorations, each bond, and the arrow and texts of a reaction.
+/// Shapes are one part.
+fn parts(document: &Document, item: Item, bounds: Rect) -> Vec<Rect> {
+    let Item::Concept(index) = item else {
+        return vec![bounds];
+    };
+    let concept = &document.concepts[index];
+    let style = &document.style;
+    let around_atom = DECORATION_DISTANCE + style.label_size * 3.0;
+    let around_bond =
+        style.bond_length * style.bond_spacing / 100.0 + style.bold_width + style.line_width;
+    let mut parts = Vec::new();
+    for molecule in concept.molecules() {
+        for atom in &molecule.atoms {
+            parts.push(Rect::from_center_size(
+                atom.position,
+                (2.0 * around_atom, 2.0 * around_atom),
+            ));
+        }
+        for bond in &molecule.bonds {
+            let (begin, end) = (
+                molecule.atoms[bond.begin].position,
+                molecule.atoms[bond.end].position,
+            );
+            parts.push(Rect::from_points(begin, end).inflate(around_bond, around_bond));
+        }
+    }
+    if let Concept::Reaction(reaction) = concept {
+        let mut scene = Scene::new(style.clone());
+        scene.arrow(&reaction.arrow);
+        parts.extend(scene.bounds());
+        for annotation in reaction.texts() {
+            let mut scene = Scene::new(style.clone());
+            scene.annotation(annotation);
+            parts.extend(scene.bounds());
+        }
+    }
+    parts
+        .into_iter()
+        .filter(|part| overlap(*part, bounds))
+        .map(|part| part.intersect(bounds))
+        .collect()
+}
+
+/// The cell `point` is in. Far away points share the cells at the edge, so
+/// counting cells never overflows.
+fn cell(point: Point) -> (i64, i64) {
+    let coordinate = |value: f64| (value / CELL_SIZE).floor().clamp(-1e9, 1e9) as i64;
+    (coordinate(point.x), coordinate(point.y))
+}
+
+/// Whether `a` and `b` share any point, edges included.
+fn overlap(a: Rect, b: Rect) -> bool {
+    a.x0 <= b.x1 && b.x0 <= a.x1 && a.y0 <= b.y1 && b.y0 <= a.y1
+}
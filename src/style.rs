@@ -0,0 +1,277 @@
+//! Document wide drawing settings, after the style sheets journals ask for.
+//!
+//! Lengths are in document units, which are CSS pixels (three quarters of a
+//! point); the presets are specified in points like their journals do.
+
+use iced::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::shape::{ColorDef, option_color};
+
+/// Document units per point.
+pub const UNITS_PER_POINT: f64 = 4.0 / 3.0;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    #[default]
+    Acs1996,
+    Rsc,
+    Wiley,
+    /// Anything edited by hand.
+    Custom,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Acs1996, Preset::Rsc, Preset::Wiley, Preset::Custom];
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Preset::Acs1996 => "ACS 1996",
+            Preset::Rsc => "RSC",
+            Preset::Wiley => "Wiley",
+            Preset::Custom => "Custom",
+        })
+    }
+}
+
+/// A setting of [`Style`] that can be edited on its own.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Field {
+    BondLength,
+    LineWidth,
+    BoldWidth,
+    HashSpacing,
+    MarginWidth,
+    BondSpacing,
+    LabelFont,
+    LabelSize,
+}
+
+impl Field {
+    pub const ALL: [Field; 8] = [
+        Field::BondLength,
+        Field::LineWidth,
+        Field::BoldWidth,
+        Field::HashSpacing,
+        Field::MarginWidth,
+        Field::BondSpacing,
+        Field::LabelFont,
+        Field::LabelSize,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::BondLength => "bond length (pt)",
+            Field::LineWidth => "line width (pt)",
+            Field::BoldWidth => "bold width (pt)",
+            Field::HashSpacing => "hash spacing (pt)",
+            Field::MarginWidth => "margin width (pt)",
+            Field::BondSpacing => "bond spacing (%)",
+            Field::LabelFont => "label font",
+            Field::LabelSize => "label size (pt)",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Style {
+    pub preset: Preset,
+    pub bond_length: f64,
+    /// Stroke width of plain bonds, arrows and hashes.
+    pub line_width: f64,
+    /// Width of the wide end of wedges.
+    pub bold_width: f64,
+    /// Distance between the hashes of hashed wedges.
+    pub hash_spacing: f64,
+    /// Gap between atom labels and the bonds ending at them.
+    pub margin_width: f64,
+    /// Distance between the lines of multiple bonds, in percent of the bond
+    /// length.
+    pub bond_spacing: f64,
+    /// Family of atom labels, drawn in the bundled font when it is not loaded.
+    pub label_font: String,
+    pub label_size: f64,
+    #[serde(with = "ColorDef")]
+    pub color: Color,
+    /// Colors atom labels by element instead of in `color`.
+    #[serde(default)]
+    pub element_colors: bool,
+}
+
+/// Dash pattern of strokes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dash {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl Dash {
+    pub const ALL: [Dash; 3] = [Dash::Solid, Dash::Dashed, Dash::Dotted];
+
+    /// Lengths of dashes and gaps for a stroke `width` wide, empty for solid
+    /// strokes.
+    pub fn pattern(self, width: f64) -> Vec<f64> {
+        // Hairlines would get dashes too short to see.
+        let unit = width.max(1.0);
+        match self {
+            Dash::Solid => Vec::new(),
+            Dash::Dashed => vec![4.0 * unit, 3.0 * unit],
+            // Round caps turn the short dashes into dots.
+            Dash::Dotted => vec![0.5 * unit, 2.0 * unit],
+        }
+    }
+}
+
+impl std::fmt::Display for Dash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Dash::Solid => "solid",
+            Dash::Dashed => "dashed",
+            Dash::Dotted => "dotted",
+        })
+    }
+}
+
+/// How one object is drawn, where it differs from the document style.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Appearance {
+    #[serde(
+        default,
+        with = "option_color",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub color: Option<Color>,
+    /// Stroke width in document units.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_width: Option<f64>,
+    #[serde(default, skip_serializing_if = "is_solid")]
+    pub dash: Dash,
+}
+
+impl Appearance {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The color of the object in `style`.
+    pub fn color(&self, style: &Style) -> Color {
+        self.color.unwrap_or(style.color)
+    }
+
+    /// The stroke width of the object in `style`.
+    pub fn line_width(&self, style: &Style) -> f64 {
+        self.line_width.unwrap_or(style.line_width)
+    }
+}
+
+fn is_solid(dash: &Dash) -> bool {
+    *dash == Dash::Solid
+}
+
+impl Style {
+    pub fn preset(preset: Preset) -> Self {
+        let points = |value: f64| value * UNITS_PER_POINT;
+        match preset {
+            Preset::Acs1996 | Preset::Custom => Self {
+                preset,
+                bond_length: points(14.4),
+                line_width: points(0.6),
+                bold_width: points(2.0),
+                hash_spacing: points(2.5),
+                margin_width: points(1.6),
+                bond_spacing: 18.0,
+                label_font: "Arial".to_string(),
+                label_size: points(10.0),
+                color: Color::BLACK,
+                element_colors: false,
+            },
+            Preset::Rsc => Self {
+                preset,
+                bond_length: points(14.4),
+                line_width: points(0.6),
+                bold_width: points(2.0),
+                hash_spacing: points(2.5),
+                margin_width: points(1.6),
+                bond_spacing: 20.0,
+                label_font: "Helvetica".to_string(),
+                label_size: points(8.0),
+                color: Color::BLACK,
+                element_colors: false,
+            },
+            Preset::Wiley => Self {
+                preset,
+                bond_length: points(17.0),
+                line_width: points(0.75),
+                bold_width: points(2.5),
+                hash_spacing: points(2.7),
+                margin_width: points(2.0),
+                bond_spacing: 18.0,
+                label_font: "Arial".to_string(),
+                label_size: points(12.0),
+                color: Color::BLACK,
+                element_colors: false,
+            },
+        }
+    }
+
+    /// `field` as shown for editing, with lengths in points.
+    pub fn get(&self, field: Field) -> String {
+        let points = |value: f64| crate::io::number(value / UNITS_PER_POINT);
+        match field {
+            Field::BondLength => points(self.bond_length),
+            Field::LineWidth => points(self.line_width),
+            Field::BoldWidth => points(self.bold_width),
+            Field::HashSpacing => points(self.hash_spacing),
+            Field::MarginWidth => points(self.margin_width),
+            Field::BondSpacing => crate::io::number(self.bond_spacing),
+            Field::LabelFont => self.label_font.clone(),
+            Field::LabelSize => points(self.label_size),
+        }
+    }
+
+    /// Sets `field` from its edited text, which makes the style custom.
+    /// Returns `None` for values that are not positive numbers.
+    pub fn set(&self, field: Field, value: &str) -> Option<Self> {
+        let mut style = Self {
+            preset: Preset::Custom,
+            ..self.clone()
+        };
+        let number = || {
+            let number: f64 = value.trim().parse().ok()?;
+            (number.is_finite() && number > 0.0).then_some(number)
+        };
+        let length = || number().map(|number| number * UNITS_PER_POINT);
+        match field {
+            Field::BondLength => style.bond_length = length()?,
+            Field::LineWidth => style.line_width = length()?,
+            Field::BoldWidth => style.bold_width = length()?,
+            Field::HashSpacing => style.hash_spacing = length()?,
+            Field::MarginWidth => style.margin_width = length()?,
+            Field::BondSpacing => style.bond_spacing = number()?,
+            Field::LabelSize => style.label_size = length()?,
+            Field::LabelFont => {
+                let value = value.trim();
+                if value.is_empty() {
+                    return None;
+                }
+                style.label_font = value.to_string();
+            }
+        }
+        Some(style)
+    }
+
+    /// Distance between the lines of multiple bonds, in document units.
+    pub fn bond_offset(&self) -> f64 {
+        self.bond_length * self.bond_spacing / 100.0
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::preset(Preset::default())
+    }
+}
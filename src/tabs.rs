@@ -0,0 +1,219 @@
+//! The open documents, one per tab, and switching between them.
+
+use iced::keyboard::{self, Key, key::Named};
+use iced::widget::{button, column, container, row, scrollable, space, text};
+use iced::{Color, Element, Length, Subscription, Task, window};
+use rfd::FileDialog;
+
+use crate::app::App;
+use crate::message::Message;
+
+#[derive(Debug)]
+pub struct Tabs {
+    /// Never empty, closing the last tab opens an untitled one.
+    pub(crate) tabs: Vec<App>,
+    pub(crate) active: usize,
+    /// The tab asking whether to save its changes before closing.
+    pub(crate) closing: Option<usize>,
+    /// Whether the window closes once no tab has unsaved changes.
+    pub(crate) quitting: bool,
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self {
+            tabs: vec![App::untitled()],
+            active: 0,
+            closing: None,
+            quitting: false,
+        }
+    }
+}
+
+impl Tabs {
+    pub fn active(&self) -> &App {
+        &self.tabs[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut App {
+        &mut self.tabs[self.active]
+    }
+
+    /// Adds `tab` after the others and switches to it.
+    fn push(&mut self, tab: App) {
+        self.tabs.push(tab);
+        self.active = self.tabs.len() - 1;
+    }
+
+    /// Closes the tab at `index`, changes or not.
+    fn close(&mut self, index: usize) {
+        self.tabs.remove(index);
+        if self.tabs.is_empty() {
+            self.tabs.push(App::untitled());
+        }
+        if self.active > index || self.active == self.tabs.len() {
+            self.active -= 1;
+        }
+        self.closing = None;
+    }
+
+    /// Asks about the next tab with unsaved changes while the window is
+    /// closing, and closes the window when there are none left.
+    fn continue_quitting(&mut self) -> Task<Message> {
+        if !self.quitting {
+            return Task::none();
+        }
+        match self.tabs.iter().position(App::dirty) {
+            Some(index) => {
+                self.active = index;
+                self.closing = Some(index);
+                Task::none()
+            }
+            None => iced::exit(),
+        }
+    }
+}
+
+pub fn update(tabs: &mut Tabs, message: Message) -> Task<Message> {
+    let count = tabs.tabs.len();
+    match message {
+        Message::NewTab => tabs.push(App::untitled()),
+        Message::Load => {
+            let picked = FileDialog::new().add_filter("text", &["json"]).pick_file();
+            match picked.map(crate::open) {
+                Some(Ok(tab)) => tabs.push(tab),
+                Some(Err(e)) => tabs.active_mut().notice = Some(e),
+                None => (),
+            }
+        }
+        Message::SelectedTab(index) if index < count => tabs.active = index,
+        Message::NextTab => tabs.active = (tabs.active + 1) % count,
+        Message::PreviousTab => tabs.active = (tabs.active + count - 1) % count,
+        Message::CloseTab(index) if index < count => {
+            if tabs.tabs[index].dirty() {
+                tabs.active = index;
+                tabs.closing = Some(index);
+            } else {
+                tabs.close(index);
+            }
+        }
+        Message::SaveAndClose => {
+            if let Some(index) = tabs.closing
+                && crate::save(&mut tabs.tabs[index])
+            {
+                tabs.close(index);
+                return tabs.continue_quitting();
+            }
+        }
+        Message::DiscardAndClose => {
+            if let Some(index) = tabs.closing {
+                tabs.close(index);
+                return tabs.continue_quitting();
+            }
+        }
+        Message::CancelClose => {
+            tabs.closing = None;
+            tabs.quitting = false;
+        }
+        Message::CloseRequested => {
+            tabs.quitting = true;
+            return tabs.continue_quitting();
+        }
+        Message::SelectedTab(_) | Message::CloseTab(_) => (),
+        message => return crate::update(tabs.active_mut(), message),
+    }
+    Task::none()
+}
+
+/// Shortcuts working wherever the focus is: Ctrl+Tab and Ctrl+Shift+Tab to
+/// switch tabs, Ctrl+Z and Ctrl+Shift+Z or Ctrl+Y to undo and redo unless a
+/// text is being typed. Closing the window asks about unsaved changes first.
+pub fn subscription(tabs: &Tabs) -> Subscription<Message> {
+    let switching = keyboard::listen().filter_map(|event| {
+        let keyboard::Event::KeyPressed { key, modifiers, .. } = event else {
+            return None;
+        };
+        match key.as_ref() {
+            Key::Named(Named::Tab) if modifiers.control() => Some(if modifiers.shift() {
+                Message::PreviousTab
+            } else {
+                Message::NextTab
+            }),
+            _ => None,
+        }
+    });
+    let close = window::close_requests().map(|_| Message::CloseRequested);
+    if tabs.active().current_text_field.is_some() {
+        return Subscription::batch([switching, close]);
+    }
+    let undoing = keyboard::listen().filter_map(|event| {
+        let keyboard::Event::KeyPressed { key, modifiers, .. } = event else {
+            return None;
+        };
+        match key.as_ref() {
+            Key::Character("z") if modifiers.command() => Some(if modifiers.shift() {
+                Message::Redo
+            } else {
+                Message::Undo
+            }),
+            Key::Character("y") if modifiers.command() => Some(Message::Redo),
+            _ => None,
+        }
+    });
+    Subscription::batch([switching, undoing, close])
+}
+
+pub fn view(tabs: &Tabs) -> Element<'_, Message> {
+    let mut bar = row![].spacing(2).padding([4, 10]);
+    for (index, tab) in tabs.tabs.iter().enumerate() {
+        bar = bar.push(
+            row![
+                button(text(tab.title()).size(12))
+                    .style(if index == tabs.active {
+                        button::primary
+                    } else {
+                        button::secondary
+                    })
+                    .on_press(Message::SelectedTab(index)),
+                button(text("×").size(12))
+                    .style(button::text)
+                    .on_press(Message::CloseTab(index)),
+            ]
+            .align_y(iced::Alignment::Center),
+        );
+    }
+    bar = bar.push(button(text("+").size(12)).on_press(Message::NewTab));
+
+    let bar = container(scrollable(bar).direction(scrollable::Direction::Horizontal(
+        scrollable::Scrollbar::default(),
+    )))
+    .width(Length::Fill)
+    .style(|_| container::Style {
+        background: Some(Color::from_rgb(0.05, 0.05, 0.05).into()),
+        ..Default::default()
+    });
+
+    let mut content = column![bar];
+    if let Some(index) = tabs.closing {
+        content = content.push(
+            container(
+                row![
+                    text(format!("Save changes to {}?", tabs.tabs[index].title())),
+                    space::horizontal(),
+                    button(text("save")).on_press(Message::SaveAndClose),
+                    button(text("discard")).on_press(Message::DiscardAndClose),
+                    button(text("cancel")).on_press(Message::CancelClose),
+                ]
+                .spacing(5)
+                .padding(5)
+                .align_y(iced::Alignment::Center),
+            )
+            .width(Length::Fill)
+            .style(|_| container::Style {
+                background: Some(Color::from_rgb(0.95, 0.85, 0.5).into()),
+                ..Default::default()
+            }),
+        );
+    }
+    content.push(crate::view(tabs.active())).into()
+}
@@ -0,0 +1,923 @@
+//! Replays message sequences through `update`, the way the canvas and the
+//! menus send them, and checks the document they leave behind.
+
+use iced::advanced::mouse::{Button, Click};
+use iced::widget::canvas::Text;
+use iced::{Point, Vector};
+
+use molecular::annotation::{Annotation, Format, Script};
+use molecular::arrow::{ArrowHead, ArrowKind};
+use molecular::concept::Concept;
+use molecular::figure::{BracketKind, Figure, FigureKind, Outline};
+use molecular::molecule::DecorationKind;
+use molecular::page::{Orientation, Page, Paper};
+use molecular::shape::Shape;
+use molecular::style::{Dash, Field, Preset, Style};
+use molecular::{Document, Item, io};
+
+use crate::app::App;
+use crate::canvas::drawn_region;
+use crate::editor::{Edit, Editor, Emphasis, Motion};
+use crate::message::Message;
+use crate::tabs::{self, Tabs};
+use crate::tool::{self, Tool};
+use crate::update;
+
+fn blank() -> App {
+    App {
+        document: Document::default(),
+        current_text_field: None,
+        ..App::default()
+    }
+}
+
+fn replay(app: &mut App, messages: impl IntoIterator<Item = Message>) {
+    for message in messages {
+        let _ = update(app, message);
+    }
+}
+
+/// The messages of pressing, dragging by `delta` and releasing the left
+/// button with the draw tool.
+fn drag(app: &App, from: Point, delta: Vector) -> [Message; 3] {
+    let click = Click::new(from, Button::Left, app.previous_click);
+    [
+        Message::StartedDrawing(from, click),
+        Message::DrawingMoved(from, delta),
+        Message::StoppedDrawing,
+    ]
+}
+
+fn click(app: &App, at: Point) -> [Message; 2] {
+    let click = Click::new(at, Button::Left, app.previous_click);
+    [Message::StartedDrawing(at, click), Message::StoppedDrawing]
+}
+
+fn ethanol(app: &mut App) {
+    let molecule = io::smiles::parse("CCO").unwrap();
+    app.document.import(vec![Concept::Molecule(molecule)]);
+}
+
+#[test]
+fn dragging_draws_a_line() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    let messages = drag(&app, Point::new(0.0, 0.0), Vector::new(40.0, 0.0));
+    replay(&mut app, messages);
+
+    let [Shape::Line { line, .. }] = &app.document.shapes[..] else {
+        panic!("expected one line, got {:?}", app.document.shapes);
+    };
+    assert_eq!(line.p0, kurbo::Point::new(0.0, 0.0));
+    assert_eq!(line.p1, kurbo::Point::new(40.0, 0.0));
+    assert!(app.drawing_starting_pos.is_none());
+}
+
+#[test]
+fn dragging_with_a_figure_tool_draws_the_figure() {
+    let mut app = blank();
+    let kind = FigureKind::Brackets(BracketKind::Square);
+    replay(&mut app, [Message::SelectedTool(Tool::Figure(kind))]);
+    let messages = drag(&app, Point::new(40.0, 30.0), Vector::new(-40.0, -30.0));
+    replay(&mut app, messages);
+
+    let [Shape::Figure(figure)] = &app.document.shapes[..] else {
+        panic!("expected one figure, got {:?}", app.document.shapes);
+    };
+    let expected = Figure::new(
+        kind,
+        kurbo::Point::new(0.0, 0.0),
+        kurbo::Point::new(40.0, 30.0),
+    );
+    assert_eq!(*figure, expected);
+
+    app.selection = vec![Item::Shape(0)];
+    replay(&mut app, [Message::SetSubscript("n".to_string())]);
+    let Shape::Figure(Figure {
+        outline: Outline::Brackets { subscript, .. },
+        ..
+    }) = &app.document.shapes[0]
+    else {
+        unreachable!()
+    };
+    assert_eq!(subscript, "n");
+}
+
+#[test]
+fn clicking_beside_an_atom_decorates_it() {
+    let mut app = blank();
+    ethanol(&mut app);
+    let atoms = &app.document.concepts[0].molecules()[0].atoms;
+    // Away from the carbon the oxygen is bonded to.
+    let (carbon, oxygen) = (atoms[1].position, atoms[2].position);
+    let outside = oxygen + (oxygen - carbon).normalize() * 14.0;
+    let beside = Point::new(outside.x as f32, outside.y as f32);
+    replay(
+        &mut app,
+        [Message::SelectedTool(Tool::Decorate(
+            DecorationKind::Radical,
+        ))],
+    );
+    let messages = click(&app, beside);
+    replay(&mut app, messages);
+
+    let oxygen = &app.document.concepts[0].molecules()[0].atoms[2];
+    assert_eq!(oxygen.radicals(), 1);
+    assert!(app.document.shapes.is_empty());
+}
+
+#[test]
+fn short_drags_draw_a_point() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    let messages = drag(&app, Point::new(5.0, 5.0), Vector::new(3.0, 0.0));
+    replay(&mut app, messages);
+
+    assert!(matches!(
+        app.document.shapes[..],
+        [Shape::Point { point, .. }] if point == kurbo::Point::new(5.0, 5.0)
+    ));
+}
+
+#[test]
+fn double_click_only_removes_its_own_point() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    let line = drag(&app, Point::new(0.0, 0.0), Vector::new(40.0, 0.0));
+    replay(&mut app, line);
+
+    // The first click of a double click draws a point, the second one starts
+    // typing and takes that point back. Everything drawn before stays.
+    let first = click(&app, Point::new(100.0, 100.0));
+    replay(&mut app, first);
+    assert_eq!(app.document.shapes.len(), 2);
+    replay(
+        &mut app,
+        [
+            Message::StartedTyping(Point::new(100.0, 100.0)),
+            Message::Edited(Edit::Insert("OH".to_string())),
+            Message::FinishedTyping,
+        ],
+    );
+
+    let [Shape::Line { .. }, Shape::Text(text)] = &app.document.shapes[..] else {
+        panic!(
+            "expected the line and the text, got {:?}",
+            app.document.shapes
+        );
+    };
+    assert_eq!(text.content(), "OH");
+    assert!(app.current_text_field.is_none());
+    assert!(matches!(app.selected_tool, Tool::Draw));
+}
+
+#[test]
+fn typing_after_a_drag_keeps_the_line() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    let line = drag(&app, Point::new(0.0, 0.0), Vector::new(40.0, 0.0));
+    replay(&mut app, line);
+    replay(
+        &mut app,
+        [
+            Message::StartedTyping(Point::new(40.0, 0.0)),
+            Message::Edited(Edit::Insert("H".to_string())),
+            Message::FinishedTyping,
+        ],
+    );
+
+    assert!(matches!(
+        app.document.shapes[..],
+        [Shape::Line { .. }, Shape::Text(_)]
+    ));
+}
+
+#[test]
+fn typing_edits_the_text_field() {
+    let mut app = blank();
+    replay(
+        &mut app,
+        [
+            Message::StartedTyping(Point::new(0.0, 0.0)),
+            Message::Edited(Edit::Insert("Cl".to_string())),
+            Message::Edited(Edit::Backspace),
+            Message::Edited(Edit::Insert(" O".to_string())),
+        ],
+    );
+
+    assert!(matches!(app.selected_tool, Tool::Typing));
+    assert_eq!(app.current_text_field.as_ref().unwrap().content(), "C O");
+    assert!(app.document.shapes.is_empty());
+}
+
+#[test]
+fn the_caret_moves_and_selects() {
+    let mut editor = Editor::new(Annotation::new(Text {
+        content: "CH3 COOH".to_string(),
+        ..Text::default()
+    }));
+    let edits = [
+        Edit::Move(Motion::Home),
+        Edit::Move(Motion::Right),
+        Edit::Select(Motion::WordRight),
+        Edit::Insert("l".to_string()),
+        Edit::Move(Motion::End),
+        Edit::Select(Motion::WordLeft),
+        Edit::Delete,
+        Edit::Insert("OH".to_string()),
+        Edit::Move(Motion::DocumentStart),
+        Edit::Delete,
+        Edit::Insert("C".to_string()),
+    ];
+    for edit in edits {
+        editor.apply(edit);
+    }
+
+    assert_eq!(editor.content(), "Cl OH");
+    assert!(editor.selection().is_empty());
+}
+
+#[test]
+fn enter_starts_a_new_line_the_caret_can_move_between() {
+    let mut editor = Editor::new(Annotation::new(Text::default()));
+    for edit in [
+        Edit::Insert("NaOH".to_string()),
+        Edit::Insert("\n".to_string()),
+        Edit::Insert("H2O".to_string()),
+        Edit::Move(Motion::Up),
+        Edit::Move(Motion::End),
+        Edit::Insert(", Δ".to_string()),
+        Edit::Move(Motion::Down),
+        Edit::Move(Motion::Home),
+        Edit::Select(Motion::End),
+    ] {
+        editor.apply(edit);
+    }
+
+    assert_eq!(editor.content(), "NaOH, Δ\nH2O");
+    assert_eq!(&editor.content()[editor.selection()], "H2O");
+    assert_eq!(editor.selection_rects().len(), 1);
+
+    // Clicking before the first character and dragging to the end of the
+    // second line selects everything.
+    let top_left = editor.annotation.text.position;
+    let start = kurbo::Point::new(top_left.x as f64 - 5.0, top_left.y as f64 + 2.0);
+    editor.apply(Edit::Press(start));
+    editor.apply(Edit::Drag(kurbo::Point::new(
+        1000.0,
+        top_left.y as f64 + 25.0,
+    )));
+    editor.apply(Edit::Release);
+    assert_eq!(editor.selection(), 0..editor.content().len());
+    assert_eq!(editor.selection_rects().len(), 2);
+}
+
+#[test]
+fn formatting_applies_to_the_selection_or_what_is_typed_next() {
+    let mut editor = Editor::new(Annotation::new(Text::default()));
+    for edit in [
+        Edit::Insert("ΔG".to_string()),
+        Edit::Format(Emphasis::Script(Script::Super)),
+        Edit::Insert("‡".to_string()),
+        Edit::Format(Emphasis::Script(Script::Super)),
+        Edit::Insert(" = 12 kcal".to_string()),
+        Edit::Move(Motion::DocumentStart),
+        Edit::Select(Motion::WordRight),
+        Edit::Format(Emphasis::Italic),
+        Edit::Format(Emphasis::Color(Some(iced::Color::from_rgb(1.0, 0.0, 0.0)))),
+    ] {
+        editor.apply(edit);
+    }
+
+    let runs = editor.annotation.runs();
+    let texts: Vec<&str> = runs.iter().map(|run| run.text.as_str()).collect();
+    assert_eq!(texts, ["ΔG", "‡", " = 12 kcal"]);
+    assert!(runs[0].format.italic && runs[0].format.color.is_some());
+    assert_eq!(runs[1].format.script, Script::Super);
+    assert!(runs[1].format.italic);
+    assert_eq!(runs[2].format, Format::default());
+}
+
+#[test]
+fn greek_swaps_the_letter_before_the_caret() {
+    let mut editor = Editor::new(Annotation::new(Text::default()));
+    for edit in [
+        Edit::Insert("DG".to_string()),
+        Edit::Move(Motion::Left),
+        Edit::Greek,
+    ] {
+        editor.apply(edit);
+    }
+    assert_eq!(editor.content(), "ΔG");
+}
+
+#[test]
+fn auto_format_subscripts_formulas_as_they_are_typed() {
+    let mut editor = Editor::new(Annotation::new(Text::default()));
+    editor.apply(Edit::AutoFormat(true));
+    editor.apply(Edit::Insert("CH2Cl2, 0 °C".to_string()));
+
+    let runs = editor.annotation.runs();
+    let subscripts: Vec<&str> = runs
+        .iter()
+        .filter(|run| run.format.script == Script::Sub)
+        .map(|run| run.text.as_str())
+        .collect();
+    assert_eq!(subscripts, ["2", "2"]);
+}
+
+#[test]
+fn clicking_away_commits_the_text() {
+    let mut app = blank();
+    replay(
+        &mut app,
+        [
+            Message::StartedTyping(Point::new(0.0, 0.0)),
+            Message::Edited(Edit::Insert("Ph".to_string())),
+            Message::FinishedTyping,
+            Message::StartedTyping(Point::new(200.0, 0.0)),
+            Message::FinishedTyping,
+            // The release of the click away, which drew nothing.
+            Message::StoppedDrawing,
+        ],
+    );
+
+    // The second, empty text is dropped.
+    let [Shape::Text(text)] = &app.document.shapes[..] else {
+        panic!("expected one text, got {:?}", app.document.shapes);
+    };
+    assert_eq!(text.content(), "Ph");
+    assert!(matches!(app.selected_tool, Tool::Draw));
+}
+
+#[test]
+fn double_clicking_a_text_edits_it_again() {
+    let mut app = blank();
+    replay(
+        &mut app,
+        [
+            Message::StartedTyping(Point::new(0.0, 0.0)),
+            Message::Edited(Edit::Insert("Me".to_string())),
+            Message::FinishedTyping,
+            Message::StartedTyping(Point::new(2.0, 8.0)),
+            Message::Edited(Edit::Move(Motion::End)),
+            Message::Edited(Edit::Insert("O".to_string())),
+            Message::FinishedTyping,
+        ],
+    );
+
+    let [Shape::Text(text)] = &app.document.shapes[..] else {
+        panic!("expected one text, got {:?}", app.document.shapes);
+    };
+    assert_eq!(text.content(), "MeO");
+}
+
+#[test]
+fn arrows_anchor_to_atoms_and_follow_them() {
+    let mut app = blank();
+    ethanol(&mut app);
+    let atom = app.document.concepts[0].molecules()[0].atoms[0].position;
+    let tail = Point::new(atom.x as f32, atom.y as f32);
+    replay(
+        &mut app,
+        [Message::SelectedTool(Tool::Arrow(ArrowKind::Curved(
+            ArrowHead::Full,
+        )))],
+    );
+    let messages = drag(&app, tail, Vector::new(60.0, 40.0));
+    replay(&mut app, messages);
+
+    let Some(Shape::Arrow(arrow)) = app.document.shapes.first() else {
+        panic!("expected an arrow, got {:?}", app.document.shapes);
+    };
+    assert!(arrow.tail_anchor.is_some());
+    assert!(arrow.head_anchor.is_none());
+
+    // Dragging the molecule with the pick tool takes the arrow's tail along,
+    // keeping it just as far from the atom.
+    let gap = arrow.tail().distance(atom);
+    replay(
+        &mut app,
+        [
+            Message::SelectedTool(Tool::Pick),
+            Message::StartedDragging(tail),
+            Message::Dragged(tail + Vector::new(0.0, 25.0)),
+            Message::StoppedDragging,
+        ],
+    );
+    let Some(Shape::Arrow(arrow)) = app.document.shapes.first() else {
+        unreachable!()
+    };
+    assert_eq!(app.selection, vec![Item::Concept(0)]);
+    let moved = app.document.concepts[0].molecules()[0].atoms[0].position;
+    assert!((moved.y - atom.y - 25.0).abs() < 1e-3);
+    assert!((arrow.tail().distance(moved) - gap).abs() < 1e-6);
+}
+
+#[test]
+fn rubber_band_selects_what_it_encloses() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    for x in [0.0, 100.0] {
+        let messages = drag(&app, Point::new(x, 0.0), Vector::new(20.0, 0.0));
+        replay(&mut app, messages);
+    }
+    replay(
+        &mut app,
+        [
+            Message::SelectedTool(Tool::Pick),
+            Message::StartedDragging(Point::new(-10.0, -10.0)),
+            Message::Dragged(Point::new(50.0, 10.0)),
+            Message::StoppedDragging,
+        ],
+    );
+
+    assert_eq!(app.selection, vec![Item::Shape(0)]);
+}
+
+#[test]
+fn picking_a_grouped_item_selects_its_group() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    for x in [0.0, 100.0] {
+        let messages = drag(&app, Point::new(x, 0.0), Vector::new(20.0, 0.0));
+        replay(&mut app, messages);
+    }
+    app.selection = vec![Item::Shape(0), Item::Shape(1)];
+    replay(
+        &mut app,
+        [
+            Message::Group,
+            Message::AddLayer,
+            Message::MoveToLayer(1),
+            Message::SelectedTool(Tool::Pick),
+            Message::StartedDragging(Point::new(110.0, 0.0)),
+            Message::StoppedDragging,
+        ],
+    );
+    assert_eq!(app.selection, vec![Item::Shape(0), Item::Shape(1)]);
+
+    replay(&mut app, [Message::ToggleLayerLocked(1, true)]);
+    assert!(app.selection.is_empty());
+}
+
+#[test]
+fn dragging_the_rotation_handle_turns_the_selection() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    let messages = drag(&app, Point::new(0.0, 0.0), Vector::new(40.0, 0.0));
+    replay(&mut app, messages);
+    app.selection = vec![Item::Shape(0)];
+    let bounds = app.document.bounds_of(&app.selection).unwrap();
+    let handle = tool::rotation_handle(bounds);
+    let center = bounds.center();
+    // A quarter turn clockwise around the center.
+    let turned = center + kurbo::Vec2::new(center.y - handle.y, 0.0);
+    replay(
+        &mut app,
+        [
+            Message::SelectedTool(Tool::Pick),
+            Message::StartedDragging(Point::new(handle.x as f32, handle.y as f32)),
+            Message::Dragged(Point::new(turned.x as f32, turned.y as f32)),
+            Message::StoppedDragging,
+        ],
+    );
+
+    let [Shape::Line { line, .. }] = &app.document.shapes[..] else {
+        panic!("expected one line, got {:?}", app.document.shapes);
+    };
+    assert!((line.p0.x - 20.0).abs() < 1e-3);
+    assert!((line.p1.x - 20.0).abs() < 1e-3);
+    assert!((line.p1.y - line.p0.y - 40.0).abs() < 1e-3);
+}
+
+#[test]
+fn moving_the_selection_moves_it_in_the_index() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    let messages = drag(&app, Point::new(0.0, 0.0), Vector::new(40.0, 0.0));
+    replay(&mut app, messages);
+    let before = app.index.bounds(Item::Shape(0)).unwrap();
+
+    replay(
+        &mut app,
+        [
+            Message::SelectedTool(Tool::Pick),
+            Message::StartedDragging(Point::new(20.0, 0.0)),
+            Message::Dragged(Point::new(20.0, 30.0)),
+        ],
+    );
+    let after = app.index.bounds(Item::Shape(0)).unwrap();
+    assert_eq!(after, before + kurbo::Vec2::new(0.0, 30.0));
+    assert_eq!(
+        app.index
+            .item_at(&app.document, kurbo::Point::new(20.0, 0.0)),
+        None
+    );
+}
+
+#[test]
+fn snapping_keeps_lines_and_moves_on_the_grid() {
+    let mut app = blank();
+    replay(
+        &mut app,
+        [
+            Message::SelectedGridSpacing(10.0),
+            Message::ToggleSnapToGrid(true),
+            Message::SelectedTool(Tool::Draw),
+        ],
+    );
+    let messages = drag(&app, Point::new(3.0, -4.0), Vector::new(38.0, 2.0));
+    replay(&mut app, messages);
+    let [Shape::Line { line, .. }] = &app.document.shapes[..] else {
+        panic!("expected one line, got {:?}", app.document.shapes);
+    };
+    assert_eq!(*line, kurbo::Line::new((0.0, 0.0), (40.0, 0.0)));
+
+    // Moves go from grid point to grid point, however far the cursor went.
+    replay(
+        &mut app,
+        [
+            Message::SelectedTool(Tool::Pick),
+            Message::StartedDragging(Point::new(20.0, 0.0)),
+            Message::Dragged(Point::new(22.0, 13.0)),
+            Message::Dragged(Point::new(23.0, 17.0)),
+            Message::StoppedDragging,
+        ],
+    );
+    let Shape::Line { line, .. } = &app.document.shapes[0] else {
+        unreachable!();
+    };
+    assert_eq!(*line, kurbo::Line::new((0.0, 20.0), (40.0, 20.0)));
+}
+
+#[test]
+fn page_setup_keeps_the_other_settings_when_the_paper_changes() {
+    let mut app = blank();
+    replay(
+        &mut app,
+        [
+            Message::SelectedPaper(Some(Paper::A3)),
+            Message::SelectedOrientation(Orientation::Landscape),
+            Message::SelectedMargin(0.0),
+            Message::SelectedPaper(Some(Paper::Letter)),
+        ],
+    );
+    let expected = Page {
+        paper: Paper::Letter,
+        orientation: Orientation::Landscape,
+        margin: 0.0,
+    };
+    assert_eq!(app.document.page, Some(expected));
+
+    replay(&mut app, [Message::SelectedPaper(None)]);
+    assert_eq!(app.document.page, None);
+    // Without a page there is nothing to turn.
+    replay(
+        &mut app,
+        [Message::SelectedOrientation(Orientation::Portrait)],
+    );
+    assert_eq!(app.document.page, None);
+}
+
+#[test]
+fn cut_removes_the_selection() {
+    let mut app = blank();
+    ethanol(&mut app);
+    ethanol(&mut app);
+    app.selection = vec![Item::Concept(0)];
+    replay(&mut app, [Message::Cut]);
+
+    assert_eq!(app.document.concepts.len(), 1);
+    assert!(app.selection.is_empty());
+}
+
+#[test]
+fn pasting_places_the_fragment_at_the_cursor() {
+    let mut app = blank();
+    replay(
+        &mut app,
+        [Message::Pasted(
+            Point::new(200.0, -50.0),
+            Some("C1CCCCC1".to_string()),
+        )],
+    );
+
+    let [Concept::Molecule(molecule)] = &app.document.concepts[..] else {
+        panic!("expected one molecule");
+    };
+    let center = molecule.bounding_box().unwrap().center();
+    assert!((center - kurbo::Point::new(200.0, -50.0)).hypot() < 1e-6);
+    let length = molecule.median_bond_length().unwrap();
+    assert!((length - app.document.style.bond_length).abs() < 1e-6);
+    assert_eq!(app.selection, vec![Item::Concept(0)]);
+}
+
+#[test]
+fn properties_restyle_only_the_selection() {
+    let mut app = blank();
+    ethanol(&mut app);
+    ethanol(&mut app);
+    app.selection = vec![Item::Concept(1)];
+    let red = iced::Color::from_rgb(0.8, 0.1, 0.1);
+    replay(
+        &mut app,
+        [
+            Message::SetColor(Some(red)),
+            Message::SetLineWidth(Some(2.0)),
+            Message::SetDash(Dash::Dotted),
+        ],
+    );
+
+    let appearance = app.document.appearance(Item::Concept(1));
+    assert_eq!(appearance.color, Some(red));
+    assert_eq!(appearance.line_width, Some(2.0));
+    assert_eq!(appearance.dash, Dash::Dotted);
+    assert!(app.document.appearance(Item::Concept(0)).is_default());
+
+    replay(&mut app, [Message::ToggleElementColors(true)]);
+    assert!(app.document.style.element_colors);
+}
+
+#[test]
+fn style_presets_rescale_the_document() {
+    let mut app = blank();
+    ethanol(&mut app);
+    replay(&mut app, [Message::SelectedStylePreset(Preset::Wiley)]);
+
+    let wiley = Style::preset(Preset::Wiley);
+    let length = app.document.concepts[0].median_bond_length().unwrap();
+    assert!((length - wiley.bond_length).abs() < 1e-6);
+
+    replay(
+        &mut app,
+        [Message::StyleChanged(Field::LineWidth, "1.".to_string())],
+    );
+    assert_eq!(app.document.style.preset, Preset::Custom);
+    assert_eq!(app.style_draft, Some((Field::LineWidth, "1.".to_string())));
+    assert_eq!(app.document.style.get(Field::LineWidth), "1");
+}
+
+#[test]
+fn saved_state_loads_back_unchanged() {
+    let mut app = blank();
+    ethanol(&mut app);
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    let line = drag(&app, Point::new(0.0, 0.0), Vector::new(40.0, 0.0));
+    replay(&mut app, line);
+    replay(
+        &mut app,
+        [
+            Message::StartedTyping(Point::new(10.0, 10.0)),
+            Message::Edited(Edit::Insert("N".to_string())),
+            Message::FinishedTyping,
+            Message::ToggleStereoDescriptors,
+            Message::SelectedStylePreset(Preset::Rsc),
+        ],
+    );
+
+    let saved = serde_json::to_string_pretty(&app).unwrap();
+    let loaded: App = serde_json::from_str(&saved).unwrap();
+    assert_eq!(serde_json::to_string_pretty(&loaded).unwrap(), saved);
+    assert_eq!(loaded.document.shapes.len(), 2);
+    assert!(loaded.document.show_stereo_descriptors);
+    assert_eq!(loaded.document.style.preset, Preset::Rsc);
+}
+
+fn x_of(app: &App) -> f64 {
+    match &app.document.shapes[0] {
+        Shape::Line { line, .. } => line.p0.x,
+        shape => panic!("expected a line, got {shape:?}"),
+    }
+}
+
+#[test]
+fn a_whole_drag_is_undone_in_one_step() {
+    let mut app = blank();
+    replay(&mut app, [Message::SelectedTool(Tool::Draw)]);
+    let line = drag(&app, Point::new(0.0, 0.0), Vector::new(40.0, 0.0));
+    replay(&mut app, line);
+    replay(
+        &mut app,
+        [
+            Message::SelectedTool(Tool::Pick),
+            Message::StartedDragging(Point::new(20.0, 0.0)),
+            Message::Dragged(Point::new(30.0, 0.0)),
+            Message::Dragged(Point::new(45.0, 0.0)),
+            Message::StoppedDragging,
+        ],
+    );
+    assert_eq!(x_of(&app), 25.0);
+    assert!(app.dirty());
+
+    replay(&mut app, [Message::Undo]);
+    assert_eq!(x_of(&app), 0.0);
+    assert!(app.selection.is_empty());
+    replay(&mut app, [Message::Undo]);
+    assert!(app.document.shapes.is_empty());
+    // Nothing left to undo.
+    replay(&mut app, [Message::Undo]);
+    assert!(app.document.shapes.is_empty());
+
+    replay(&mut app, [Message::Redo, Message::Redo]);
+    assert_eq!(x_of(&app), 25.0);
+    // The index follows, so the line is picked where it is now.
+    assert_eq!(
+        app.index
+            .item_at(&app.document, kurbo::Point::new(45.0, 0.0)),
+        Some(Item::Shape(0))
+    );
+
+    // A new change drops what was undone.
+    replay(&mut app, [Message::Undo, Message::ToggleStereoDescriptors]);
+    replay(&mut app, [Message::Redo]);
+    assert_eq!(x_of(&app), 0.0);
+}
+
+#[test]
+fn tabs_keep_their_own_documents_and_views() {
+    let mut tabs = Tabs::default();
+    assert!(tabs.active().document.shapes.is_empty());
+    assert!(tabs.active().current_text_field.is_none());
+    let _ = tabs::update(&mut tabs, Message::NewTab);
+    assert_eq!(tabs.active, 1);
+    assert!(tabs.active().document.shapes.is_empty());
+
+    let line = drag(tabs.active(), Point::new(0.0, 0.0), Vector::new(40.0, 0.0));
+    for message in [Message::SelectedTool(Tool::Draw)].into_iter().chain(line) {
+        let _ = tabs::update(&mut tabs, message);
+    }
+    let _ = tabs::update(
+        &mut tabs,
+        Message::CanvasMoved(Point::ORIGIN, Vector::new(5.0, 0.0)),
+    );
+    assert_eq!(tabs.active().document.shapes.len(), 1);
+    assert_eq!(tabs.active().title(), "untitled •");
+
+    let _ = tabs::update(&mut tabs, Message::NextTab);
+    assert_eq!(tabs.active, 0);
+    assert_eq!(tabs.active().position, Point::ORIGIN);
+    assert!(!tabs.active().dirty());
+    let _ = tabs::update(&mut tabs, Message::PreviousTab);
+    assert_eq!(tabs.active, 1);
+    assert_eq!(tabs.active().position, Point::new(5.0, 0.0));
+}
+
+#[test]
+fn closing_a_changed_tab_asks_first() {
+    let mut tabs = Tabs::default();
+    let _ = tabs::update(&mut tabs, Message::NewTab);
+    let _ = tabs::update(&mut tabs, Message::ToggleStereoDescriptors);
+    let _ = tabs::update(&mut tabs, Message::SelectedTab(0));
+
+    let _ = tabs::update(&mut tabs, Message::CloseTab(1));
+    assert_eq!(
+        (tabs.tabs.len(), tabs.active, tabs.closing),
+        (2, 1, Some(1))
+    );
+    let _ = tabs::update(&mut tabs, Message::CancelClose);
+    assert_eq!((tabs.tabs.len(), tabs.closing), (2, None));
+
+    let _ = tabs::update(&mut tabs, Message::CloseTab(1));
+    let _ = tabs::update(&mut tabs, Message::DiscardAndClose);
+    assert_eq!((tabs.tabs.len(), tabs.active, tabs.closing), (1, 0, None));
+
+    // Unchanged tabs close right away, the last one leaving an empty tab.
+    let _ = tabs::update(&mut tabs, Message::CloseTab(0));
+    assert_eq!(tabs.tabs.len(), 1);
+    assert!(tabs.active().document.shapes.is_empty());
+}
+
+#[test]
+fn saving_writes_the_tab_file_and_clears_its_changes() {
+    let path = std::env::temp_dir().join(format!("molecular-{}.json", std::process::id()));
+    let mut tabs = Tabs::default();
+    let _ = tabs::update(&mut tabs, Message::NewTab);
+    tabs.active_mut().path = Some(path.clone());
+    ethanol(tabs.active_mut());
+    let _ = tabs::update(&mut tabs, Message::ToggleStereoDescriptors);
+    assert!(tabs.active().dirty());
+
+    let _ = tabs::update(&mut tabs, Message::CloseTab(1));
+    let _ = tabs::update(&mut tabs, Message::SaveAndClose);
+    assert_eq!(tabs.tabs.len(), 1);
+
+    let opened = crate::open(path.clone()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(opened.document.concepts.len(), 1);
+    assert!(opened.document.show_stereo_descriptors);
+    assert!(!opened.dirty());
+    assert_eq!(opened.title(), path.file_stem().unwrap().to_string_lossy());
+    assert_eq!(opened.index.len(), 1);
+}
+
+#[test]
+fn undoing_back_to_the_saved_document_has_no_changes() {
+    let path = std::env::temp_dir().join(format!("molecular-saved-{}.json", std::process::id()));
+    let mut tabs = Tabs::default();
+    tabs.active_mut().path = Some(path.clone());
+    let _ = tabs::update(&mut tabs, Message::ToggleStereoDescriptors);
+    let _ = tabs::update(&mut tabs, Message::Save);
+    std::fs::remove_file(&path).unwrap();
+    assert!(!tabs.active().dirty());
+
+    // Messages that leave the document alone are not changes to undo.
+    let _ = tabs::update(&mut tabs, Message::ToggleStereoDescriptors);
+    for message in [Message::ToggleStylePanel, Message::Copy, Message::Save] {
+        let _ = tabs::update(&mut tabs, message);
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert!(!tabs.active().dirty());
+    let _ = tabs::update(&mut tabs, Message::Undo);
+    assert!(tabs.active().document.show_stereo_descriptors);
+    assert!(tabs.active().dirty());
+    let _ = tabs::update(&mut tabs, Message::Redo);
+    assert!(!tabs.active().dirty());
+
+    // A change made after undoing past the save cannot get back to it.
+    let _ = tabs::update(&mut tabs, Message::Undo);
+    let _ = tabs::update(&mut tabs, Message::ToggleRulers(true));
+    let _ = tabs::update(&mut tabs, Message::ToggleRulers(false));
+    assert!(tabs.active().dirty());
+}
+
+#[test]
+fn closing_the_window_asks_about_every_changed_tab() {
+    let mut tabs = Tabs::default();
+    for _ in 0..2 {
+        let _ = tabs::update(&mut tabs, Message::NewTab);
+        let _ = tabs::update(&mut tabs, Message::ToggleStereoDescriptors);
+    }
+    let _ = tabs::update(&mut tabs, Message::SelectedTab(0));
+
+    let _ = tabs::update(&mut tabs, Message::CloseRequested);
+    assert_eq!((tabs.active, tabs.closing), (1, Some(1)));
+    let _ = tabs::update(&mut tabs, Message::DiscardAndClose);
+    assert_eq!((tabs.tabs.len(), tabs.closing), (2, Some(1)));
+
+    // Cancelling keeps the window and the tab.
+    let _ = tabs::update(&mut tabs, Message::CancelClose);
+    assert!(!tabs.quitting);
+    let _ = tabs::update(&mut tabs, Message::CloseTab(0));
+    assert_eq!((tabs.tabs.len(), tabs.closing), (1, None));
+
+    let _ = tabs::update(&mut tabs, Message::CloseRequested);
+    let _ = tabs::update(&mut tabs, Message::DiscardAndClose);
+    assert!(tabs.quitting);
+    assert_eq!(tabs.closing, None);
+    assert!(tabs.tabs.iter().all(|tab| !tab.dirty()));
+}
+
+#[test]
+fn panning_redraws_the_document_only_past_the_drawn_region() {
+    let viewport = |x: f64, y: f64| kurbo::Rect::from_origin_size((x, y), (800.0, 600.0));
+    let region = drawn_region(viewport(-390.0, -290.0));
+    for step in 0..200 {
+        let (x, y) = (step as f64 * 37.0 - 3000.0, step as f64 * -23.0 + 2000.0);
+        let drawn = drawn_region(viewport(x, y));
+        assert_eq!(drawn.union(viewport(x, y)), drawn, "{x} {y}");
+    }
+    // A short pan stays within what was drawn.
+    assert_eq!(drawn_region(viewport(-300.0, -250.0)), region);
+    assert_ne!(drawn_region(viewport(100.0, -290.0)), region);
+}
+
+#[test]
+fn failed_actions_are_noticed_until_the_next_one() {
+    let mut app = blank();
+    replay(
+        &mut app,
+        [Message::SelectedTool(Tool::Decorate(
+            DecorationKind::Radical,
+        ))],
+    );
+    let messages = click(&app, Point::new(0.0, 0.0));
+    replay(&mut app, messages);
+    assert_eq!(app.notice.as_deref(), Some("No atom to decorate here"));
+
+    // Moving the mouse leaves it, the next action replaces it.
+    replay(
+        &mut app,
+        [Message::CanvasMoved(Point::ORIGIN, Vector::new(5.0, 0.0))],
+    );
+    assert!(app.notice.is_some());
+    replay(&mut app, [Message::AssembleReaction]);
+    assert_eq!(
+        app.notice.as_deref(),
+        Some("No reaction arrow to assemble around")
+    );
+    replay(&mut app, [Message::SelectedTool(Tool::Pick)]);
+    assert_eq!(app.notice, None);
+
+    // A resolution that cannot be exported at keeps the last one.
+    replay(&mut app, [Message::ExportDpiChanged("300".to_string())]);
+    for dpi in ["", "0", "high"] {
+        replay(&mut app, [Message::ExportDpiChanged(dpi.to_string())]);
+        assert_eq!(app.document.export_dpi, 300);
+        assert!(app.notice.as_deref().unwrap().contains("300 dpi"));
+    }
+
+    let missing = std::env::temp_dir().join("molecular-no-such-document.json");
+    let error = crate::open(missing).unwrap_err();
+    assert!(error.contains("molecular-no-such-document.json"), "{error}");
+}
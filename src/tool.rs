@@ -1,12 +1,41 @@
-#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
+use molecular::arrow::ArrowKind;
+use molecular::figure::FigureKind;
+use molecular::molecule::DecorationKind;
+
+#[derive(Copy, Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub enum Tool {
+    #[default]
     Pick,
     Draw,
     Typing,
+    Arrow(ArrowKind),
+    Figure(FigureKind),
+    /// Adds or removes decorations on the atom clicked next to.
+    Decorate(DecorationKind),
+}
+
+/// What the pick tool is currently dragging.
+#[derive(Copy, Clone, Debug)]
+pub enum Drag {
+    /// One of the four points of a curved arrow in the shape storage, 0 being
+    /// the tail and 3 the head.
+    ArrowPoint { shape: usize, point: usize },
+    /// The selection, last seen under the cursor at `last`.
+    Move { last: kurbo::Point },
+    /// The rotation handle of the selection, turning it about `center`. The
+    /// cursor was last seen at `angle` from it.
+    Rotate { center: kurbo::Point, angle: f64 },
+    /// A rubber band selecting everything inside it.
+    Selection {
+        start: kurbo::Point,
+        end: kurbo::Point,
+    },
 }
 
-impl Default for Tool {
-    fn default() -> Self {
-        Self::Pick
-    }
+/// How far above the selection its rotation handle sits.
+const ROTATION_HANDLE_OFFSET: f64 = 20.0;
+
+/// Where the rotation handle of a selection with `bounds` is.
+pub fn rotation_handle(bounds: kurbo::Rect) -> kurbo::Point {
+    kurbo::Point::new(bounds.center().x, bounds.y0 - ROTATION_HANDLE_OFFSET)
 }
@@ -0,0 +1,165 @@
+//! Formatted text annotations: chemistry auto-formatting, editing of runs,
+//! the saved format and rendering.
+
+use iced::widget::canvas::Text;
+use molecular::Document;
+use molecular::annotation::{Annotation, Format, Script, chemistry_scripts, greek};
+use molecular::convert::{self, Format as FileFormat};
+use molecular::render::{Primitive, Scene};
+use molecular::shape::Shape;
+
+/// The parts of `content` [`chemistry_scripts`] gives `script`.
+fn scripted(content: &str, script: Script) -> Vec<&str> {
+    chemistry_scripts(content)
+        .into_iter()
+        .filter(|(_, s)| *s == script)
+        .map(|(range, _)| &content[range])
+        .collect()
+}
+
+fn annotation(content: &str) -> Annotation {
+    Annotation::new(Text {
+        content: content.to_string(),
+        ..Text::default()
+    })
+}
+
+#[test]
+fn formulas_get_subscripts() {
+    assert_eq!(scripted("CH2Cl2, 0 °C", Script::Sub), ["2", "2"]);
+    assert_eq!(scripted("(CH3)2CO", Script::Sub), ["3", "2"]);
+    assert_eq!(scripted("Pd(PPh3)4/Et3N", Script::Sub), ["3", "4", "3"]);
+    assert_eq!(scripted("2 H2O", Script::Sub), ["2"]);
+    assert_eq!(scripted("2H2O", Script::Sub), ["2"]);
+    // Words, numbers and temperatures are left alone.
+    assert!(chemistry_scripts("Step 1: reflux 12 h at 80 °C").is_empty());
+    assert!(chemistry_scripts("THF").is_empty());
+}
+
+#[test]
+fn charges_and_unit_exponents_get_superscripts() {
+    assert_eq!(scripted("Fe3+", Script::Super), ["3+"]);
+    assert_eq!(scripted("SO42-", Script::Super), ["2-"]);
+    assert_eq!(scripted("SO42-", Script::Sub), ["4"]);
+    assert_eq!(scripted("Na+", Script::Super), ["+"]);
+    assert_eq!(scripted("ΔG = 12 kcal mol-1", Script::Super), ["-1"]);
+}
+
+#[test]
+fn greek_letters_follow_the_symbol_keyboard() {
+    assert_eq!(greek('a'), Some('α'));
+    assert_eq!(greek('D'), Some('Δ'));
+    assert_eq!(greek('μ'), Some('m'));
+    assert_eq!(greek('1'), None);
+}
+
+#[test]
+fn replacing_text_keeps_the_formatting_around_it() {
+    let mut text = annotation("CH2Cl2");
+    text.apply_chemistry();
+    text.replace(0..1, "Br", Format::default());
+    text.set_format(0..2, |format| format.bold = true);
+
+    let runs = text.runs();
+    let pieces: Vec<(&str, bool, Script)> = runs
+        .iter()
+        .map(|run| (run.text.as_str(), run.format.bold, run.format.script))
+        .collect();
+    assert_eq!(
+        pieces,
+        [
+            ("Br", true, Script::Normal),
+            ("H", false, Script::Normal),
+            ("2", false, Script::Sub),
+            ("Cl", false, Script::Normal),
+            ("2", false, Script::Sub),
+        ]
+    );
+
+    // Formatting everything back to plain drops the runs.
+    text.set_format(0..text.content().len(), |format| {
+        *format = Format::default()
+    });
+    assert!(text.runs.is_empty());
+}
+
+#[test]
+fn texts_saved_before_formatting_still_load() {
+    let saved = r#"{"Text": {
+        "content": "rt, 2 h",
+        "position": {"x": 10.0, "y": 20.0},
+        "max_width": 3.4028235e38,
+        "color": {"r": 0.0, "g": 0.0, "b": 0.0, "a": 1.0},
+        "size": 16.0,
+        "line_height": {"Relative": 1.3},
+        "font": {"weight": "Normal", "stretch": "Normal", "style": "Normal"},
+        "align_x": "Default",
+        "align_y": "Top",
+        "shaping": "Basic"
+    }}"#;
+    let Shape::Text(text) = serde_json::from_str(saved).unwrap() else {
+        panic!("expected a text");
+    };
+    assert_eq!(text.content(), "rt, 2 h");
+    assert!(text.runs.is_empty() && !text.auto_format);
+}
+
+#[test]
+fn formatting_survives_saving() {
+    let mut text = annotation("ΔG‡");
+    text.set_format(3..6, |format| format.script = Script::Super);
+    text.set_format(0..3, |format| {
+        format.italic = true;
+        format.color = Some(iced::Color::from_rgb(0.8, 0.1, 0.1));
+    });
+    text.auto_format = true;
+    let saved = serde_json::to_string(&Shape::Text(text.clone())).unwrap();
+    let Shape::Text(loaded) = serde_json::from_str(&saved).unwrap() else {
+        unreachable!()
+    };
+    assert_eq!(loaded.runs, text.runs);
+    assert!(loaded.auto_format);
+}
+
+#[test]
+fn scripts_are_smaller_and_shifted() {
+    let mut text = annotation("H2O");
+    text.apply_chemistry();
+    let mut scene = Scene::default();
+    scene.annotation(&text);
+
+    let sizes: Vec<(String, f64, f64)> = scene
+        .primitives
+        .iter()
+        .map(|primitive| match primitive {
+            Primitive::Text {
+                content,
+                size,
+                position,
+                ..
+            } => (content.clone(), *size, position.y),
+            _ => unreachable!(),
+        })
+        .collect();
+    let [(_, h, h_y), (_, two, two_y), (_, o, o_y)] = &sizes[..] else {
+        panic!("expected three runs, got {sizes:?}");
+    };
+    assert!(two < h && h == o);
+    assert!(two_y > h_y && h_y == o_y);
+}
+
+#[test]
+fn rendered_caption_matches_the_golden_file() {
+    let mut caption = annotation("CH2Cl2, 0 °C\nΔG‡ = 12 kcal mol-1");
+    caption.apply_chemistry();
+    caption.set_format(14..20, |format| format.italic = true);
+    let mut document = Document::default();
+    document.shapes.push(Shape::Text(caption));
+
+    let svg = String::from_utf8(convert::write(&document, &[], FileFormat::Svg).unwrap()).unwrap();
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/caption.svg");
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(path, &svg).unwrap();
+    }
+    assert_eq!(svg, std::fs::read_to_string(path).unwrap());
+}
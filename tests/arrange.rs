@@ -0,0 +1,134 @@
+//! Aligning, distributing, rotating and flipping selections.
+
+mod common;
+
+use common::close;
+use kurbo::{Point, Rect};
+use molecular::cip;
+use molecular::concept::Concept;
+use molecular::io;
+use molecular::molecule::BondStereo;
+use molecular::shape::Shape;
+use molecular::{Alignment, Axis, Document, Item};
+
+/// A square of `size` with its top left corner at `(x, y)`.
+fn square(x: f64, y: f64, size: f64) -> Shape {
+    Shape::line(kurbo::Line::new((x, y), (x + size, y + size)))
+}
+
+fn bounds(document: &Document, item: Item) -> Rect {
+    document.bounds(item).unwrap()
+}
+
+/// Equal as far as the single precision positions of texts go.
+#[test]
+fn aligning_lines_up_edges_and_moves_groups_as_one() {
+    let mut document = Document::default();
+    document.add_shape(square(0.0, 0.0, 10.0));
+    document.add_shape(square(50.0, 30.0, 20.0));
+    document.add_shape(square(80.0, 60.0, 10.0));
+    let items = [Item::Shape(0), Item::Shape(1), Item::Shape(2)];
+    document.group(&[Item::Shape(1), Item::Shape(2)]);
+
+    document.align(&items, Alignment::Left);
+    let lefts: Vec<f64> = items.iter().map(|i| bounds(&document, *i).x0).collect();
+    // The group keeps its own layout, only its left edge lines up.
+    assert!(close(lefts[0], lefts[1]));
+    assert!(close(lefts[2] - lefts[1], 30.0));
+
+    document.ungroup(&items);
+    document.align(&items, Alignment::Middle);
+    let middle = bounds(&document, Item::Shape(0)).center().y;
+    for item in items {
+        assert!(close(bounds(&document, item).center().y, middle));
+    }
+}
+
+#[test]
+fn distributing_leaves_equal_gaps() {
+    let mut document = Document::default();
+    document.add_shape(square(0.0, 0.0, 10.0));
+    document.add_shape(square(100.0, 0.0, 10.0));
+    document.add_shape(square(15.0, 0.0, 30.0));
+    let items = [Item::Shape(0), Item::Shape(1), Item::Shape(2)];
+    let [start, end, _] = items.map(|item| bounds(&document, item));
+
+    document.distribute(&items, Axis::Horizontal);
+    let [first, last, middle] = items.map(|item| bounds(&document, item));
+    assert_eq!((first, last), (start, end));
+    assert!(close(middle.x0 - first.x1, last.x0 - middle.x1));
+    assert!(close(middle.y0, first.y0));
+}
+
+#[test]
+fn rotating_turns_about_the_given_center() {
+    let mut document = Document::default();
+    document.add_shape(Shape::line(kurbo::Line::new((0.0, 0.0), (20.0, 0.0))));
+    document.rotate(
+        &[Item::Shape(0)],
+        std::f64::consts::FRAC_PI_2,
+        Point::new(10.0, 0.0),
+    );
+
+    let Shape::Line { line, .. } = &document.shapes[0] else {
+        unreachable!()
+    };
+    assert!((line.p0 - Point::new(10.0, -10.0)).hypot() < 1e-9);
+    assert!((line.p1 - Point::new(10.0, 10.0)).hypot() < 1e-9);
+}
+
+#[test]
+fn flipping_swaps_wedges_and_keeps_the_configuration() {
+    let mut document = Document::default();
+    let alanine = io::smiles::parse("N[C@@H](C)C(=O)O").unwrap();
+    document.import(vec![Concept::Molecule(alanine)]);
+    let molecule = |document: &Document| document.concepts[0].molecules()[0].clone();
+    let before = molecule(&document);
+    let stereo = |molecule: &molecular::molecule::Molecule| -> Vec<BondStereo> {
+        molecule.bonds.iter().map(|bond| bond.stereo).collect()
+    };
+    let wedged = stereo(&before)
+        .iter()
+        .position(|stereo| *stereo != BondStereo::None)
+        .expect("no wedge");
+    let bounds_before = bounds(&document, Item::Concept(0));
+
+    for axis in [Axis::Horizontal, Axis::Vertical] {
+        document.flip(&[Item::Concept(0)], axis);
+        let flipped = molecule(&document);
+        assert_ne!(stereo(&flipped)[wedged], stereo(&before)[wedged]);
+        assert_eq!(cip::assign(&flipped), cip::assign(&before));
+        assert!(close(
+            bounds(&document, Item::Concept(0)).center().x,
+            bounds_before.center().x
+        ));
+
+        document.flip(&[Item::Concept(0)], axis);
+        let back = molecule(&document);
+        assert_eq!(stereo(&back), stereo(&before));
+        for (atom, original) in back.atoms.iter().zip(&before.atoms) {
+            assert!((atom.position - original.position).hypot() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn texts_stay_upright_when_flipped() {
+    let mut document = Document::default();
+    document.add_shape(square(0.0, 0.0, 10.0));
+    let text = molecular::annotation::Annotation::new(iced::widget::canvas::Text {
+        content: "hv".to_string(),
+        position: iced::Point::new(100.0, 0.0),
+        ..Default::default()
+    });
+    document.add_shape(Shape::Text(text));
+    let items = [Item::Shape(0), Item::Shape(1)];
+    let all = document.bounds_of(&items).unwrap();
+    let width = bounds(&document, Item::Shape(1)).width();
+
+    document.flip(&items, Axis::Horizontal);
+    let text = bounds(&document, Item::Shape(1));
+    assert!(close(text.width(), width));
+    assert!(close(text.x0, all.x0));
+    assert!(close(bounds(&document, Item::Shape(0)).x1, all.x1));
+}
@@ -0,0 +1,55 @@
+//! CIP descriptors of structures with a known configuration, read from the
+//! wedges and coordinates their SMILES are laid out with.
+
+use molecular::cip::{self, Descriptor, StereoUnit};
+use molecular::io::smiles;
+
+fn descriptors(text: &str) -> Vec<Option<Descriptor>> {
+    let molecule = smiles::parse(text).unwrap();
+    cip::assign(&molecule)
+        .iter()
+        .map(StereoUnit::descriptor)
+        .collect()
+}
+
+#[test]
+fn stereocenters_are_r_or_s() {
+    // L-alanine, written from either end.
+    assert_eq!(descriptors("N[C@@H](C)C(=O)O"), [Some(Descriptor::S)]);
+    assert_eq!(descriptors("C[C@H](N)C(=O)O"), [Some(Descriptor::S)]);
+    assert_eq!(descriptors("N[C@H](C)C(=O)O"), [Some(Descriptor::R)]);
+
+    // Four halogens, ranked by atomic number alone.
+    assert_eq!(descriptors("Br[C@](Cl)(F)I"), [Some(Descriptor::R)]);
+    assert_eq!(descriptors("Br[C@@](Cl)(F)I"), [Some(Descriptor::S)]);
+}
+
+#[test]
+fn double_bonds_are_e_or_z() {
+    assert_eq!(descriptors("F/C=C/F"), [Some(Descriptor::E)]);
+    assert_eq!(descriptors("F/C=C\\F"), [Some(Descriptor::Z)]);
+    // Bromine outranks the methyl group on the same carbon.
+    assert_eq!(descriptors("C/C(Br)=C/C"), [Some(Descriptor::Z)]);
+}
+
+#[test]
+fn rings_and_double_bonds_rank_with_duplicate_atoms() {
+    // (R)-limonene: the two ring branches differ only where one reaches the
+    // ring double bond, whose duplicated carbon decides.
+    assert_eq!(
+        descriptors("CC1=CC[C@@H](CC1)C(=C)C"),
+        [Some(Descriptor::R)]
+    );
+    assert_eq!(descriptors("CC1=CC[C@H](CC1)C(=C)C"), [Some(Descriptor::S)]);
+}
+
+#[test]
+fn undefined_and_missing_stereo_is_told_apart() {
+    assert_eq!(descriptors("NC(C)C(=O)O"), [None]);
+    // Double bonds are read from the drawing, which the layout always defines.
+    assert_eq!(descriptors("FC=CF").len(), 1);
+    // Equal substituents make no stereogenic unit at all.
+    assert!(descriptors("CC(C)O").is_empty());
+    assert!(descriptors("C1CCCCC1").is_empty());
+    assert!(descriptors("CC=C(C)C").is_empty());
+}
@@ -0,0 +1,26 @@
+//! Fixtures and comparisons shared by the integration tests. Each test file
+//! uses only some of them.
+#![allow(dead_code)]
+
+use kurbo::Point;
+use molecular::Document;
+use molecular::concept::Concept;
+use molecular::io;
+
+/// Whether `a` and `b` are equal up to rounding. Texts are placed in `f32`,
+/// so their bounds only agree to about a thousandth.
+pub fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-3
+}
+
+/// Whether `a` and `b` are the same point up to rounding.
+pub fn close_points(a: Point, b: Point) -> bool {
+    a.distance(b) < 1e-9
+}
+
+/// A document holding ethanol.
+pub fn ethanol() -> Document {
+    let mut document = Document::default();
+    document.import(vec![Concept::Molecule(io::smiles::parse("CCO").unwrap())]);
+    document
+}
@@ -0,0 +1,56 @@
+//! The `convert` subcommand's handling of its arguments.
+
+use molecular::convert::{self, Error};
+
+#[test]
+fn bad_arguments_are_explained() {
+    for (args, problem) in [
+        (
+            &["a.smi", "b.png", "--to", "gif"][..],
+            "unknown format `gif`",
+        ),
+        (&["a.smi", "b.png", "--from", "xyz"], "unknown format `xyz`"),
+        (&["a.smi", "b.png", "--to"], "--to needs a value"),
+        (&["a.smi", "b.png", "--dpi"], "--dpi needs a value"),
+        (
+            &["a.smi", "b.png", "--dpi", "high"],
+            "`high` is not a resolution",
+        ),
+        (
+            &["a.smi", "b.png", "--dpi", "-300"],
+            "`-300` is not a resolution",
+        ),
+        (&["a.smi", "b.png", "--dpi", "0"], "`0` is not a resolution"),
+        (
+            &["a.smi", "b.png", "--style", "nature"],
+            "unknown style `nature`",
+        ),
+        (&["a.smi", "b.png", "--page", "B5"], "unknown paper `B5`"),
+        (
+            &["a.smi", "b.png", "--verbose"],
+            "unknown option `--verbose`",
+        ),
+        (&["a.smi"], "expected an input and an output"),
+        (
+            &["a.smi", "b.png", "c.svg"],
+            "expected an input and an output",
+        ),
+        (&["-", "b.png"], "cannot tell the format of `-`"),
+        (&["a.smi", "-"], "cannot tell the format of `-`"),
+        (&["a.smi", "b.gif"], "cannot tell the format of `b.gif`"),
+    ] {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        match convert::run(&args) {
+            Err(Error::Unsupported(message)) => {
+                assert!(message.contains(problem), "{args:?}: {message}");
+            }
+            other => panic!("{args:?}: expected an error about the arguments, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn missing_input_files_are_io_errors() {
+    let args = ["no-such-file.smi", "out.svg"].map(String::from);
+    assert!(matches!(convert::run(&args), Err(Error::Io(_))));
+}
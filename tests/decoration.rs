@@ -0,0 +1,163 @@
+//! Lone pairs, radicals, circled charges and orbitals on atoms, and what
+//! they do to valences.
+
+use kurbo::{Point, Vec2};
+use molecular::arrow::Anchor;
+use molecular::concept::Concept;
+use molecular::io;
+use molecular::molecule::{DECORATION_DISTANCE, Decoration, DecorationKind, Molecule, Orbital};
+use molecular::render::Primitive;
+use molecular::{Document, Item};
+
+fn document(smiles: &str) -> Document {
+    let mut document = Document::default();
+    document.import(vec![Concept::Molecule(io::smiles::parse(smiles).unwrap())]);
+    document
+}
+
+fn molecule(document: &Document) -> &Molecule {
+    document.concepts[0].molecules()[0]
+}
+
+/// The point `DECORATION_DISTANCE` from `atom` at `angle`.
+fn beside(document: &Document, atom: usize, angle: f64) -> Point {
+    molecule(document).atoms[atom].position + Vec2::from_angle(angle) * DECORATION_DISTANCE
+}
+
+/// The angle from `atom` pointing away from its neighbors.
+fn outside(document: &Document, atom: usize) -> f64 {
+    let molecule = molecule(document);
+    let center = molecule.atoms[atom].position;
+    let away = molecule
+        .neighbors(atom)
+        .fold(Vec2::ZERO, |away, (_, neighbor)| {
+            away + (center - molecule.atoms[neighbor].position)
+        });
+    away.atan2()
+}
+
+/// Text of the labels drawn for `document`.
+fn labels(document: &Document) -> Vec<String> {
+    document
+        .scene(&[])
+        .primitives
+        .into_iter()
+        .filter_map(|primitive| match primitive {
+            Primitive::Text { content, .. } => Some(content),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn radicals_and_extra_lone_pairs_take_the_place_of_hydrogens() {
+    let mut methyl = document("C");
+    assert_eq!(molecule(&methyl).implicit_hydrogens(0), 4);
+    assert!(methyl.decorate(beside(&methyl, 0, 0.0), DecorationKind::Radical));
+    assert_eq!(molecule(&methyl).implicit_hydrogens(0), 3);
+
+    let mut carbene = document("C");
+    carbene.decorate(beside(&carbene, 0, 0.0), DecorationKind::LonePair);
+    assert_eq!(molecule(&carbene).implicit_hydrogens(0), 2);
+
+    // Ammonia has a lone pair anyway, drawing it changes nothing.
+    let mut ammonia = document("N");
+    ammonia.decorate(beside(&ammonia, 0, 0.0), DecorationKind::LonePair);
+    assert_eq!(molecule(&ammonia).implicit_hydrogens(0), 3);
+
+    let mut hydroxyl = document("O");
+    for angle in [0.0, 2.0] {
+        hydroxyl.decorate(beside(&hydroxyl, 0, angle), DecorationKind::LonePair);
+    }
+    hydroxyl.decorate(beside(&hydroxyl, 0, 4.0), DecorationKind::Radical);
+    assert_eq!(molecule(&hydroxyl).implicit_hydrogens(0), 1);
+}
+
+#[test]
+fn circled_charges_are_charges_drawn_outside_the_label() {
+    let mut document = document("CNC");
+    let point = beside(&document, 1, outside(&document, 1));
+    document.decorate(point, DecorationKind::Charge(1));
+    let nitrogen = &molecule(&document).atoms[1];
+    assert_eq!(nitrogen.charge, 1);
+    assert_eq!(nitrogen.circled_charge(), 1);
+    assert_eq!(molecule(&document).implicit_hydrogens(1), 2);
+
+    assert_eq!(labels(&document), ["NH2"]);
+
+    // Clicking it again takes the charge away with it.
+    document.decorate(point, DecorationKind::Charge(1));
+    let nitrogen = &molecule(&document).atoms[1];
+    assert_eq!((nitrogen.charge, nitrogen.decorations.len()), (0, 0));
+}
+
+#[test]
+fn extreme_charges_are_labelled_without_overflowing() {
+    for (smiles, sign) in [("[Cl-128]", "128-"), ("[Cl+127]", "127+")] {
+        let labels = labels(&document(smiles));
+        assert!(
+            labels.iter().any(|label| label.ends_with(sign)),
+            "{labels:?}"
+        );
+    }
+
+    // More circled charges than an atom can hold add up to the most it can.
+    let mut document = document("[Cl-128]");
+    let chlorine = &mut document.concepts[0].molecules_mut()[0].atoms[0];
+    for _ in 0..200 {
+        chlorine.decorations.push(Decoration {
+            kind: DecorationKind::Charge(-1),
+            angle: 0.0,
+        });
+    }
+    assert_eq!(chlorine.circled_charge(), -128);
+    assert_eq!(labels(&document), ["Cl"]);
+}
+
+#[test]
+fn decorations_survive_saving_and_turn_with_their_atom() {
+    let mut document = document("CC");
+    for kind in [
+        DecorationKind::Orbital(Orbital::P),
+        DecorationKind::Orbital(Orbital::Sp3),
+        DecorationKind::Orbital(Orbital::S),
+    ] {
+        document.decorate(beside(&document, 0, outside(&document, 0)), kind);
+    }
+    let saved = serde_json::to_string(&document).unwrap();
+    let loaded: Document = serde_json::from_str(&saved).unwrap();
+    assert_eq!(
+        molecule(&loaded).atoms[0].decorations,
+        molecule(&document).atoms[0].decorations
+    );
+
+    let center = molecule(&document).atoms[0].position;
+    document.concepts[0].transform(
+        kurbo::Affine::translate(center.to_vec2())
+            * kurbo::Affine::rotate(1.0)
+            * kurbo::Affine::translate(-center.to_vec2()),
+    );
+    let turned = outside(&document, 0);
+    for Decoration { angle, .. } in &molecule(&document).atoms[0].decorations {
+        assert!((angle - turned).abs() < 1e-9);
+    }
+    assert!(document.bounds(Item::Concept(0)).is_some());
+}
+
+#[test]
+fn curved_arrows_attach_to_drawn_electrons_on_carbon() {
+    let mut document = document("C=C");
+    let angle = outside(&document, 0);
+    let pair = beside(&document, 0, angle);
+    document.decorate(pair, DecorationKind::LonePair);
+
+    let nearby = pair + Vec2::from_angle(angle + 1.0) * 3.0;
+    let Some(Anchor::LonePair {
+        atom, angle: at, ..
+    }) = Anchor::at(&document.concepts, nearby)
+    else {
+        panic!("no lone pair near {nearby:?}");
+    };
+    assert_eq!(atom, 0);
+    assert!((at - angle).abs() < 1e-9);
+}
@@ -0,0 +1,254 @@
+//! The document model through the public API, with golden files for the
+//! saved document format and for rendered output.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the golden files after an intended
+//! change, and review the diff.
+
+use std::path::PathBuf;
+
+use kurbo::Point;
+use molecular::arrow::{Arrow, ArrowHead, ArrowKind};
+use molecular::concept::Concept;
+use molecular::convert::{self, Format};
+use molecular::io;
+use molecular::shape::Shape;
+use molecular::style::{Preset, Style};
+use molecular::{Document, Item};
+
+fn golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("cannot read {}: {e}", path.display()));
+    assert!(
+        actual == expected,
+        "{name} differs from its golden file, rerun with UPDATE_GOLDEN=1 if intended"
+    );
+}
+
+/// Alanine read from a molfile, with a curved arrow from its nitrogen.
+fn alanine() -> Document {
+    let text = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/golden/alanine.mol"
+    ))
+    .unwrap();
+    let mut document = Document::default();
+    document.import(convert::read_structures(&text, Format::Molfile).unwrap());
+
+    let nitrogen = document.concepts[0].molecules()[0]
+        .atoms
+        .iter()
+        .find(|atom| atom.element.symbol() == "N")
+        .unwrap()
+        .position;
+    let arrow = Arrow::anchored(
+        ArrowKind::Curved(ArrowHead::Full),
+        nitrogen,
+        nitrogen + kurbo::Vec2::new(40.0, -30.0),
+        &document.concepts,
+    );
+    document.shapes.push(Shape::Arrow(arrow));
+    document
+}
+
+#[test]
+fn saved_documents_match_the_golden_file() {
+    let document = alanine();
+    golden(
+        "alanine.json",
+        &serde_json::to_string_pretty(&document).unwrap(),
+    );
+}
+
+#[test]
+fn golden_documents_load_and_save_unchanged() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/alanine.json");
+    let saved = std::fs::read_to_string(path).unwrap();
+    let document: Document = serde_json::from_str(&saved).unwrap();
+
+    assert_eq!(document.concepts.len(), 1);
+    let [Shape::Arrow(arrow)] = &document.shapes[..] else {
+        panic!("expected the arrow");
+    };
+    assert!(arrow.tail_anchor.is_some());
+    assert_eq!(serde_json::to_string_pretty(&document).unwrap(), saved);
+}
+
+#[test]
+fn rendered_svg_matches_the_golden_file() {
+    let svg = convert::write(&alanine(), &[], Format::Svg).unwrap();
+    golden("alanine.svg", &String::from_utf8(svg).unwrap());
+}
+
+#[test]
+fn molfiles_round_trip_through_documents() {
+    let mut document = alanine();
+    document.set_style(Style::preset(Preset::Wiley));
+    let molfile = String::from_utf8(convert::write(&document, &[], Format::Molfile).unwrap());
+    let molecule = io::molfile::parse(&molfile.unwrap()).unwrap();
+    let original = &document.concepts[0].molecules()[0];
+
+    assert_eq!(molecule.atoms.len(), original.atoms.len());
+    assert_eq!(molecule.bonds.len(), original.bonds.len());
+    assert_eq!(
+        io::smiles::write(&molecule),
+        io::smiles::write(original),
+        "stereo and charges survive"
+    );
+}
+
+#[test]
+fn copied_fragments_keep_their_anchors() {
+    let mut document = alanine();
+    let fragment = document.fragment(&[Item::Concept(0), Item::Shape(0)]);
+    let pasted = document.paste(fragment, Point::new(300.0, 0.0));
+
+    assert_eq!(pasted, vec![Item::Concept(1), Item::Shape(1)]);
+    let Shape::Arrow(copy) = &document.shapes[1] else {
+        panic!("expected the copied arrow");
+    };
+    assert_eq!(copy.tail_anchor.map(|anchor| anchor.concept()), Some(1));
+}
+
+#[test]
+fn deleting_a_structure_drops_anchors_on_it() {
+    let mut document = alanine();
+    let extra = io::smiles::parse("CCO").unwrap();
+    document.concepts.insert(0, Concept::Molecule(extra));
+    if let Shape::Arrow(arrow) = &mut document.shapes[0] {
+        arrow.tail_anchor = arrow.tail_anchor.map(|anchor| anchor.shifted(1));
+    }
+
+    // Removing the unrelated structure renumbers the anchor...
+    document.delete(&[Item::Concept(0)]);
+    let Shape::Arrow(arrow) = &document.shapes[0] else {
+        unreachable!()
+    };
+    assert_eq!(arrow.tail_anchor.map(|anchor| anchor.concept()), Some(0));
+
+    // ...and removing the anchored one lets go of it.
+    document.delete(&[Item::Concept(0)]);
+    let Shape::Arrow(arrow) = &document.shapes[0] else {
+        unreachable!()
+    };
+    assert!(arrow.tail_anchor.is_none());
+}
+
+#[test]
+fn reactions_assemble_around_the_last_arrow() {
+    let mut document = Document::default();
+    let structures = convert::read_structures("CC=O\nCCO\n", Format::Smiles).unwrap();
+    document.import(structures);
+    let bounds = |index: usize| document.concepts[index].bounding_box().unwrap();
+    let (left, right) = (bounds(0), bounds(1));
+    let y = left.center().y;
+    document.shapes.push(Shape::Arrow(Arrow::new(
+        ArrowKind::Forward,
+        Point::new(left.x1 + 5.0, y),
+        Point::new(right.x0 - 5.0, y),
+    )));
+
+    assert!(document.assemble_reaction());
+    let [Concept::Reaction(reaction)] = &document.concepts[..] else {
+        panic!("expected one reaction, got {:?}", document.concepts);
+    };
+    assert_eq!(reaction.reactants.len(), 1);
+    assert_eq!(reaction.products.len(), 1);
+    assert!(!document.assemble_reaction());
+}
+
+#[test]
+fn reactions_leave_out_what_lies_past_the_arrow() {
+    let mut document = Document::default();
+    let structures = convert::read_structures("CC=O\nCCO\nCC(=O)O\n", Format::Smiles).unwrap();
+    document.import(structures);
+    // Three steps of a scheme in a row, with an arrow between each pair.
+    let step = document.style.bond_length * 8.0;
+    for index in 0..3 {
+        let center = document.concepts[index].bounding_box().unwrap().center();
+        document.translate(
+            Item::Concept(index),
+            Point::new(step * index as f64, 0.0) - center,
+        );
+    }
+    let bounds = |index: usize| document.concepts[index].bounding_box().unwrap();
+    let arrow = |from: usize, to: usize| {
+        Shape::Arrow(Arrow::new(
+            ArrowKind::Forward,
+            Point::new(bounds(from).x1 + 5.0, 0.0),
+            Point::new(bounds(to).x0 - 5.0, 0.0),
+        ))
+    };
+    document.shapes.push(arrow(0, 1));
+    document.shapes.push(arrow(1, 2));
+
+    assert!(document.assemble_reaction());
+    let reaction = document
+        .concepts
+        .iter()
+        .find_map(|concept| match concept {
+            Concept::Reaction(reaction) => Some(reaction),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(reaction.reactants.len(), 1);
+    assert_eq!(reaction.products.len(), 1);
+    assert_eq!(document.concepts.len(), 2);
+    assert!(matches!(&document.shapes[..], [Shape::Arrow(_)]));
+}
+
+#[test]
+fn reactions_leave_out_what_is_far_from_the_arrow() {
+    let mut document = Document::default();
+    let structures = convert::read_structures("CC=O\nCCO\nCl\n", Format::Smiles).unwrap();
+    document.import(structures);
+    let bounds = |index: usize| document.concepts[index].bounding_box().unwrap();
+    let (left, right) = (bounds(0), bounds(1));
+    let y = left.center().y;
+    let arrow = Arrow::new(
+        ArrowKind::Forward,
+        Point::new(left.x1 + 5.0, y),
+        Point::new(right.x0 - 5.0, y),
+    );
+    // Chloride far below the arrow, one label beside it and one far above.
+    let far = document.style.bond_length * 10.0;
+    let center = bounds(2).center();
+    document.translate(
+        Item::Concept(2),
+        Point::new(arrow.tail().x, y + far) - center,
+    );
+    let label = |y: f64| {
+        Shape::Text(molecular::annotation::Annotation::new(
+            iced::widget::canvas::Text {
+                content: "hv".to_string(),
+                position: iced::Point::new(arrow.tail().midpoint(arrow.head()).x as f32, y as f32),
+                ..Default::default()
+            },
+        ))
+    };
+    document.shapes.push(label(y - 20.0));
+    document.shapes.push(label(y - far));
+    document.shapes.push(Shape::Arrow(arrow));
+
+    assert!(document.assemble_reaction());
+    let reaction = document
+        .concepts
+        .iter()
+        .find_map(|concept| match concept {
+            Concept::Reaction(reaction) => Some(reaction),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(reaction.reactants.len(), 1);
+    assert_eq!(reaction.products.len(), 1);
+    assert_eq!(reaction.agents_above.len(), 1);
+    assert!(reaction.agents_below.is_empty());
+    assert_eq!(document.concepts.len(), 2);
+    assert!(matches!(&document.shapes[..], [Shape::Text(_)]));
+}
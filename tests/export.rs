@@ -0,0 +1,208 @@
+//! Images and vector files exported from documents, read back.
+
+mod common;
+
+use std::io::Cursor;
+
+use molecular::Document;
+use molecular::concept::Concept;
+use molecular::convert::{self, Format};
+use molecular::page::Page;
+use molecular::{font, io};
+
+fn ethanol(dpi: u32, transparent: bool) -> Document {
+    Document {
+        export_dpi: dpi,
+        transparent_export: transparent,
+        ..common::ethanol()
+    }
+}
+
+/// Pixels of a PNG, with its size and resolution in pixels per meter.
+fn decode(png: &[u8]) -> (png::OutputInfo, Option<png::PixelDimensions>, Vec<u8>) {
+    let mut reader = png::Decoder::new(Cursor::new(png)).read_info().unwrap();
+    let dimensions = reader.info().pixel_dims;
+    let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    pixels.truncate(info.buffer_size());
+    (info, dimensions, pixels)
+}
+
+/// The text between `start` and the end of its line.
+fn line_after<'a>(text: &'a str, start: &str) -> &'a str {
+    let at = text.find(start).unwrap_or_else(|| panic!("no {start}")) + start.len();
+    text[at..].lines().next().unwrap()
+}
+
+/// Byte offset of `needle` in `bytes` at or after `from`.
+fn find(bytes: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    bytes[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|at| from + at)
+}
+
+/// Contents of the stream of object `number`, inflated, with its dictionary.
+fn stream(pdf: &[u8], number: usize) -> (String, Vec<u8>) {
+    let start = find(pdf, format!("\n{number} 0 obj\n").as_bytes(), 0).unwrap();
+    let data = find(pdf, b"stream\n", start).unwrap() + b"stream\n".len();
+    let dictionary = String::from_utf8_lossy(&pdf[start..data]).into_owned();
+    let length: usize = line_after(&dictionary, "/Length ")
+        .split(' ')
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(pdf[data + length..].starts_with(b"\nendstream"));
+    let inflated = miniz_oxide::inflate::decompress_to_vec_zlib(&pdf[data..data + length]);
+    (dictionary, inflated.unwrap())
+}
+
+#[test]
+fn png_images_decode_at_their_resolution() {
+    let png = convert::write(&ethanol(96, false), &[], Format::Png).unwrap();
+    let (info, dimensions, pixels) = decode(&png);
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+    let dimensions = dimensions.unwrap();
+    assert_eq!(dimensions.unit, png::Unit::Meter);
+    assert_eq!(dimensions.xppu, 3780);
+
+    // White behind the drawing, and something dark drawn on it.
+    assert_eq!(&pixels[..4], [255, 255, 255, 255]);
+    assert!(
+        pixels
+            .chunks(4)
+            .any(|pixel| pixel[0] < 128 && pixel[3] == 255)
+    );
+
+    let double = convert::write(&ethanol(192, false), &[], Format::Png).unwrap();
+    let (large, dimensions, _) = decode(&double);
+    assert_eq!(dimensions.unwrap().xppu, 7559);
+    assert!(large.width.abs_diff(info.width * 2) <= 1);
+    assert!(large.height.abs_diff(info.height * 2) <= 1);
+}
+
+#[test]
+fn png_images_need_a_resolution() {
+    assert!(matches!(
+        convert::write(&ethanol(0, false), &[], Format::Png),
+        Err(convert::Error::Unsupported(_))
+    ));
+}
+
+#[test]
+fn transparent_png_images_keep_straight_alpha() {
+    let png = convert::write(&ethanol(96, true), &[], Format::Png).unwrap();
+    let (_, _, pixels) = decode(&png);
+    assert_eq!(pixels[3], 0, "nothing drawn in the corner");
+    // Partly covered edges are the drawing's color, not blended with white.
+    assert!(
+        pixels
+            .chunks(4)
+            .filter(|pixel| pixel[3] > 0 && pixel[3] < 255)
+            .all(|pixel| pixel[1] < 128)
+    );
+}
+
+#[test]
+fn eps_files_are_bounded_by_their_content_or_page() {
+    let document = ethanol(96, false);
+    let eps = String::from_utf8(convert::write(&document, &[], Format::Eps).unwrap()).unwrap();
+    assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n"));
+    assert!(eps.ends_with("showpage\n%%EOF\n"));
+    assert_eq!(
+        eps.matches("gsave").count(),
+        eps.matches("grestore").count()
+    );
+
+    // Points are three quarters of a document unit.
+    let extent = document.scene(&[]).extent();
+    let bounding_box = format!(
+        "0 0 {} {}",
+        (extent.width() * 0.75).ceil(),
+        (extent.height() * 0.75).ceil()
+    );
+    assert_eq!(line_after(&eps, "%%BoundingBox: "), bounding_box);
+    let high: Vec<f64> = line_after(&eps, "%%HiResBoundingBox: ")
+        .split(' ')
+        .map(|n| n.parse().unwrap())
+        .collect();
+    assert!((high[2] - extent.width() * 0.75).abs() < 0.01);
+    assert!((high[3] - extent.height() * 0.75).abs() < 0.01);
+
+    // A4 is 595.28 by 841.89 points.
+    let on_page = Document {
+        page: Some(Page::default()),
+        ..document
+    };
+    let eps = String::from_utf8(convert::write(&on_page, &[], Format::Eps).unwrap()).unwrap();
+    assert_eq!(line_after(&eps, "%%BoundingBox: "), "0 0 596 842");
+}
+
+#[test]
+fn pdf_cross_references_point_at_their_objects() {
+    let pdf = convert::write(&ethanol(96, false), &[], Format::Pdf).unwrap();
+    assert!(pdf.starts_with(b"%PDF-1.4\n"));
+    assert!(pdf.ends_with(b"%%EOF\n"));
+    let text = String::from_utf8_lossy(&pdf);
+
+    let xref: usize = line_after(&text, "startxref\n").parse().unwrap();
+    assert!(pdf[xref..].starts_with(b"xref\n0 "));
+    let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+    let count: usize = line_after(table, "xref\n0 ").parse().unwrap();
+    let entries: Vec<&str> = table.lines().skip(2).take(count).collect();
+    assert_eq!(entries[0], "0000000000 65535 f ");
+    for (number, entry) in entries.iter().enumerate().skip(1) {
+        assert!(entry.ends_with(" 00000 n "), "{entry}");
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(format!("{number} 0 obj\n").as_bytes()));
+    }
+
+    let trailer = line_after(table, "trailer\n");
+    assert!(trailer.contains(&format!("/Size {count} ")), "{trailer}");
+    let root: usize = line_after(trailer, "/Root ")
+        .split(' ')
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let catalog: usize = entries[root][..10].parse().unwrap();
+    let object = format!("{root} 0 obj\n<< /Type /Catalog /Pages ");
+    assert!(pdf[catalog..].starts_with(object.as_bytes()));
+}
+
+#[test]
+fn pdf_labels_are_set_in_the_embedded_font() {
+    let pdf = convert::write(&ethanol(96, false), &[], Format::Pdf).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.contains(&format!("/BaseFont /{} ", font::NAME)));
+    assert!(text.contains("/Resources << /Font << /F1 "));
+
+    let file: usize = line_after(&text, "/FontFile2 ")
+        .split(' ')
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let (dictionary, data) = stream(&pdf, file);
+    assert!(dictionary.contains(&format!("/Length1 {}", font::DATA.len())));
+    assert!(data == font::DATA, "the embedded font is the bundled one");
+
+    // The hydroxy label is shown as text, not drawn as outlines.
+    let contents: usize = line_after(&text, "/Contents ")
+        .split(' ')
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let (_, content) = stream(&pdf, contents);
+    assert!(String::from_utf8(content).unwrap().contains("(OH) Tj"));
+
+    // Nothing to label, nothing to embed.
+    let mut ethane = Document::default();
+    ethane.import(vec![Concept::Molecule(io::smiles::parse("CC").unwrap())]);
+    let pdf = convert::write(&ethane, &[], Format::Pdf).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(!text.contains("/FontFile2") && text.contains("/Resources << >>"));
+}
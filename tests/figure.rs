@@ -0,0 +1,121 @@
+//! Rectangles, ellipses, polygons and brackets: their geometry, saving them
+//! and drawing them.
+
+use kurbo::{Point, Rect};
+use molecular::Document;
+use molecular::convert::{self, Format};
+use molecular::figure::{BracketKind, Figure, FigureKind, Outline};
+use molecular::render::Primitive;
+use molecular::shape::Shape;
+
+const KINDS: [FigureKind; 7] = [
+    FigureKind::Rectangle,
+    FigureKind::RoundedRectangle,
+    FigureKind::Ellipse,
+    FigureKind::Polygon(6),
+    FigureKind::Brackets(BracketKind::Square),
+    FigureKind::Brackets(BracketKind::Round),
+    FigureKind::Brackets(BracketKind::Curly),
+];
+
+fn figure(kind: FigureKind) -> Figure {
+    Figure::new(kind, Point::new(60.0, 40.0), Point::new(0.0, 0.0))
+}
+
+#[test]
+fn figures_survive_saving() {
+    for kind in KINDS {
+        let mut figure = figure(kind);
+        if let Outline::Brackets { subscript, .. } = &mut figure.outline {
+            *subscript = "n".to_string();
+        }
+        let saved = serde_json::to_string(&Shape::Figure(figure.clone())).unwrap();
+        let Shape::Figure(loaded) = serde_json::from_str(&saved).unwrap() else {
+            panic!("{kind:?} did not load as a figure");
+        };
+        assert_eq!(loaded, figure);
+    }
+}
+
+#[test]
+fn figures_fill_the_box_they_are_drawn_in() {
+    let drawn = Rect::new(0.0, 0.0, 60.0, 40.0);
+    for kind in KINDS {
+        let mut bounds = Rect::ZERO;
+        for (i, path) in figure(kind).strokes().iter().enumerate() {
+            let path_bounds = kurbo::Shape::bounding_box(path);
+            bounds = if i == 0 {
+                path_bounds
+            } else {
+                bounds.union(path_bounds)
+            };
+        }
+        for (edge, expected) in [
+            (bounds.x0, drawn.x0),
+            (bounds.y0, drawn.y0),
+            (bounds.x1, drawn.x1),
+            (bounds.y1, drawn.y1),
+        ] {
+            assert!((edge - expected).abs() < 1e-6, "{kind:?}: {bounds:?}");
+        }
+    }
+}
+
+#[test]
+fn polygons_point_up_and_are_symmetric() {
+    let Outline::Polygon { points } = figure(FigureKind::Polygon(6)).outline else {
+        unreachable!()
+    };
+    assert_eq!(points.len(), 6);
+    assert!((points[0] - Point::new(30.0, 0.0)).hypot() < 1e-9);
+    assert!((points[3] - Point::new(30.0, 40.0)).hypot() < 1e-9);
+    for (left, right) in [(1, 5), (2, 4)] {
+        assert!((points[left].x + points[right].x - 60.0).abs() < 1e-9);
+        assert!((points[left].y - points[right].y).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn scaling_a_rounded_rectangle_scales_its_corners() {
+    let mut shape = Shape::Figure(figure(FigureKind::RoundedRectangle));
+    shape.transform(kurbo::Affine::scale(2.0));
+    let Shape::Figure(Figure {
+        outline: Outline::Rectangle {
+            rect,
+            corner_radius,
+        },
+        ..
+    }) = shape
+    else {
+        unreachable!()
+    };
+    assert_eq!(rect, Rect::new(0.0, 0.0, 120.0, 80.0));
+    assert_eq!(corner_radius, 16.0);
+}
+
+#[test]
+fn bracket_subscripts_are_set_after_the_right_bracket() {
+    let mut document = Document::default();
+    let mut brackets = figure(FigureKind::Brackets(BracketKind::Square));
+    if let Outline::Brackets { subscript, .. } = &mut brackets.outline {
+        *subscript = "n".to_string();
+    }
+    document.shapes.push(Shape::Figure(brackets));
+
+    let scene = document.scene(&[]);
+    let position = scene
+        .primitives
+        .iter()
+        .find_map(|primitive| match primitive {
+            Primitive::Text {
+                content, position, ..
+            } if content == "n" => Some(*position),
+            _ => None,
+        })
+        .expect("no subscript");
+    assert!(position.x > 60.0);
+    assert_eq!(position.y, 40.0);
+
+    let svg = String::from_utf8(convert::write(&document, &[], Format::Svg).unwrap()).unwrap();
+    assert!(svg.contains(">n</text>"));
+}
@@ -0,0 +1,93 @@
+//! Fonts named by documents: saving the names, loading font files and
+//! falling back to the bundled font.
+
+use iced::font::Family;
+use iced::widget::canvas::Text;
+use molecular::Document;
+use molecular::annotation::Annotation;
+use molecular::convert::{self, Format};
+use molecular::font;
+use molecular::shape::Shape;
+
+fn text_in(family: Family) -> Shape {
+    Shape::Text(Annotation::new(Text {
+        content: "rt, 2 h".to_string(),
+        font: iced::Font {
+            family,
+            ..iced::Font::default()
+        },
+        ..Text::default()
+    }))
+}
+
+fn family_of(shape: &Shape) -> Family {
+    match shape {
+        Shape::Text(annotation) => annotation.text.font.family,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn font_families_survive_saving() {
+    for family in [Family::Name("Source Serif 4"), Family::Monospace] {
+        let saved = serde_json::to_string(&text_in(family)).unwrap();
+        let loaded: Shape = serde_json::from_str(&saved).unwrap();
+        assert_eq!(family_of(&loaded), family);
+    }
+}
+
+#[test]
+fn texts_saved_without_a_family_are_sans_serif() {
+    let mut old = serde_json::to_value(text_in(Family::Name("Arial"))).unwrap();
+    old["Text"]["font"]
+        .as_object_mut()
+        .unwrap()
+        .remove("family");
+    let loaded: Shape = serde_json::from_value(old).unwrap();
+    assert_eq!(family_of(&loaded), Family::SansSerif);
+}
+
+#[test]
+fn missing_fonts_fall_back_to_the_bundled_font() {
+    let missing = font::resolve("No Such Sans");
+    assert!(missing.is_missing());
+    assert_eq!(missing.family, "No Such Sans");
+    assert_eq!(missing.typeface, font::bundled());
+
+    let bundled = font::resolve("fira sans");
+    assert!(!bundled.is_missing());
+    assert_eq!(bundled.typeface, font::bundled());
+}
+
+#[test]
+fn loading_a_font_makes_its_family_available() {
+    let typeface = font::load(font::DATA.to_vec()).unwrap();
+    assert_eq!(typeface.family(), "Fira Sans");
+    // A family that is loaded already keeps its first font.
+    assert_eq!(typeface, font::bundled());
+    assert!(font::families().contains(&"Fira Sans"));
+    assert!(font::loaded("FIRA SANS").is_some());
+
+    assert!(font::load(b"not a font".to_vec()).is_err());
+}
+
+#[test]
+fn documents_report_and_name_their_missing_fonts() {
+    let mut document = Document::default();
+    document.shapes.push(text_in(Family::Name("No Such Serif")));
+    document.shapes.push(text_in(Family::SansSerif));
+    assert_eq!(document.missing_fonts(), ["No Such Serif"]);
+
+    // Measured in the bundled font, so the same text in it is just as wide.
+    let [missing, bundled] = [0, 1].map(|index| {
+        document
+            .bounds(molecular::Item::Shape(index))
+            .unwrap()
+            .width()
+    });
+    assert_eq!(missing, bundled);
+
+    let svg = String::from_utf8(convert::write(&document, &[], Format::Svg).unwrap()).unwrap();
+    assert!(svg.contains(r#"font-family="'No Such Serif', 'Fira Sans', sans-serif""#));
+    assert!(svg.contains(r#"font-family="'Fira Sans', sans-serif""#));
+}
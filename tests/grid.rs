@@ -0,0 +1,147 @@
+//! Square and hexagonal grids, and snapping to them.
+
+mod common;
+
+use common::close_points;
+use kurbo::{Point, Rect, Vec2};
+use molecular::Document;
+use molecular::grid::{Grid, GridKind};
+use molecular::style::Style;
+
+fn hexagonal(spacing: f64) -> Grid {
+    Grid {
+        kind: GridKind::Hexagonal,
+        spacing,
+        ..Grid::default()
+    }
+}
+
+#[test]
+fn square_grids_snap_to_the_nearest_crossing() {
+    let grid = Grid {
+        spacing: 10.0,
+        ..Grid::default()
+    };
+    assert_eq!(grid.snap(Point::new(14.0, -6.0)), Point::new(10.0, -10.0));
+    assert_eq!(grid.snap(Point::new(16.0, 4.9)), Point::new(20.0, 0.0));
+    assert_eq!(
+        grid.snap_offset(Vec2::new(-3.0, 27.0)),
+        Vec2::new(0.0, 30.0)
+    );
+}
+
+#[test]
+fn hexagonal_grids_join_corners_a_bond_apart_at_120_degrees() {
+    let grid = hexagonal(20.0);
+    let corner = grid.snap(Point::new(1.0, 2.0));
+    assert!(close_points(corner, Point::ZERO));
+
+    // The three corners around any corner, found by snapping points a bond
+    // away in the directions of its sides.
+    let around: Vec<Point> = [90.0f64, 210.0, 330.0]
+        .map(|degrees| {
+            let direction = Vec2::from_angle(degrees.to_radians());
+            grid.snap(corner + direction * 19.0)
+        })
+        .into();
+    for neighbor in &around {
+        assert!((neighbor.distance(corner) - 20.0).abs() < 1e-9);
+    }
+    for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+        let angle = (around[a] - corner).angle() - (around[b] - corner).angle();
+        let angle = angle.to_degrees().rem_euclid(360.0);
+        assert!(
+            (angle - 120.0).abs() < 1e-9 || (angle - 240.0).abs() < 1e-9,
+            "{angle}"
+        );
+    }
+    // The lower corner has its neighbors the other way up.
+    let lower = around[0];
+    let above = grid.snap(lower + Vec2::new(0.0, -19.0));
+    assert!(close_points(above, corner));
+}
+
+#[test]
+fn snapped_moves_keep_grid_points_on_the_grid() {
+    for grid in [
+        Grid {
+            spacing: 15.0,
+            ..Grid::default()
+        },
+        hexagonal(15.0),
+    ] {
+        let corner = grid.snap(Point::new(31.0, 47.0));
+        for offset in [Vec2::new(13.0, -2.0), Vec2::new(-40.0, 22.5)] {
+            let moved = corner + grid.snap_offset(offset);
+            assert!(close_points(grid.snap(moved), moved), "{grid:?} {offset:?}");
+        }
+        // Short moves do not move at all.
+        assert_eq!(grid.snap_offset(Vec2::new(2.0, 1.0)), Vec2::ZERO);
+    }
+}
+
+#[test]
+fn lines_cross_the_rectangle_unless_too_dense_to_see() {
+    let rect = Rect::new(-50.0, -30.0, 120.0, 90.0);
+    for grid in [
+        Grid {
+            spacing: 20.0,
+            ..Grid::default()
+        },
+        hexagonal(20.0),
+    ] {
+        let lines = grid.lines(rect);
+        assert!(!lines.is_empty());
+        for line in &lines {
+            let bounds = Rect::from_points(line.p0, line.p1);
+            assert!(bounds.x1 >= rect.x0 && bounds.x0 <= rect.x1);
+            assert!(bounds.y1 >= rect.y0 && bounds.y0 <= rect.y1);
+            // Sides of hexagons run from corner to corner.
+            if grid.kind == GridKind::Hexagonal {
+                assert!(close_points(grid.snap(line.p0), line.p0));
+                assert!(close_points(grid.snap(line.p1), line.p1));
+                assert!((line.length() - 20.0).abs() < 1e-9);
+            }
+        }
+
+        let huge = Rect::new(0.0, 0.0, 1e7, 1e7);
+        assert!(grid.lines(huge).is_empty());
+        let broken = Grid {
+            spacing: 0.0,
+            ..grid
+        };
+        assert!(broken.lines(rect).is_empty());
+    }
+}
+
+#[test]
+fn grids_are_saved_with_the_document() {
+    let document = Document {
+        grid: Grid {
+            shown: true,
+            snap: true,
+            ..hexagonal(12.0)
+        },
+        ..Document::default()
+    };
+    let saved = serde_json::to_string(&document).unwrap();
+    let loaded: Document = serde_json::from_str(&saved).unwrap();
+    assert_eq!(loaded.grid, document.grid);
+
+    // Documents saved before grids, or with the default one, have none.
+    let saved = serde_json::to_value(Document::default()).unwrap();
+    assert!(saved.as_object().unwrap().get("grid").is_none());
+    let loaded: Document = serde_json::from_value(saved).unwrap();
+    assert!(loaded.grid.is_default());
+}
+
+#[test]
+fn changing_the_bond_length_scales_the_grid() {
+    let mut document = Document::default();
+    let bond_length = document.style.bond_length;
+    document.set_style(Style {
+        bond_length: bond_length * 2.0,
+        ..document.style.clone()
+    });
+    assert!((document.grid.spacing - bond_length * 2.0).abs() < 1e-9);
+}
@@ -0,0 +1,242 @@
+//! Reading and writing chemical file formats, malformed input included.
+
+use molecular::io::molfile::{self, Version};
+use molecular::io::{rxn, smiles};
+use molecular::molecule::Molecule;
+use molecular::reaction::{Agent, Reaction};
+
+const ESTERIFICATION: &str =
+    "[CH3:1][C:2](=O)[OH:3].[OH:4][CH2:5]C>[H+]>[CH3:1][C:2](=O)[O:4][CH2:5]C.[OH2:3]";
+
+fn maps(molecule: &Molecule) -> Vec<u32> {
+    let mut maps: Vec<u32> = molecule.atoms.iter().filter_map(|atom| atom.map).collect();
+    maps.sort();
+    maps
+}
+
+/// SMILES and atom maps of every molecule, by role.
+fn summary(reaction: &Reaction) -> Vec<(String, Vec<u32>)> {
+    let agents = reaction
+        .agents_above
+        .iter()
+        .chain(&reaction.agents_below)
+        .filter_map(|agent| match agent {
+            Agent::Molecule(molecule) => Some(molecule),
+            Agent::Text(_) => None,
+        });
+    reaction
+        .reactants
+        .iter()
+        .chain(agents)
+        .chain(&reaction.products)
+        .map(|molecule| (smiles::write(molecule), maps(molecule)))
+        .collect()
+}
+
+/// Every edit of one character of `text` to one of a few troublesome ones.
+fn edits(text: &str) -> impl Iterator<Item = String> + '_ {
+    let chars: Vec<char> = text.chars().collect();
+    (0..chars.len()).flat_map(move |i| {
+        let chars = chars.clone();
+        ['9', '-', '0', ' ', '\n'].into_iter().map(move |c| {
+            let mut edited = chars.clone();
+            edited[i] = c;
+            edited.into_iter().collect()
+        })
+    })
+}
+
+#[test]
+fn reaction_smiles_keep_their_atom_maps() {
+    let reaction = smiles::parse_reaction(ESTERIFICATION).unwrap();
+    assert_eq!((reaction.reactants.len(), reaction.products.len()), (2, 2));
+    assert_eq!(maps(&reaction.reactants[0]), [1, 2, 3]);
+    assert_eq!(maps(&reaction.products[0]), [1, 2, 4, 5]);
+
+    let written = smiles::write_reaction(&reaction);
+    let again = smiles::parse_reaction(&written).unwrap();
+    assert_eq!(summary(&again), summary(&reaction));
+    assert_eq!(smiles::write_reaction(&again), written);
+}
+
+#[test]
+fn rxn_files_keep_their_atom_maps() {
+    let reaction = smiles::parse_reaction(ESTERIFICATION).unwrap();
+    for version in [Version::V2000, Version::V3000] {
+        let written = rxn::write(&reaction, version);
+        let again = rxn::parse(&written).unwrap();
+        assert_eq!(summary(&again), summary(&reaction), "{version:?}");
+    }
+}
+
+#[test]
+fn malformed_smiles_are_errors() {
+    for text in [
+        "C(",
+        "C)",
+        "(C)",
+        "C1CC",
+        "C11",
+        "C12CC12",
+        "C%1",
+        "[C",
+        "[Xx]",
+        "[C@H2",
+        "[99999C]",
+        "[99999999999C]",
+        "[CH300]",
+        "[CH99999999999]",
+        "[C+200]",
+        "[C-99999999999]",
+        "[C:]",
+        "[C:99999999999]",
+        "C$",
+    ] {
+        assert!(smiles::parse(text).is_err(), "{text}");
+    }
+    let charges = format!("[C{}]", "+".repeat(128));
+    assert!(smiles::parse(&charges).is_err());
+    let charges = format!("[C{}]", "-".repeat(129));
+    assert!(smiles::parse(&charges).is_err());
+
+    for text in ["CC>O", "C>>>C", "C>(>C", "C1>>C1"] {
+        assert!(smiles::parse_reaction(text).is_err(), "{text}");
+    }
+}
+
+#[test]
+fn extreme_charges_are_written_back() {
+    let molecule = smiles::parse(&format!("[C{}]", "-".repeat(128))).unwrap();
+    assert_eq!(molecule.atoms[0].charge, -128);
+    assert_eq!(smiles::write(&molecule), "[C-128]");
+}
+
+#[test]
+fn truncated_molfiles_are_errors() {
+    let molecule = smiles::parse("C[C@@H](N)C(=O)[O-]").unwrap();
+    for version in [Version::V2000, Version::V3000] {
+        let written = molfile::write(&molecule, version);
+        let lines: Vec<&str> = written.lines().collect();
+        let properties = lines.iter().position(|line| line.starts_with("M  "));
+        for cut in 0..lines.len() {
+            let text = lines[..cut].join("\n");
+            // V3000 blocks have no counts to check against, only no panic.
+            if version == Version::V2000 && cut < properties.unwrap() {
+                assert!(molfile::parse(&text).is_err(), "{cut}");
+            } else {
+                let _ = molfile::parse(&text);
+            }
+        }
+    }
+}
+
+#[test]
+fn inconsistent_molfiles_are_errors() {
+    let molfile = |atoms: &str, bonds: &str, properties: &str| {
+        let atom_count = atoms.lines().count();
+        let bond_count = bonds.lines().count();
+        format!(
+            "\n\n\n{atom_count:>3}{bond_count:>3}  0  0  0  0  0  0  0  0999 V2000\n{atoms}{bonds}{properties}M  END\n"
+        )
+    };
+    let atom =
+        |symbol: &str, rest: &str| format!("    0.0000    0.0000    0.0000 {symbol:<3}{rest}\n");
+    let two = atom("C", "") + &atom("C", "");
+    let good = molfile(&two, "  1  2  1  0\n", "");
+    assert!(molfile::parse(&good).is_ok());
+
+    for text in [
+        // A bond from an atom to itself, and the same bond twice.
+        molfile(&two, "  2  2  1  0\n", ""),
+        molfile(&two, "  1  2  1  0\n  2  1  2  0\n", ""),
+        // Bonds to atoms that are not there.
+        molfile(&two, "  1  3  1  0\n", ""),
+        molfile(&two, "  0  1  1  0\n", ""),
+        // More atoms counted than listed.
+        good.replacen("  2  1", "  9  1", 1),
+        // A hydrogen lighter than nothing.
+        molfile(&atom("H", "-9"), "", ""),
+        // Charges and isotopes out of range.
+        molfile(&two, "", "M  CHG  1   1 200\n"),
+        molfile(&two, "", "M  ISO  1   1  -5\n"),
+        molfile(&two, "", "M  CHG  1   3   1\n"),
+        "\n\n\n".to_string(),
+        "garbage".to_string(),
+    ] {
+        assert!(molfile::parse(&text).is_err(), "{text}");
+    }
+
+    let v3000 = |atoms: &str, bonds: &str| {
+        format!(
+            "\n\n\n  0  0  0     0  0            999 V3000\nM  V30 BEGIN CTAB\nM  V30 BEGIN ATOM\n{atoms}M  V30 END ATOM\nM  V30 BEGIN BOND\n{bonds}M  V30 END BOND\nM  V30 END CTAB\nM  END\n"
+        )
+    };
+    let two = "M  V30 1 C 0 0 0 0\nM  V30 2 C 1.5 0 0 0\n";
+    assert!(molfile::parse(&v3000(two, "M  V30 1 1 1 2\n")).is_ok());
+    for text in [
+        v3000(two, "M  V30 1 1 2 2\n"),
+        v3000(two, "M  V30 1 1 1 2\nM  V30 2 1 2 1\n"),
+        v3000(two, "M  V30 1 1 1 3\n"),
+        v3000(two, "M  V30 1 9 1 2\n"),
+        v3000("M  V30 1 C 0 0 0 0 CHG=200\n", ""),
+        v3000("M  V30 1 C 0 0 0 0 MASS=-1\n", ""),
+        v3000("M  V30 1 C 0 0 0 0 VAL=300\n", ""),
+        v3000("M  V30 1 C 0 0 0 -1\n", ""),
+        v3000("M  V30 x C 0 0 0 0\n", ""),
+        v3000("M  V30 1 C 0 0\n", ""),
+    ] {
+        assert!(molfile::parse(&text).is_err(), "{text}");
+    }
+}
+
+#[test]
+fn truncated_rxn_files_are_errors() {
+    let reaction = smiles::parse_reaction(ESTERIFICATION).unwrap();
+    let written = rxn::write(&reaction, Version::V2000);
+    let lines: Vec<&str> = written.lines().collect();
+
+    // A molecule block ending before its counts line.
+    let last = lines.iter().rposition(|line| *line == "$MOL").unwrap();
+    for cut in last + 1..last + 5 {
+        assert!(rxn::parse(&lines[..cut].join("\n")).is_err(), "{cut}");
+    }
+    for cut in 0..lines.len() {
+        let _ = rxn::parse(&lines[..cut].join("\n"));
+    }
+    assert!(rxn::parse("").is_err());
+    assert!(rxn::parse("$RXN\n\n\n\n  x").is_err());
+    assert!(rxn::parse("$RXN\n\n\n\n  5  5\n$MOL\n").is_err());
+}
+
+#[test]
+fn edited_files_never_panic() {
+    let reaction = smiles::parse_reaction(ESTERIFICATION).unwrap();
+    let molecule = smiles::parse("[13CH3][C@@H](/C=C/[N+](=O)[O-])c1ccccc1Br").unwrap();
+    let texts = [
+        rxn::write(&reaction, Version::V2000),
+        rxn::write(&reaction, Version::V3000),
+        molfile::write(&molecule, Version::V2000),
+        molfile::write(&molecule, Version::V3000),
+    ];
+    for text in &texts {
+        for edited in edits(text) {
+            if let Ok(reaction) = rxn::parse(&edited) {
+                smiles::write_reaction(&reaction);
+            }
+            if let Ok(molecule) = molfile::parse(&edited) {
+                smiles::write(&molecule);
+                molfile::write(&molecule, Version::V2000);
+            }
+        }
+    }
+    for text in [ESTERIFICATION.to_string(), smiles::write(&molecule)] {
+        for edited in edits(&text) {
+            if let Ok(reaction) = smiles::parse_reaction(&edited) {
+                rxn::write(&reaction, Version::V3000);
+            }
+            if let Ok(molecule) = smiles::parse(&edited) {
+                molfile::write(&molecule, Version::V2000);
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Layers, groups and the order items are drawn in.
+
+use kurbo::{Point, Rect};
+use molecular::arrow::{Arrow, ArrowHead, ArrowKind};
+use molecular::concept::Concept;
+use molecular::convert::{self, Format};
+use molecular::io;
+use molecular::shape::Shape;
+use molecular::{Document, Item};
+
+fn line(x: f64) -> Shape {
+    Shape::line(kurbo::Line::new((x, 0.0), (x + 20.0, 0.0)))
+}
+
+/// Three lines side by side and a molecule, the last line on a second layer.
+fn layered() -> Document {
+    let mut document = Document::default();
+    document.add_shape(line(0.0));
+    document.add_shape(line(100.0));
+    document.import(vec![Concept::Molecule(io::smiles::parse("CCO").unwrap())]);
+    document.add_layer("Notes");
+    document.add_shape(line(200.0));
+    document
+}
+
+fn x_of(shape: &Shape) -> f64 {
+    match shape {
+        Shape::Line { line, .. } => line.p0.x,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn documents_saved_without_layers_have_everything_on_one() {
+    let mut saved = serde_json::to_value(layered()).unwrap();
+    let object = saved.as_object_mut().unwrap();
+    object.remove("layers");
+    object.remove("active_layer");
+    let loaded: Document = serde_json::from_value(saved).unwrap();
+
+    assert_eq!(loaded.layers.len(), 1);
+    assert_eq!(loaded.layer_of(Item::Shape(2)), 0);
+    assert_eq!(loaded.draw_order().len(), 4);
+}
+
+#[test]
+fn layers_are_drawn_in_order_and_survive_saving() {
+    let mut document = layered();
+    // The bottom layer draws its shapes below its concepts.
+    assert_eq!(
+        document.draw_order(),
+        [
+            Item::Shape(0),
+            Item::Shape(1),
+            Item::Concept(0),
+            Item::Shape(2)
+        ]
+    );
+    document.layers[1].locked = true;
+
+    let saved = serde_json::to_string(&document).unwrap();
+    let loaded: Document = serde_json::from_str(&saved).unwrap();
+    assert_eq!(loaded.layers, document.layers);
+    assert_eq!(loaded.active_layer, 1);
+}
+
+#[test]
+fn hidden_layers_are_not_drawn_and_locked_ones_not_picked() {
+    let mut document = layered();
+    let on_top = Point::new(210.0, 0.0);
+    assert_eq!(document.item_at(on_top), Some(Item::Shape(2)));
+
+    document.layers[1].locked = true;
+    assert_eq!(document.item_at(on_top), None);
+    {
+        let editable = document.editable();
+        assert!(editable(Item::Shape(0)) && !editable(Item::Shape(2)));
+    }
+    assert!(
+        !document
+            .items_within(Rect::new(-50.0, -50.0, 300.0, 50.0))
+            .contains(&Item::Shape(2))
+    );
+    let drawn = document.scene(&[]).primitives.len();
+    let exported = convert::write(&document, &[], Format::Svg).unwrap().len();
+
+    document.layers[1].hidden = true;
+    assert!(!document.draw_order().contains(&Item::Shape(2)));
+    assert!(document.scene(&[]).primitives.len() < drawn);
+    assert!(convert::write(&document, &[], Format::Svg).unwrap().len() < exported);
+}
+
+#[test]
+fn deleting_keeps_layers_and_groups_on_their_items() {
+    let mut document = layered();
+    document.group(&[Item::Shape(1), Item::Shape(2)]);
+    document.delete(&[Item::Shape(0)]);
+
+    assert_eq!(document.layer_of(Item::Shape(1)), 1);
+    assert_eq!(x_of(&document.shapes[1]), 200.0);
+    assert_eq!(
+        document.with_groups(&[Item::Shape(0)]),
+        [Item::Shape(0), Item::Shape(1)]
+    );
+
+    // A group of one is no group.
+    document.delete(&[Item::Shape(1)]);
+    assert!(document.groups.is_empty());
+}
+
+#[test]
+fn deleting_many_items_moves_the_rest_down() {
+    let mut document = Document::default();
+    for x in 0..20 {
+        document.add_shape(line(x as f64 * 100.0));
+    }
+    document.add_layer("Odd");
+    let odd: Vec<Item> = (1..20).step_by(2).map(Item::Shape).collect();
+    document.set_layer(&odd, 1);
+
+    let every_third: Vec<Item> = (0..20).step_by(3).map(Item::Shape).collect();
+    document.delete(&every_third);
+    assert_eq!(document.shapes.len(), 13);
+    for (index, shape) in document.shapes.iter().enumerate() {
+        let was = (x_of(shape) / 100.0) as usize;
+        assert_eq!(document.layer_of(Item::Shape(index)), was % 2, "{was}");
+    }
+    let on_layers: usize = document.layers.iter().map(|layer| layer.items.len()).sum();
+    assert_eq!(on_layers, 13);
+}
+
+#[test]
+fn groups_are_selected_together_until_ungrouped() {
+    let mut document = layered();
+    document.group(&[Item::Shape(0), Item::Concept(0)]);
+    assert_eq!(
+        document.with_groups(&[Item::Concept(0)]),
+        [Item::Concept(0), Item::Shape(0)]
+    );
+
+    document.ungroup(&[Item::Shape(0)]);
+    assert_eq!(
+        document.with_groups(&[Item::Concept(0)]),
+        [Item::Concept(0)]
+    );
+}
+
+#[test]
+fn bringing_to_front_reorders_within_the_layer() {
+    let mut document = layered();
+    document.group(&[Item::Shape(0), Item::Shape(2)]);
+    let moved = document.bring_to_front(&[Item::Shape(0)]);
+
+    assert_eq!(moved, [Item::Shape(2)]);
+    let order: Vec<f64> = document.shapes.iter().map(x_of).collect();
+    assert_eq!(order, [100.0, 200.0, 0.0]);
+    // Still on the bottom layer, so still below the notes.
+    assert_eq!(document.layer_of(Item::Shape(2)), 0);
+    assert_eq!(document.draw_order().last(), Some(&Item::Shape(1)));
+    assert_eq!(document.groups, [vec![Item::Shape(2), Item::Shape(1)]]);
+
+    let moved = document.send_to_back(&moved);
+    assert_eq!(moved, [Item::Shape(0)]);
+    assert_eq!(x_of(&document.shapes[0]), 0.0);
+}
+
+#[test]
+fn reordering_structures_keeps_arrows_on_their_atoms() {
+    let mut document = Document::default();
+    let structures = convert::read_structures("CCO\nCCN\n", Format::Smiles).unwrap();
+    document.import(structures);
+    let nitrogen = document.concepts[1].molecules()[0].atoms[2].position;
+    document.add_shape(Shape::Arrow(Arrow::anchored(
+        ArrowKind::Curved(ArrowHead::Full),
+        nitrogen,
+        nitrogen + kurbo::Vec2::new(30.0, -30.0),
+        &document.concepts,
+    )));
+
+    document.send_to_back(&[Item::Concept(1)]);
+    let Shape::Arrow(arrow) = &document.shapes[0] else {
+        unreachable!()
+    };
+    assert_eq!(arrow.tail_anchor.map(|anchor| anchor.concept()), Some(0));
+}
+
+#[test]
+fn new_items_go_on_the_active_layer() {
+    let mut document = layered();
+    document.active_layer = 0;
+    let item = document.add_shape(line(300.0));
+    assert_eq!(document.layer_of(item), 0);
+
+    document.active_layer = 1;
+    let fragment = document.fragment(&[Item::Concept(0)]);
+    let pasted = document.paste(fragment, Point::new(0.0, 100.0));
+    assert_eq!(document.layer_of(pasted[0]), 1);
+}
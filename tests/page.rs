@@ -0,0 +1,160 @@
+//! Paper sizes, margins and exports laid out on the page.
+
+mod common;
+
+use common::close;
+use molecular::concept::Concept;
+use molecular::convert::{self, Format};
+use molecular::io;
+use molecular::page::{Orientation, Page, Paper, UNITS_PER_MM};
+use molecular::shape::Shape;
+use molecular::{Document, Item};
+
+/// Width and height of an SVG image.
+fn svg_size(svg: &str) -> (f64, f64) {
+    let attribute = |name: &str| {
+        let start = svg.find(&format!(r#" {name}=""#)).unwrap() + name.len() + 3;
+        let end = start + svg[start..].find('"').unwrap();
+        svg[start..end].parse::<f64>().unwrap()
+    };
+    (attribute("width"), attribute("height"))
+}
+
+/// Ethanol in the middle of an A4 page and a line running off its edge.
+fn on_a4() -> Document {
+    let mut document = Document {
+        page: Some(Page::default()),
+        ..Document::default()
+    };
+    document.import(vec![Concept::Molecule(io::smiles::parse("CCO").unwrap())]);
+    let center = document.bounds(Item::Concept(0)).unwrap().center();
+    document.translate(Item::Concept(0), kurbo::Point::ZERO - center);
+    document.add_shape(Shape::line(kurbo::Line::new((0.0, 0.0), (500.0, 0.0))));
+    document
+}
+
+#[test]
+fn pages_are_the_size_of_their_paper() {
+    let page = Page::default();
+    let size = page.size();
+    assert!(close(size.width, 210.0 * UNITS_PER_MM));
+    assert!(close(size.height, 297.0 * UNITS_PER_MM));
+    assert_eq!(page.rect().center(), kurbo::Point::ZERO);
+
+    let landscape = Page {
+        orientation: Orientation::Landscape,
+        ..page
+    };
+    assert_eq!(landscape.size(), kurbo::Size::new(size.height, size.width));
+
+    let printable = page.printable();
+    assert!(close(printable.width(), 190.0 * UNITS_PER_MM));
+    assert!(close(printable.height(), 277.0 * UNITS_PER_MM));
+
+    // Margins cannot turn the printable area inside out.
+    let crammed = Page {
+        margin: 1e6,
+        ..page
+    };
+    assert!(crammed.printable().width() >= 0.0 && crammed.printable().height() >= 0.0);
+    assert_eq!(Paper::named("letter"), Some(Paper::Letter));
+    assert_eq!(Paper::named("B5"), None);
+}
+
+#[test]
+fn whole_documents_export_on_their_page() {
+    let document = on_a4();
+
+    let svg = String::from_utf8(convert::write(&document, &[], Format::Svg).unwrap()).unwrap();
+    let size = Page::default().size();
+    let (width, height) = svg_size(&svg);
+    assert!((width - size.width).abs() < 0.01 && (height - size.height).abs() < 0.01);
+    let corner = Page::default().rect().origin();
+    assert!(svg.contains(&format!(r#"viewBox="{:.2} {:.2}"#, corner.x, corner.y)));
+
+    // A4 is 595 by 842 points, 794 by 1123 pixels at 96 DPI.
+    let pdf = convert::write(&document, &[], Format::Pdf).unwrap();
+    assert!(String::from_utf8_lossy(&pdf).contains("/MediaBox [0 0 595.2"));
+    let png = convert::write(
+        &Document {
+            export_dpi: 96,
+            ..document.clone()
+        },
+        &[],
+        Format::Png,
+    )
+    .unwrap();
+    let dimension = |at: usize| u32::from_be_bytes(png[at..at + 4].try_into().unwrap());
+    assert_eq!((dimension(16), dimension(20)), (794, 1123));
+
+    // Selections are still cropped to what is selected.
+    let selected =
+        String::from_utf8(convert::write(&document, &[Item::Concept(0)], Format::Svg).unwrap())
+            .unwrap();
+    assert!(svg_size(&selected).0 < 200.0);
+}
+
+#[test]
+fn items_past_the_margins_are_off_the_page() {
+    let mut document = on_a4();
+    assert_eq!(document.off_page(), [Item::Shape(0)]);
+
+    document.page.as_mut().unwrap().orientation = Orientation::Landscape;
+    assert!(document.off_page().is_empty());
+
+    document.page = None;
+    document.add_shape(Shape::line(kurbo::Line::new((0.0, 0.0), (1e5, 0.0))));
+    assert!(document.off_page().is_empty());
+}
+
+#[test]
+fn pages_are_saved_with_the_document() {
+    let document = Document {
+        page: Some(Page {
+            paper: Paper::A0,
+            orientation: Orientation::Landscape,
+            margin: 0.0,
+        }),
+        ..Document::default()
+    };
+    let saved = serde_json::to_string(&document).unwrap();
+    let loaded: Document = serde_json::from_str(&saved).unwrap();
+    assert_eq!(loaded.page, document.page);
+
+    // Documents saved before pages, or without one, have an endless canvas.
+    let saved = serde_json::to_value(Document::default()).unwrap();
+    assert!(saved.as_object().unwrap().get("page").is_none());
+    let loaded: Document = serde_json::from_value(saved).unwrap();
+    assert_eq!(loaded.page, None);
+}
+
+#[test]
+fn the_command_line_lays_images_out_on_a_page() {
+    let directory = std::env::temp_dir().join(format!("molecular-page-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let input = directory.join("ethanol.smi");
+    let output = directory.join("ethanol.svg");
+    std::fs::write(&input, "CCO\n").unwrap();
+
+    let args = [
+        input.to_str().unwrap(),
+        output.to_str().unwrap(),
+        "--page",
+        "a5",
+        "--landscape",
+    ]
+    .map(String::from);
+    convert::run(&args).unwrap();
+    let svg = std::fs::read_to_string(&output).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let size = Page {
+        paper: Paper::A5,
+        orientation: Orientation::Landscape,
+        ..Page::default()
+    }
+    .size();
+    let (width, height) = svg_size(&svg);
+    assert!((width - size.width).abs() < 0.01 && (height - size.height).abs() < 0.01);
+    assert!(convert::run(&["a.smi", "b.svg", "--page", "B5"].map(String::from)).is_err());
+}
@@ -0,0 +1,190 @@
+//! The spatial index finds the same items as looking at all of them.
+
+use kurbo::{Point, Rect};
+use molecular::arrow::Anchor;
+use molecular::convert::{self, Format};
+use molecular::shape::Shape;
+use molecular::spatial::SpatialIndex;
+use molecular::{Document, Item};
+
+/// Molecules and short lines spread over a large grid.
+fn crowded() -> Document {
+    let mut document = Document::default();
+    let smiles: String = (0..60).map(|_| "CC(=O)O\n").collect();
+    document.import(convert::read_structures(&smiles, Format::Smiles).unwrap());
+    for (index, concept) in document.concepts.iter_mut().enumerate() {
+        let at = Point::new((index % 8) as f64 * 150.0, (index / 8) as f64 * 150.0);
+        let center = concept.bounding_box().unwrap().center();
+        concept.translate(at - center);
+    }
+    for i in 0..100 {
+        let start = Point::new((i % 10) as f64 * 97.0, (i / 10) as f64 * 113.0);
+        document.add_shape(Shape::line(kurbo::Line::new(start, start + (30.0, 10.0))));
+    }
+    document
+}
+
+#[test]
+fn queries_find_exactly_the_overlapping_items() {
+    let document = crowded();
+    let index = SpatialIndex::new(&document);
+    assert_eq!(index.len(), 160);
+
+    let rect = Rect::new(100.0, 200.0, 420.0, 390.0);
+    let mut expected: Vec<Item> = document
+        .draw_order()
+        .into_iter()
+        .filter(|item| {
+            let bounds = document.bounds(*item).unwrap();
+            bounds.intersect(rect).area() > 0.0
+        })
+        .collect();
+    let mut found = index.query(rect);
+    assert!(!found.is_empty() && found.len() < 40);
+    // Bottom first, like the document draws them.
+    let order = document.draw_order();
+    let positions: Vec<usize> = found
+        .iter()
+        .map(|item| order.iter().position(|i| i == item).unwrap())
+        .collect();
+    assert!(positions.is_sorted());
+
+    expected.sort_by_key(|item| format!("{item:?}"));
+    found.sort_by_key(|item| format!("{item:?}"));
+    assert_eq!(found, expected);
+}
+
+/// The topmost item under `point`, looking at every item.
+fn item_at(document: &Document, point: Point) -> Option<Item> {
+    if let Some(anchor) = Anchor::at(&document.concepts, point) {
+        return Some(Item::Concept(anchor.concept()));
+    }
+    document
+        .draw_order()
+        .into_iter()
+        .rev()
+        .filter(|item| matches!(item, Item::Shape(_)))
+        .find(|item| document.bounds(*item).unwrap().contains(point))
+}
+
+#[test]
+fn hit_testing_agrees_with_looking_at_everything() {
+    let document = crowded();
+    let index = SpatialIndex::new(&document);
+    let mut hits = 0;
+    for x in (0..1100).step_by(23) {
+        for y in (0..1100).step_by(29) {
+            let point = Point::new(x as f64, y as f64);
+            let hit = index.item_at(&document, point);
+            assert_eq!(hit, item_at(&document, point), "{point:?}");
+            hits += usize::from(hit.is_some());
+        }
+    }
+    assert!(hits > 0);
+
+    let rect = Rect::new(-50.0, -50.0, 500.0, 300.0);
+    let within: Vec<Item> = document
+        .draw_order()
+        .into_iter()
+        .filter(|item| rect.union(document.bounds(*item).unwrap()) == rect)
+        .collect();
+    assert_eq!(index.items_within(&document, rect), within);
+}
+
+#[test]
+fn large_items_are_found_anywhere_inside_them() {
+    let mut document = Document::default();
+    let far = Point::new(100_000.0, 50_000.0);
+    document.add_shape(Shape::line(kurbo::Line::new((0.0, 0.0), far)));
+    let index = SpatialIndex::new(&document);
+
+    let middle = Rect::from_center_size(far.midpoint(Point::ZERO), (10.0, 10.0));
+    assert_eq!(index.query(middle), [Item::Shape(0)]);
+    assert!(
+        index
+            .query(Rect::new(-100.0, -100.0, -50.0, -50.0))
+            .is_empty()
+    );
+}
+
+#[test]
+fn large_molecules_are_found_only_where_they_are_drawn() {
+    let mut document = Document::default();
+    let chain = "C".repeat(200);
+    document.import(convert::read_structures(&chain, Format::Smiles).unwrap());
+    // Along the diagonal its bounds cover far too many cells for the grid.
+    document.concepts[0].transform(kurbo::Affine::rotate(std::f64::consts::FRAC_PI_4));
+    let bounds = document.bounds(Item::Concept(0)).unwrap();
+    let index = SpatialIndex::new(&document);
+
+    for corner in [
+        Point::new(bounds.x1, bounds.y0),
+        Point::new(bounds.x0, bounds.y1),
+    ] {
+        let empty = Rect::from_center_size(corner, (100.0, 100.0));
+        assert!(index.query(empty).is_empty(), "{corner:?}");
+        assert_eq!(index.item_at(&document, corner), None);
+    }
+    let atom = document.concepts[0].molecules()[0].atoms[100].position;
+    assert_eq!(
+        index.query(Rect::from_center_size(atom, (1.0, 1.0))),
+        [Item::Concept(0)]
+    );
+    assert_eq!(index.item_at(&document, atom), Some(Item::Concept(0)));
+}
+
+#[test]
+fn hidden_layers_are_left_out() {
+    let mut document = crowded();
+    document.add_layer("Hidden");
+    let item = document.add_shape(Shape::line(kurbo::Line::new((0.0, 0.0), (9.0, 9.0))));
+    document.layers[1].hidden = true;
+
+    let index = SpatialIndex::new(&document);
+    assert_eq!(index.bounds(item), None);
+    assert!(!index.query(Rect::new(0.0, 0.0, 9.0, 9.0)).contains(&item));
+}
+
+#[test]
+fn updated_indexes_agree_with_new_ones() {
+    let mut document = crowded();
+    let mut index = SpatialIndex::new(&document);
+    let before = document.clone();
+    // Move a molecule far away, and one line onto the huge cells.
+    document.translate(Item::Concept(3), kurbo::Vec2::new(2000.0, -700.0));
+    if let Shape::Line { line, .. } = &mut document.shapes[7] {
+        line.p1 = Point::new(90_000.0, 40_000.0);
+    }
+    index.refresh(&before, &document);
+    let fresh = SpatialIndex::new(&document);
+
+    let everywhere = Rect::new(-1e5, -1e5, 1e5, 1e5);
+    assert_eq!(index.query(everywhere), fresh.query(everywhere));
+    for rect in [
+        Rect::new(0.0, 0.0, 300.0, 300.0),
+        Rect::new(1900.0, -400.0, 2600.0, 200.0),
+        Rect::new(50_000.0, 20_000.0, 50_100.0, 30_000.0),
+    ] {
+        assert_eq!(index.query(rect), fresh.query(rect), "{rect:?}");
+    }
+    assert_eq!(
+        index.bounds(Item::Concept(3)),
+        document.bounds(Item::Concept(3))
+    );
+
+    // Removed items are gone, the others keep their place.
+    index.remove(Item::Shape(7));
+    assert_eq!(index.len(), fresh.len() - 1);
+    assert!(!index.query(everywhere).contains(&Item::Shape(7)));
+    index.update(Item::Shape(7), Rect::new(0.0, 0.0, 1.0, 1.0));
+    assert_eq!(index.query(everywhere).last(), Some(&Item::Shape(7)));
+
+    // Adding items rebuilds it.
+    let before = document.clone();
+    document.add_shape(Shape::line(kurbo::Line::new((5.0, 5.0), (6.0, 6.0))));
+    index.refresh(&before, &document);
+    assert_eq!(
+        index.query(everywhere),
+        SpatialIndex::new(&document).query(everywhere)
+    );
+}
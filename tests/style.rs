@@ -0,0 +1,121 @@
+//! Per-object colors, line widths and dashes over the document style.
+
+mod common;
+
+use common::ethanol;
+use iced::Color;
+use molecular::concept::Concept;
+use molecular::io;
+use molecular::render::Primitive;
+use molecular::shape::Shape;
+use molecular::style::{Appearance, Dash};
+use molecular::{Document, Item};
+
+const RED: Color = Color::from_rgb(0.8, 0.1, 0.1);
+
+fn strokes(document: &Document) -> Vec<(f64, Color, Vec<f64>)> {
+    document
+        .scene(&[])
+        .primitives
+        .into_iter()
+        .filter_map(|primitive| match primitive {
+            Primitive::Stroke {
+                width, color, dash, ..
+            } => Some((width, color, dash)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn label_color(document: &Document, label: &str) -> Color {
+    document
+        .scene(&[])
+        .primitives
+        .into_iter()
+        .find_map(|primitive| match primitive {
+            Primitive::Text { content, color, .. } if content.starts_with(label) => Some(color),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn appearances_survive_saving() {
+    let mut document = ethanol();
+    document
+        .shapes
+        .push(Shape::line(kurbo::Line::new((0.0, 0.0), (40.0, 0.0))));
+    let items = [Item::Concept(0), Item::Shape(0)];
+    document.restyle(&items, |appearance| {
+        *appearance = Appearance {
+            color: Some(RED),
+            line_width: Some(2.0),
+            dash: Dash::Dashed,
+        }
+    });
+
+    let saved = serde_json::to_string(&document).unwrap();
+    let loaded: Document = serde_json::from_str(&saved).unwrap();
+    for item in items {
+        assert_eq!(loaded.appearance(item), document.appearance(item));
+    }
+    assert_eq!(loaded.appearance(Item::Shape(0)).dash, Dash::Dashed);
+}
+
+#[test]
+fn objects_saved_without_an_appearance_follow_the_style() {
+    let mut document = ethanol();
+    document
+        .shapes
+        .push(Shape::line(kurbo::Line::new((0.0, 0.0), (40.0, 0.0))));
+    let saved = serde_json::to_string(&document).unwrap();
+    assert!(!saved.contains("appearance"));
+
+    let loaded: Document = serde_json::from_str(&saved).unwrap();
+    assert!(loaded.appearance(Item::Concept(0)).is_default());
+    assert!(loaded.appearance(Item::Shape(0)).is_default());
+}
+
+#[test]
+fn restyled_molecules_draw_colored_dashed_bonds_and_labels() {
+    let mut document = ethanol();
+    document.restyle(&[Item::Concept(0)], |appearance| {
+        appearance.color = Some(RED);
+        appearance.line_width = Some(2.0);
+        appearance.dash = Dash::Dashed;
+    });
+
+    let strokes = strokes(&document);
+    assert!(!strokes.is_empty());
+    for (width, color, dash) in strokes {
+        assert_eq!((width, color), (2.0, RED));
+        assert_eq!(dash, Dash::Dashed.pattern(2.0));
+    }
+    assert_eq!(label_color(&document, "O"), RED);
+
+    document.restyle(&[Item::Concept(0)], |appearance| appearance.color = None);
+    assert_eq!(label_color(&document, "O"), document.style.color);
+}
+
+#[test]
+fn element_colors_color_heteroatoms_but_not_carbon() {
+    let mut document = Document::default();
+    let molecule = io::smiles::parse("NC(Cl)=O").unwrap();
+    document.import(vec![Concept::Molecule(molecule)]);
+    document.style.element_colors = true;
+
+    for label in ["N", "Cl", "O"] {
+        assert_ne!(label_color(&document, label), document.style.color);
+    }
+    assert!(
+        strokes(&document)
+            .iter()
+            .all(|(_, color, _)| *color == document.style.color)
+    );
+
+    // An atom colored on its own keeps its color.
+    document.restyle(&[Item::Concept(0)], |appearance| {
+        appearance.color = Some(RED)
+    });
+    assert_eq!(label_color(&document, "N"), RED);
+}